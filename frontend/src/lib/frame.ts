@@ -0,0 +1,49 @@
+import type { Engine } from 'physics-engine';
+
+// mirrors `physics::frame::header` of the engine, which has the full layout
+export const HEADER_LEN = 41;
+export const header = {
+	GENERATION: 0,
+	DATA_LEN: 1,
+	POLYGONS: 2,
+	CIRCLES: 3,
+	FLAGS: 4,
+	RIGID_BINDINGS: 5,
+	HINGES: 6,
+	UNBOUND_RIGID_BINDINGS: 7,
+	UNBOUND_HINGES: 8
+} as const;
+
+/**
+ * Typed-array views over the shared frame of an engine in wasm memory, to read
+ * the frames `run_iteration_shared` writes without deserializing them.
+ */
+export class FrameViews {
+	header = new Uint32Array();
+	data = new Float32Array();
+
+	constructor(
+		private memory: WebAssembly.Memory,
+		private engine: Engine
+	) {}
+
+	/**
+	 * Recreates the views that no longer cover the frame, after memory grew or
+	 * the engine moved or resized the data. Call it after every frame written.
+	 */
+	update() {
+		let buffer = this.memory.buffer;
+		if (this.header.buffer !== buffer) {
+			this.header = new Uint32Array(buffer, this.engine.frame_header_ptr(), HEADER_LEN);
+		}
+		let pointer = this.engine.frame_data_ptr();
+		let length = this.header[header.DATA_LEN];
+		if (
+			this.data.buffer !== buffer ||
+			this.data.byteOffset !== pointer ||
+			this.data.length !== length
+		) {
+			this.data = new Float32Array(buffer, pointer, length);
+		}
+	}
+}
@@ -0,0 +1,240 @@
+//! Benchmarks of the hot paths of the engine: narrowphase collision detection,
+//! the full pair loop of a step, the same with the entities in a spatial order,
+//! and binding enforcement.
+//!
+//! Native only, run with `cargo bench`.
+
+use std::f64::consts::TAU;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use physics_engine::{
+    geometry::Point,
+    levels::{Entity, Level},
+    physics::{
+        self,
+        capacity::EngineConfig,
+        compute,
+        shape::{Collidable, Polygon},
+        Engine,
+    },
+};
+
+const STEP_MICROSECONDS: f64 = 2_000.0;
+
+fn regular_polygon(center: Point, radius: f64, vertex_count: usize) -> Vec<Point> {
+    (0..vertex_count)
+        .map(|i| center + Point(radius, 0.0).rotate(TAU * i as f64 / vertex_count as f64))
+        .collect()
+}
+
+fn square(center: Point, half_side: f64) -> Vec<Point> {
+    vec![
+        center + Point(-half_side, -half_side),
+        center + Point(half_side, -half_side),
+        center + Point(half_side, half_side),
+        center + Point(-half_side, half_side),
+    ]
+}
+
+fn level(polygons: Vec<Entity<Vec<Point>>>) -> Level {
+    Level {
+        initial_ball_position: Point(-100.0, -100.0),
+        main_ball: Default::default(),
+        circles: vec![],
+        polygons,
+        flags_positions: vec![],
+        time_limit: None,
+        stars: Default::default(),
+        tool_limits: Default::default(),
+        rigid_compliance: 0.0,
+        terrain: vec![],
+        hints: vec![],
+        bombs: vec![],
+        fans: vec![],
+        lasers: vec![],
+        time_zones: vec![],
+        gravity_pickups: vec![],
+        size_pickups: vec![],
+        wrecking_balls: vec![],
+        magnets: vec![],
+        dispensers: vec![],
+        world_half_extent: Some(physics::WORLD_HALF_EXTENT),
+        script: None,
+    }
+}
+
+fn gjk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gjk");
+
+    for vertex_count in [3, 8, 24, 64] {
+        let first = Polygon::new(regular_polygon(Point(0.0, 0.0), 1.0, vertex_count));
+        let mut second = Polygon::new(regular_polygon(Point(0.0, 0.0), 1.0, vertex_count));
+        second.rotate(0.3);
+
+        for (name, offset) in [("overlapping", 1.5), ("separate", 2.5)] {
+            let mut second = second.clone();
+            second.translate(Point(offset, 0.2));
+
+            group.bench_with_input(
+                BenchmarkId::new(name, vertex_count),
+                &vertex_count,
+                |b, _| b.iter(|| compute::collision(&first, &second)),
+            );
+        }
+    }
+}
+
+fn support(c: &mut Criterion) {
+    let mut group = c.benchmark_group("support");
+
+    for vertex_count in [8, 24, 64] {
+        let vertices = regular_polygon(Point(0.0, 0.0), 1.0, vertex_count);
+        let direction = Point(0.3, -0.7);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(vertex_count),
+            &vertex_count,
+            |b, _| b.iter(|| compute::support::farthest_point(black_box(&vertices), direction)),
+        );
+    }
+}
+
+/// a floor with a grid of boxes falling onto it, added row by row
+fn pile(body_count: usize) -> Engine {
+    pile_in_order(body_count, |i| i)
+}
+
+/// the boxes of `pile`, with the `i`th one added in the place `place(i)`
+fn pile_in_order(body_count: usize, place: impl Fn(usize) -> usize) -> Engine {
+    let columns = (body_count as f64).sqrt().ceil() as usize;
+    let floor = Entity {
+        shape: vec![
+            Point(-1.0, columns as f64 * 0.3),
+            Point(columns as f64 * 0.3 + 1.0, columns as f64 * 0.3),
+            Point(columns as f64 * 0.3 + 1.0, columns as f64 * 0.3 + 1.0),
+            Point(-1.0, columns as f64 * 0.3 + 1.0),
+        ],
+        is_static: true,
+        is_bindable: false,
+        hit_points: None,
+        breaking_impulse: None,
+        fracture_impulse: None,
+        bump_impulse: None,
+        is_ferrous: false,
+        is_erasable: false,
+        render_layer: 0,
+    };
+
+    let mut engine = Engine::new(level(vec![floor]));
+    for i in (0..body_count).map(place) {
+        let center = Point((i % columns) as f64 * 0.3, (i / columns) as f64 * 0.3);
+        engine.add_polygon(Polygon::new(square(center, 0.1)));
+    }
+    // let the boxes settle into contact
+    engine.run_n_iterations(100, STEP_MICROSECONDS);
+    engine
+}
+
+fn pair_loop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pair_loop");
+    group.sample_size(10);
+
+    for body_count in [100, 500, 1000] {
+        let engine = pile(body_count);
+        let snapshot = serde_json::to_string(&engine).unwrap();
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(body_count),
+            &body_count,
+            |b, _| {
+                b.iter_batched(
+                    || serde_json::from_str::<Engine>(&snapshot).unwrap(),
+                    |mut engine| engine.run_n_iterations(1, STEP_MICROSECONDS),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+}
+
+/// a pile whose boxes were added in no particular order, kept that way
+/// or reordered along the curve before the measured step
+fn spatial_order(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spatial_order");
+    group.sample_size(10);
+
+    let body_count = 1000;
+    // 7919 is a prime, so this visits every place exactly once
+    let shuffled = |i| i * 7919 % body_count;
+    for (name, interval) in [("by_id", None), ("z_order", Some(60))] {
+        let mut engine = pile_in_order(body_count, shuffled);
+        engine.set_config(EngineConfig {
+            spatial_order_interval: interval,
+            ..Default::default()
+        });
+        // the reordering itself happens in this step, not in the measured one
+        engine.run_n_iterations(1, STEP_MICROSECONDS);
+        let snapshot = serde_json::to_string(&engine).unwrap();
+
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || serde_json::from_str::<Engine>(&snapshot).unwrap(),
+                |mut engine| engine.run_n_iterations(1, STEP_MICROSECONDS),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+/// a chain of links hinged to each other, hanging from a static anchor
+fn chain(link_count: usize) -> Engine {
+    let anchor = Entity {
+        shape: square(Point(0.0, 0.0), 0.1),
+        is_static: true,
+        is_bindable: true,
+        hit_points: None,
+        breaking_impulse: None,
+        fracture_impulse: None,
+        bump_impulse: None,
+        is_ferrous: false,
+        is_erasable: false,
+        render_layer: 0,
+    };
+
+    let mut engine = Engine::new(level(vec![anchor]));
+    for i in 0..link_count {
+        let joint = Point(0.0, i as f64 * 0.2 + 0.05);
+        engine.add_hinge(joint, 0.0);
+        engine.add_polygon(Polygon::new(vec![
+            joint + Point(-0.02, -0.01),
+            joint + Point(0.02, -0.01),
+            joint + Point(0.02, 0.21),
+            joint + Point(-0.02, 0.21),
+        ]));
+    }
+    engine
+}
+
+fn bindings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chain");
+
+    for link_count in [10, 50, 200] {
+        let engine = chain(link_count);
+        let snapshot = serde_json::to_string(&engine).unwrap();
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(link_count),
+            &link_count,
+            |b, _| {
+                b.iter_batched(
+                    || serde_json::from_str::<Engine>(&snapshot).unwrap(),
+                    |mut engine| engine.run_n_iterations(1, STEP_MICROSECONDS),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+}
+
+criterion_group!(benches, gjk, support, pair_loop, spatial_order, bindings);
+criterion_main!(benches);
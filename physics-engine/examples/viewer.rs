@@ -0,0 +1,194 @@
+//! A minimal desktop viewer for the engine, useful for debugging and profiling
+//! outside of the browser.
+//!
+//! Run with `cargo run --example viewer --no-default-features --features viewer`.
+//! Left click drops a ball, right click drops a box, middle click erases.
+//! `B` toggles drawing the bounding boxes of the bodies.
+
+use macroquad::prelude::*;
+use physics_engine::{
+    geometry::{self, Point},
+    levels::{Entity, Level},
+    physics::{self, compute, shape, DisplayMessage, Engine},
+};
+
+const WORLD_HALF_EXTENT: f32 = 5.0;
+
+fn level() -> Level {
+    Level {
+        initial_ball_position: Point(-2.0, -2.0),
+        main_ball: Default::default(),
+        circles: vec![Entity {
+            shape: geometry::Circle {
+                center: Point(1.0, 0.5),
+                radius: 0.3,
+            },
+            is_static: true,
+            is_bindable: true,
+            hit_points: None,
+            breaking_impulse: None,
+            fracture_impulse: None,
+            bump_impulse: None,
+            is_ferrous: false,
+            is_erasable: false,
+            render_layer: 0,
+        }],
+        polygons: vec![Entity {
+            shape: vec![
+                Point(-4.0, 2.0),
+                Point(4.0, 2.0),
+                Point(4.0, 2.3),
+                Point(-4.0, 2.3),
+            ],
+            is_static: true,
+            is_bindable: false,
+            hit_points: None,
+            breaking_impulse: None,
+            fracture_impulse: None,
+            bump_impulse: None,
+            is_ferrous: false,
+            is_erasable: false,
+            render_layer: 0,
+        }],
+        flags_positions: vec![Point(3.0, 1.5)],
+        time_limit: None,
+        stars: Default::default(),
+        tool_limits: Default::default(),
+        rigid_compliance: 0.0,
+        terrain: vec![],
+        hints: vec![],
+        bombs: vec![],
+        fans: vec![],
+        lasers: vec![],
+        time_zones: vec![],
+        gravity_pickups: vec![],
+        size_pickups: vec![],
+        wrecking_balls: vec![],
+        magnets: vec![],
+        dispensers: vec![],
+        world_half_extent: Some(physics::WORLD_HALF_EXTENT),
+        script: None,
+    }
+}
+
+struct View {
+    scale: f32,
+    origin: Vec2,
+}
+
+impl View {
+    fn current() -> Self {
+        Self {
+            scale: screen_width().min(screen_height()) / (2.0 * WORLD_HALF_EXTENT),
+            origin: vec2(screen_width() / 2.0, screen_height() / 2.0),
+        }
+    }
+
+    fn to_screen(&self, Point(x, y): Point) -> Vec2 {
+        self.origin + vec2(x as f32, y as f32) * self.scale
+    }
+
+    fn to_world(&self, (x, y): (f32, f32)) -> Point {
+        let world = (vec2(x, y) - self.origin) / self.scale;
+        Point(world.x as f64, world.y as f64)
+    }
+
+    fn draw_polygon(&self, vertices: &[Point], color: Color) {
+        let Some((&first, rest)) = vertices.split_first() else {
+            return;
+        };
+
+        let first = self.to_screen(first);
+        for pair in rest.windows(2) {
+            draw_triangle(
+                first,
+                self.to_screen(pair[0]),
+                self.to_screen(pair[1]),
+                color,
+            );
+        }
+    }
+
+    fn draw_points(&self, points: &[Point], color: Color) {
+        for &point in points {
+            let point = self.to_screen(point);
+            draw_circle(point.x, point.y, 4.0, color);
+        }
+    }
+
+    fn draw(&self, message: &DisplayMessage) {
+        let to_color = |[r, g, b]: [f32; 3]| Color::new(r, g, b, 1.0);
+
+        for polygon in &message.polygons {
+            self.draw_polygon(&polygon.shape.vertices, to_color(polygon.color));
+        }
+
+        for circle in &message.circles {
+            let center = self.to_screen(circle.shape.center);
+            let radius = circle.shape.radius as f32 * self.scale;
+            draw_circle(center.x, center.y, radius, to_color(circle.color));
+        }
+
+        for flag in &message.flags {
+            self.draw_polygon(&flag.vertices, BLACK);
+        }
+
+        self.draw_points(&message.rigid_bindings, RED);
+        self.draw_points(&message.hinges, BLUE);
+        self.draw_points(&message.unbound_rigid_bindings, ORANGE);
+        self.draw_points(&message.unbound_hinges, GREEN);
+    }
+
+    fn draw_aabbs(&self, engine: &Engine) {
+        for body in engine.body_states() {
+            let min = self.to_screen(body.aabb.min);
+            let size = self.to_screen(body.aabb.max) - min;
+            draw_rectangle_lines(min.x, min.y, size.x, size.y, 1.0, GRAY);
+        }
+    }
+}
+
+#[macroquad::main("what-if engine")]
+async fn main() {
+    let mut engine = Engine::new(level());
+    let mut show_aabbs = false;
+
+    loop {
+        let view = View::current();
+        let cursor = view.to_world(mouse_position());
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            engine.add_circle(shape::Circle::new(cursor, 0.15));
+        }
+
+        if is_mouse_button_pressed(MouseButton::Right) {
+            let Point(x, y) = cursor;
+            engine.add_polygon(compute::hull::<24>(
+                [
+                    Point(x - 0.2, y - 0.2),
+                    Point(x + 0.2, y - 0.2),
+                    Point(x + 0.2, y + 0.2),
+                    Point(x - 0.2, y + 0.2),
+                ]
+                .into_iter(),
+            ));
+        }
+
+        if is_mouse_button_pressed(MouseButton::Middle) {
+            engine.erase_at(cursor);
+        }
+
+        if is_key_pressed(KeyCode::B) {
+            show_aabbs = !show_aabbs;
+        }
+
+        let message = engine.run_iteration(get_frame_time() as f64 * 1_000_000.0);
+
+        clear_background(WHITE);
+        view.draw(&message);
+        if show_aabbs {
+            view.draw_aabbs(&engine);
+        }
+        next_frame().await
+    }
+}
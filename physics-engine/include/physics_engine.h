@@ -0,0 +1,30 @@
+/* C API of the physics engine, available when built with the `cdylib` feature */
+
+#ifndef PHYSICS_ENGINE_H
+#define PHYSICS_ENGINE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct PhysicsEngine PhysicsEngine;
+
+typedef struct {
+    uint32_t id;
+    double x;
+    double y;
+    double angle;
+} BodyTransform;
+
+/* returns NULL if the level JSON cannot be parsed */
+PhysicsEngine *physics_engine_create(const char *level_json);
+void physics_engine_destroy(PhysicsEngine *engine);
+
+void physics_engine_step(PhysicsEngine *engine, uint32_t count, double microseconds);
+uint32_t physics_engine_is_won(const PhysicsEngine *engine);
+uint64_t physics_engine_state_hash(const PhysicsEngine *engine);
+
+/* the first body is always the main ball */
+size_t physics_engine_body_count(const PhysicsEngine *engine);
+size_t physics_engine_body_transforms(const PhysicsEngine *engine, BodyTransform *out, size_t capacity);
+
+#endif
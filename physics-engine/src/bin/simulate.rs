@@ -0,0 +1,119 @@
+//! Runs a level headlessly and reports whether it was won.
+//!
+//! Usage: `simulate <level.ron|level.json> [seconds] [step_microseconds] [trace.csv|trace.jsonl]`
+//!
+//! With a trace file, the motion of the main ball is written to it for every step.
+//!
+//! Steps longer than the longest one the engine simulates at once are split into
+//! as many substeps as they take, so that none of the time is dropped.
+//!
+//! Exits with a non-zero status if the level could not be loaded
+//! or if it was not won by the end of the simulation.
+
+use std::{env, fs, process::ExitCode};
+
+use physics_engine::{
+    levels::Level,
+    physics::{
+        capacity::EngineConfig, game::GameState, ids::EntityId, instrument::TraceFormat, Engine,
+    },
+};
+
+const DEFAULT_SECONDS: f64 = 10.0;
+const DEFAULT_STEP_MICROSECONDS: f64 = 16_000.0;
+
+fn load_level(path: &str) -> Result<Level, String> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("cannot read {path}: {err}"))?;
+
+    if path.ends_with(".json") {
+        serde_json::from_str(&contents).map_err(|err| format!("invalid level {path}: {err}"))
+    } else {
+        ron::from_str(&contents).map_err(|err| format!("invalid level {path}: {err}"))
+    }
+}
+
+fn parse_arg(arg: Option<String>, default: f64) -> Result<f64, String> {
+    arg.map_or(Ok(default), |arg| {
+        arg.parse()
+            .map_err(|_| format!("expected a number, found {arg}"))
+    })
+}
+
+fn run() -> Result<bool, String> {
+    let mut args = env::args().skip(1);
+    let path = args
+        .next()
+        .ok_or("usage: simulate <level> [seconds] [step_microseconds] [trace]")?;
+    let seconds = parse_arg(args.next(), DEFAULT_SECONDS)?;
+    let step = parse_arg(args.next(), DEFAULT_STEP_MICROSECONDS)?;
+    if !(seconds.is_finite() && seconds >= 0.0) {
+        return Err(format!(
+            "usage: seconds must be a finite number that is not negative, found {seconds}"
+        ));
+    }
+    if !(step.is_finite() && step > 0.0) {
+        return Err(format!(
+            "usage: step_microseconds must be a finite positive number, found {step}"
+        ));
+    }
+
+    let trace = args.next();
+
+    let config = EngineConfig {
+        max_substeps: u32::MAX,
+        ..EngineConfig::default()
+    };
+    let mut engine = Engine::with_config(load_level(&path)?, config);
+    if trace.is_some() {
+        engine.start_trace(vec![EntityId(0)]);
+    }
+    let steps = (seconds * 1_000_000.0 / step).ceil() as u32;
+    let message = engine.run_n_iterations(steps, step);
+
+    if let (Some(trace_path), Some(trace)) = (trace, engine.stop_trace()) {
+        let format = if trace_path.ends_with(".jsonl") {
+            TraceFormat::JsonLines
+        } else {
+            TraceFormat::Csv
+        };
+        fs::write(&trace_path, trace.export(format))
+            .map_err(|err| format!("cannot write {trace_path}: {err}"))?;
+        println!("trace of the ball written to {trace_path}");
+    }
+
+    let ball = &message.circles[0].shape.center;
+    println!("simulated {seconds}s in {steps} steps");
+    println!("ball position: ({:.4}, {:.4})", ball.0, ball.1);
+    println!(
+        "shapes: {} polygons, {} circles",
+        message.polygons.len(),
+        message.circles.len()
+    );
+    println!(
+        "flags remaining: {}/{}",
+        message.flags_remaining, message.flags_total
+    );
+
+    if let GameState::Won { score } = message.state {
+        println!("won after {:.2}s, {} stars", score.seconds, score.stars);
+    }
+
+    Ok(matches!(message.state, GameState::Won { .. }))
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => {
+            println!("level won");
+            ExitCode::SUCCESS
+        }
+        Ok(false) => {
+            println!("level not won");
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
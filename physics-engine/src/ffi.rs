@@ -0,0 +1,158 @@
+//! Every function taking an engine expects a pointer returned by
+//! [`physics_engine_create`] that has not been passed to
+//! [`physics_engine_destroy`] yet. The declarations for C are in
+//! `include/physics_engine.h`.
+
+use std::{
+    ffi::{c_char, CStr},
+    ptr, slice,
+};
+
+use crate::{
+    levels::Level,
+    physics::{game::GameState, Engine},
+};
+
+/// Position and rotation of a single body
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BodyTransform {
+    pub id: u32,
+    pub x: f64,
+    pub y: f64,
+    pub angle: f64,
+}
+
+/// Creates an engine from a level serialized as JSON.
+/// Returns null if the level cannot be parsed.
+///
+/// # Safety
+/// `level_json` must point to a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn physics_engine_create(level_json: *const c_char) -> *mut Engine {
+    let Ok(level_json) = CStr::from_ptr(level_json).to_str() else {
+        return ptr::null_mut();
+    };
+
+    match serde_json::from_str::<Level>(level_json) {
+        Ok(level) => Box::into_raw(Box::new(Engine::new(level))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `engine` must be a live engine or null, and must not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn physics_engine_destroy(engine: *mut Engine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Advances the simulation by `count` steps of `microseconds` each
+///
+/// # Safety
+/// `engine` must be a live engine
+#[no_mangle]
+pub unsafe extern "C" fn physics_engine_step(engine: *mut Engine, count: u32, microseconds: f64) {
+    (*engine).run_n_iterations(count, microseconds);
+}
+
+/// Returns 1 once every flag of the level has been collected, 0 otherwise
+///
+/// # Safety
+/// `engine` must be a live engine
+#[no_mangle]
+pub unsafe extern "C" fn physics_engine_is_won(engine: *const Engine) -> u32 {
+    matches!((*engine).game_state(), GameState::Won { .. }) as u32
+}
+
+/// Returns a checksum of the state of all bodies, see [`Engine::state_hash`]
+///
+/// # Safety
+/// `engine` must be a live engine
+#[no_mangle]
+pub unsafe extern "C" fn physics_engine_state_hash(engine: *const Engine) -> u64 {
+    (*engine).state_hash()
+}
+
+/// Returns the number of bodies, the first of which is the main ball
+///
+/// # Safety
+/// `engine` must be a live engine
+#[no_mangle]
+pub unsafe extern "C" fn physics_engine_body_count(engine: *const Engine) -> usize {
+    (*engine).body_states().len()
+}
+
+/// Writes the transforms of at most `capacity` bodies into `out`,
+/// in the same order as they are simulated, and returns how many were written
+///
+/// # Safety
+/// `engine` must be a live engine and `out` must be valid for `capacity` writes
+#[no_mangle]
+pub unsafe extern "C" fn physics_engine_body_transforms(
+    engine: *const Engine,
+    out: *mut BodyTransform,
+    capacity: usize,
+) -> usize {
+    if out.is_null() {
+        return 0;
+    }
+
+    let out = slice::from_raw_parts_mut(out, capacity);
+    let states = (*engine).body_states();
+
+    for (transform, state) in out.iter_mut().zip(&states) {
+        *transform = BodyTransform {
+            id: state.id.0,
+            x: state.centroid.0,
+            y: state.centroid.1,
+            angle: state.angle,
+        };
+    }
+    states.len().min(capacity)
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::CString;
+
+    use super::*;
+
+    #[test]
+    fn test_lifecycle() {
+        let level = CString::new(
+            r#"{
+                "initial_ball_position": [0.0, 0.0],
+                "circles": [],
+                "polygons": [],
+                "flags_positions": []
+            }"#,
+        )
+        .unwrap();
+
+        unsafe {
+            let engine = physics_engine_create(level.as_ptr());
+            assert!(!engine.is_null());
+            assert_eq!(physics_engine_body_count(engine), 1);
+
+            physics_engine_step(engine, 10, 2_000.0);
+
+            let mut transforms = [BodyTransform::default(); 2];
+            let written = physics_engine_body_transforms(engine, transforms.as_mut_ptr(), 2);
+            assert_eq!(written, 1);
+            assert_eq!(transforms[0].x, 0.0);
+            assert!(transforms[0].y > 0.0);
+            assert_eq!(physics_engine_is_won(engine), 0);
+
+            physics_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_invalid_level() {
+        let level = CString::new("not a level").unwrap();
+        assert!(unsafe { physics_engine_create(level.as_ptr()) }.is_null());
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::ops;
+#[cfg(feature = "wasm")]
 use tsify::Tsify;
 
 pub const EPSILON: f64 = 1e-7;
@@ -9,8 +10,8 @@ pub const EPSILON: f64 = 1e-7;
 /// The types of receivers and parameters are mostly specified explicitly
 /// as either `Point` or the type alias `Vector`, to suggest the correct intepretation
 /// of these values within a given context
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Tsify)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Point(pub f64, pub f64);
 
 impl Point {
@@ -56,6 +57,45 @@ impl Point {
             * if self.cross(other) > 0.0 { 1.0 } else { -1.0 }
     }
 
+    /// the point of the segment between `start` and `end` closest to this one
+    pub fn closest_on_segment(self, start: Point, end: Point) -> Point {
+        let segment = start.to(end);
+        let length_squared = segment.dot(segment);
+        let along = if length_squared > 0.0 {
+            (start.to(self).dot(segment) / length_squared).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        start + segment * along
+    }
+
+    /// distance to the closest point of the segment between `start` and `end`
+    pub fn distance_to_segment(self, start: Point, end: Point) -> f64 {
+        self.closest_on_segment(start, end).to(self).norm()
+    }
+
+    /// where the segment between `start` and `end` crosses the one between
+    /// `other_start` and `other_end`, `None` if they do not cross or are parallel
+    pub fn segment_intersection(
+        start: Point,
+        end: Point,
+        other_start: Point,
+        other_end: Point,
+    ) -> Option<Point> {
+        let (segment, other) = (start.to(end), other_start.to(other_end));
+        let determinant = segment.cross(other);
+        if determinant == 0.0 {
+            return None;
+        }
+
+        let offset = start.to(other_start);
+        let along = offset.cross(other) / determinant;
+        let along_other = offset.cross(segment) / determinant;
+        ((0.0..=1.0).contains(&along) && (0.0..=1.0).contains(&along_other))
+            .then(|| start + segment * along)
+    }
+
     pub fn triple_product(self: Vector, other: Vector) -> Vector {
         let segment = other.to(self);
         -other * segment.dot(segment) - segment * segment.dot(-other)
@@ -125,21 +165,61 @@ impl From<[f32; 2]> for Point {
     }
 }
 
-#[derive(Serialize, Deserialize, Tsify, Debug)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Polygon {
     pub vertices: Vec<Point>,
+    /// how far the outline is rounded out beyond the vertices, zero for sharp corners
+    #[serde(default)]
+    pub corner_radius: f64,
     #[serde(skip_serializing)]
     pub centroid: Point,
 }
 
-#[derive(Serialize, Deserialize, Tsify, Debug)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Circle {
     pub center: Point,
     pub radius: f64,
 }
 
+/// An axis-aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Aabb {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Aabb {
+    /// the smallest box containing all of the points, which must not be empty
+    pub fn around(points: impl IntoIterator<Item = Point>) -> Self {
+        let mut points = points.into_iter();
+        let first = points.next().expect("a bounding box of no points");
+        points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |aabb, point| Self {
+                min: Point(aabb.min.0.min(point.0), aabb.min.1.min(point.1)),
+                max: Point(aabb.max.0.max(point.0), aabb.max.1.max(point.1)),
+            },
+        )
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.0 <= other.max.0
+            && other.min.0 <= self.max.0
+            && self.min.1 <= other.max.1
+            && other.min.1 <= self.max.1
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min.0..=self.max.0).contains(&point.0) && (self.min.1..=self.max.1).contains(&point.1)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::f64::consts::PI;
@@ -152,6 +232,48 @@ mod test {
             .rotate(PI / 2.0)
             .is_close_enough_to(Point(0.0, 1.0)))
     }
+
+    #[test]
+    fn test_distance_to_segment() {
+        let (start, end) = (Point(0.0, 0.0), Point(2.0, 0.0));
+        assert_eq!(Point(1.0, 1.0).distance_to_segment(start, end), 1.0);
+        assert_eq!(Point(3.0, 0.0).distance_to_segment(start, end), 1.0);
+        assert_eq!(Point(0.0, -2.0).distance_to_segment(start, start), 2.0);
+    }
+
+    #[test]
+    fn test_segment_intersection() {
+        let (start, end) = (Point(0.0, 0.0), Point(2.0, 2.0));
+        assert_eq!(
+            Point::segment_intersection(start, end, Point(0.0, 2.0), Point(2.0, 0.0)),
+            Some(Point(1.0, 1.0))
+        );
+        // touching at an end counts as crossing
+        assert_eq!(
+            Point::segment_intersection(start, end, Point(2.0, 2.0), Point(3.0, 0.0)),
+            Some(Point(2.0, 2.0))
+        );
+        assert_eq!(
+            Point::segment_intersection(start, end, Point(3.0, 0.0), Point(3.0, 5.0)),
+            None
+        );
+        assert_eq!(
+            Point::segment_intersection(start, end, Point(1.0, 0.0), Point(3.0, 2.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_aabb() {
+        let aabb = Aabb::around([Point(1.0, 0.0), Point(-1.0, 2.0), Point(0.0, 1.0)]);
+        assert_eq!(aabb.min, Point(-1.0, 0.0));
+        assert_eq!(aabb.max, Point(1.0, 2.0));
+
+        assert!(aabb.contains(Point(1.0, 1.0)));
+        assert!(!aabb.contains(Point(1.5, 1.0)));
+        assert!(aabb.overlaps(&Aabb::around([Point(1.0, 2.0), Point(3.0, 3.0)])));
+        assert!(!aabb.overlaps(&Aabb::around([Point(1.5, 0.0), Point(3.0, 3.0)])));
+    }
 }
 
 /// An iterator very much like the standard library [std::slice::Windows], [`std::slice::Windows`],
@@ -1,13 +1,297 @@
-use crate::geometry::{Circle, Point};
+use crate::{
+    geometry::{Circle, Point, Vector},
+    physics::{game::Tool, FLAG_SIZE, WORLD_HALF_EXTENT},
+};
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
 use tsify::Tsify;
 
-#[derive(Serialize, Deserialize, Tsify, Debug)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Entity<S> {
     pub shape: S,
     pub is_static: bool,
     pub is_bindable: bool,
+    /// makes the entity destructible, every contact takes away
+    /// as many hit points as the impulse exchanged in it
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub hit_points: Option<f64>,
+    /// makes the entity fragile, it shatters when the impulse
+    /// of a single contact exceeds this value
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub breaking_impulse: Option<f64>,
+    /// makes a polygon brittle, it breaks into smaller pieces when the
+    /// impulse of a single contact exceeds this value
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub fracture_impulse: Option<f64>,
+    /// makes the entity a bumper, it kicks everything touching it
+    /// away from its centroid with this impulse
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub bump_impulse: Option<f64>,
+    /// makes the entity stick to powered magnets
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub is_ferrous: bool,
+    /// lets the player erase the entity as if they had drawn it
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub is_erasable: bool,
+    /// entities on higher layers are drawn on top, see [`crate::physics::layer`]
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub render_layer: i32,
+}
+
+/// Upper bounds a win has to stay within to earn a star.
+/// Bounds that are not set always hold
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct StarThreshold {
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub seconds: Option<f64>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub shapes_drawn: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub erases: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub bindings_placed: Option<u32>,
+}
+
+/// Maximal number of uses of each tool. Tools without a limit can be used freely
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct ToolLimits {
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub polygons: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub circles: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub hinges: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub rigids: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub springs: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub ropes: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub pulleys: Option<u32>,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub erases: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum HintTrigger {
+    /// the center of the main ball enters the convex region
+    BallEnters(Vec<Point>),
+    /// the main ball has respawned this many times
+    FailedAttempts(u32),
+    /// the player selects the tool for the first time
+    ToolSelected(Tool),
+}
+
+/// A tutorial hint, shown at most once when its trigger fires
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Hint {
+    pub text: String,
+    pub trigger: HintTrigger,
+}
+
+/// A bomb, which explodes after its fuse burns out or on a hard impact
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Bomb {
+    pub position: Point,
+    /// seconds from the start of the level to the explosion, if any
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub fuse_seconds: Option<f64>,
+    /// the impulse of a single contact that sets the bomb off, if any
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub detonation_impulse: Option<f64>,
+    pub blast_radius: f64,
+    pub blast_impulse: f64,
+}
+
+/// A fan blowing on everything in the `length` by `width` rectangle in front of it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Fan {
+    pub position: Point,
+    pub direction: Vector,
+    pub length: f64,
+    pub width: f64,
+    /// force in multiples of the weight of a unit of mass
+    pub strength: f64,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub starts_disabled: bool,
+}
+
+/// A laser beam between two points, erasing the player-drawn shapes crossing it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Laser {
+    pub start: Point,
+    pub end: Point,
+    /// whether the main ball respawns when it crosses the beam
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub resets_ball: bool,
+}
+
+/// A convex region in which time passes `time_scale` times as fast
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TimeZone {
+    pub region: Vec<Point>,
+    pub time_scale: f64,
+}
+
+/// Turns gravity by `rotation` radians for `duration_seconds` once the main ball touches it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct GravityPickup {
+    pub position: Point,
+    pub rotation: f64,
+    pub duration_seconds: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SizePickup {
+    pub position: Point,
+    /// size of the ball relative to its original size while the pickup is in effect
+    pub scale: f64,
+    pub duration_seconds: f64,
+}
+
+/// The main ball of a level, missing fields take their default values
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct MainBall {
+    pub radius: f64,
+    /// mass per unit of area, every other body has a density of 1
+    pub density: f64,
+    /// multiplies the restitution of the contacts of the ball
+    pub restitution: f64,
+    /// multiplies the friction of the contacts of the ball
+    pub friction: f64,
+    /// RGB color with channels in `0.0..=1.0`, `None` (`null`) to paint it from the palette
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub color: Option<[f32; 3]>,
+    /// the velocity the ball starts with, and starts over with after respawning
+    pub initial_velocity: Vector,
+}
+
+impl Default for MainBall {
+    fn default() -> Self {
+        Self {
+            radius: 0.1,
+            density: 1.0,
+            restitution: 1.0,
+            friction: 1.0,
+            color: None,
+            initial_velocity: Vector::ZERO,
+        }
+    }
+}
+
+/// A round electromagnet, which picks up the first ferrous entity
+/// it touches while powered and drops it when switched off
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Magnet {
+    pub position: Point,
+    pub radius: f64,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub is_static: bool,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub starts_powered: bool,
+}
+
+/// A heavy ball hung on a rod from a static anchor, swinging from the start of the level
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct WreckingBall {
+    pub anchor: Point,
+    /// center of the ball
+    pub position: Point,
+    pub radius: f64,
+    /// initial angular velocity about the anchor in radians per second,
+    /// positive turns from `+x` towards `+y`
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub swing_speed: f64,
+}
+
+/// What happens to a dispensed ball once it leaves the world
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum OutOfBounds {
+    /// it goes back to its dispenser and starts over
+    #[default]
+    Respawn,
+    /// it is removed
+    Despawn,
+    /// it is removed, and the level is lost
+    Loss,
+}
+
+/// Emits balls of its own every `interval_seconds`, or whenever the host or the
+/// script triggers it. The balls are tracked like the main ball, but do not
+/// collect flags
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Dispenser {
+    /// where the balls appear
+    pub position: Point,
+    #[serde(default = "default_ball_radius")]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub radius: f64,
+    /// mass per unit of area, every other body has a density of 1
+    #[serde(default = "default_ball_density")]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub density: f64,
+    /// RGB color with channels in `0.0..=1.0`, `None` (`null`) to paint the balls from the palette
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub color: Option<[f32; 3]>,
+    /// the velocity the balls start with, and start over with after respawning
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub initial_velocity: Vector,
+    /// seconds between two balls, the first one included, `None` (`null`)
+    /// for a dispenser that only emits when triggered
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub interval_seconds: Option<f64>,
+    /// the most balls it emits over the level, `None` (`null`) for no limit
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub capacity: Option<u32>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub out_of_bounds: OutOfBounds,
+}
+
+fn default_ball_radius() -> f64 {
+    MainBall::default().radius
+}
+
+fn default_ball_density() -> f64 {
+    MainBall::default().density
 }
 
 /// Represents a single level
@@ -16,11 +300,358 @@ pub struct Entity<S> {
 /// and passed directly to the physics engine
 ///
 
-#[derive(Serialize, Deserialize, Tsify, Debug)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Level {
     pub initial_ball_position: Point,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub main_ball: MainBall,
     pub circles: Vec<Entity<Circle>>,
     pub polygons: Vec<Entity<Vec<Point>>>,
+    /// static polylines the bodies collide with along their edges, for ground,
+    /// ramps and caves that are not convex, see [`crate::physics::terrain`]
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub terrain: Vec<Vec<Point>>,
     pub flags_positions: Vec<Point>,
+    /// seconds after which the level is lost, if any
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub time_limit: Option<f64>,
+    /// thresholds of the first, second and third star
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub stars: [StarThreshold; 3],
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub tool_limits: ToolLimits,
+    /// how far the rigid bindings the player places give per unit of force,
+    /// zero for perfectly stiff ones
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub rigid_compliance: f64,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub hints: Vec<Hint>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub bombs: Vec<Bomb>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub fans: Vec<Fan>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub lasers: Vec<Laser>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub time_zones: Vec<TimeZone>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub gravity_pickups: Vec<GravityPickup>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub size_pickups: Vec<SizePickup>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub wrecking_balls: Vec<WreckingBall>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub magnets: Vec<Magnet>,
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub dispensers: Vec<Dispenser>,
+    /// half the side of the square the main ball respawns when leaving, in which the
+    /// other bodies are kept, `None` (`null`) for a world without bounds.
+    /// Levels scrolling far beyond the default also want [`Engine::set_origin_shift_distance`]
+    ///
+    /// [`Engine::set_origin_shift_distance`]: crate::physics::Engine::set_origin_shift_distance
+    #[serde(default = "default_world_half_extent")]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub world_half_extent: Option<f64>,
+    /// the source of a Rhai script with the logic of the level, which only
+    /// runs with the `scripting` feature, see [`crate::physics::script`]
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub script: Option<String>,
+}
+
+fn default_world_half_extent() -> Option<f64> {
+    Some(WORLD_HALF_EXTENT)
+}
+
+/// An affine map of the plane that only mirrors and scales uniformly, so that
+/// the rest of a level can follow the positions it moves
+struct Transform<F> {
+    point: F,
+    /// how much longer lengths get
+    scale: f64,
+    /// whether it flips the plane, which turns every winding around
+    is_mirror: bool,
+}
+
+impl<F: Fn(Point) -> Point> Transform<F> {
+    fn vector(&self, Point(x, y): Vector) -> Vector {
+        if self.is_mirror {
+            Point(-x, y)
+        } else {
+            Point(x, y)
+        }
+    }
+
+    /// a rotation the other way around when mirrored
+    fn turn(&self, angle: f64) -> f64 {
+        if self.is_mirror {
+            -angle
+        } else {
+            angle
+        }
+    }
+
+    fn outline(&self, outline: Vec<Point>) -> Vec<Point> {
+        let mut outline: Vec<_> = outline.into_iter().map(&self.point).collect();
+        if self.is_mirror {
+            outline.reverse();
+        }
+        outline
+    }
+
+    fn circle(&self, Circle { center, radius }: Circle) -> Circle {
+        Circle {
+            center: (self.point)(center),
+            radius: radius * self.scale,
+        }
+    }
+
+    /// the corner of a flag whose center moved along, as flags keep their size
+    fn flag(&self, corner: Point) -> Point {
+        let half = Point(FLAG_SIZE, FLAG_SIZE) / 2.0;
+        (self.point)(corner + half) - half
+    }
+}
+
+impl<S> Entity<S> {
+    fn map_shape<T>(self, shape: impl FnOnce(S) -> T) -> Entity<T> {
+        Entity {
+            shape: shape(self.shape),
+            is_static: self.is_static,
+            is_bindable: self.is_bindable,
+            hit_points: self.hit_points,
+            breaking_impulse: self.breaking_impulse,
+            fracture_impulse: self.fracture_impulse,
+            bump_impulse: self.bump_impulse,
+            is_ferrous: self.is_ferrous,
+            is_erasable: self.is_erasable,
+            render_layer: self.render_layer,
+        }
+    }
+}
+
+/// Variants of a level, for generating levels and for tests. Everything with a
+/// position moves along and every length scales, while masses, impulses, times
+/// and the script of the level stay as they are. The bounds of the world stay
+/// centered on the origin, so only scaling changes them
+impl Level {
+    /// the level flipped left to right about the vertical axis through the origin
+    pub fn mirrored_x(self) -> Level {
+        self.transformed(Transform {
+            point: |Point(x, y)| Point(-x, y),
+            scale: 1.0,
+            is_mirror: true,
+        })
+    }
+
+    /// the level scaled about the origin by a positive `factor`
+    pub fn scaled(self, factor: f64) -> Level {
+        self.transformed(Transform {
+            point: |point| point * factor,
+            scale: factor,
+            is_mirror: false,
+        })
+    }
+
+    pub fn translated(self, dx: f64, dy: f64) -> Level {
+        self.transformed(Transform {
+            point: |point| point + Point(dx, dy),
+            scale: 1.0,
+            is_mirror: false,
+        })
+    }
+
+    fn transformed(self, transform: Transform<impl Fn(Point) -> Point>) -> Level {
+        let point = &transform.point;
+        let scale = transform.scale;
+        Level {
+            initial_ball_position: point(self.initial_ball_position),
+            main_ball: MainBall {
+                radius: self.main_ball.radius * scale,
+                initial_velocity: transform.vector(self.main_ball.initial_velocity) * scale,
+                ..self.main_ball
+            },
+            circles: self
+                .circles
+                .into_iter()
+                .map(|entity| entity.map_shape(|circle| transform.circle(circle)))
+                .collect(),
+            polygons: self
+                .polygons
+                .into_iter()
+                .map(|entity| entity.map_shape(|outline| transform.outline(outline)))
+                .collect(),
+            terrain: self
+                .terrain
+                .into_iter()
+                .map(|chain| chain.into_iter().map(point).collect())
+                .collect(),
+            flags_positions: self
+                .flags_positions
+                .into_iter()
+                .map(|corner| transform.flag(corner))
+                .collect(),
+            hints: self
+                .hints
+                .into_iter()
+                .map(|hint| Hint {
+                    trigger: match hint.trigger {
+                        HintTrigger::BallEnters(region) => {
+                            HintTrigger::BallEnters(transform.outline(region))
+                        }
+                        trigger => trigger,
+                    },
+                    ..hint
+                })
+                .collect(),
+            bombs: self
+                .bombs
+                .into_iter()
+                .map(|bomb| Bomb {
+                    position: point(bomb.position),
+                    blast_radius: bomb.blast_radius * scale,
+                    ..bomb
+                })
+                .collect(),
+            fans: self
+                .fans
+                .into_iter()
+                .map(|fan| Fan {
+                    position: point(fan.position),
+                    direction: transform.vector(fan.direction),
+                    length: fan.length * scale,
+                    width: fan.width * scale,
+                    ..fan
+                })
+                .collect(),
+            lasers: self
+                .lasers
+                .into_iter()
+                .map(|laser| Laser {
+                    start: point(laser.start),
+                    end: point(laser.end),
+                    ..laser
+                })
+                .collect(),
+            time_zones: self
+                .time_zones
+                .into_iter()
+                .map(|zone| TimeZone {
+                    region: transform.outline(zone.region),
+                    ..zone
+                })
+                .collect(),
+            gravity_pickups: self
+                .gravity_pickups
+                .into_iter()
+                .map(|pickup| GravityPickup {
+                    position: point(pickup.position),
+                    rotation: transform.turn(pickup.rotation),
+                    ..pickup
+                })
+                .collect(),
+            size_pickups: self
+                .size_pickups
+                .into_iter()
+                .map(|pickup| SizePickup {
+                    position: point(pickup.position),
+                    ..pickup
+                })
+                .collect(),
+            wrecking_balls: self
+                .wrecking_balls
+                .into_iter()
+                .map(|wrecking_ball| WreckingBall {
+                    anchor: point(wrecking_ball.anchor),
+                    position: point(wrecking_ball.position),
+                    radius: wrecking_ball.radius * scale,
+                    swing_speed: transform.turn(wrecking_ball.swing_speed),
+                })
+                .collect(),
+            magnets: self
+                .magnets
+                .into_iter()
+                .map(|magnet| Magnet {
+                    position: point(magnet.position),
+                    radius: magnet.radius * scale,
+                    ..magnet
+                })
+                .collect(),
+            dispensers: self
+                .dispensers
+                .into_iter()
+                .map(|dispenser| Dispenser {
+                    position: point(dispenser.position),
+                    radius: dispenser.radius * scale,
+                    initial_velocity: transform.vector(dispenser.initial_velocity) * scale,
+                    ..dispenser
+                })
+                .collect(),
+            world_half_extent: self.world_half_extent.map(|extent| extent * scale),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::Engine;
+
+    fn ramp() -> Level {
+        ron::from_str(include_str!("../levels/ramp.ron")).unwrap()
+    }
+
+    fn ball_after(level: Level, steps: u32) -> Point {
+        let mut engine = Engine::new(level);
+        engine.run_n_iterations(steps, 2_000.0);
+        engine.body_states()[0].centroid
+    }
+
+    /// twice the signed area, positive for the winding of the levels
+    fn winding(outline: &[Point]) -> f64 {
+        let shifted = outline.iter().cycle().skip(1);
+        outline.iter().zip(shifted).map(|(a, b)| a.cross(*b)).sum()
+    }
+
+    #[test]
+    fn test_transformed_levels() {
+        let level = ramp();
+        assert_eq!(level.clone().mirrored_x().mirrored_x(), level);
+
+        let mirrored = level.clone().mirrored_x();
+        assert_eq!(mirrored.initial_ball_position, Point(3.0, -1.0));
+        assert!(mirrored.flags_positions[0].is_close_enough_to(Point(-2.6, 1.35)));
+        for (entity, original) in mirrored.polygons.iter().zip(&level.polygons) {
+            assert!(winding(&original.shape) > 0.0);
+            assert!((winding(&entity.shape) - winding(&original.shape)).abs() < 1e-9);
+        }
+
+        // the ball rolls down the ramp the same way anywhere
+        let ball = ball_after(level.clone(), 150);
+        let translated = ball_after(level.clone().translated(0.5, -0.25), 150);
+        assert!(translated.to(ball + Point(0.5, -0.25)).norm() < 1e-4);
+
+        let scaled = level.scaled(0.5);
+        assert_eq!(scaled.world_half_extent, Some(WORLD_HALF_EXTENT / 2.0));
+        assert_eq!(scaled.polygons[1].shape[1], Point(2.25, 0.75));
+        assert!(scaled.flags_positions[0].is_close_enough_to(Point(1.225, 0.65)));
+    }
 }
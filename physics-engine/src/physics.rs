@@ -1,82 +1,427 @@
 use std::{
     cell::RefCell,
+    collections::HashMap,
     os::raw::c_void,
     rc::{Rc, Weak},
 };
 
-use rand::Rng;
+use bumpalo::Bump;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
 use tsify::Tsify;
 
 use self::{
-    binding::{Binding, Unbound},
-    shape::{Circle, Collidable, Polygon},
+    achievements::Achievements,
+    binding::{Binding, PointOnShape, Unbound},
+    bodies::Bodies,
+    bomb::{ArmedBomb, BOMB_RADIUS},
+    capacity::EngineConfig,
+    cloth::{Cloth, ClothFrame},
+    curve::ChainPath,
+    debug::{DebugContact, DebugDraw},
+    dispenser::{BallStatus, Dispenser, TrackedBall},
+    divergence::BodyHashes,
+    editor::LevelIds,
+    energy::Energy,
+    fan::Fan,
+    game::{Game, GameEvent, GameState, Tool, Usage},
+    ghost::Ghost,
+    grab::Grab,
+    gravity::Gravity,
+    hints::Hints,
+    ids::{BindingId, EntityId, IdGenerator, RegionId},
+    instrument::Tracer,
+    load::{BindingLoad, BindingLoads},
+    magnet::Magnet,
+    motor::Motor,
+    narrowphase::WarmStarts,
+    palette::Painter,
+    particles::{ParticleKind, Particles},
+    perf::PerfStats,
+    recording::Recorder,
+    shape::{Circle, Collidable, Compound, Polygon},
+    size::BallSize,
+    slots::Slots,
+    snap::Snapping,
+    soft_body::SoftBody,
+    spring::{SpringConfig, TorsionSpring},
+    time_zone::{time_scale_at, TimeZone},
+    trail::{Trail, Trails},
+    tuning::SolverTuning,
 };
 use crate::{
     geometry::{self, Point, Vector},
-    levels::Level,
+    levels::{self, Laser, Level, MainBall, ToolLimits},
+    trace::{event, span},
 };
 
+pub mod achievements;
+pub mod assist;
+pub mod ball;
+pub mod batch;
 mod binding;
+mod bodies;
+pub mod bomb;
+mod bumper;
+pub mod capacity;
+pub mod cloth;
+mod compound;
 pub mod compute;
+pub mod curve;
+pub mod debug;
+pub mod dispenser;
+pub mod divergence;
+pub mod editor;
+pub mod energy;
+pub mod fan;
+pub mod fracture;
+pub mod frame;
+pub mod game;
+mod ghost;
+mod grab;
+pub mod gravity;
+pub mod hints;
+pub mod ids;
+pub mod instrument;
+mod laser;
+pub mod layer;
+pub mod load;
+pub mod magnet;
+pub mod measure;
+pub mod motor;
+mod narrowphase;
+pub mod origin;
+pub mod palette;
+pub mod particles;
+pub mod perf;
+pub mod prefab;
+pub mod pulley;
+pub mod recording;
+mod rope;
+pub mod sandbox;
+#[cfg(feature = "scripting")]
+pub mod script;
 pub mod shape;
+pub mod size;
+mod slots;
+pub mod snap;
+pub mod snapshot;
+pub mod soft_body;
+pub mod spring;
+pub mod terrain;
+pub mod time_zone;
+pub mod timestep;
+pub mod trail;
+pub mod tuning;
 
 const GRAVITY_COEFFICIENT: f64 = 0.00000981;
 const MOVEMENT_COEFFICIENT: f64 = 0.00004;
+/// the main ball respawns when it leaves the square of this half side centered on
+/// the origin, while other shapes are removed when they leave it through the top
+pub const WORLD_HALF_EXTENT: f64 = 5.0;
+/// side of the square of a flag, whose position is its corner with the lowest coordinates
+pub const FLAG_SIZE: f64 = 0.1;
 
-#[derive(Serialize, Deserialize, Tsify, Debug)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct WithColor<S> {
     pub color: [f32; 3],
     pub shape: S,
 }
 
-impl<S> From<S> for WithColor<S> {
-    fn from(shape: S) -> Self {
-        let mut rng = rand::thread_rng();
-
-        Self {
-            color: [
-                rng.gen_range(0.0..1.0),
-                rng.gen_range(0.0..1.0),
-                rng.gen_range(0.0..1.0),
-                // 0.0,
-                // 0.0,
-                // 0.0,
-            ],
-            shape,
-        }
-    }
+/// the next random number of [SplitMix64](https://prng.di.unimi.it/splitmix64.c),
+/// which spares the wasm module a dependency on the randomness of the host
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
-#[derive(Serialize, Deserialize, Tsify)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
+#[derive(Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct DisplayMessage {
     pub polygons: Vec<WithColor<geometry::Polygon>>,
+    /// the entities of the polygons, in the order of the polygons
+    pub polygon_entities: Vec<EntityId>,
+    /// the render layers of the polygons, in the order of the polygons, see [`layer`]
+    pub polygon_layers: Vec<i32>,
     pub circles: Vec<WithColor<geometry::Circle>>,
+    /// the entities of the circles, in the order of the circles
+    pub circle_entities: Vec<EntityId>,
+    /// the render layers of the circles, in the order of the circles
+    pub circle_layers: Vec<i32>,
+    /// the outlines of the soft bodies, see [`soft_body`]
+    pub soft_bodies: Vec<WithColor<geometry::Polygon>>,
+    /// the entities of the soft bodies, in the order of their outlines
+    pub soft_body_entities: Vec<EntityId>,
+    /// the nodes of the cloth, see [`cloth`]
+    pub cloths: Vec<ClothFrame>,
+    /// the positions of the particles as `x, y` pairs, see [`particles`]
+    pub particles: Vec<f64>,
+    /// the kinds of the particles, in the order of their positions
+    pub particle_kinds: Vec<ParticleKind>,
     pub flags: Vec<geometry::Polygon>,
     pub rigid_bindings: Vec<geometry::Point>,
+    /// the entities the anchors of the rigid bindings are on, in the order of the anchors,
+    /// and likewise for the other bindings
+    pub rigid_binding_entities: Vec<EntityId>,
+    /// the entities the rigid bindings connect the entities of their anchors to,
+    /// in the order of the anchors, and likewise for the hinges
+    pub rigid_binding_targets: Vec<EntityId>,
+    /// whether the rigid bindings took at least [`load::STRESSED`] of their break
+    /// impulse since the previous message, in the order of the anchors, and
+    /// likewise for the hinges
+    pub rigid_binding_stressed: Vec<bool>,
     pub hinges: Vec<Point>,
+    pub hinge_entities: Vec<EntityId>,
+    pub hinge_targets: Vec<EntityId>,
+    pub hinge_stressed: Vec<bool>,
+    /// the ends of the spring bindings, on the entity holding the spring and on its target
+    pub springs: Vec<[Point; 2]>,
+    pub spring_entities: Vec<EntityId>,
+    pub spring_targets: Vec<EntityId>,
+    /// the ends of the ropes, like the ones of the springs, with the max length
+    /// of every rope, it is slack while its ends are closer than that
+    pub ropes: Vec<[Point; 2]>,
+    pub rope_lengths: Vec<f64>,
+    pub rope_entities: Vec<EntityId>,
+    pub rope_targets: Vec<EntityId>,
+    /// points along every rope, in the order of the ropes, sagging below its ends
+    /// while it is slack, see [`curve`]
+    pub rope_curves: Vec<Vec<Point>>,
+    /// the rope of every pulley, from its end on the entity holding it
+    /// over its two anchors to its end on its target
+    pub pulleys: Vec<[Point; 4]>,
+    pub pulley_entities: Vec<EntityId>,
+    pub pulley_targets: Vec<EntityId>,
+    /// points along every run of links of a chain still hinged together, see [`curve`]
+    pub chain_curves: Vec<Vec<Point>>,
+    /// the links of the chains, which the curves may be drawn in place of
+    pub chain_links: Vec<EntityId>,
     pub unbound_rigid_bindings: Vec<Point>,
+    pub unbound_rigid_binding_entities: Vec<EntityId>,
     pub unbound_hinges: Vec<Point>,
+    pub unbound_hinge_entities: Vec<EntityId>,
+    pub flags_remaining: usize,
+    pub flags_total: usize,
+    pub state: GameState,
+    /// simulated seconds since the start of the level
+    pub elapsed_seconds: f64,
+    /// simulated seconds since the main ball was last (re)spawned
+    pub attempt_seconds: f64,
+    pub usage: Usage,
+    /// recent positions of the entities with trails enabled, the main ball by default
+    pub trails: Vec<Trail>,
+    /// path of the main ball during the previous attempt
+    pub ghost: Vec<Point>,
+    pub fans: Vec<Fan>,
+    pub lasers: Vec<Laser>,
+    pub time_zones: Vec<levels::TimeZone>,
+    pub gravity_pickups: Vec<levels::GravityPickup>,
+    /// seconds until the last gravity pickup wears off, if one is in effect
+    pub gravity_seconds_remaining: Option<f64>,
+    pub size_pickups: Vec<levels::SizePickup>,
+    /// seconds until the ball returns to its original size, if a size pickup is in effect
+    pub ball_size_seconds_remaining: Option<f64>,
+    pub torsion_springs: Vec<TorsionSpring>,
+    pub motors: Vec<Motor>,
+    pub magnets: Vec<Magnet>,
+    pub dispensers: Vec<Dispenser>,
+    /// the balls emitted by the dispensers, including the ones no longer in play
+    pub balls: Vec<TrackedBall>,
+    /// everything that happened since the previous message, in order
+    pub events: Vec<GameEvent>,
+    /// where the positions of the message are relative to, see [`Engine::shift_origin`]
+    pub origin: Point,
+    /// simulated time skipped since the previous message, as its steps were longer
+    /// than the engine catches up on, see [`timestep`]
+    pub dropped_microseconds: f64,
+    /// what the solver saw, only while debug drawing is enabled, see [`debug`]
+    pub debug: Option<DebugDraw>,
+    /// the energy of the bodies, see [`energy`]
+    pub energy: Energy,
+    /// the largest load of every binding since the previous message, see [`load`]
+    pub binding_loads: Vec<BindingLoad>,
+}
+
+/// What the editing tools would act on at a point
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct PointDescription {
+    /// the entity `erase_at` would remove, if it is erasable
+    pub entity: Option<EntityId>,
+    pub is_erasable: bool,
+    pub is_bindable: bool,
+    /// bound and unbound bindings drawn within [`ANCHOR_PICK_RADIUS`] of the point
+    pub anchors: Vec<BindingId>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum BindingKind {
+    Hinge,
+    Rigid,
+    Spring,
+    Rope,
+    Pulley,
+}
+
+/// A binding, bound or waiting for an entity to be added over it
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BindingInfo {
+    pub id: BindingId,
+    pub kind: BindingKind,
+    /// where the binding is drawn, the first end of a spring, a rope or a pulley
+    pub position: Point,
+    /// the entity holding the binding
+    pub entity: EntityId,
+    /// the entity it binds the one holding it to, `None` while it is unbound
+    pub target: Option<EntityId>,
+    /// see [`Engine::set_break_impulse`], `None` while it is unbound or unbreakable
+    pub break_impulse: Option<f64>,
+}
+
+/// how close to a binding a point has to be to refer to it
+pub const ANCHOR_PICK_RADIUS: f64 = 0.05;
+
+/// Physical state of a single body, mostly intended for tests and tooling
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BodyState {
+    pub id: EntityId,
+    pub is_static: bool,
+    pub centroid: Point,
+    pub angle: f64,
+    pub velocity: Vector,
+    pub angular_velocity: f64,
+    pub aabb: geometry::Aabb,
+}
+
+impl BodyState {
+    // 64 bit FNV-1a
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn of(entity: &Entity) -> Self {
+        let mut shape = entity.shape.borrow_mut();
+        let data = shape.collision_data_mut().clone();
+        BodyState {
+            id: entity.id,
+            is_static: entity.is_static,
+            centroid: data.centroid,
+            angle: shape.angle(),
+            velocity: data.velocity,
+            angular_velocity: data.angular_velocity,
+            aabb: shape.aabb(),
+        }
+    }
+
+    /// continues `hash` with the transform and velocity of the body
+    fn hash(&self, hash: u64) -> u64 {
+        [
+            self.centroid.0,
+            self.centroid.1,
+            self.angle,
+            self.velocity.0,
+            self.velocity.1,
+            self.angular_velocity,
+        ]
+        .map(f64::to_bits)
+        .into_iter()
+        .chain([self.id.0 as u64])
+        .flat_map(u64::to_le_bytes)
+        .fold(hash, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(Self::PRIME)
+        })
+    }
+}
+
+/// Mass and inertia of a single body. Every body has a uniform density,
+/// so its center of mass is its centroid
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct MassProperties {
+    pub id: EntityId,
+    /// `None` for static bodies, which are infinitely heavy
+    pub mass: Option<f64>,
+    /// moment of inertia about the centroid, `None` for static bodies
+    pub inertia: Option<f64>,
+    pub centroid: Point,
+    /// the mass relative to the mass of the main ball,
+    /// to tell how heavy a body is at a glance
+    pub relative_mass: Option<f64>,
+}
+
+/// the entities by the address of their shape, to find the entity of a shape in a list
+fn shape_entities(entities: &[Entity]) -> HashMap<*const c_void, &Entity> {
+    entities
+        .iter()
+        .map(|entity| ((&*entity.shape) as *const _ as *const c_void, entity))
+        .collect()
+}
+
+/// whether an impulse something breaks above is one to set, which neither a negative
+/// nor an infinite one is, the latter being what `None` stands for
+fn is_valid_break_threshold(impulse: Option<f64>) -> bool {
+    impulse.is_none_or(|impulse| impulse.is_finite() && impulse >= 0.0)
 }
 
-fn to_geometry<G>(
+/// overwrites `geometry_shapes` with the shapes that still exist, and `ids` and
+/// `layers` with their entities and render layers, forgetting the shapes that do not
+fn write_geometry<G>(
     shapes: &mut Vec<WithColor<Weak<RefCell<impl Into<G> + Clone>>>>,
-) -> Vec<WithColor<G>> {
-    let mut geometry_shapes = Vec::with_capacity(shapes.len());
+    entities: &HashMap<*const c_void, &Entity>,
+    geometry_shapes: &mut Vec<WithColor<G>>,
+    ids: &mut Vec<EntityId>,
+    layers: &mut Vec<i32>,
+) {
+    geometry_shapes.clear();
+    ids.clear();
+    layers.clear();
     shapes.retain(|colored_shape| {
-        if let Some(shape) = colored_shape.shape.upgrade() {
+        let entity = entities.get(&(colored_shape.shape.as_ptr() as *const c_void));
+        if let (Some(shape), Some(entity)) = (colored_shape.shape.upgrade(), entity) {
             geometry_shapes.push(WithColor {
                 color: colored_shape.color,
                 shape: shape.borrow().clone().into(),
             });
+            ids.push(entity.id);
+            layers.push(entity.render_layer);
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// like [`write_geometry`], but adds every part of the compounds as a polygon of its own
+/// after the ones already written
+fn write_parts(
+    compounds: &mut Vec<WithColor<Weak<RefCell<Compound>>>>,
+    entities: &HashMap<*const c_void, &Entity>,
+    polygons: &mut Vec<WithColor<geometry::Polygon>>,
+    ids: &mut Vec<EntityId>,
+    layers: &mut Vec<i32>,
+) {
+    compounds.retain(|colored_compound| {
+        let entity = entities.get(&(colored_compound.shape.as_ptr() as *const c_void));
+        if let (Some(compound), Some(entity)) = (colored_compound.shape.upgrade(), entity) {
+            for part in compound.borrow().parts().unwrap_or_default() {
+                polygons.push(WithColor {
+                    color: colored_compound.color,
+                    shape: part.clone().into(),
+                });
+                ids.push(entity.id);
+                layers.push(entity.render_layer);
+            }
             true
         } else {
             false
         }
     });
-    geometry_shapes
 }
 
 #[cfg(test)]
@@ -95,6 +440,10 @@ struct EntityCfg {
     is_erasable: bool,
     is_bindable: bool,
     is_static: bool,
+    durability: Durability,
+    bump_impulse: Option<f64>,
+    is_ferrous: bool,
+    render_layer: i32,
 }
 
 impl Default for EntityCfg {
@@ -103,65 +452,170 @@ impl Default for EntityCfg {
             is_erasable: true,
             is_bindable: true,
             is_static: false,
+            durability: Durability::default(),
+            bump_impulse: None,
+            is_ferrous: false,
+            render_layer: layer::DRAWN,
+        }
+    }
+}
+
+/// How an entity is worn down by the impulses of its contacts.
+/// Kept apart from the entity so that it can be hit while the shape is borrowed
+#[derive(Clone, Copy, Default)]
+struct Durability {
+    /// `None` for entities that do not wear down
+    hit_points: Option<f64>,
+    /// `None` for entities that do not shatter
+    breaking_impulse: Option<f64>,
+    /// `None` for entities that do not fracture, see [`fracture`]
+    fracture_impulse: Option<f64>,
+    is_shattered: bool,
+    is_fractured: bool,
+}
+
+impl Durability {
+    fn new(
+        hit_points: Option<f64>,
+        breaking_impulse: Option<f64>,
+        fracture_impulse: Option<f64>,
+    ) -> Self {
+        Self {
+            hit_points,
+            breaking_impulse,
+            fracture_impulse,
+            is_shattered: false,
+            is_fractured: false,
+        }
+    }
+
+    /// takes away as many hit points as the impulse of a contact,
+    /// shattering or fracturing the entity if the impulse is too strong
+    fn hit(&mut self, impulse: f64) {
+        if let Some(hit_points) = &mut self.hit_points {
+            *hit_points -= impulse;
+        }
+        if self
+            .breaking_impulse
+            .is_some_and(|breaking| impulse > breaking)
+        {
+            self.is_shattered = true;
+        }
+        if self
+            .fracture_impulse
+            .is_some_and(|fracture| impulse > fracture)
+        {
+            self.is_fractured = true;
+        }
+    }
+
+    /// the event of removing the entity, if it has been broken
+    fn removal(&self, id: EntityId) -> Option<GameEvent> {
+        if self.is_shattered {
+            Some(GameEvent::Shattered { id })
+        } else if self.is_fractured {
+            Some(GameEvent::Fractured { id })
+        } else if self.hit_points.is_some_and(|hit_points| hit_points <= 0.0) {
+            Some(GameEvent::Destroyed { id })
+        } else {
+            None
         }
     }
 }
 
+type WeakShape = Weak<RefCell<dyn Collidable>>;
+
 struct Entity {
-    bindings: Vec<(Binding, Weak<RefCell<dyn Collidable>>)>,
-    unbound: Vec<Unbound>,
+    id: EntityId,
+    bindings: Vec<(BindingId, Binding, WeakShape)>,
+    unbound: Vec<(BindingId, Unbound)>,
     is_erasable: bool,
     is_bindable: bool,
     is_static: bool,
+    durability: Durability,
+    bump_impulse: Option<f64>,
+    is_ferrous: bool,
+    /// see [`layer`]
+    render_layer: i32,
+    /// how fast time passes for the entity during the current step
+    time_scale: f64,
+    /// where the entity goes in the canonical order, see [`slots`]
+    order_key: u32,
     shape: Rc<RefCell<dyn Collidable>>,
 }
 
 impl Entity {
-    fn new(shape: Rc<RefCell<dyn Collidable>>, entity_type: EntityCfg) -> Self {
+    fn new(id: EntityId, shape: Rc<RefCell<dyn Collidable>>, entity_type: EntityCfg) -> Self {
         let EntityCfg {
             is_erasable,
             is_bindable,
             is_static,
+            durability,
+            bump_impulse,
+            is_ferrous,
+            render_layer,
         } = entity_type;
 
         Self {
+            id,
             bindings: vec![],
             unbound: vec![],
             shape,
             is_static,
             is_erasable,
             is_bindable,
+            durability,
+            bump_impulse,
+            is_ferrous,
+            render_layer,
+            time_scale: 1.0,
+            order_key: slots::UNPLACED,
         }
     }
 
-    fn add_rigid(&mut self, at: Point) {
-        self.unbound
-            .push(Unbound::new_rigid(&*self.shape.borrow(), at))
+    /// point query of the shape, skipping the exact test when outside of its bounding box
+    fn includes(&self, point: Point) -> bool {
+        let shape = self.shape.borrow();
+        shape.aabb().contains(point) && shape.includes(point)
+    }
+
+    fn add_rigid(&mut self, id: BindingId, at: Point, compliance: f64) {
+        self.unbound.push((
+            id,
+            Unbound::new_rigid(&*self.shape.borrow(), at, compliance),
+        ))
     }
 
-    fn add_hinge(&mut self, at: Point) {
+    fn add_hinge(&mut self, id: BindingId, at: Point, damping: f64) {
         self.unbound
-            .push(Unbound::new_hinge(&*self.shape.borrow(), at))
+            .push((id, Unbound::new_hinge(&*self.shape.borrow(), at, damping)))
     }
 
-    fn try_bind(&mut self, target: &Rc<RefCell<dyn Collidable>>) {
-        self.unbound.retain(|unbound| {
+    /// returns whether any of the unbound bindings attached to `target`
+    fn try_bind(&mut self, target: &Rc<RefCell<dyn Collidable>>) -> bool {
+        let bindings_before = self.bindings.len();
+        self.unbound.retain(|&(id, unbound)| {
             if let Some(binding) =
-                Binding::try_bind(&*self.shape.borrow_mut(), *unbound, &*target.borrow_mut())
+                Binding::try_bind(&*self.shape.borrow_mut(), unbound, &*target.borrow_mut())
             {
-                self.bindings.push((binding, Rc::downgrade(target)));
+                self.bindings.push((id, binding, Rc::downgrade(target)));
                 false
             } else {
                 true
             }
-        })
+        });
+        self.bindings.len() > bindings_before
     }
 }
 
 pub struct Engine {
-    // each entity may contain bidings with pointers to entities
-    // ocurring later in the vector
+    // each entity may contain bidings with pointers to other entities,
+    // usually ones added after it, though removals may reorder them
     entities: Vec<Entity>,
+    /// the index of every entity in `entities`
+    slots: Slots,
+    /// counts up to the spatial order interval of the config
+    steps_since_reorder: u32,
     // circles and polygons kept separate on the side,
     // because that's how they need to be passed to the graphics.
     // The Rc<RefCell<_>> is pretty much unavoidable,
@@ -170,237 +624,871 @@ pub struct Engine {
     // weak pointers then they would have to be manually updated after removing an entity
     polygons: Vec<WithColor<Weak<RefCell<Polygon>>>>,
     circles: Vec<WithColor<Weak<RefCell<Circle>>>>,
+    compounds: Vec<WithColor<Weak<RefCell<Compound>>>>,
+    soft_bodies: Vec<WithColor<SoftBody>>,
+    particles: Particles,
+    cloths: Vec<Cloth>,
+    chains: Vec<ChainPath>,
+    /// the bindings placed with the tools, see [`Engine::cut_along`]
+    placed_bindings: Vec<BindingId>,
     main_ball_starting_position: Point,
+    main_ball: MainBall,
+    /// `None` in an unbounded world, in the coordinates of the level
+    world_half_extent: Option<f64>,
+    /// in the coordinates of the level, everything else is relative to it
+    origin: Point,
+    origin_shift_distance: Option<f64>,
     flags: Vec<Polygon>,
+    flags_total: usize,
     restitution_mulipiler: f64,
     friction_mulipiler: f64,
     gravity_mulipiler: f64,
     static_friction_enabled: bool,
     dynamic_friction_enabled: bool,
+    solver_tuning: SolverTuning,
+    config: EngineConfig,
+    snapping: Snapping,
+    painter: Painter,
+    entity_ids: IdGenerator,
+    binding_ids: IdGenerator,
+    game: Game,
+    events: Vec<GameEvent>,
+    hints: Hints,
+    achievements: Achievements,
+    bombs: Vec<ArmedBomb>,
+    fans: Vec<Fan>,
+    lasers: Vec<Laser>,
+    time_zones: Vec<TimeZone>,
+    gravity: Gravity,
+    ball_size: BallSize,
+    springs: Vec<TorsionSpring>,
+    spring_config: SpringConfig,
+    grab: Option<Grab>,
+    motors: Vec<Motor>,
+    magnets: Vec<Magnet>,
+    dispensers: Vec<Dispenser>,
+    balls: Vec<TrackedBall>,
+    region_ids: IdGenerator,
+    ghost: Ghost,
+    trails: Trails,
+    perf: PerfStats,
+    /// arena for the temporary data of a step, reset at the start of every step
+    /// so that it stops allocating once it is large enough
+    scratch: Bump,
+    warm_starts: WarmStarts,
+    /// the contacts of the last step, `None` unless debug drawing is enabled
+    debug_contacts: Option<Vec<DebugContact>>,
+    /// taken away by the contacts and bindings since the last message
+    dissipated_energy: f64,
+    /// the largest loads of the bindings since the last message
+    binding_loads: BindingLoads,
+    /// the hashes of the bodies after every step, `None` unless recording them
+    divergence_log: Option<Vec<BodyHashes>>,
+    /// the run being recorded, `None` unless recording
+    recorder: Option<Box<Recorder>>,
+    /// the trace being taken, `None` unless tracing
+    tracer: Option<Box<Tracer>>,
+    /// the script of the level, `None` without one or once it failed
+    #[cfg(feature = "scripting")]
+    script: Option<Box<script::LevelScript>>,
+    #[cfg(feature = "scripting")]
+    script_error: Option<String>,
+    /// the level the engine was loaded from, which the editing methods change,
+    /// `None` when restored from a snapshot that predates them
+    level: Option<Level>,
+    level_ids: LevelIds,
 }
 
 impl Engine {
-    pub fn new(
-        Level {
+    pub fn new(level: Level) -> Self {
+        let source = level.clone();
+        #[cfg(feature = "scripting")]
+        let (level_script, script_error) = script::load(source.script.as_deref());
+        let Level {
             initial_ball_position,
+            main_ball,
             circles,
             polygons,
+            terrain,
             flags_positions,
-        }: Level,
-    ) -> Self {
+            time_limit,
+            stars,
+            tool_limits,
+            rigid_compliance: _,
+            hints,
+            bombs,
+            fans,
+            lasers,
+            time_zones,
+            gravity_pickups,
+            size_pickups,
+            wrecking_balls,
+            magnets,
+            dispensers,
+            world_half_extent,
+            script: _,
+        } = level;
         let n_of_circles = circles.len() + 1;
         let n_of_polygons = polygons.len();
+        let flags_total = flags_positions.len();
 
         let mut engine = Self {
             entities: Vec::with_capacity(n_of_circles + n_of_polygons),
+            slots: Slots::default(),
+            steps_since_reorder: 0,
             circles: Vec::with_capacity(n_of_circles),
+            compounds: vec![],
             polygons: Vec::with_capacity(n_of_polygons),
+            soft_bodies: vec![],
+            particles: Particles::default(),
+            cloths: vec![],
+            chains: vec![],
+            placed_bindings: vec![],
             main_ball_starting_position: initial_ball_position,
+            main_ball,
+            world_half_extent,
+            origin: Point::ZERO,
+            origin_shift_distance: None,
             flags: flags_positions
                 .into_iter()
                 .map(|Point(x, y)| {
                     Polygon::new(vec![
                         geometry::Point(x, y),
-                        geometry::Point(x + 0.1, y),
-                        geometry::Point(x + 0.1, y + 0.1),
-                        geometry::Point(x, y + 0.1),
+                        geometry::Point(x + FLAG_SIZE, y),
+                        geometry::Point(x + FLAG_SIZE, y + FLAG_SIZE),
+                        geometry::Point(x, y + FLAG_SIZE),
                     ])
                 })
                 .collect(),
+            flags_total,
             friction_mulipiler: 1.0,
             restitution_mulipiler: 1.0,
             gravity_mulipiler: 1.0,
             dynamic_friction_enabled: true,
             static_friction_enabled: true,
+            solver_tuning: SolverTuning::default(),
+            config: EngineConfig::default(),
+            snapping: Snapping::default(),
+            painter: Painter::default(),
+            entity_ids: IdGenerator::default(),
+            binding_ids: IdGenerator::default(),
+            game: Game::new(time_limit, stars, tool_limits),
+            events: vec![],
+            hints: Hints::new(hints),
+            achievements: Achievements::default(),
+            bombs: vec![],
+            fans: vec![],
+            lasers,
+            time_zones: time_zones.into_iter().map(Into::into).collect(),
+            gravity: Gravity::new(gravity_pickups),
+            ball_size: BallSize::new(size_pickups),
+            springs: vec![],
+            spring_config: SpringConfig::default(),
+            grab: None,
+            motors: vec![],
+            magnets: vec![],
+            dispensers: vec![],
+            balls: vec![],
+            region_ids: IdGenerator::default(),
+            ghost: Ghost::default(),
+            trails: Trails::default(),
+            perf: PerfStats::default(),
+            scratch: Bump::new(),
+            warm_starts: WarmStarts::default(),
+            debug_contacts: None,
+            dissipated_energy: 0.0,
+            binding_loads: BindingLoads::default(),
+            divergence_log: None,
+            recorder: None,
+            tracer: None,
+            #[cfg(feature = "scripting")]
+            script: level_script,
+            #[cfg(feature = "scripting")]
+            script_error,
+            level: Some(source),
+            level_ids: LevelIds::default(),
         };
 
-        let main_ball_weak = engine.add_entity(
-            Circle::new(initial_ball_position, 0.1),
-            EntityCfg {
-                is_bindable: true,
-                is_erasable: false,
-                is_static: false,
-            },
+        let main_ball_id = engine.add_ball(
+            ball::shape(
+                initial_ball_position,
+                main_ball.radius,
+                main_ball.density,
+                main_ball.initial_velocity,
+            ),
+            main_ball.color,
         );
-        engine.circles.push(main_ball_weak.into());
+        engine.trails.track(main_ball_id);
 
         for entity in polygons {
-            let weak = engine.add_entity(
+            let (id, weak) = engine.add_entity(
                 Polygon::new(entity.shape),
                 EntityCfg {
                     is_bindable: entity.is_bindable,
                     is_static: entity.is_static,
-                    is_erasable: false,
+                    is_erasable: entity.is_erasable,
+                    durability: Durability::new(
+                        entity.hit_points,
+                        entity.breaking_impulse,
+                        entity.fracture_impulse,
+                    ),
+                    bump_impulse: entity.bump_impulse,
+                    is_ferrous: entity.is_ferrous,
+                    render_layer: entity.render_layer,
                 },
             );
-            engine.polygons.push(weak.into())
+            engine.polygons.push(engine.painter.paint(weak));
+            engine.level_ids.polygons.push(id);
         }
 
         for entity in circles {
             let geometry::Circle { center, radius } = entity.shape;
-            let weak = engine.add_entity(
+            let (id, weak) = engine.add_entity(
                 Circle::new(center, radius),
                 EntityCfg {
                     is_bindable: entity.is_bindable,
                     is_static: entity.is_static,
+                    is_erasable: entity.is_erasable,
+                    durability: Durability::new(
+                        entity.hit_points,
+                        entity.breaking_impulse,
+                        entity.fracture_impulse,
+                    ),
+                    bump_impulse: entity.bump_impulse,
+                    is_ferrous: entity.is_ferrous,
+                    render_layer: entity.render_layer,
+                },
+            );
+            engine.circles.push(engine.painter.paint(weak));
+            engine.level_ids.circles.push(id);
+        }
+
+        for chain in &terrain {
+            engine.add_terrain(chain);
+        }
+
+        for bomb in bombs {
+            let (id, weak) = engine.add_entity(
+                Circle::new(bomb.position, BOMB_RADIUS),
+                EntityCfg {
+                    is_bindable: true,
+                    is_static: false,
+                    is_erasable: false,
+                    durability: Durability::new(None, bomb.detonation_impulse, None),
+                    bump_impulse: None,
+                    is_ferrous: false,
+                    render_layer: layer::TERRAIN,
+                },
+            );
+            engine.circles.push(engine.painter.paint(weak));
+            engine.bombs.push(ArmedBomb::new(id, &bomb));
+        }
+
+        for magnet in magnets {
+            let (id, weak) = engine.add_entity(
+                Circle::new(magnet.position, magnet.radius),
+                EntityCfg {
+                    is_static: magnet.is_static,
                     is_erasable: false,
+                    render_layer: layer::TERRAIN,
+                    ..Default::default()
                 },
             );
-            engine.circles.push(weak.into())
+            engine.circles.push(engine.painter.paint(weak));
+            engine.magnets.push(Magnet::new(id, &magnet));
+        }
+
+        for wrecking_ball in &wrecking_balls {
+            let first = engine.entities.len();
+            engine.add_wrecking_ball(wrecking_ball);
+            // unlike the prefabs placed in the editor, the ones of the level are fixed
+            for entity in &mut engine.entities[first..] {
+                entity.is_erasable = false;
+                entity.render_layer = layer::TERRAIN;
+            }
+        }
+
+        for fan in fans {
+            let id = engine.region_ids.generate();
+            engine.fans.push(Fan::new(id, &fan));
+        }
+
+        for dispenser in dispensers {
+            let id = engine.region_ids.generate();
+            engine.dispensers.push(Dispenser::new(id, &dispenser));
         }
 
-        engine.prune_and_send_shapes();
+        engine.prune_and_write_shapes(&mut DisplayMessage::default());
         engine
     }
 
     pub fn run_iteration(&mut self, microseconds: f64) -> DisplayMessage {
-        // move all shapes, removing ones out of bounds
-        // don't remove the first one though, as it's the main ball
-        let mut is_main_ball = true;
-        self.entities.retain_mut(|entity| {
-            let mut shape = entity.shape.borrow_mut();
+        self.run_n_iterations(1, microseconds)
+    }
 
-            if !entity.is_static {
-                shape.update_position(microseconds, self.gravity_mulipiler);
-            }
+    /// advances the simulation by `count` steps of `microseconds` each,
+    /// building the display message only once, after the last step
+    pub fn run_n_iterations(&mut self, count: u32, microseconds: f64) -> DisplayMessage {
+        let mut message = DisplayMessage::default();
+        self.run_n_iterations_into(count, microseconds, &mut message);
+        message
+    }
 
-            let retain = shape.collision_data_mut().centroid.1 > -5.0 || is_main_ball;
-            is_main_ball = false;
-            retain
-        });
+    /// like [`Engine::run_n_iterations`], but overwrites `message` in place,
+    /// reusing the buffers it holds instead of allocating new ones every frame
+    pub fn run_n_iterations_into(
+        &mut self,
+        count: u32,
+        microseconds: f64,
+        message: &mut DisplayMessage,
+    ) {
+        let start = perf::now();
+        self.perf = PerfStats::default();
+        perf::take_gjk_iterations();
+
+        let substeps = timestep::substeps(&self.config, microseconds);
+        self.perf.steps = count * substeps.count;
+        for _ in 0..count * substeps.count {
+            self.step(substeps.microseconds);
+        }
+        message.dropped_microseconds = count as f64 * substeps.dropped_microseconds;
+
+        let display_start = perf::now();
+        self.prune_and_write_shapes(message);
+        let end = perf::now();
+
+        self.perf.display_ms = end - display_start;
+        self.perf.total_ms = end - start;
+        self.perf.gjk_iterations = perf::take_gjk_iterations();
+    }
+
+    /// the state of every body, in the same order as they are simulated,
+    /// starting with the main ball
+    pub fn body_states(&self) -> Vec<BodyState> {
+        self.entities.iter().map(BodyState::of).collect()
+    }
+
+    /// the mass properties of every body, in the same order as [`Engine::body_states`]
+    pub fn mass_properties(&self) -> Vec<MassProperties> {
+        let finite = |value: f64| value.is_finite().then_some(value);
+        let ball_mass = self.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .mass;
+        self.entities
+            .iter()
+            .map(|entity| {
+                let mut shape = entity.shape.borrow_mut();
+                let data = shape.collision_data_mut();
+                MassProperties {
+                    id: entity.id,
+                    mass: finite(data.mass),
+                    inertia: finite(data.inertia),
+                    centroid: data.centroid,
+                    relative_mass: finite(data.mass / ball_mass),
+                }
+            })
+            .collect()
+    }
+
+    /// a checksum of the transforms and velocities of all bodies.
+    /// Unlike `std`'s hashers it is stable across platforms and compiler versions,
+    /// so it can be compared between replays or different machines
+    pub fn state_hash(&self) -> u64 {
+        self.body_states()
+            .iter()
+            .fold(BodyState::OFFSET_BASIS, |hash, state| state.hash(hash))
+    }
+
+    /// timings and counters of the last call to `run_iteration` or `run_n_iterations`
+    pub fn perf_stats(&self) -> PerfStats {
+        self.perf
+    }
 
-        // return main ball to starting point if out of bounds
-        // and check win condition
+    fn step(&mut self, microseconds: f64) {
+        span!(DEBUG, "step", microseconds);
+        self.scratch.reset();
+        #[cfg(feature = "scripting")]
+        let events = self.events.len();
+        self.restore_canonical_order();
+        let integration_start = perf::now();
+
+        // move all shapes, removing ones out of bounds
+        // don't remove the first one though, as it's the main ball,
+        // nor the dispensed balls, which follow the policy of their dispensers
+        let gravity = self.gravity.vector(self.gravity_mulipiler);
+        {
+            let mut bodies = Bodies::gather(&self.entities, &self.scratch);
+            bodies.integrate(&mut self.entities, microseconds, gravity, &self.time_zones);
+            let mut i = 0;
+            self.entities.retain(|entity| {
+                let in_level = bodies.centroids[i] + self.origin;
+                let is_ball = i == 0
+                    || self
+                        .balls
+                        .iter()
+                        .any(|ball| ball.id == entity.id && ball.status == BallStatus::InPlay);
+                i += 1;
+                is_ball
+                    || self
+                        .world_half_extent
+                        .is_none_or(|extent| in_level.1 > -extent)
+            });
+        }
+        self.renumber_slots();
+
+        // return main ball to starting point if out of bounds or hit by a laser
+        // and collect the flags it touches
         {
             let mut ball = self.entities[0].shape.borrow_mut();
+            let is_lasered = self
+                .lasers
+                .iter()
+                .any(|laser| laser.resets_ball && compute::intersects(&*ball, laser));
             let data = ball.collision_data_mut();
 
-            if data.centroid.0.abs() > 5.0 || data.centroid.1 < -5.0 {
+            if self.is_out_of_world(data.centroid) || is_lasered {
+                event!(DEBUG, centroid = ?data.centroid, is_lasered, "main ball respawning");
                 data.centroid = self.main_ball_starting_position;
                 data.angular_velocity = 0.0;
-                data.velocity = Vector::ZERO;
+                data.velocity = self.main_ball.initial_velocity;
+                self.game.restart_attempt();
+                self.ghost.finish_attempt();
+                self.achievements.ball_respawned();
             }
+            self.ghost.record(microseconds, data.centroid);
+            self.hints
+                .check(data.centroid, self.game.failed_attempts(), &mut self.events);
+            self.gravity.advance(microseconds, &mut self.events);
+            self.gravity.collect(&*ball, &mut self.events);
+            self.ball_size
+                .advance(microseconds, &mut *ball, &mut self.events);
+            self.ball_size.collect(&mut *ball, &mut self.events);
 
+            let flags_before = self.flags.len();
             self.flags
                 .retain(|flag| compute::collision(&*ball, flag).is_none());
 
-            // if self.flags.is_empty() {
-            //     alert("you win");
-            // }
+            if self.flags.len() < flags_before {
+                event!(INFO, remaining = self.flags.len(), "flag collected");
+                self.events.push(GameEvent::FlagCollected {
+                    remaining: self.flags.len(),
+                });
+            }
+        }
+        self.check_balls();
+        self.run_dispensers(microseconds);
+
+        // lasers vaporize the player-drawn shapes crossing them
+        let lasers = &self.lasers;
+        self.entities.retain(|entity| {
+            let is_vaporized = entity.is_erasable
+                && lasers
+                    .iter()
+                    .any(|laser| compute::intersects(&*entity.shape.borrow(), laser));
+            if is_vaporized {
+                event!(DEBUG, id = ?entity.id, "entity vaporized");
+                self.events.push(GameEvent::Vaporized { id: entity.id });
+            }
+            !is_vaporized
+        });
+        self.renumber_slots();
+
+        if let Some(transition) =
+            self.game
+                .advance(microseconds, self.flags.len(), self.flags_total)
+        {
+            event!(INFO, ?transition, "game state changed");
+            self.events.push(transition);
+            if let GameEvent::Won { score } = transition {
+                self.achievements.won(score.usage, &mut self.events);
+            }
+        }
+
+        self.trails.record(&self.entities);
+        for fan in &self.fans {
+            fan.blow(&self.entities, microseconds);
         }
+        self.enforce_springs(microseconds);
+        self.enforce_motors();
+        self.attract();
+        self.drag_grabbed(microseconds);
+        self.achievements.record_structures(
+            microseconds,
+            &self.entities,
+            &self.scratch,
+            &mut self.events,
+        );
 
-        // iterate over all pairs of shapes
+        let pairs_start = perf::now();
+        self.perf.integration_ms += pairs_start - integration_start;
+
+        // for now every pair that is not bound together is a candidate
+        let mut is_ball_in_contact = false;
+        let energy_before = energy::mechanical_energy(&self.entities, gravity, self.origin);
         {
+            span!(DEBUG, "broadphase", entities = self.entities.len());
+            let pairs = narrowphase::candidate_pairs(&mut self.entities, &self.scratch);
+            self.perf.pairs_tested += pairs.len() as u32;
+            let contacts =
+                narrowphase::contacts(&self.entities, &pairs, &mut self.warm_starts, &self.scratch);
+            debug::record_contacts(&mut self.debug_contacts, &self.entities, &pairs, &contacts);
+            let mut contacts = pairs.iter().copied().zip(contacts).peekable();
+
             let mut i = 0;
             while let [this, rest @ ..] = &mut self.entities[i..] {
                 let mut shape = this.shape.borrow_mut();
 
-                // collide them if they are not bound
-                rest.iter_mut().for_each(|other| {
-                    let mut is_boud_to_other = false;
-                    this.bindings.retain(|(_, target)| {
-                        let valid = target.strong_count() > 0;
-                        if valid {
-                            is_boud_to_other = is_boud_to_other
-                                || std::ptr::eq(
-                                    target.as_ptr() as *const c_void,
-                                    (&*other.shape) as *const _ as *const c_void,
-                                )
-                        }
-                        valid
-                    });
-
-                    if !is_boud_to_other {
-                        shape.collide(
+                // resolve the contacts with the later entities, in order
+                while let Some(((_, j), contact)) = contacts.next_if(|((first, _), _)| *first == i)
+                {
+                    let other = &mut rest[j - i - 1];
+                    // the material of the main ball applies to all its contacts
+                    let (restitution, friction) = if i == 0 {
+                        (self.main_ball.restitution, self.main_ball.friction)
+                    } else {
+                        (1.0, 1.0)
+                    };
+                    let impulse = contact.map(|contact| {
+                        shape.resolve_collision_with(
                             &mut *other.shape.borrow_mut(),
-                            microseconds,
-                            self.restitution_mulipiler,
-                            self.friction_mulipiler,
+                            contact,
+                            microseconds * this.time_scale.min(other.time_scale),
+                            &self.solver_tuning,
+                            self.restitution_mulipiler * restitution,
+                            self.friction_mulipiler * friction,
                             self.static_friction_enabled,
                             self.dynamic_friction_enabled,
                         )
+                    });
+                    let in_contact = impulse.is_some();
+                    if let (Some(tracer), Some(impulse)) = (&mut self.tracer, impulse) {
+                        tracer.add_impulse((this.id, other.id), impulse);
                     }
-                });
+                    #[cfg(feature = "scripting")]
+                    if let (Some(script), Some(impulse)) = (&mut self.script, impulse) {
+                        script.contact(this.id, other.id, impulse);
+                    }
+                    self.perf.contacts_resolved += in_contact as u32;
 
-                // enforce binding constraints
-                this.bindings.iter().for_each(|(binding, target)| {
-                    if let Some(other) = target.upgrade() {
-                        binding.enforce(
-                            &mut *shape,
-                            &mut *other.borrow_mut(),
-                            microseconds,
-                            self.restitution_mulipiler,
-                            self.friction_mulipiler,
-                            self.static_friction_enabled,
-                            self.dynamic_friction_enabled,
-                        )
+                    if in_contact {
+                        let mut other_shape = other.shape.borrow_mut();
+                        if let Some(bump_impulse) = this.bump_impulse {
+                            bumper::bump(&mut *shape, &mut *other_shape, bump_impulse);
+                            self.events.push(GameEvent::Bumped { id: this.id });
+                        }
+                        if let Some(bump_impulse) = other.bump_impulse {
+                            bumper::bump(&mut *other_shape, &mut *shape, bump_impulse);
+                            self.events.push(GameEvent::Bumped { id: other.id });
+                        }
+                    }
+
+                    // overlapping static shapes exchange an infinite impulse,
+                    // which should not destroy them
+                    if let Some(impulse) = impulse.filter(|impulse| impulse.is_finite()) {
+                        this.durability.hit(impulse);
+                        other.durability.hit(impulse);
+                    }
+                    // the main ball is always first, so it is never the `other`
+                    is_ball_in_contact = is_ball_in_contact || (in_contact && i == 0);
+                }
+
+                // enforce binding constraints, breaking the ones that took too large an impulse
+                this.bindings.retain(|(id, binding, target)| {
+                    let Some(other) = target.upgrade() else {
+                        return true;
+                    };
+                    self.perf.bindings_enforced += 1;
+                    let mut other = other.borrow_mut();
+                    let time_scale = this.time_scale.min(time_scale_at(
+                        &self.time_zones,
+                        other.collision_data_mut().centroid,
+                    ));
+                    let scaled_microseconds = microseconds * time_scale;
+                    let impulse = binding.enforce(
+                        &mut *shape,
+                        &mut *other,
+                        scaled_microseconds,
+                        &self.solver_tuning,
+                        self.restitution_mulipiler,
+                        self.friction_mulipiler,
+                        self.static_friction_enabled,
+                        self.dynamic_friction_enabled,
+                    );
+                    self.binding_loads.record(
+                        *id,
+                        impulse,
+                        scaled_microseconds,
+                        binding.break_impulse(),
+                    );
+
+                    // static shapes bound together exchange an infinite impulse, like overlapping ones
+                    let is_broken = impulse.is_finite()
+                        && binding.break_impulse().is_some_and(|max| impulse > max);
+                    if is_broken {
+                        event!(DEBUG, ?id, impulse, "binding broken");
+                        self.events.push(GameEvent::BindingBroken { id: *id });
+                        magnet::release(&mut self.magnets, *id);
                     }
+                    !is_broken
                 });
 
                 i += 1;
             }
         }
 
-        self.prune_and_send_shapes()
+        self.step_soft_bodies(microseconds, gravity);
+        self.step_particles(microseconds, gravity);
+        self.step_cloths(microseconds, gravity);
+        self.dissipated_energy +=
+            energy_before - energy::mechanical_energy(&self.entities, gravity, self.origin);
+        self.achievements
+            .record_airtime(microseconds, is_ball_in_contact, &mut self.events);
+        self.detonate_bombs(microseconds);
+
+        // the main ball cannot break, so it is never removed here
+        let pieces = self.fracture_polygons();
+        self.entities.retain(|entity| {
+            let removal = entity.durability.removal(entity.id);
+            if let Some(removal) = removal {
+                event!(DEBUG, ?removal, "entity broken");
+                self.events.push(removal);
+            }
+            removal.is_none()
+        });
+        self.renumber_slots();
+        self.add_pieces(pieces);
+        self.perf.pairs_ms += perf::now() - pairs_start;
+
+        #[cfg(feature = "scripting")]
+        self.run_script(events);
+        self.follow_ball_with_origin();
+        divergence::record_hashes(&mut self.divergence_log, &self.entities);
+        if let Some(tracer) = &mut self.tracer {
+            let seconds = self.game.elapsed_seconds();
+            tracer.record(&self.entities, &self.slots, seconds, self.origin);
+        }
     }
 
-    fn prune_and_send_shapes(&mut self) -> DisplayMessage {
-        let mut rigid_bindings = Vec::new();
-        let mut hinges = Vec::new();
-        let mut unbound_rigid_bindings = Vec::new();
-        let mut unbound_hinges = Vec::new();
+    fn prune_and_write_shapes(&mut self, message: &mut DisplayMessage) {
+        message.rigid_bindings.clear();
+        message.hinges.clear();
+        message.unbound_rigid_bindings.clear();
+        message.unbound_hinges.clear();
+        message.rigid_binding_entities.clear();
+        message.hinge_entities.clear();
+        message.rigid_binding_targets.clear();
+        message.hinge_targets.clear();
+        message.rigid_binding_stressed.clear();
+        message.hinge_stressed.clear();
+        message.springs.clear();
+        message.spring_entities.clear();
+        message.spring_targets.clear();
+        message.ropes.clear();
+        message.rope_lengths.clear();
+        message.rope_entities.clear();
+        message.rope_targets.clear();
+        message.pulleys.clear();
+        message.pulley_entities.clear();
+        message.pulley_targets.clear();
+        message.unbound_rigid_binding_entities.clear();
+        message.unbound_hinge_entities.clear();
 
+        let entities = shape_entities(&self.entities);
         for Entity {
+            id,
             bindings,
             unbound,
             shape,
             ..
         } in &self.entities
         {
-            for (binding, _) in bindings {
-                match binding {
-                    Binding::Hinge { first, .. } => hinges.push(first.on(&*shape.borrow())),
-                    Binding::Rigid {
-                        first: (p1, p2), ..
-                    } => {
-                        let shape = shape.borrow();
-                        rigid_bindings.push((p1.on(&*shape) + p2.on(&*shape)) * 0.5)
+            let shape = shape.borrow();
+
+            // a binding whose target was just removed is pruned before the next step
+            for (binding_id, binding, target) in bindings {
+                let Some(target) = entities.get(&(target.as_ptr() as *const c_void)) else {
+                    continue;
+                };
+                let (anchors, ids, targets, stressed) = match binding {
+                    Binding::Hinge { .. } => (
+                        &mut message.hinges,
+                        &mut message.hinge_entities,
+                        &mut message.hinge_targets,
+                        &mut message.hinge_stressed,
+                    ),
+                    Binding::Rigid { .. } => (
+                        &mut message.rigid_bindings,
+                        &mut message.rigid_binding_entities,
+                        &mut message.rigid_binding_targets,
+                        &mut message.rigid_binding_stressed,
+                    ),
+                    Binding::Spring { .. } => {
+                        let target_shape = target.shape.borrow();
+                        if let Some(ends) = binding.ends(&*shape, &*target_shape) {
+                            message.springs.push(ends);
+                            message.spring_entities.push(*id);
+                            message.spring_targets.push(target.id);
+                        }
+                        continue;
                     }
-                }
+                    Binding::Rope { max_length, .. } => {
+                        let target_shape = target.shape.borrow();
+                        if let Some(ends) = binding.ends(&*shape, &*target_shape) {
+                            message.ropes.push(ends);
+                            message.rope_lengths.push(*max_length);
+                            message.rope_entities.push(*id);
+                            message.rope_targets.push(target.id);
+                        }
+                        continue;
+                    }
+                    Binding::Pulley { .. } => {
+                        let target_shape = target.shape.borrow();
+                        if let Some(path) = binding.pulley_path(&*shape, &*target_shape) {
+                            message.pulleys.push(path);
+                            message.pulley_entities.push(*id);
+                            message.pulley_targets.push(target.id);
+                        }
+                        continue;
+                    }
+                };
+                anchors.push(binding.anchor(&*shape));
+                ids.push(*id);
+                targets.push(target.id);
+                stressed.push(
+                    self.binding_loads
+                        .stress(*binding_id)
+                        .is_some_and(|stress| stress >= load::STRESSED),
+                );
             }
 
-            for binding in unbound {
-                match binding {
-                    Unbound::Hinge(point) => unbound_hinges.push(point.on(&*shape.borrow())),
-                    Unbound::Rigid(point) => {
-                        unbound_rigid_bindings.push(point.on(&*shape.borrow()))
-                    }
-                }
+            for (_, binding) in unbound {
+                let (anchors, ids) = match binding {
+                    Unbound::Hinge { .. } => (
+                        &mut message.unbound_hinges,
+                        &mut message.unbound_hinge_entities,
+                    ),
+                    Unbound::Rigid { .. } => (
+                        &mut message.unbound_rigid_bindings,
+                        &mut message.unbound_rigid_binding_entities,
+                    ),
+                };
+                anchors.push(binding.anchor(&*shape));
+                ids.push(*id);
             }
         }
 
-        DisplayMessage {
-            polygons: to_geometry(&mut self.polygons),
-            circles: to_geometry(&mut self.circles),
-            flags: self.flags.iter().cloned().map(Into::into).collect(),
-            rigid_bindings,
-            hinges,
-            unbound_rigid_bindings,
-            unbound_hinges,
-        }
+        write_geometry(
+            &mut self.polygons,
+            &entities,
+            &mut message.polygons,
+            &mut message.polygon_entities,
+            &mut message.polygon_layers,
+        );
+        write_parts(
+            &mut self.compounds,
+            &entities,
+            &mut message.polygons,
+            &mut message.polygon_entities,
+            &mut message.polygon_layers,
+        );
+        write_geometry(
+            &mut self.circles,
+            &entities,
+            &mut message.circles,
+            &mut message.circle_entities,
+            &mut message.circle_layers,
+        );
+        self.write_soft_bodies(&mut message.soft_bodies, &mut message.soft_body_entities);
+        self.write_cloths(&mut message.cloths);
+        self.write_particles(&mut message.particles, &mut message.particle_kinds);
+        self.write_curves(message);
+        message.flags.clear();
+        message
+            .flags
+            .extend(self.flags.iter().cloned().map(Into::into));
+        message.flags_remaining = self.flags.len();
+        message.flags_total = self.flags_total;
+        message.origin = self.origin;
+        message.state = self.game.state();
+        message.elapsed_seconds = self.game.elapsed_seconds();
+        message.attempt_seconds = self.game.attempt_seconds();
+        message.usage = self.game.usage();
+        self.trails.write_display(&mut message.trails);
+        message.ghost.clear();
+        message.ghost.extend_from_slice(self.ghost.previous());
+        message.fans.clone_from(&self.fans);
+        message.lasers.clone_from(&self.lasers);
+        message.time_zones.clear();
+        message
+            .time_zones
+            .extend(self.time_zones.iter().map(Into::into));
+        message.gravity_pickups.clear();
+        message
+            .gravity_pickups
+            .extend_from_slice(self.gravity.pickups());
+        message.gravity_seconds_remaining = self.gravity.seconds_remaining();
+        message.size_pickups.clear();
+        message
+            .size_pickups
+            .extend_from_slice(self.ball_size.pickups());
+        message.ball_size_seconds_remaining = self.ball_size.seconds_remaining();
+        message.torsion_springs.clone_from(&self.springs);
+        message.motors.clone_from(&self.motors);
+        message.magnets.clone_from(&self.magnets);
+        message.dispensers.clone_from(&self.dispensers);
+        message.balls.clone_from(&self.balls);
+        message.events.clear();
+        message.events.append(&mut self.events);
+        message.debug = self.debug_draw();
+        message.energy = self.take_energy();
+        self.binding_loads.take_into(&mut message.binding_loads);
+        self.record_keyframe();
+    }
+
+    pub fn game_state(&self) -> GameState {
+        self.game.state()
+    }
+
+    pub fn usage(&self) -> Usage {
+        self.game.usage()
+    }
+
+    pub fn tool_limits(&self) -> ToolLimits {
+        self.game.tool_limits()
     }
 
     pub fn try_bind(&mut self, new_shape: &Rc<RefCell<dyn Collidable>>) {
-        self.entities
-            .iter_mut()
-            .for_each(|shape| shape.try_bind(new_shape))
+        // every entity has to try binding, so no short-circuiting `any` here
+        let mut created = false;
+        for entity in &mut self.entities {
+            created |= entity.try_bind(new_shape);
+        }
+
+        if created {
+            self.achievements.binding_created(&mut self.events);
+        }
     }
 
     fn add_entity<S: Collidable + 'static>(
+        &mut self,
+        shape: S,
+        entity_cfg: EntityCfg,
+    ) -> (EntityId, Weak<RefCell<S>>) {
+        let (id, shape_weak) = self.insert_entity(shape, entity_cfg);
+        // the new entity has nothing to bind yet, so it may try binding to itself
+        let new_shape = self.entities[self.entities.len() - 1].shape.clone();
+        self.try_bind(&new_shape);
+        (id, shape_weak)
+    }
+
+    /// adds the entity without binding the entities before it to it
+    fn insert_entity<S: Collidable + 'static>(
         &mut self,
         mut shape: S,
         entity_cfg: EntityCfg,
-    ) -> Weak<RefCell<S>> {
+    ) -> (EntityId, Weak<RefCell<S>>) {
         if entity_cfg.is_static {
             shape.collision_data_mut().mass = f64::INFINITY;
             shape.collision_data_mut().inertia = f64::INFINITY;
@@ -410,70 +1498,1562 @@ impl Engine {
         let shape_weak = Rc::downgrade(&shape);
         let shape_dyn: Rc<RefCell<dyn Collidable>> = shape;
 
-        self.try_bind(&shape_dyn);
-        self.entities.push(Entity::new(shape_dyn, entity_cfg));
-        shape_weak
+        let id = self.entity_ids.generate();
+        self.slots.insert(id, self.entities.len());
+        self.entities.push(Entity::new(id, shape_dyn, entity_cfg));
+        (id, shape_weak)
     }
 
-    pub fn add_circle(&mut self, circle: Circle) {
-        let weak_circle = self.add_entity(circle, EntityCfg::default());
-        self.circles.push(weak_circle.into());
+    /// adds a circle drawn by the player, unless the circle limit of the level is reached
+    pub fn add_circle(&mut self, mut circle: Circle) -> Option<EntityId> {
+        let center = &mut circle.collision_data_mut().centroid;
+        *center = self.snap_point(*center);
+        self.add_drawn_circle(circle)
     }
 
-    pub fn add_polygon(&mut self, polygon: Polygon) {
-        let weak_polygon = self.add_entity(polygon, EntityCfg::default());
-        self.polygons.push(weak_polygon.into());
+    /// adds a polygon drawn by the player, unless the polygon limit of the level is reached,
+    /// snapping leaves nothing of it, see [`snap`], or its corner radius is negative or infinite
+    pub fn add_polygon(&mut self, mut polygon: Polygon) -> Option<EntityId> {
+        let corner_radius = polygon.corner_radius();
+        if !corner_radius.is_finite() || corner_radius < 0.0 || !self.snap_polygon(&mut polygon) {
+            return None;
+        }
+        self.add_drawn_polygon(polygon)
     }
 
-    pub fn erase_at(&mut self, point: Point) {
-        if let Some(i) = self
-            .entities
-            .iter()
-            .position(|shape| shape.shape.borrow().includes(point))
-        {
-            if self.entities[i].is_erasable {
-                self.entities.remove(i);
-            }
+    /// adds a circle that is already snapped, unless the circle limit of the level is reached
+    fn add_drawn_circle(&mut self, circle: Circle) -> Option<EntityId> {
+        if !self.has_room_for(Tool::Circle, 1) || !self.game.try_use(Tool::Circle) {
+            return None;
+        }
+
+        let (id, weak_circle) = self.add_entity(circle, EntityCfg::default());
+        self.circles.push(self.painter.paint(weak_circle));
+        Some(id)
+    }
+
+    /// adds a polygon that is already snapped, unless the polygon limit of the level is reached
+    fn add_drawn_polygon(&mut self, polygon: Polygon) -> Option<EntityId> {
+        if !self.has_room_for(Tool::Polygon, 1) || !self.game.try_use(Tool::Polygon) {
+            return None;
         }
+
+        let (id, weak_polygon) = self.add_entity(polygon, EntityCfg::default());
+        self.polygons.push(self.painter.paint(weak_polygon));
+        Some(id)
     }
 
-    pub fn add_hinge(&mut self, point: Point) {
-        if let Some(i) = self
+    /// describes the entity under `point` and the bindings around it,
+    /// using the same hit tests as the editing methods
+    pub fn describe_at(&self, point: Point) -> PointDescription {
+        let entity = self
+            .slot_at(point, |_| true)
+            .map(|slot| &self.entities[slot]);
+
+        let anchors = self
             .entities
             .iter()
-            .position(|shape| shape.shape.borrow().includes(point) && shape.is_bindable)
-        {
-            self.entities[i].add_hinge(point);
+            .flat_map(|entity| {
+                let shape = entity.shape.borrow();
+                let bound = entity
+                    .bindings
+                    .iter()
+                    .map(|(id, binding, _)| (*id, binding.anchor(&*shape)));
+                let unbound = entity
+                    .unbound
+                    .iter()
+                    .map(|(id, binding)| (*id, binding.anchor(&*shape)));
+                bound.chain(unbound).collect::<Vec<_>>()
+            })
+            .filter(|(_, anchor)| anchor.to(point).norm() <= ANCHOR_PICK_RADIUS)
+            .map(|(id, _)| id)
+            .collect();
+
+        PointDescription {
+            entity: entity.map(|entity| entity.id),
+            is_erasable: entity.is_some_and(|entity| entity.is_erasable),
+            is_bindable: entity.is_some_and(|entity| entity.is_bindable),
+            anchors,
         }
     }
 
-    pub fn add_rigid(&mut self, point: Point) {
-        if let Some(i) = self
-            .entities
+    /// every binding, in the order of the entities holding them
+    pub fn bindings(&self) -> Vec<BindingInfo> {
+        let entities = shape_entities(&self.entities);
+        self.entities
             .iter()
-            .position(|shape| shape.shape.borrow().includes(point) && shape.is_bindable)
-        {
-            self.entities[i].add_rigid(point);
+            .flat_map(|entity| {
+                let shape = entity.shape.borrow();
+                let bound = entity.bindings.iter().filter_map(|(id, binding, target)| {
+                    let target = entities.get(&(target.as_ptr() as *const c_void))?;
+                    let kind = match binding {
+                        Binding::Hinge { .. } => BindingKind::Hinge,
+                        Binding::Rigid { .. } => BindingKind::Rigid,
+                        Binding::Spring { .. } => BindingKind::Spring,
+                        Binding::Rope { .. } => BindingKind::Rope,
+                        Binding::Pulley { .. } => BindingKind::Pulley,
+                    };
+                    Some(BindingInfo {
+                        id: *id,
+                        kind,
+                        position: binding.anchor(&*shape),
+                        entity: entity.id,
+                        target: Some(target.id),
+                        break_impulse: binding.break_impulse(),
+                    })
+                });
+                let unbound = entity.unbound.iter().map(|(id, unbound)| {
+                    let kind = match unbound {
+                        Unbound::Hinge { .. } => BindingKind::Hinge,
+                        Unbound::Rigid { .. } => BindingKind::Rigid,
+                    };
+                    BindingInfo {
+                        id: *id,
+                        kind,
+                        position: unbound.anchor(&*shape),
+                        entity: entity.id,
+                        target: None,
+                        break_impulse: None,
+                    }
+                });
+                bound.chain(unbound).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// removes the binding with the given id, bound or not, returning whether there was one
+    pub fn remove_binding(&mut self, id: BindingId) -> bool {
+        for entity in &mut self.entities {
+            let count = entity.bindings.len() + entity.unbound.len();
+            entity.bindings.retain(|(binding_id, ..)| *binding_id != id);
+            entity.unbound.retain(|(binding_id, _)| *binding_id != id);
+            if entity.bindings.len() + entity.unbound.len() < count {
+                magnet::release(&mut self.magnets, id);
+                return true;
+            }
         }
+        false
     }
 
-    pub fn set_gravity_multipier(&mut self, value: f64) {
-        self.gravity_mulipiler = value;
+    pub fn erase_at(&mut self, point: Point) -> Option<EntityId> {
+        let i = self.slot_at(point, |_| true)?;
+
+        self.remove_erasable(i)
     }
 
-    pub fn set_restitution_multipier(&mut self, value: f64) {
-        self.restitution_mulipiler = value;
+    /// erases the entity with the given id, as long as it is erasable
+    pub fn erase(&mut self, id: EntityId) -> Option<EntityId> {
+        let i = self.slots.get(id)?;
+        self.remove_erasable(i)
     }
 
-    pub fn set_friction_multipier(&mut self, value: f64) {
-        self.friction_mulipiler = value;
+    fn remove_erasable(&mut self, i: usize) -> Option<EntityId> {
+        if self.entities[i].is_erasable && self.game.try_use(Tool::Erase) {
+            Some(self.remove_entity(i).id)
+        } else {
+            None
+        }
     }
 
-    pub fn set_static_friction(&mut self, enabled: bool) {
-        self.static_friction_enabled = enabled;
+    /// places a hinge damping the entities it binds turning relative to each other
+    /// by `damping` torque per radian per second. Returns `None` for a damping that
+    /// is negative or not finite
+    pub fn add_hinge(&mut self, point: Point, damping: f64) -> Option<BindingId> {
+        if !(damping.is_finite() && damping >= 0.0) {
+            return None;
+        }
+        let point = self.snap_point(point);
+        let i = self.slot_at(point, |entity| entity.is_bindable)?;
+
+        if !self.has_room_for(Tool::Hinge, 1) || !self.game.try_use(Tool::Hinge) {
+            return None;
+        }
+
+        let id = self.binding_ids.generate();
+        self.entities[i].add_hinge(id, point, damping);
+        self.placed_bindings.push(id);
+        Some(id)
     }
 
-    pub fn set_dynamic_friction(&mut self, enabled: bool) {
-        self.dynamic_friction_enabled = enabled;
+    /// places a rigid binding that gives by `compliance` per unit of force, or by the
+    /// compliance of the level when `None`. Returns `None` for a compliance that is
+    /// negative or not finite
+    pub fn add_rigid(&mut self, point: Point, compliance: Option<f64>) -> Option<BindingId> {
+        let compliance = compliance.unwrap_or(self.rigid_compliance());
+        if !(compliance.is_finite() && compliance >= 0.0) {
+            return None;
+        }
+        let point = self.snap_point(point);
+        let i = self.slot_at(point, |entity| entity.is_bindable)?;
+
+        if !self.has_room_for(Tool::Rigid, 1) || !self.game.try_use(Tool::Rigid) {
+            return None;
+        }
+
+        let id = self.binding_ids.generate();
+        self.entities[i].add_rigid(id, point, compliance);
+        self.placed_bindings.push(id);
+        Some(id)
+    }
+
+    /// the compliance of the rigid bindings placed without one of their own
+    fn rigid_compliance(&self) -> f64 {
+        self.level
+            .as_ref()
+            .map_or(0.0, |level| level.rigid_compliance)
+    }
+
+    /// makes the hinge or rigid binding with the given id break when holding its
+    /// entities together takes an impulse larger than `impulse`, `None` for never.
+    /// Returns whether there is such a binding, bound to a target, and the impulse
+    /// is neither negative nor infinite
+    pub fn set_break_impulse(&mut self, id: BindingId, impulse: Option<f64>) -> bool {
+        if !is_valid_break_threshold(impulse) {
+            return false;
+        }
+        self.entities
+            .iter_mut()
+            .flat_map(|entity| &mut entity.bindings)
+            .find(|(binding_id, ..)| *binding_id == id)
+            .is_some_and(|(_, binding, _)| binding.set_break_impulse(impulse))
+    }
+
+    /// changes the damping of the hinge with the given id, see [`Engine::add_hinge`].
+    /// Returns whether there is such a hinge, bound to a target, and the damping is
+    /// neither negative nor infinite
+    pub fn set_hinge_damping(&mut self, id: BindingId, damping: f64) -> bool {
+        if !(damping.is_finite() && damping >= 0.0) {
+            return false;
+        }
+        self.entities
+            .iter_mut()
+            .flat_map(|entity| &mut entity.bindings)
+            .find(|(binding_id, ..)| *binding_id == id)
+            .is_some_and(|(_, binding, _)| binding.set_damping(damping))
+    }
+
+    /// binds the entities under the two points with the binding `bind` makes out of
+    /// the points on them and the points themselves, once snapped. The binding goes to
+    /// the entity under the first point. Returns `None` if either point is not on a
+    /// bindable entity or both are on the same one, or when the limit of `tool` or
+    /// the binding cap of the engine is reached
+    fn add_binding_between(
+        &mut self,
+        tool: Tool,
+        first: Point,
+        second: Point,
+        bind: impl FnOnce(PointOnShape, PointOnShape, [Point; 2]) -> Binding,
+    ) -> Option<BindingId> {
+        let (first, second) = (self.snap_point(first), self.snap_point(second));
+        let i = self.slot_at(first, |entity| entity.is_bindable)?;
+        let j = self.slot_at(second, |entity| entity.is_bindable)?;
+        if i == j || !self.has_room_for(tool, 1) || !self.game.try_use(tool) {
+            return None;
+        }
+
+        let target = self.entities[j].shape.clone();
+        let binding = bind(
+            self.entities[i]
+                .shape
+                .borrow()
+                .create_point_reference(first),
+            target.borrow().create_point_reference(second),
+            [first, second],
+        );
+
+        let id = self.binding_ids.generate();
+        self.entities[i]
+            .bindings
+            .push((id, binding, Rc::downgrade(&target)));
+        self.placed_bindings.push(id);
+        Some(id)
+    }
+
+    /// severs every binding whose anchor the swipe along `points` passes within
+    /// [`ANCHOR_PICK_RADIUS`] of, and every spring, rope and pulley it crosses,
+    /// returning the ids of the severed bindings. Only ropes, pulleys, the hinges
+    /// of chains, the bindings that break and the ones placed with the tools are
+    /// cut, the others hold the level and its prefabs together
+    pub fn cut_along(&mut self, points: &[Point]) -> Vec<BindingId> {
+        let (placed, chains) = (&self.placed_bindings, &self.chains);
+        let is_cuttable = |id: &BindingId, binding: &Binding| {
+            matches!(binding, Binding::Rope { .. } | Binding::Pulley { .. })
+                || binding.break_impulse().is_some()
+                || placed.contains(id)
+                || chains.iter().any(|chain| chain.hinges.contains(id))
+        };
+
+        let is_crossed = |anchor: Point| {
+            points
+                .iter()
+                .zip(points.iter().skip(1))
+                .map(|(&start, &end)| anchor.distance_to_segment(start, end))
+                .chain(points.first().map(|point| anchor.to(*point).norm()))
+                .any(|distance| distance <= ANCHOR_PICK_RADIUS)
+        };
+
+        // a spring, a rope or a pulley is also cut anywhere along its length
+        let is_length_crossed = |path: &[Point]| {
+            path.iter().zip(path.iter().skip(1)).any(|(&start, &end)| {
+                points
+                    .iter()
+                    .zip(points.iter().skip(1))
+                    .any(|(&from, &to)| Point::segment_intersection(start, end, from, to).is_some())
+            })
+        };
+
+        let mut severed = vec![];
+        for entity in &mut self.entities {
+            let shape = entity.shape.borrow();
+            entity.bindings.retain(|(id, binding, target)| {
+                if !is_cuttable(id, binding) {
+                    return true;
+                }
+                let is_length_cut = target.upgrade().is_some_and(|target| {
+                    let target = target.borrow();
+                    let ends = binding.ends(&*shape, &*target);
+                    let pulley = binding.pulley_path(&*shape, &*target);
+                    ends.is_some_and(|ends| is_length_crossed(&ends))
+                        || pulley.is_some_and(|path| is_length_crossed(&path))
+                });
+                let is_cut = is_crossed(binding.anchor(&*shape)) || is_length_cut;
+                if is_cut {
+                    severed.push(*id);
+                }
+                !is_cut
+            });
+        }
+
+        event!(DEBUG, ?severed, "bindings cut");
+        self.events
+            .extend(severed.iter().map(|&id| GameEvent::Cut { id }));
+        for &id in &severed {
+            magnet::release(&mut self.magnets, id);
+        }
+        severed
+    }
+
+    /// lets the engine know the player picked a tool, for the hints triggered by it
+    pub fn select_tool(&mut self, tool: Tool) {
+        self.hints.tool_selected(tool, &mut self.events);
+    }
+
+    /// makes the entity shatter on any contact with an impulse above `breaking_impulse`,
+    /// or never shatter if it is `None`. Returns whether the entity exists and the
+    /// impulse is neither negative nor infinite
+    pub fn set_breaking_impulse(&mut self, id: EntityId, breaking_impulse: Option<f64>) -> bool {
+        if !is_valid_break_threshold(breaking_impulse) {
+            return false;
+        }
+        let Some(i) = self.slots.get(id) else {
+            return false;
+        };
+        let entity = &mut self.entities[i];
+        entity.durability.breaking_impulse = breaking_impulse;
+        true
+    }
+
+    /// turns a fan on or off, returns whether it exists
+    pub fn set_fan_enabled(&mut self, id: RegionId, enabled: bool) -> bool {
+        let Some(fan) = self.fans.iter_mut().find(|fan| fan.id == id) else {
+            return false;
+        };
+        fan.is_enabled = enabled;
+        true
+    }
+
+    /// starts or stops recording the trail of an entity
+    pub fn set_trail(&mut self, id: EntityId, enabled: bool) {
+        if enabled {
+            self.trails.track(id);
+        } else {
+            self.trails.untrack(id);
+        }
+    }
+
+    pub fn set_gravity_multipier(&mut self, value: f64) {
+        self.gravity_mulipiler = value;
+    }
+
+    pub fn set_restitution_multipier(&mut self, value: f64) {
+        self.restitution_mulipiler = value;
+    }
+
+    pub fn set_friction_multipier(&mut self, value: f64) {
+        self.friction_mulipiler = value;
+    }
+
+    pub fn set_static_friction(&mut self, enabled: bool) {
+        self.static_friction_enabled = enabled;
+    }
+
+    pub fn set_dynamic_friction(&mut self, enabled: bool) {
+        self.dynamic_friction_enabled = enabled;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{levels, physics::game::LossReason};
+
+    fn empty_level() -> Level {
+        Level {
+            initial_ball_position: Point(0.0, 0.0),
+            main_ball: Default::default(),
+            circles: vec![],
+            polygons: vec![],
+            flags_positions: vec![],
+            time_limit: None,
+            stars: Default::default(),
+            tool_limits: Default::default(),
+            rigid_compliance: 0.0,
+            terrain: vec![],
+            hints: vec![],
+            bombs: vec![],
+            fans: vec![],
+            lasers: vec![],
+            time_zones: vec![],
+            gravity_pickups: vec![],
+            size_pickups: vec![],
+            wrecking_balls: vec![],
+            magnets: vec![],
+            dispensers: vec![],
+            world_half_extent: Some(WORLD_HALF_EXTENT),
+            script: None,
+        }
+    }
+
+    #[test]
+    fn test_mass_properties() {
+        let mut engine = Engine::new(Level {
+            polygons: vec![floor(1.0, None, None)],
+            ..empty_level()
+        });
+        let square = engine
+            .add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) })
+            .unwrap();
+
+        let properties = engine.mass_properties();
+        let [ball, floor, drawn] = &properties[..] else {
+            panic!("expected three bodies");
+        };
+        assert_eq!(ball.relative_mass, Some(1.0));
+        assert_eq!(
+            (floor.mass, floor.inertia, floor.relative_mass),
+            (None, None, None)
+        );
+        assert_eq!(drawn.id, square);
+        assert!((drawn.mass.unwrap() - 1.0).abs() < 1e-9);
+        assert!((drawn.inertia.unwrap() - 1.0 / 6.0).abs() < 1e-9);
+        assert!(drawn.centroid.is_close_enough_to(Point(1.5, 1.5)));
+        let ball_area = std::f64::consts::PI * 0.1 * 0.1;
+        assert!((drawn.relative_mass.unwrap() - 1.0 / ball_area).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_run_n_iterations() {
+        let mut batched = Engine::new(empty_level());
+        let mut stepped = Engine::new(empty_level());
+
+        let batched_message = batched.run_n_iterations(10, 16_000.0);
+        let stepped_message = (0..10)
+            .map(|_| stepped.run_iteration(16_000.0))
+            .last()
+            .unwrap();
+
+        assert!(batched_message.circles[0]
+            .shape
+            .center
+            .is_close_enough_to(stepped_message.circles[0].shape.center));
+    }
+
+    #[test]
+    fn test_run_n_iterations_into() {
+        let build = || {
+            let mut engine = Engine::new(empty_level());
+            engine.add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) });
+            engine.add_hinge(Point(1.5, 1.5), 0.0);
+            engine.add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) });
+            engine
+        };
+        let mut fresh = build();
+        let mut reused = build();
+
+        let mut message = reused.run_n_iterations(10, 2_000.0);
+        message.polygons.reserve(16);
+        let polygons = message.polygons.as_ptr();
+        let hinges = message.hinges.as_ptr();
+
+        fresh.run_n_iterations(10, 2_000.0);
+        for _ in 0..10 {
+            reused.run_n_iterations_into(1, 2_000.0, &mut message);
+        }
+
+        assert_eq!(message.polygons.as_ptr(), polygons);
+        assert_eq!(message.hinges.as_ptr(), hinges);
+
+        // colors are random, everything else has to match
+        let to_json = |mut message: DisplayMessage| {
+            message
+                .polygons
+                .iter_mut()
+                .for_each(|polygon| polygon.color = [0.0; 3]);
+            message
+                .circles
+                .iter_mut()
+                .for_each(|circle| circle.color = [0.0; 3]);
+            serde_json::to_string(&message).unwrap()
+        };
+        assert_eq!(
+            to_json(message),
+            to_json(fresh.run_n_iterations(10, 2_000.0))
+        );
+    }
+
+    #[test]
+    fn test_game_state() {
+        let mut engine = Engine::new(Level {
+            flags_positions: vec![Point(-0.05, -0.05), Point(3.0, 3.0)],
+            ..empty_level()
+        });
+
+        let message = engine.run_iteration(16_000.0);
+        assert_eq!(message.flags_total, 2);
+        assert_eq!(message.flags_remaining, 1);
+        assert_eq!(message.state, GameState::Playing);
+        assert_eq!(
+            message.events,
+            vec![GameEvent::FlagCollected { remaining: 1 }]
+        );
+
+        engine.flags.clear();
+        let message = engine.run_iteration(16_000.0);
+        let GameState::Won { score } = message.state else {
+            panic!("the level should have been won");
+        };
+        assert_eq!(score.seconds, 0.032);
+        assert_eq!(
+            message.events,
+            vec![
+                GameEvent::Won { score },
+                GameEvent::Achievement {
+                    achievement: achievements::Achievement::WonWithoutErasing
+                }
+            ]
+        );
+        assert!(engine.run_iteration(16_000.0).events.is_empty());
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let mut engine = Engine::new(Level {
+            flags_positions: vec![Point(3.0, 3.0)],
+            time_limit: Some(0.1),
+            ..empty_level()
+        });
+
+        let message = engine.run_n_iterations(10, 16_000.0);
+        assert_eq!(
+            message.state,
+            GameState::Lost {
+                reason: LossReason::OutOfTime
+            }
+        );
+        assert_eq!(message.events.len(), 1);
+    }
+
+    #[test]
+    fn test_tool_limits() {
+        let mut engine = Engine::new(Level {
+            tool_limits: ToolLimits {
+                polygons: Some(1),
+                erases: Some(0),
+                ..Default::default()
+            },
+            ..empty_level()
+        });
+        let square = || {
+            make_shape! {
+                (1.0, 1.0),
+                (2.0, 1.0),
+                (2.0, 2.0),
+                (1.0, 2.0),
+            }
+        };
+
+        assert!(engine.add_polygon(square()).is_some());
+        assert!(engine.add_polygon(square()).is_none());
+        assert!(engine.erase_at(Point(1.5, 1.5)).is_none());
+        assert!(engine.add_hinge(Point(1.5, 1.5), 0.0).is_some());
+
+        let usage = engine.run_iteration(16_000.0).usage;
+        assert_eq!(usage.polygons, 1);
+        assert_eq!(usage.erases, 0);
+        assert_eq!(usage.hinges, 1);
+    }
+
+    #[test]
+    fn test_achievements() {
+        use achievements::{Achievement, STRUCTURE_SECONDS, STRUCTURE_SIZE};
+
+        let mut engine = Engine::new(empty_level());
+        // a row of overlapping squares, each bound to the next one
+        for i in 0..STRUCTURE_SIZE {
+            let x = 1.0 + i as f64 * 0.5;
+            engine.add_polygon(make_shape! {
+                (x, 1.0),
+                (x + 0.6, 1.0),
+                (x + 0.6, 1.6),
+                (x, 1.6),
+            });
+            engine.add_rigid(Point(x + 0.55, 1.3), None);
+        }
+
+        let achievements = |message: DisplayMessage| {
+            message
+                .events
+                .into_iter()
+                .filter_map(|event| match event {
+                    GameEvent::Achievement { achievement } => Some(achievement),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let steps = (STRUCTURE_SECONDS * 1_000_000.0 / 16_000.0).ceil() as u32;
+        let message = engine.run_n_iterations(steps - 1, 16_000.0);
+        let unlocked = achievements(message);
+        assert!(unlocked.contains(&Achievement::FirstBinding));
+        assert!(!unlocked.contains(&Achievement::StructureSurvived));
+
+        let message = engine.run_n_iterations(1, 16_000.0);
+        assert!(achievements(message).contains(&Achievement::StructureSurvived));
+    }
+
+    /// a static floor under the starting position of the ball
+    fn floor(
+        y: f64,
+        hit_points: Option<f64>,
+        breaking_impulse: Option<f64>,
+    ) -> levels::Entity<Vec<Point>> {
+        levels::Entity {
+            shape: vec![
+                Point(-1.0, y),
+                Point(1.0, y),
+                Point(1.0, y + 0.2),
+                Point(-1.0, y + 0.2),
+            ],
+            is_static: true,
+            is_bindable: false,
+            hit_points,
+            breaking_impulse,
+            fracture_impulse: None,
+            bump_impulse: None,
+            is_ferrous: false,
+            is_erasable: false,
+            render_layer: 0,
+        }
+    }
+
+    fn ball_height(engine: &Engine) -> f64 {
+        engine.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .centroid
+            .1
+    }
+
+    #[test]
+    fn test_destructible() {
+        let mut engine = Engine::new(Level {
+            polygons: vec![floor(0.2, Some(1e-3), None), floor(1.0, None, None)],
+            ..empty_level()
+        });
+        let destructible = engine.entities[1].id;
+
+        let events: Vec<_> = (0..60)
+            .flat_map(|_| engine.run_iteration(16_000.0).events)
+            .collect();
+
+        assert_eq!(events, vec![GameEvent::Destroyed { id: destructible }]);
+        assert_eq!(engine.entities.len(), 2);
+        // the ball fell through onto the indestructible floor
+        assert!(ball_height(&engine) > 0.5);
+    }
+
+    #[test]
+    fn test_fragile() {
+        let mut engine = Engine::new(Level {
+            polygons: vec![floor(0.2, Some(1e9), Some(1e-3)), floor(1.0, None, None)],
+            ..empty_level()
+        });
+        let fragile = engine.entities[1].id;
+        let sturdy = engine.entities[2].id;
+        assert!(engine.set_breaking_impulse(sturdy, None));
+        for invalid in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(!engine.set_breaking_impulse(fragile, Some(invalid)));
+        }
+
+        let events: Vec<_> = (0..60)
+            .flat_map(|_| engine.run_iteration(16_000.0).events)
+            .collect();
+
+        // shattering does not depend on the hit points
+        assert_eq!(events, vec![GameEvent::Shattered { id: fragile }]);
+        assert!(ball_height(&engine) > 0.5);
+    }
+
+    #[test]
+    fn test_bumpers() {
+        let lowest_bounce = |bump_impulse| {
+            let mut engine = Engine::new(Level {
+                polygons: vec![levels::Entity {
+                    bump_impulse,
+                    ..floor(0.5, None, None)
+                }],
+                ..empty_level()
+            });
+            let bumper = engine.entities[1].id;
+
+            let mut events = vec![];
+            let mut lowest = f64::INFINITY;
+            // the ball lands after about 30 steps
+            for step in 0..150 {
+                events.extend(engine.run_iteration(2_000.0).events);
+                if step > 40 {
+                    lowest = lowest.min(ball_height(&engine));
+                }
+            }
+            let bumps = events
+                .iter()
+                .filter(|&&event| event == GameEvent::Bumped { id: bumper })
+                .count();
+            (lowest, bumps)
+        };
+
+        let (without_bumper, bumps) = lowest_bounce(None);
+        assert_eq!(bumps, 0);
+        let (with_bumper, bumps) = lowest_bounce(Some(1e-2));
+        assert!(bumps > 0);
+        // the ball is kicked back up much higher than it bounces off a plain floor
+        assert!(with_bumper < without_bumper - 0.1);
+    }
+
+    #[test]
+    fn test_solver_tuning() {
+        let lowest_bounce = |restitution| {
+            let mut engine = Engine::new(Level {
+                polygons: vec![floor(0.5, None, None)],
+                ..empty_level()
+            });
+            engine.set_solver_tuning(SolverTuning {
+                restitution,
+                ..Default::default()
+            });
+
+            // the ball lands after about 30 steps
+            (0..150)
+                .map(|_| {
+                    engine.run_iteration(2_000.0);
+                    ball_height(&engine)
+                })
+                .skip(40)
+                .fold(f64::INFINITY, f64::min)
+        };
+
+        // a bouncier ball gets back up higher, which is lower down the y axis
+        assert!(lowest_bounce(0.9) < lowest_bounce(0.0) - 0.05);
+    }
+
+    #[test]
+    fn test_bombs() {
+        let bomb = |x, fuse_seconds| levels::Bomb {
+            position: Point(x, 0.0),
+            fuse_seconds,
+            detonation_impulse: None,
+            blast_radius: 1.0,
+            blast_impulse: 1e-2,
+        };
+        let mut engine = Engine::new(Level {
+            polygons: vec![floor(0.5, None, Some(1e9))],
+            bombs: vec![bomb(0.3, Some(0.1)), bomb(0.9, None), bomb(3.0, None)],
+            ..empty_level()
+        });
+        let ids: Vec<_> = engine.entities.iter().map(|entity| entity.id).collect();
+
+        let events: Vec<_> = (0..60)
+            .flat_map(|_| engine.run_iteration(2_000.0).events)
+            .collect();
+
+        let exploded: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::Exploded { id, .. } => Some(*id),
+                _ => None,
+            })
+            .collect();
+        // the second bomb is set off by the first one, the third one is out of range
+        assert_eq!(exploded, vec![ids[2], ids[3]]);
+        assert!(events.contains(&GameEvent::Shattered { id: ids[1] }));
+        assert_eq!(engine.bombs.len(), 1);
+
+        let mut ball = engine.entities[0].shape.borrow_mut();
+        assert!(ball.collision_data_mut().velocity.0 < 0.0);
+    }
+
+    #[test]
+    fn test_fans() {
+        let mut engine = Engine::new(Level {
+            fans: vec![levels::Fan {
+                position: Point(0.0, 0.5),
+                direction: Point(0.0, -1.0),
+                length: 1.0,
+                width: 0.5,
+                strength: 0.1,
+                starts_disabled: false,
+            }],
+            ..empty_level()
+        });
+        let fan = engine.fans[0].id;
+
+        engine.run_n_iterations(20, 2_000.0);
+        assert!(ball_height(&engine) < 0.0);
+
+        assert!(engine.set_fan_enabled(fan, false));
+        let message = engine.run_n_iterations(100, 2_000.0);
+        assert!(!message.fans[0].is_enabled);
+        assert!(ball_height(&engine) > 0.0);
+    }
+
+    #[test]
+    fn test_lasers() {
+        let laser = |y, resets_ball| Laser {
+            start: Point(-1.0, y),
+            end: Point(1.0, y),
+            resets_ball,
+        };
+        let mut engine = Engine::new(Level {
+            lasers: vec![laser(0.5, false), laser(1.0, true)],
+            ..empty_level()
+        });
+        let square = engine
+            .add_polygon(make_shape! {
+                (0.4, 0.0),
+                (0.6, 0.0),
+                (0.6, 0.2),
+                (0.4, 0.2),
+            })
+            .unwrap();
+
+        let message = engine.run_n_iterations(30, 2_000.0);
+        assert_eq!(message.events, vec![GameEvent::Vaporized { id: square }]);
+        // the ball passes through the first laser, but the second one resets it
+        assert!(ball_height(&engine) > 0.5);
+        assert_eq!(engine.game.failed_attempts(), 0);
+
+        engine.run_n_iterations(10, 2_000.0);
+        assert_eq!(engine.game.failed_attempts(), 1);
+        assert!(ball_height(&engine) < 0.5);
+    }
+
+    #[test]
+    fn test_time_zones() {
+        let mut normal = Engine::new(empty_level());
+        let mut slowed = Engine::new(Level {
+            time_zones: vec![levels::TimeZone {
+                region: vec![
+                    Point(-1.0, -1.0),
+                    Point(1.0, -1.0),
+                    Point(1.0, 1.0),
+                    Point(-1.0, 1.0),
+                ],
+                time_scale: 0.25,
+            }],
+            ..empty_level()
+        });
+
+        normal.run_n_iterations(20, 2_000.0);
+        let message = slowed.run_n_iterations(20, 2_000.0);
+
+        assert_eq!(message.time_zones.len(), 1);
+        // both the gravity and the movement are slowed down
+        let expected = ball_height(&normal) / 16.0;
+        assert!((ball_height(&slowed) - expected).abs() < 1e-9);
+        // the clocks of the level are not affected
+        assert_eq!(message.elapsed_seconds, 0.04);
+    }
+
+    #[test]
+    fn test_gravity_pickups() {
+        let mut engine = Engine::new(Level {
+            gravity_pickups: vec![levels::GravityPickup {
+                position: Point(0.0, 0.5),
+                rotation: std::f64::consts::PI,
+                duration_seconds: 1.0,
+            }],
+            ..empty_level()
+        });
+
+        let message = engine.run_n_iterations(50, 2_000.0);
+        assert!(message.gravity_pickups.is_empty());
+        assert!(message.gravity_seconds_remaining.is_some());
+
+        // the ball falls back up once gravity turns
+        let turned_at = ball_height(&engine);
+        engine.run_n_iterations(200, 2_000.0);
+        assert!(ball_height(&engine) < turned_at);
+    }
+
+    #[test]
+    fn test_size_pickups() {
+        let mut engine = Engine::new(Level {
+            size_pickups: vec![levels::SizePickup {
+                position: Point(0.0, 0.5),
+                scale: 2.0,
+                duration_seconds: 0.1,
+            }],
+            ..empty_level()
+        });
+
+        let message = engine.run_n_iterations(30, 2_000.0);
+        assert!(message.size_pickups.is_empty());
+        assert!(message.ball_size_seconds_remaining.is_some());
+        assert_eq!(message.circles[0].shape.radius, 0.2);
+
+        let message = engine.run_n_iterations(50, 2_000.0);
+        assert_eq!(message.ball_size_seconds_remaining, None);
+        assert_eq!(message.circles[0].shape.radius, 0.1);
+    }
+
+    #[test]
+    fn test_seesaw() {
+        let mut engine = Engine::new(Level {
+            initial_ball_position: Point(-0.8, 0.0),
+            ..empty_level()
+        });
+        let seesaw = engine.add_seesaw(Point(0.0, 1.0), 2.0, 0.0);
+
+        let message = engine.run_n_iterations(200, 2_000.0);
+        assert_eq!(message.hinges.len(), 1);
+        assert_eq!(message.usage, Usage::default());
+
+        // the ball lands on the left end, which tips down
+        let plank = engine
+            .body_states()
+            .into_iter()
+            .find(|body| body.id == seesaw.plank)
+            .unwrap();
+        assert!(plank.angle < 0.0);
+        // while the pivot holds it in place
+        assert!(plank.centroid.to(Point(0.0, 1.0)).norm() < 0.05);
+    }
+
+    #[test]
+    fn test_display_entities() {
+        let mut engine = Engine::new(empty_level());
+        let square = engine
+            .add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) })
+            .unwrap();
+        let circle = engine
+            .add_circle(Circle::new(Point(3.0, 1.0), 0.2))
+            .unwrap();
+        engine.add_hinge(Point(1.5, 1.5), 0.0).unwrap();
+
+        let message = engine.run_iteration(2_000.0);
+        assert_eq!(message.polygon_entities, [square]);
+        assert_eq!(message.circle_entities, [EntityId(0), circle]);
+        assert_eq!(message.unbound_hinge_entities, [square]);
+        assert!(message.hinge_entities.is_empty());
+
+        // binding the hinge keeps it on the square it was added to
+        let other = engine
+            .add_polygon(make_shape! { (1.2, 1.2), (1.8, 1.2), (1.8, 1.8), (1.2, 1.8) })
+            .unwrap();
+        let message = engine.run_iteration(2_000.0);
+        assert_eq!(message.polygon_entities, [square, other]);
+        assert_eq!(message.hinge_entities, [square]);
+        assert_eq!(message.hinge_targets, [other]);
+        assert!(message.unbound_hinge_entities.is_empty());
+
+        // the binding goes away along with the entity it connects to
+        engine.erase(other).unwrap();
+        let message = engine.run_iteration(2_000.0);
+        assert!(message.hinges.is_empty() && message.hinge_targets.is_empty());
+    }
+
+    #[test]
+    fn test_bindings() {
+        let mut engine = Engine::new(empty_level());
+        let square = engine
+            .add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) })
+            .unwrap();
+        let hinge = engine.add_hinge(Point(1.5, 1.5), 0.0).unwrap();
+        let unbound = BindingInfo {
+            id: hinge,
+            kind: BindingKind::Hinge,
+            position: Point(1.5, 1.5),
+            entity: square,
+            target: None,
+            break_impulse: None,
+        };
+        assert_eq!(engine.bindings(), [unbound]);
+
+        let other = engine
+            .add_polygon(make_shape! { (1.2, 1.2), (1.8, 1.2), (1.8, 1.8), (1.2, 1.8) })
+            .unwrap();
+        let circle = engine
+            .add_circle(Circle::new(Point(3.0, 1.5), 0.2))
+            .unwrap();
+        let spring = engine.add_spring(Point(3.0, 1.5), Point(1.9, 1.5)).unwrap();
+        let bindings = engine.bindings();
+        assert_eq!(
+            bindings[0],
+            BindingInfo {
+                target: Some(other),
+                ..unbound
+            }
+        );
+        assert_eq!(bindings[1].id, spring);
+        assert_eq!(bindings[1].kind, BindingKind::Spring);
+        assert_eq!(
+            (bindings[1].entity, bindings[1].target),
+            (circle, Some(square))
+        );
+
+        assert!(engine.remove_binding(hinge));
+        assert!(!engine.remove_binding(hinge));
+        let message = engine.run_iteration(2_000.0);
+        assert!(message.hinges.is_empty());
+        assert_eq!(engine.bindings().len(), 1);
+    }
+
+    #[test]
+    fn test_catapult() {
+        let mut engine = Engine::new(Level {
+            initial_ball_position: Point(-3.0, 0.0),
+            ..empty_level()
+        });
+        let catapult = engine.add_catapult(Point(0.0, 1.0), 1.0, 3.0);
+        let body = |engine: &Engine, id| {
+            engine
+                .body_states()
+                .into_iter()
+                .find(|body| body.id == id)
+                .unwrap()
+        };
+        let bucket_start = body(&engine, catapult.bucket).centroid;
+
+        let message = engine.run_n_iterations(100, 2_000.0);
+        assert_eq!(message.hinges.len(), 1);
+        assert_eq!(message.rigid_bindings.len(), 1);
+        assert_eq!(message.torsion_springs.len(), 1);
+
+        // the arm swings up, throwing the bucket up and back
+        assert!(body(&engine, catapult.arm).angle < 0.0);
+        let bucket = body(&engine, catapult.bucket).centroid;
+        assert!(bucket.1 < bucket_start.1 && bucket.0 < bucket_start.0);
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut engine = Engine::new(empty_level());
+        let left = engine.add_prefab_polygon(
+            make_shape! { (-1.2, 0.9), (-0.9, 0.9), (-0.9, 1.1), (-1.2, 1.1) },
+            true,
+        );
+        let right = engine.add_prefab_polygon(
+            make_shape! { (0.9, 0.9), (1.2, 0.9), (1.2, 1.1), (0.9, 1.1) },
+            true,
+        );
+        assert_eq!(engine.add_chain(vec![Point(0.0, 0.0)], 0.05), None);
+        assert_eq!(
+            engine.add_chain(vec![Point(0.0, 0.0), Point(0.0, 0.0)], 0.05),
+            None
+        );
+        assert_eq!(
+            engine.add_chain(vec![Point(0.0, 0.0), Point(1.0, 0.0)], 0.0),
+            None
+        );
+
+        let points = (0..=8)
+            .map(|i| Point(-1.0 + 0.25 * i as f64, 1.0))
+            .collect();
+        let chain = engine.add_chain(points, 0.05).unwrap();
+        assert_eq!(chain.links.len(), 8);
+        assert_eq!(chain.hinges.len(), 7);
+        let message = engine.run_n_iterations(500, 2_000.0);
+        assert_eq!(message.hinges.len(), 9);
+        assert!(message.hinge_targets.contains(&left) && message.hinge_targets.contains(&right));
+        assert!(message.unbound_hinges.is_empty());
+        assert_eq!(message.usage, Usage::default());
+
+        // it sags between the two ends, like a rope bridge
+        let bodies = engine.body_states();
+        let link = |id| bodies.iter().find(|body| body.id == id).unwrap();
+        let middle = link(chain.links[3]).centroid;
+        assert!(middle.1 > 1.1 && middle.1 < 1.5, "{middle:?}");
+        for &id in &chain.links {
+            let Point(x, _) = link(id).centroid;
+            assert!((-1.0..1.0).contains(&x), "{x}");
+        }
+    }
+
+    /// a powered magnet touching a ferrous box and a box that is not
+    fn magnet_level() -> Level {
+        let hanging_box = |x: f64, is_ferrous| levels::Entity {
+            shape: vec![
+                Point(x - 0.1, 0.09),
+                Point(x + 0.1, 0.09),
+                Point(x + 0.1, 0.29),
+                Point(x - 0.1, 0.29),
+            ],
+            is_static: false,
+            is_ferrous,
+            ..floor(0.0, None, None)
+        };
+        Level {
+            initial_ball_position: Point(-3.0, 0.0),
+            polygons: vec![hanging_box(0.0, true), hanging_box(0.15, false)],
+            magnets: vec![levels::Magnet {
+                position: Point(0.0, 0.0),
+                radius: 0.1,
+                is_static: true,
+                starts_powered: true,
+            }],
+            ..empty_level()
+        }
+    }
+
+    #[test]
+    fn test_magnets() {
+        let mut engine = Engine::new(magnet_level());
+        let ferrous = engine.entities[1].id;
+        let magnet = engine.entities[3].id;
+        let height = |engine: &Engine, id| {
+            engine
+                .body_states()
+                .into_iter()
+                .find(|body| body.id == id)
+                .unwrap()
+                .centroid
+                .1
+        };
+
+        let message = engine.run_n_iterations(100, 2_000.0);
+        assert_eq!(
+            message.events,
+            vec![GameEvent::MagnetAttached {
+                id: magnet,
+                target: ferrous
+            }]
+        );
+        assert_eq!(message.rigid_bindings.len(), 1);
+        // only the ferrous box is held up by the magnet
+        assert!((height(&engine, ferrous) - 0.19).abs() < 0.05);
+        assert!(height(&engine, engine.entities[2].id) > 0.5);
+
+        assert!(engine.set_magnet_powered(magnet, false));
+        let message = engine.run_n_iterations(100, 2_000.0);
+        assert_eq!(
+            message.events,
+            vec![GameEvent::MagnetReleased { id: magnet }]
+        );
+        assert!(message.rigid_bindings.is_empty());
+        assert!(height(&engine, ferrous) > 0.5);
+    }
+
+    #[test]
+    fn test_magnet_binding_removed() {
+        for is_cut in [false, true] {
+            let mut engine = Engine::new(magnet_level());
+            let message = engine.run_n_iterations(100, 2_000.0);
+            let held = engine.magnets[0].held.unwrap();
+            let anchor = message.rigid_bindings[0];
+            if is_cut {
+                // the binding of the magnet is not placed by the player, it is only
+                // cut once it breaks
+                assert!(engine.cut_along(&[anchor]).is_empty());
+                assert!(engine.set_break_impulse(held, Some(1e3)));
+                assert_eq!(engine.cut_along(&[anchor]), vec![held]);
+            } else {
+                assert!(engine.remove_binding(held));
+            }
+            assert_eq!(engine.magnets[0].held, None);
+        }
+    }
+
+    #[test]
+    fn test_wrecking_ball() {
+        let wrecking_ball = levels::WreckingBall {
+            anchor: Point(1.0, 0.0),
+            position: Point(1.0, 1.0),
+            radius: 0.2,
+            swing_speed: 10.0,
+        };
+        let mut engine = Engine::new(Level {
+            initial_ball_position: Point(-3.0, 0.0),
+            wrecking_balls: vec![wrecking_ball],
+            ..empty_level()
+        });
+        let ball = engine.entities[1].id;
+        assert!(!engine.describe_at(wrecking_ball.position).is_erasable);
+
+        // the ball swings out towards negative x while the rod keeps it at its length
+        let mut furthest_swing = f64::INFINITY;
+        for _ in 0..100 {
+            let message = engine.run_iteration(2_000.0);
+            assert_eq!(message.hinges.len(), 1);
+            assert_eq!(message.rigid_bindings.len(), 1);
+
+            let ball = engine
+                .body_states()
+                .into_iter()
+                .find(|body| body.id == ball)
+                .unwrap();
+            assert!((ball.centroid.to(wrecking_ball.anchor).norm() - 1.0).abs() < 0.05);
+            furthest_swing = furthest_swing.min(ball.centroid.0);
+        }
+        assert!(furthest_swing < 0.7);
+    }
+
+    #[test]
+    fn test_vehicle() {
+        let mut engine = Engine::new(Level {
+            initial_ball_position: Point(-3.0, 0.0),
+            polygons: vec![levels::Entity {
+                shape: vec![
+                    Point(-3.0, 1.0),
+                    Point(3.0, 1.0),
+                    Point(3.0, 1.2),
+                    Point(-3.0, 1.2),
+                ],
+                ..floor(1.0, None, None)
+            }],
+            ..empty_level()
+        });
+        let body = || {
+            make_shape! {
+                (-0.3, 0.7),
+                (0.3, 0.7),
+                (0.3, 0.8),
+                (-0.3, 0.8),
+            }
+        };
+        let wheels = [Point(-0.2, 0.85), Point(0.2, 0.85)];
+        for radius in [0.0, -0.15, f64::NAN, f64::INFINITY] {
+            assert_eq!(engine.add_vehicle(body(), &wheels, radius, 5.0), None);
+        }
+        let stray = [Point(-0.2, 0.85), Point(f64::NAN, 0.85)];
+        assert_eq!(engine.add_vehicle(body(), &stray, 0.15, 5.0), None);
+        let flat = make_shape! { (-0.3, 0.7), (0.3, 0.7), (0.0, 0.7) };
+        assert_eq!(engine.add_vehicle(flat, &wheels, 0.15, 5.0), None);
+        assert_eq!(engine.entities.len(), 2);
+
+        let vehicle = engine.add_vehicle(body(), &wheels, 0.15, 5.0).unwrap();
+        assert_eq!(vehicle.wheels.len(), 2);
+
+        let message = engine.run_n_iterations(300, 2_000.0);
+        assert_eq!(message.hinges.len(), 2);
+        assert_eq!(message.motors.len(), 2);
+
+        // the wheels roll the body along the floor
+        let body = engine
+            .body_states()
+            .into_iter()
+            .find(|body| body.id == vehicle.body)
+            .unwrap();
+        assert!(body.centroid.0 > 0.2, "{:?}", body.centroid);
+        assert!(body.centroid.1 < 1.0);
+    }
+
+    #[test]
+    fn test_timers() {
+        // the ball starts out of bounds, so it respawns on every step
+        let mut engine = Engine::new(Level {
+            initial_ball_position: Point(6.0, 0.0),
+            ..empty_level()
+        });
+
+        let message = engine.run_n_iterations(10, 16_000.0);
+        assert!((message.elapsed_seconds - 0.16).abs() < 1e-9);
+        assert_eq!(message.attempt_seconds, 0.016);
+        assert_eq!(message.ghost, vec![Point(6.0, 0.0)]);
+    }
+
+    #[test]
+    fn test_trails() {
+        let mut engine = Engine::new(empty_level());
+        let circle = engine
+            .add_circle(Circle::new(Point(1.0, 0.0), 0.1))
+            .unwrap();
+        engine.set_trail(circle, true);
+
+        let message = engine.run_n_iterations(trail::TRAIL_LENGTH as u32 + 5, 16_000.0);
+        assert_eq!(message.trails.len(), 2);
+        assert_eq!(message.trails[0].points.len(), trail::TRAIL_LENGTH);
+        assert_eq!(
+            message.trails[0].points.last(),
+            Some(&message.circles[0].shape.center)
+        );
+
+        engine.erase(circle);
+        assert_eq!(engine.run_iteration(16_000.0).trails.len(), 1);
+    }
+
+    #[test]
+    fn test_perf_stats() {
+        let mut engine = Engine::new(Level {
+            polygons: vec![levels::Entity {
+                shape: vec![
+                    Point(-1.0, 0.1),
+                    Point(1.0, 0.1),
+                    Point(1.0, 0.2),
+                    Point(-1.0, 0.2),
+                ],
+                is_static: true,
+                is_bindable: true,
+                hit_points: None,
+                breaking_impulse: None,
+                fracture_impulse: None,
+                bump_impulse: None,
+                is_ferrous: false,
+                is_erasable: false,
+                render_layer: 0,
+            }],
+            ..empty_level()
+        });
+
+        engine.run_n_iterations(30, 16_000.0);
+        let stats = engine.perf_stats();
+
+        assert_eq!(stats.steps, 30);
+        // the ball only ever overlaps the bounding box of the floor
+        assert!(stats.pairs_tested > 0 && stats.pairs_tested <= 30);
+        assert!(stats.gjk_iterations >= stats.pairs_tested);
+        assert!(stats.contacts_resolved > 0);
+        assert_eq!(stats.bindings_enforced, 0);
+    }
+
+    #[test]
+    fn test_state_hash() {
+        let mut first = Engine::new(empty_level());
+        let mut second = Engine::new(empty_level());
+        assert_eq!(first.state_hash(), second.state_hash());
+
+        first.run_n_iterations(3, 16_000.0);
+        assert_ne!(first.state_hash(), second.state_hash());
+
+        second.run_n_iterations(3, 16_000.0);
+        assert_eq!(first.state_hash(), second.state_hash());
+    }
+
+    #[test]
+    fn test_describe_at() {
+        let mut engine = Engine::new(empty_level());
+        let polygon = engine
+            .add_polygon(make_shape! {
+                (1.0, 1.0),
+                (2.0, 1.0),
+                (2.0, 2.0),
+                (1.0, 2.0),
+            })
+            .unwrap();
+        let hinge = engine.add_hinge(Point(1.5, 1.5), 0.0).unwrap();
+
+        assert_eq!(
+            engine.describe_at(Point(1.52, 1.5)),
+            PointDescription {
+                entity: Some(polygon),
+                is_erasable: true,
+                is_bindable: true,
+                anchors: vec![hinge],
+            }
+        );
+        assert!(engine.describe_at(Point(1.8, 1.8)).anchors.is_empty());
+
+        let ball = engine.describe_at(Point(0.0, 0.0));
+        assert!(!ball.is_erasable);
+        assert!(ball.is_bindable);
+
+        assert_eq!(
+            engine.describe_at(Point(5.0, 5.0)),
+            PointDescription::default()
+        );
+    }
+
+    #[test]
+    fn test_cut_along() {
+        let mut engine = Engine::new(empty_level());
+        let square = |x| {
+            make_shape! {
+                (x, 1.0),
+                (x + 1.0, 1.0),
+                (x + 1.0, 2.0),
+                (x, 2.0),
+            }
+        };
+        engine.add_polygon(square(1.0));
+        let hinge = engine.add_hinge(Point(1.8, 1.5), 0.0).unwrap();
+        engine.add_polygon(square(1.5));
+        let rigid = engine.add_rigid(Point(1.2, 1.5), None).unwrap();
+        engine.add_polygon(square(0.5));
+
+        assert!(engine
+            .cut_along(&[Point(0.0, 0.0), Point(1.0, 0.0)])
+            .is_empty());
+        assert_eq!(
+            engine.cut_along(&[Point(1.8, 0.0), Point(1.8, 1.4), Point(1.85, 1.6)]),
+            vec![hinge]
+        );
+        assert!(engine
+            .run_iteration(2_000.0)
+            .events
+            .contains(&GameEvent::Cut { id: hinge }));
+        assert_eq!(engine.cut_along(&[Point(1.2, 1.5)]), vec![rigid]);
+
+        // the hinges of the prefabs hold unless they break
+        engine.add_prefab_polygon(square(3.0), true);
+        let prefab_hinge = engine.add_prefab_hinge(Point(3.8, 1.5));
+        engine.add_prefab_polygon(square(3.5), false);
+        assert!(engine.cut_along(&[Point(3.8, 1.5)]).is_empty());
+        assert!(engine.set_break_impulse(prefab_hinge, Some(1e3)));
+        assert_eq!(engine.cut_along(&[Point(3.8, 1.5)]), vec![prefab_hinge]);
+    }
+
+    #[test]
+    fn test_rigid_compliance() {
+        // a plank welded to a static block by one end, returning how far its other end sags
+        let sag = |level_compliance: f64, compliance: Option<f64>| {
+            let mut engine = Engine::new(Level {
+                rigid_compliance: level_compliance,
+                ..empty_level()
+            });
+            engine.add_prefab_polygon(
+                make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 1.2), (1.0, 1.2) },
+                true,
+            );
+            engine.add_rigid(Point(1.9, 1.1), compliance).unwrap();
+            let plank = engine
+                .add_polygon(make_shape! { (1.8, 1.05), (3.0, 1.05), (3.0, 1.15), (1.8, 1.15) })
+                .unwrap();
+            engine.run_n_iterations(500, 2_000.0);
+            let slot = engine.slots.get(plank).unwrap();
+            engine.body_states()[slot].aabb.max.1 - 1.15
+        };
+
+        // the more compliant, the more it gives under the weight of the plank
+        let sags = [0.001, 0.01, 0.1].map(|compliance| sag(0.0, Some(compliance)));
+        assert!(sags.windows(2).all(|pair| pair[0] < pair[1]), "{sags:?}");
+        assert!(sags[0] < 0.1, "{sags:?}");
+        // without one of its own, a binding takes the compliance of the level
+        assert_eq!(sag(0.01, None), sags[1]);
+
+        let mut engine = Engine::new(empty_level());
+        engine.add_prefab_polygon(
+            make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 1.2), (1.0, 1.2) },
+            true,
+        );
+        assert_eq!(engine.add_rigid(Point(1.9, 1.1), Some(-1.0)), None);
+    }
+
+    #[test]
+    fn test_hinge_damping() {
+        let mut engine = Engine::new(empty_level());
+        engine.add_prefab_polygon(
+            make_shape! { (0.5, 0.5), (1.5, 0.5), (1.5, 1.5), (0.5, 1.5) },
+            true,
+        );
+        assert_eq!(engine.add_hinge(Point(1.0, 1.0), -1.0), None);
+        let damped = engine.add_hinge(Point(1.0, 1.0), 0.001).unwrap();
+        let wheel = engine
+            .add_circle(Circle::new(Point(1.0, 1.0), 0.3))
+            .unwrap();
+        engine.add_prefab_polygon(
+            make_shape! { (2.5, 0.5), (3.5, 0.5), (3.5, 1.5), (2.5, 1.5) },
+            true,
+        );
+        let free = engine.add_hinge(Point(3.0, 1.0), 0.0).unwrap();
+        let free_wheel = engine
+            .add_circle(Circle::new(Point(3.0, 1.0), 0.3))
+            .unwrap();
+        let spin = |engine: &mut Engine, id| {
+            let slot = engine.slots.get(id).unwrap();
+            engine.entities[slot]
+                .shape
+                .borrow_mut()
+                .collision_data_mut()
+                .angular_velocity = 0.01;
+        };
+        let angular_velocity = |engine: &Engine, id| {
+            engine.body_states()[engine.slots.get(id).unwrap()].angular_velocity
+        };
+        spin(&mut engine, wheel);
+        spin(&mut engine, free_wheel);
+
+        // the damped wheel slows down to a stop, while the free one spins on
+        engine.run_n_iterations(1_000, 2_000.0);
+        assert!(angular_velocity(&engine, wheel).abs() < 1e-4);
+        assert!(angular_velocity(&engine, free_wheel) > 0.009);
+
+        assert!(!engine.set_hinge_damping(free, f64::NAN));
+        assert!(engine.set_hinge_damping(free, 0.001));
+        assert!(engine.set_hinge_damping(damped, 0.0));
+        spin(&mut engine, wheel);
+        // the damping is part of the binding, so it is restored along with it
+        let mut restored = Engine::from(snapshot::Snapshot::from(&engine));
+        for engine in [&mut engine, &mut restored] {
+            engine.run_n_iterations(1_000, 2_000.0);
+            assert!(angular_velocity(engine, wheel) > 0.009);
+            assert!(angular_velocity(engine, free_wheel).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_break_impulse() {
+        let mut engine = Engine::new(empty_level());
+        engine.add_prefab_polygon(
+            make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 1.2), (1.0, 1.2) },
+            true,
+        );
+        let hinge = engine.add_hinge(Point(1.5, 1.1), 0.0).unwrap();
+        engine.add_polygon(make_shape! { (1.4, 1.0), (1.6, 1.0), (1.6, 1.6), (1.4, 1.6) });
+        engine.run_iteration(2_000.0);
+        // the same impulses are invalid as for the entities that shatter
+        for invalid in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(!engine.set_break_impulse(hinge, Some(invalid)));
+        }
+        assert!(!engine.set_break_impulse(BindingId(1_000), Some(1.0)));
+
+        // the hinge holds the hanging block as long as it is strong enough
+        assert!(engine.set_break_impulse(hinge, Some(1.0)));
+        assert_eq!(engine.bindings()[0].break_impulse, Some(1.0));
+        let message = engine.run_n_iterations(100, 2_000.0);
+        assert_eq!(message.hinges.len(), 1);
+        // its stress is how close the hinge came to breaking
+        let [load] = message.binding_loads[..] else {
+            panic!("{:?} are not one load", message.binding_loads);
+        };
+        assert_eq!(load.stress, Some(load.impulse));
+        assert!(load
+            .stress
+            .is_some_and(|stress| stress > 0.0 && stress < 1.0));
+        assert_eq!(message.hinge_stressed, [false]);
+        // close to breaking, it is stressed
+        assert!(engine.set_break_impulse(hinge, Some(load.impulse * 1.1)));
+        let message = engine.run_n_iterations(100, 2_000.0);
+        assert_eq!(message.hinge_stressed, [true]);
+
+        assert!(engine.set_break_impulse(hinge, Some(1e-6)));
+        // the break impulse is part of the binding, so it is restored along with it
+        let mut restored = Engine::from(snapshot::Snapshot::from(&engine));
+        for engine in [&mut engine, &mut restored] {
+            let message = engine.run_iteration(2_000.0);
+            assert!(message
+                .events
+                .contains(&GameEvent::BindingBroken { id: hinge }));
+            assert!(message.hinges.is_empty());
+        }
     }
 }
@@ -0,0 +1,197 @@
+//! Semantic gameplay events for hosts that track achievements
+//!
+//! Each milestone is reported as a [`GameEvent::Achievement`], so the host
+//! does not have to derive it from the positions of the shapes. Apart from
+//! [`Achievement::LongAirtime`], which is reported once per flight of the
+//! main ball, every achievement is reported at most once per level.
+
+use std::{os::raw::c_void, rc::Rc};
+
+use bumpalo::{collections::Vec as ScratchVec, Bump};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    game::{GameEvent, Usage},
+    Entity,
+};
+
+/// number of shapes bound together that counts as a structure
+pub const STRUCTURE_SIZE: usize = 10;
+pub const STRUCTURE_SECONDS: f64 = 5.0;
+pub const AIRTIME_SECONDS: f64 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum Achievement {
+    /// the first binding of the level attached to both of its shapes
+    FirstBinding,
+    /// at least [`STRUCTURE_SIZE`] shapes stayed bound together
+    /// for [`STRUCTURE_SECONDS`]
+    StructureSurvived,
+    /// the main ball flew without touching anything for [`AIRTIME_SECONDS`]
+    LongAirtime,
+    /// the level was won without using the eraser
+    WonWithoutErasing,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Achievements {
+    has_binding: bool,
+    structure_survived: bool,
+    /// simulated time for which a large enough structure has existed
+    structure_microseconds: f64,
+    /// simulated time since the main ball last touched anything
+    airtime_microseconds: f64,
+}
+
+fn push(events: &mut Vec<GameEvent>, achievement: Achievement) {
+    events.push(GameEvent::Achievement { achievement });
+}
+
+impl Achievements {
+    pub fn binding_created(&mut self, events: &mut Vec<GameEvent>) {
+        if !self.has_binding {
+            self.has_binding = true;
+            push(events, Achievement::FirstBinding);
+        }
+    }
+
+    pub(super) fn record_structures(
+        &mut self,
+        microseconds: f64,
+        entities: &[Entity],
+        scratch: &Bump,
+        events: &mut Vec<GameEvent>,
+    ) {
+        if self.structure_survived {
+            return;
+        }
+
+        if largest_structure(entities, scratch) < STRUCTURE_SIZE {
+            self.structure_microseconds = 0.0;
+            return;
+        }
+
+        self.structure_microseconds += microseconds;
+        if self.structure_microseconds >= STRUCTURE_SECONDS * 1_000_000.0 {
+            self.structure_survived = true;
+            push(events, Achievement::StructureSurvived);
+        }
+    }
+
+    /// `in_contact` tells whether the main ball touched anything during the step
+    pub fn record_airtime(
+        &mut self,
+        microseconds: f64,
+        in_contact: bool,
+        events: &mut Vec<GameEvent>,
+    ) {
+        if in_contact {
+            self.airtime_microseconds = 0.0;
+            return;
+        }
+
+        let threshold = AIRTIME_SECONDS * 1_000_000.0;
+        let before = self.airtime_microseconds;
+        self.airtime_microseconds += microseconds;
+        if before < threshold && self.airtime_microseconds >= threshold {
+            push(events, Achievement::LongAirtime);
+        }
+    }
+
+    /// a respawned ball starts a new flight
+    pub fn ball_respawned(&mut self) {
+        self.airtime_microseconds = 0.0;
+    }
+
+    pub fn won(&mut self, usage: Usage, events: &mut Vec<GameEvent>) {
+        if usage.erases == 0 {
+            push(events, Achievement::WonWithoutErasing);
+        }
+    }
+}
+
+/// the size of the largest group of entities connected by bindings
+fn largest_structure(entities: &[Entity], scratch: &Bump) -> usize {
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    // sorted by address, to look up the entities bindings point to
+    let mut indices = ScratchVec::from_iter_in(
+        entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (Rc::as_ptr(&entity.shape) as *const c_void, i)),
+        scratch,
+    );
+    indices.sort_unstable_by_key(|&(shape, _)| shape);
+
+    let mut parents = ScratchVec::from_iter_in(0..entities.len(), scratch);
+    for (i, entity) in entities.iter().enumerate() {
+        for (_, _, target) in &entity.bindings {
+            let target = target.as_ptr() as *const c_void;
+            if let Ok(found) = indices.binary_search_by_key(&target, |&(shape, _)| shape) {
+                let j = indices[found].1;
+                let (first, second) = (root(&mut parents, i), root(&mut parents, j));
+                parents[first] = second;
+            }
+        }
+    }
+
+    let mut sizes = bumpalo::vec![in scratch; 0; entities.len()];
+    for i in 0..entities.len() {
+        sizes[root(&mut parents, i)] += 1;
+    }
+    sizes.into_iter().max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_airtime() {
+        let mut achievements = Achievements::default();
+        let mut events = vec![];
+
+        achievements.record_airtime(1_500_000.0, false, &mut events);
+        achievements.record_airtime(1_000.0, true, &mut events);
+        achievements.record_airtime(1_500_000.0, false, &mut events);
+        assert!(events.is_empty());
+
+        achievements.record_airtime(1_500_000.0, false, &mut events);
+        achievements.record_airtime(1_500_000.0, false, &mut events);
+        assert_eq!(
+            events,
+            vec![GameEvent::Achievement {
+                achievement: Achievement::LongAirtime
+            }]
+        );
+    }
+
+    #[test]
+    fn test_won_without_erasing() {
+        let mut achievements = Achievements::default();
+        let mut events = vec![];
+
+        achievements.won(
+            Usage {
+                erases: 1,
+                ..Default::default()
+            },
+            &mut events,
+        );
+        assert!(events.is_empty());
+
+        achievements.won(Usage::default(), &mut events);
+        assert_eq!(events.len(), 1);
+    }
+}
@@ -0,0 +1,277 @@
+//! Assisted drawing, turning the raw samples of a stroke into a clean shape
+//!
+//! Instead of the hull of whatever the pointer went through, a stroke becomes
+//! a straight plank from its first sample to its last, the circle that fits
+//! its samples best, or the axis-aligned box around them. The engine does the
+//! fitting so that the editor and the game draw the same shapes from the same
+//! strokes, and [`Engine::assist`] lets the frontend preview the shape before
+//! [`Engine::add_assisted`] adds it.
+//!
+//! The shapes follow the [snapping](super::snap) of the engine: a plank is first turned to
+//! the closest angle increment, then the points that define the shape snap to
+//! the grid, and then to the edges of the shapes within the edge distance, so
+//! that a plank ends right on the edge it was drawn to. A circle keeps its
+//! center on the grid and instead grows or shrinks to touch the edge closest
+//! to it. As the shape is already snapped, it is added as it is.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    ids::EntityId,
+    shape::{Circle, Collidable, Polygon},
+    snap::round_to,
+    Engine,
+};
+use crate::geometry::{Aabb, Point};
+
+/// What a stroke is turned into
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum Stroke {
+    Plank { thickness: f64 },
+    Circle,
+    Box,
+}
+
+/// The shape fitted to a stroke, in the coordinates relative to the current origin
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum AssistedShape {
+    Polygon { vertices: Vec<Point> },
+    Circle { center: Point, radius: f64 },
+}
+
+fn is_finite(point: Point) -> bool {
+    point.0.is_finite() && point.1.is_finite()
+}
+
+/// the quadrilateral with the corners in drawing order, or `None` if it has no area
+fn quadrilateral(corners: [Point; 4]) -> Option<AssistedShape> {
+    let mass = Polygon::new(corners.to_vec()).collision_data_mut().mass;
+    (mass.is_finite() && mass > 0.0).then(|| AssistedShape::Polygon {
+        vertices: corners.to_vec(),
+    })
+}
+
+impl Engine {
+    /// the point on the edge of any polygon or circle closest to `point`
+    fn closest_edge_point(&self, point: Point) -> Option<Point> {
+        let polygons = self
+            .polygons
+            .iter()
+            .filter_map(|polygon| polygon.shape.upgrade())
+            .flat_map(|polygon| {
+                let polygon = polygon.borrow();
+                let vertices = polygon.vertices();
+                let edges = vertices.iter().zip(vertices.iter().cycle().skip(1));
+                edges
+                    .map(|(&start, &end)| point.closest_on_segment(start, end))
+                    .collect::<Vec<_>>()
+            });
+        let circles = self
+            .circles
+            .iter()
+            .filter_map(|circle| circle.shape.upgrade())
+            .map(|circle| {
+                let (center, radius) = circle.borrow().bounding_circle();
+                center + center.to(point).unit() * radius
+            });
+
+        polygons
+            .chain(circles)
+            .filter(|&closest| is_finite(closest))
+            .min_by(|first, second| {
+                let distance = |closest: &Point| closest.to(point).norm();
+                distance(first).total_cmp(&distance(second))
+            })
+    }
+
+    /// the point snapped to the grid, and then to an edge within the edge distance
+    fn snap_to_edge(&self, point: Point) -> Point {
+        let point = self.snap_point(point);
+        match self.snapping.edge_distance {
+            Some(distance) if distance > 0.0 => self
+                .closest_edge_point(point)
+                .filter(|closest| closest.to(point).norm() <= distance)
+                .unwrap_or(point),
+            _ => point,
+        }
+    }
+
+    fn assist_plank(&self, start: Point, end: Point, thickness: f64) -> Option<AssistedShape> {
+        if !(thickness > 0.0 && thickness.is_finite()) {
+            return None;
+        }
+        let along = start.to(end);
+        let angle = along.1.atan2(along.0);
+        let turned = round_to(angle, self.snapping.angle_increment);
+        let end = start + Point(turned.cos(), turned.sin()) * along.norm();
+
+        let (start, end) = (self.snap_to_edge(start), self.snap_to_edge(end));
+        let across = start.to(end).unit().perpendicular() * (thickness / 2.0);
+        if !is_finite(across) {
+            return None;
+        }
+        quadrilateral([start + across, end + across, end - across, start - across])
+    }
+
+    fn assist_circle(&self, samples: &[Point]) -> Option<AssistedShape> {
+        let count = samples.len() as f64;
+        let fitted = samples
+            .iter()
+            .fold(Point(0.0, 0.0), |sum, &sample| sum + sample)
+            / count;
+        let radius = samples
+            .iter()
+            .map(|sample| fitted.to(*sample).norm())
+            .sum::<f64>()
+            / count;
+
+        let center = self.snap_point(fitted);
+        let touching = match self.snapping.edge_distance {
+            Some(distance) if distance > 0.0 => self
+                .closest_edge_point(center)
+                .map(|closest| center.to(closest).norm())
+                .filter(|touching| (touching - radius).abs() <= distance),
+            _ => None,
+        };
+        let radius = touching.unwrap_or(radius);
+
+        (radius > 0.0 && radius.is_finite() && is_finite(center))
+            .then_some(AssistedShape::Circle { center, radius })
+    }
+
+    fn assist_box(&self, samples: &[Point]) -> Option<AssistedShape> {
+        let Aabb { min, max } = Aabb::around(samples.iter().copied());
+        let Aabb { min, max } = Aabb::around([self.snap_to_edge(min), self.snap_to_edge(max)]);
+        quadrilateral([min, Point(max.0, min.1), max, Point(min.0, max.1)])
+    }
+
+    /// the shape the stroke through `samples` turns into, or `None` if it
+    /// leaves nothing with an area, like a stroke with a single sample
+    pub fn assist(&self, samples: &[Point], stroke: Stroke) -> Option<AssistedShape> {
+        let samples: Vec<_> = samples.iter().copied().filter(|&p| is_finite(p)).collect();
+        let (&first, &last) = (samples.first()?, samples.last()?);
+        if samples.len() < 2 {
+            return None;
+        }
+
+        match stroke {
+            Stroke::Plank { thickness } => self.assist_plank(first, last, thickness),
+            Stroke::Circle => self.assist_circle(&samples),
+            Stroke::Box => self.assist_box(&samples),
+        }
+    }
+
+    /// adds the shape of [`Engine::assist`] as drawn by the player, under the
+    /// same limits as [`Engine::add_polygon`] and [`Engine::add_circle`]
+    pub fn add_assisted(&mut self, samples: &[Point], stroke: Stroke) -> Option<EntityId> {
+        match self.assist(samples, stroke)? {
+            AssistedShape::Polygon { vertices } => self.add_drawn_polygon(Polygon::new(vertices)),
+            AssistedShape::Circle { center, radius } => {
+                self.add_drawn_circle(Circle::new(center, radius))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::{FRAC_PI_4, TAU};
+
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig, snap::Snapping};
+
+    fn engine(snapping: Snapping) -> Engine {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (-3.0, 2.0), (3.0, 2.0), (3.0, 2.2), (-3.0, 2.2) },
+            true,
+        );
+        engine.set_snapping(snapping);
+        engine
+    }
+
+    fn vertices(shape: Option<AssistedShape>) -> Vec<Point> {
+        let Some(AssistedShape::Polygon { mut vertices }) = shape else {
+            panic!("{shape:?} is not a polygon");
+        };
+        vertices.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        vertices
+    }
+
+    #[test]
+    fn test_plank() {
+        let engine = engine(Snapping {
+            angle_increment: Some(FRAC_PI_4),
+            edge_distance: Some(0.1),
+            ..Snapping::default()
+        });
+        // a shaky stroke, a little steeper than 45 degrees, ending just above the floor
+        let samples = [Point(1.0, 0.05), Point(2.3, 1.0), Point(2.85, 1.95)];
+        let stroke = Stroke::Plank { thickness: 0.2 };
+
+        let plank = vertices(engine.assist(&samples, stroke));
+        assert_eq!(plank.len(), 4);
+        let (start, end) = ((plank[0] + plank[1]) / 2.0, (plank[2] + plank[3]) / 2.0);
+        assert!(start.to(Point(1.0, 0.05)).norm() < 1e-9, "{start:?}");
+        assert!((end.1 - 2.0).abs() < 1e-9, "{end:?}");
+        assert!((plank[0].to(plank[1]).norm() - 0.2).abs() < 1e-9);
+
+        assert_eq!(engine.assist(&samples[..1], stroke), None);
+        assert_eq!(
+            engine.assist(&samples, Stroke::Plank { thickness: 0.0 }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_circle_and_box() {
+        let mut engine = engine(Snapping {
+            grid: Some(0.5),
+            edge_distance: Some(0.2),
+            ..Snapping::default()
+        });
+
+        // a wobbly loop around (1, 1.1), whose radius grows to touch the floor
+        let samples: Vec<_> = (0..24)
+            .map(|i| {
+                let angle = TAU * i as f64 / 24.0;
+                let radius = 0.85 + 0.05 * (3.0 * angle).sin();
+                Point(1.0, 1.1) + Point(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+        let Some(AssistedShape::Circle { center, radius }) =
+            engine.assist(&samples, Stroke::Circle)
+        else {
+            panic!("no circle");
+        };
+        assert_eq!(center, Point(1.0, 1.0));
+        assert!((radius - 1.0).abs() < 1e-9, "{radius}");
+
+        // the corners go to the grid, and the bottom one further onto the floor
+        let samples = [Point(-1.1, 0.6), Point(-0.4, 1.3), Point(0.1, 1.95)];
+        let corners = vertices(engine.assist(&samples, Stroke::Box));
+        assert_eq!(
+            corners,
+            [
+                Point(-1.0, 0.5),
+                Point(-1.0, 2.0),
+                Point(0.0, 0.5),
+                Point(0.0, 2.0)
+            ]
+        );
+
+        let polygons = engine.usage().polygons;
+        let id = engine.add_assisted(&samples, Stroke::Box).unwrap();
+        assert_eq!(engine.usage().polygons, polygons + 1);
+        let slot = engine.slots.get(id).unwrap();
+        assert_eq!(
+            engine.entities[slot].shape.borrow().aabb().max,
+            Point(0.0, 2.0)
+        );
+        assert!(engine.add_assisted(&samples, Stroke::Circle).is_some());
+    }
+}
@@ -0,0 +1,179 @@
+//! The main ball, as the level configures it
+//!
+//! The level gives the ball its radius, density, material and color, and the
+//! velocity it starts with. The material multiplies the restitution and the
+//! friction of every contact of the ball on top of the multipliers of the
+//! engine. Size pickups scale the configured radius, and a respawning ball
+//! starts over with the initial velocity.
+//!
+//! [`Engine::set_main_ball`] changes the ball while the level runs, and the
+//! level of the editor along with it, see [`super::editor`].
+
+use std::{ffi::c_void, rc::Rc};
+
+use super::{
+    ids::EntityId,
+    layer,
+    shape::{Circle, Collidable},
+    Engine, EntityCfg,
+};
+use crate::{
+    geometry::{Point, Vector},
+    levels::MainBall,
+};
+
+fn is_valid(main_ball: &MainBall) -> bool {
+    let Point(x, y) = main_ball.initial_velocity;
+    main_ball.radius > 0.0
+        && main_ball.radius.is_finite()
+        && main_ball.density > 0.0
+        && main_ball.density.is_finite()
+        && main_ball.restitution >= 0.0
+        && main_ball.restitution.is_finite()
+        && main_ball.friction >= 0.0
+        && main_ball.friction.is_finite()
+        && x.is_finite()
+        && y.is_finite()
+        && main_ball
+            .color
+            .is_none_or(|color| color.iter().all(|channel| (0.0..=1.0).contains(channel)))
+}
+
+/// a ball at `position`, `density` times as heavy as other shapes of its size
+pub(super) fn shape(position: Point, radius: f64, density: f64, velocity: Vector) -> Circle {
+    let mut ball = Circle::new(position, radius);
+    let data = ball.collision_data_mut();
+    data.mass *= density;
+    data.inertia *= density;
+    data.velocity = velocity;
+    ball
+}
+
+impl Engine {
+    /// adds a ball on the layer of the main ball, `color` or painted from the palette
+    pub(super) fn add_ball(&mut self, ball: Circle, color: Option<[f32; 3]>) -> EntityId {
+        let (id, weak) = self.add_entity(
+            ball,
+            EntityCfg {
+                is_erasable: false,
+                render_layer: layer::BALL,
+                ..Default::default()
+            },
+        );
+        let mut colored = self.painter.paint(weak);
+        if let Some(color) = color {
+            colored.color = color;
+        }
+        self.circles.push(colored);
+        id
+    }
+
+    pub fn main_ball(&self) -> MainBall {
+        self.main_ball
+    }
+
+    /// resizes the ball and changes its mass, material and color where it is,
+    /// the initial velocity applies from the next respawn. A `None` color keeps
+    /// the color of the ball. Returns whether the ball is valid, with positive
+    /// and finite dimensions and a material that is not negative
+    pub fn set_main_ball(&mut self, main_ball: MainBall) -> bool {
+        if !is_valid(&main_ball) {
+            return false;
+        }
+
+        let ball = &self.entities[0].shape;
+        {
+            let mut ball = ball.borrow_mut();
+            let radius = ball.bounding_circle().1;
+            ball.scale(main_ball.radius * self.ball_size.scale() / radius);
+            let data = ball.collision_data_mut();
+            data.mass *= main_ball.density / self.main_ball.density;
+            data.inertia *= main_ball.density / self.main_ball.density;
+        }
+        if let Some(color) = main_ball.color {
+            let ball = Rc::as_ptr(ball) as *const c_void;
+            for circle in &mut self.circles {
+                if circle.shape.as_ptr() as *const c_void == ball {
+                    circle.color = color;
+                }
+            }
+        }
+
+        self.main_ball = main_ball;
+        if let Some(level) = &mut self.level {
+            level.main_ball = main_ball;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{sandbox::SandboxConfig, snapshot::Snapshot};
+
+    #[test]
+    fn test_main_ball() {
+        let main_ball = MainBall {
+            radius: 0.2,
+            density: 3.0,
+            color: Some([0.1, 0.2, 0.3]),
+            initial_velocity: Point(1.0, 0.5),
+            ..MainBall::default()
+        };
+        let mut level = Engine::sandbox(SandboxConfig::default())
+            .export_level()
+            .unwrap();
+        level.polygons.clear();
+        level.main_ball = main_ball;
+        level.world_half_extent = Some(2.0);
+        let mut engine = Engine::new(level);
+        let message = engine.run_iteration(2_000.0);
+        assert_eq!(message.circles[0].color, [0.1, 0.2, 0.3]);
+        assert!((message.circles[0].shape.radius - 0.2).abs() < 1e-12);
+        let mass = engine.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .mass;
+        let default_mass = Circle::new(Point(0.0, 0.0), 0.2).collision_data_mut().mass;
+        assert!((mass - 3.0 * default_mass).abs() < 1e-12);
+        assert!(engine.body_states()[0].velocity.0 > 0.9);
+
+        // it leaves the world, and starts over at the same speed
+        let start = engine.body_states()[0].centroid;
+        for _ in 0..10_000 {
+            let before = engine.body_states()[0].centroid;
+            engine.run_iteration(2_000.0);
+            if engine.body_states()[0].centroid.0 < before.0 {
+                break;
+            }
+        }
+        assert!(engine.body_states()[0].centroid.0 < start.0 + 0.01);
+        assert_eq!(engine.body_states()[0].velocity, Point(1.0, 0.5));
+
+        let bigger = MainBall {
+            radius: 0.3,
+            density: 1.0,
+            restitution: 0.5,
+            color: None,
+            ..main_ball
+        };
+        assert!(engine.set_main_ball(bigger));
+        let message = engine.run_iteration(2_000.0);
+        assert_eq!(message.circles[0].color, [0.1, 0.2, 0.3]);
+        assert!((message.circles[0].shape.radius - 0.3).abs() < 1e-12);
+        assert_eq!(engine.export_level().unwrap().main_ball, bigger);
+        assert!(!engine.set_main_ball(MainBall {
+            radius: 0.0,
+            ..bigger
+        }));
+        assert!(!engine.set_main_ball(MainBall {
+            initial_velocity: Point(f64::NAN, 0.0),
+            ..bigger
+        }));
+
+        let restored = Engine::from(Snapshot::from(&engine));
+        assert_eq!(restored.main_ball(), bigger);
+    }
+}
@@ -0,0 +1,313 @@
+//! Adding many drawn shapes in one call
+//!
+//! Loading a large structure shape by shape costs a call across the wasm
+//! boundary and a binding scan over every entity for each shape. A batch is
+//! validated as a whole first, so that it is either added completely or not at
+//! all, and each of its shapes only tries binding to the entities that still
+//! have unbound bindings.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    compute,
+    game::Tool,
+    ids::{BindingId, EntityId},
+    shape::{Bounded, Circle, Polygon},
+    Engine, EntityCfg,
+};
+use crate::geometry::Point;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BatchShape {
+    Circle {
+        center: Point,
+        radius: f64,
+    },
+    /// the convex hull of the vertices is added, as with [`Engine::add_polygon`] in wasm
+    Polygon {
+        vertices: Vec<Point>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BatchEntry {
+    pub shape: BatchShape,
+    /// anchors of hinges on the shape, which bind to the next shape added over them
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub hinges: Vec<Point>,
+    /// anchors of rigid bindings on the shape, which bind the same way as the hinges
+    #[serde(default)]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub rigids: Vec<Point>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ShapeBatch {
+    pub entries: Vec<BatchEntry>,
+}
+
+/// the ids of everything a batch added, in the order of its entries
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct AddedShapes {
+    pub entities: Vec<EntityId>,
+    pub hinges: Vec<BindingId>,
+    pub rigids: Vec<BindingId>,
+}
+
+enum Built {
+    Circle(Circle),
+    Polygon(Polygon),
+}
+
+impl Built {
+    fn new(shape: BatchShape) -> Option<Self> {
+        match shape {
+            BatchShape::Circle { center, radius } => {
+                let is_valid = center.0.is_finite()
+                    && center.1.is_finite()
+                    && radius.is_finite()
+                    && radius > 0.0;
+                is_valid.then(|| Self::Circle(Circle::new(center, radius)))
+            }
+            BatchShape::Polygon { vertices } => {
+                let is_valid = !vertices.is_empty()
+                    && vertices
+                        .iter()
+                        .all(|vertex| vertex.0.is_finite() && vertex.1.is_finite());
+                is_valid.then(|| Self::Polygon(compute::hull::<24>(vertices.into_iter())))
+            }
+        }
+    }
+
+    fn includes(&self, point: Point) -> bool {
+        match self {
+            Self::Circle(circle) => circle.includes(point),
+            Self::Polygon(polygon) => polygon.includes(point),
+        }
+    }
+}
+
+impl Engine {
+    /// adds every shape of the batch along with its bindings, as if drawn one
+    /// after another. Adds nothing if any shape is degenerate, any anchor lies
+    /// outside of its shape, or the batch would exceed a tool limit of the level
+    /// or a cap of the engine
+    pub fn add_shapes(&mut self, batch: ShapeBatch) -> Option<AddedShapes> {
+        let mut built = Vec::with_capacity(batch.entries.len());
+        for entry in batch.entries {
+            let shape = Built::new(entry.shape)?;
+            let anchors_fit = entry
+                .hinges
+                .iter()
+                .chain(&entry.rigids)
+                .all(|&anchor| shape.includes(anchor));
+            if !anchors_fit {
+                return None;
+            }
+            built.push((shape, entry.hinges, entry.rigids));
+        }
+
+        let count = |tool: Tool| -> u32 {
+            built
+                .iter()
+                .map(|(shape, hinges, rigids)| match (tool, shape) {
+                    (Tool::Circle, Built::Circle(_)) | (Tool::Polygon, Built::Polygon(_)) => 1,
+                    (Tool::Hinge, _) => hinges.len(),
+                    (Tool::Rigid, _) => rigids.len(),
+                    _ => 0,
+                })
+                .sum::<usize>() as u32
+        };
+        let (usage, limits) = (self.game.usage(), self.game.tool_limits());
+        let fits_limits = [Tool::Circle, Tool::Polygon, Tool::Hinge, Tool::Rigid]
+            .into_iter()
+            .all(|tool| {
+                limits
+                    .of(tool)
+                    .is_none_or(|limit| usage.of(tool) + count(tool) <= limit)
+            });
+        if !fits_limits {
+            return None;
+        }
+        let shapes = built.len();
+        let bindings = count(Tool::Hinge) + count(Tool::Rigid);
+        // a circle stands for every drawn shape, as they share the entity cap
+        if !self.has_room_for(Tool::Circle, shapes)
+            || !self.has_room_for(Tool::Hinge, bindings as usize)
+        {
+            return None;
+        }
+
+        // only the entities with unbound bindings can bind to the new shapes
+        let mut waiting: Vec<usize> = (0..self.entities.len())
+            .filter(|&i| !self.entities[i].unbound.is_empty())
+            .collect();
+        let mut created = false;
+        let mut added = AddedShapes::default();
+        for (shape, hinges, rigids) in built {
+            let id = match shape {
+                Built::Circle(circle) => {
+                    self.game.try_use(Tool::Circle);
+                    let (id, weak_circle) = self.insert_entity(circle, EntityCfg::default());
+                    self.circles.push(self.painter.paint(weak_circle));
+                    id
+                }
+                Built::Polygon(polygon) => {
+                    self.game.try_use(Tool::Polygon);
+                    let (id, weak_polygon) = self.insert_entity(polygon, EntityCfg::default());
+                    self.polygons.push(self.painter.paint(weak_polygon));
+                    id
+                }
+            };
+            added.entities.push(id);
+
+            let new = self.entities.len() - 1;
+            let new_shape = self.entities[new].shape.clone();
+            for &i in &waiting {
+                created |= self.entities[i].try_bind(&new_shape);
+            }
+            waiting.retain(|&i| !self.entities[i].unbound.is_empty());
+
+            for at in hinges {
+                self.game.try_use(Tool::Hinge);
+                let binding = self.binding_ids.generate();
+                self.entities[new].add_hinge(binding, at, 0.0);
+                self.placed_bindings.push(binding);
+                added.hinges.push(binding);
+            }
+            let compliance = self.rigid_compliance();
+            for at in rigids {
+                self.game.try_use(Tool::Rigid);
+                let binding = self.binding_ids.generate();
+                self.entities[new].add_rigid(binding, at, compliance);
+                self.placed_bindings.push(binding);
+                added.rigids.push(binding);
+            }
+            if !self.entities[new].unbound.is_empty() {
+                waiting.push(new);
+            }
+        }
+
+        if created {
+            self.achievements.binding_created(&mut self.events);
+        }
+        Some(added)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::sandbox::SandboxConfig;
+
+    fn square(x: f64, y: f64) -> BatchShape {
+        BatchShape::Polygon {
+            vertices: vec![
+                Point(x, y),
+                Point(x + 1.0, y),
+                Point(x + 1.0, y + 1.0),
+                Point(x, y + 1.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_add_shapes() {
+        let mut batched = Engine::sandbox(SandboxConfig::default());
+        let mut one_by_one = Engine::sandbox(SandboxConfig::default());
+
+        let added = batched
+            .add_shapes(ShapeBatch {
+                entries: vec![
+                    BatchEntry {
+                        shape: square(1.0, 1.0),
+                        hinges: vec![Point(1.8, 1.5)],
+                        rigids: vec![],
+                    },
+                    BatchEntry {
+                        shape: square(1.6, 1.0),
+                        hinges: vec![],
+                        rigids: vec![],
+                    },
+                ],
+            })
+            .unwrap();
+        assert_eq!(added.entities.len(), 2);
+        assert_eq!(added.hinges.len(), 1);
+        assert!(batched
+            .entities
+            .iter()
+            .any(|entity| !entity.bindings.is_empty()));
+
+        // the same hulls as the batch, so that the vertices can be compared
+        let hull = |shape| match shape {
+            BatchShape::Polygon { vertices } => compute::hull::<24>(vertices.into_iter()),
+            BatchShape::Circle { .. } => unreachable!(),
+        };
+        one_by_one.add_polygon(hull(square(1.0, 1.0)));
+        one_by_one.add_hinge(Point(1.8, 1.5), 0.0);
+        one_by_one.add_polygon(hull(square(1.6, 1.0)));
+
+        // the hinge holds the squares together in both
+        let batched = batched.run_n_iterations(100, 2000.0);
+        let one_by_one = one_by_one.run_n_iterations(100, 2000.0);
+        for (batched, one_by_one) in batched.polygons.iter().zip(&one_by_one.polygons) {
+            for (a, b) in batched
+                .shape
+                .vertices
+                .iter()
+                .zip(&one_by_one.shape.vertices)
+            {
+                assert!(a.is_close_enough_to(*b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_batch() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let entities = engine.entities.len();
+
+        let outside_anchor = ShapeBatch {
+            entries: vec![
+                BatchEntry {
+                    shape: square(1.0, 1.0),
+                    hinges: vec![],
+                    rigids: vec![],
+                },
+                BatchEntry {
+                    shape: BatchShape::Circle {
+                        center: Point(4.0, 4.0),
+                        radius: 0.5,
+                    },
+                    hinges: vec![Point(1.5, 1.5)],
+                    rigids: vec![],
+                },
+            ],
+        };
+        assert_eq!(engine.add_shapes(outside_anchor), None);
+
+        let degenerate = ShapeBatch {
+            entries: vec![BatchEntry {
+                shape: BatchShape::Circle {
+                    center: Point(4.0, 4.0),
+                    radius: f64::NAN,
+                },
+                hinges: vec![],
+                rigids: vec![],
+            }],
+        };
+        assert_eq!(engine.add_shapes(degenerate), None);
+
+        assert_eq!(engine.entities.len(), entities);
+        assert_eq!(engine.game.usage().of(Tool::Polygon), 0);
+    }
+}
@@ -1,13 +1,24 @@
-use std::time::Duration;
+use crate::{
+    geometry::{Point, Vector},
+    trace::span,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
-use crate::geometry::{Point, Vector};
+use super::{
+    compute::simplex::Vertex, motor::HingeMotor, shape::Collidable, tuning::SolverTuning,
+    MOVEMENT_COEFFICIENT,
+};
 
-use super::{compute::simplex::Vertex, shape::Collidable};
+/// how close the ends of a pulley get to its anchors, as if they hit the wheels there
+const PULLEY_WHEEL_RADIUS: f64 = 0.05;
 
 /// Refers to a point on a shape. The shape may be translated or rotated
 /// without invalidating this reference, since the reference refers to
 /// the point relative to center and the first vertex
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct PointOnShape {
     pub angle_offset: f64,
     pub length_scale: f64,
@@ -19,19 +30,205 @@ impl PointOnShape {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub enum Binding {
     Hinge {
         first: PointOnShape,
         second: PointOnShape,
+        /// turns the second shape relative to the first one,
+        /// missing from the bindings of snapshots taken before hinges had motors
+        #[serde(default)]
+        #[cfg_attr(feature = "wasm", tsify(optional))]
+        motor: Option<HingeMotor>,
+        /// the binding breaks when holding the shapes together takes a larger impulse,
+        /// missing from the bindings of snapshots taken before bindings could break
+        #[serde(default)]
+        #[cfg_attr(feature = "wasm", tsify(optional))]
+        break_impulse: Option<f64>,
+        /// like the one of a rigid binding, missing from the bindings of snapshots
+        /// taken before hinges could give
+        #[serde(default)]
+        #[cfg_attr(feature = "wasm", tsify(optional))]
+        compliance: f64,
+        /// the torque against the shapes turning relative to each other per radian per
+        /// second they turn, missing from the bindings of snapshots taken before hinges
+        /// could be damped
+        #[serde(default)]
+        #[cfg_attr(feature = "wasm", tsify(optional))]
+        damping: f64,
     },
     Rigid {
         first: (PointOnShape, PointOnShape),
         second: (PointOnShape, PointOnShape),
+        #[serde(default)]
+        #[cfg_attr(feature = "wasm", tsify(optional))]
+        break_impulse: Option<f64>,
+        /// how far the binding gives per unit of force, zero for a perfectly stiff one,
+        /// missing from the bindings of snapshots taken before rigid bindings could give
+        #[serde(default)]
+        #[cfg_attr(feature = "wasm", tsify(optional))]
+        compliance: f64,
+    },
+    /// pulls the two points together when stretched beyond the rest length,
+    /// and pushes them apart when compressed
+    Spring {
+        first: PointOnShape,
+        second: PointOnShape,
+        rest_length: f64,
+        /// force per unit of length away from the rest length
+        stiffness: f64,
+        /// force per unit of length per second the ends move apart or together
+        damping: f64,
+    },
+    /// keeps the two points at most the max length apart, but lets them come closer
+    Rope {
+        first: PointOnShape,
+        second: PointOnShape,
+        max_length: f64,
+    },
+    /// a rope from the first point over the two fixed anchors to the second point,
+    /// the length of the first side plus the ratio times the length of the second
+    /// side is at most the total length
+    Pulley {
+        first: PointOnShape,
+        second: PointOnShape,
+        /// the fixed points the rope runs over, next to the first and the second point
+        anchors: [Point; 2],
+        length: f64,
+        ratio: f64,
     },
 }
 
 impl Binding {
+    /// where the binding is drawn, given the shape owning it
+    pub fn anchor(&self, shape: &(impl Collidable + ?Sized)) -> Point {
+        match self {
+            Self::Hinge { first, .. }
+            | Self::Spring { first, .. }
+            | Self::Rope { first, .. }
+            | Self::Pulley { first, .. } => first.on(shape),
+            Self::Rigid {
+                first: (p1, p2), ..
+            } => (p1.on(shape) + p2.on(shape)) * 0.5,
+        }
+    }
+
+    /// the ends of a spring or a rope, given the shape owning it and the one it
+    /// binds to, `None` for the other bindings
+    pub fn ends(
+        &self,
+        shape: &(impl Collidable + ?Sized),
+        target: &(impl Collidable + ?Sized),
+    ) -> Option<[Point; 2]> {
+        match self {
+            Self::Spring { first, second, .. } | Self::Rope { first, second, .. } => {
+                Some([first.on(shape), second.on(target)])
+            }
+            _ => None,
+        }
+    }
+
+    /// the rope of a pulley from its point on the shape owning it over the anchors
+    /// to its point on the one it binds to, `None` for the other bindings
+    pub fn pulley_path(
+        &self,
+        shape: &(impl Collidable + ?Sized),
+        target: &(impl Collidable + ?Sized),
+    ) -> Option<[Point; 4]> {
+        match self {
+            Self::Pulley {
+                first,
+                second,
+                anchors: [first_anchor, second_anchor],
+                ..
+            } => Some([
+                first.on(shape),
+                *first_anchor,
+                *second_anchor,
+                second.on(target),
+            ]),
+            _ => None,
+        }
+    }
+
+    /// the largest impulse a hinge or a rigid binding takes, `None` for the
+    /// bindings that do not break
+    pub fn break_impulse(&self) -> Option<f64> {
+        match self {
+            Self::Hinge { break_impulse, .. } | Self::Rigid { break_impulse, .. } => *break_impulse,
+            _ => None,
+        }
+    }
+
+    /// returns whether the binding is a hinge or a rigid binding, the others do not break
+    pub fn set_break_impulse(&mut self, impulse: Option<f64>) -> bool {
+        match self {
+            Self::Hinge { break_impulse, .. } | Self::Rigid { break_impulse, .. } => {
+                *break_impulse = impulse;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// returns whether the binding is a hinge, the others do not turn
+    pub fn set_damping(&mut self, value: f64) -> bool {
+        match self {
+            Self::Hinge { damping, .. } => {
+                *damping = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// moves the points of the binding that are fixed in the world instead of on a shape
+    pub fn translate(&mut self, translation: Vector) {
+        if let Self::Pulley { anchors, .. } = self {
+            for anchor in anchors {
+                *anchor += translation;
+            }
+        }
+    }
+
+    /// hinges the two shapes together at the point, whether they include it or not
+    pub fn hinge_at(
+        shape1: &(impl Collidable + ?Sized),
+        shape2: &(impl Collidable + ?Sized),
+        point: Point,
+        compliance: f64,
+    ) -> Self {
+        Self::Hinge {
+            first: shape1.create_point_reference(point),
+            second: shape2.create_point_reference(point),
+            motor: None,
+            break_impulse: None,
+            compliance,
+            damping: 0.0,
+        }
+    }
+
+    /// rigidly binds the two shapes together at the point, whether they include it or not
+    pub fn rigid_at(
+        shape1: &(impl Collidable + ?Sized),
+        shape2: &(impl Collidable + ?Sized),
+        point: Point,
+        compliance: f64,
+    ) -> Self {
+        let first_left = shape1.create_point_reference(point + Point(0.2, 0.0));
+        let first_right = shape1.create_point_reference(point - Point(0.2, 0.0));
+        let second_left = shape2.create_point_reference(point + Point(0.2, 0.0));
+        let second_right = shape2.create_point_reference(point - Point(0.2, 0.0));
+
+        Self::Rigid {
+            first: (first_left, first_right),
+            second: (second_left, second_right),
+            break_impulse: None,
+            compliance,
+        }
+    }
+
     /// attempts to bind the two shapes together
     /// it is assumed that the unbound binding is attached to the first shape
     pub fn try_bind(
@@ -40,7 +237,10 @@ impl Binding {
         shape2: &(impl Collidable + ?Sized),
     ) -> Option<Self> {
         match unbound {
-            Unbound::Hinge(first) => {
+            Unbound::Hinge {
+                point: first,
+                damping,
+            } => {
                 let point = shape1.resolve_point_reference(first);
                 if !shape2.includes(point) {
                     return None;
@@ -48,113 +248,526 @@ impl Binding {
 
                 let second = shape2.create_point_reference(point);
 
-                Some(Self::Hinge { first, second })
+                Some(Self::Hinge {
+                    first,
+                    second,
+                    motor: None,
+                    break_impulse: None,
+                    compliance: 0.0,
+                    damping,
+                })
             }
-            Unbound::Rigid(first) => {
+            Unbound::Rigid {
+                point: first,
+                compliance,
+            } => {
                 let point = shape1.resolve_point_reference(first);
                 if !shape2.includes(point) {
                     return None;
                 }
 
-                let first_left = shape1.create_point_reference(point + Point(0.2, 0.0));
-                let first_right = shape1.create_point_reference(point - Point(0.2, 0.0));
-                let second_left = shape2.create_point_reference(point + Point(0.2, 0.0));
-                let second_right = shape2.create_point_reference(point - Point(0.2, 0.0));
-
-                Some(Self::Rigid {
-                    first: (first_left, first_right),
-                    second: (second_left, second_right),
-                })
+                Some(Self::rigid_at(shape1, shape2, point, compliance))
             }
         }
     }
 
-    /// enforces the spacial constraints of this binding
+    /// enforces the spacial constraints of this binding, returning the largest
+    /// impulse it took to hold the shapes together, leaving out the torque of a motor
+    #[allow(clippy::too_many_arguments)]
     pub fn enforce(
         self,
         shape1: &mut dyn Collidable,
         shape2: &mut dyn Collidable,
         microseconds: f64,
+        tuning: &SolverTuning,
         restitution_mulipiler: f64,
         friction_mulipiler: f64,
         static_friction_enabled: bool,
         dynamic_friction_enabled: bool,
-    ) {
+    ) -> f64 {
+        span!(TRACE, "binding enforcement");
         match self {
-            Self::Hinge { first, second } => Self::enforce_hinge(
-                (shape1, first),
-                (shape2, second),
-                microseconds,
-                restitution_mulipiler,
-                friction_mulipiler,
-                static_friction_enabled,
-                dynamic_friction_enabled,
-            ),
-            Self::Rigid { first, second } => {
-                Self::enforce_hinge(
+            Self::Hinge {
+                first,
+                second,
+                motor,
+                compliance,
+                damping,
+                ..
+            } => {
+                let impulse = if compliance > 0.0 {
+                    Self::enforce_soft_hinge(
+                        (shape1, first),
+                        (shape2, second),
+                        microseconds,
+                        compliance,
+                    )
+                } else {
+                    Self::enforce_hinge(
+                        (shape1, first),
+                        (shape2, second),
+                        microseconds,
+                        tuning,
+                        restitution_mulipiler,
+                        friction_mulipiler,
+                        static_friction_enabled,
+                        dynamic_friction_enabled,
+                    )
+                };
+                if damping > 0.0 {
+                    Self::enforce_damping(shape1, shape2, microseconds, damping);
+                }
+                if let Some(motor) = motor {
+                    Self::enforce_motor(shape1, shape2, microseconds, motor);
+                }
+                impulse
+            }
+            Self::Rigid {
+                first,
+                second,
+                compliance,
+                ..
+            } if compliance > 0.0 => {
+                let first_impulse = Self::enforce_soft_hinge(
+                    (shape1, first.0),
+                    (shape2, second.0),
+                    microseconds,
+                    compliance,
+                );
+                let second_impulse = Self::enforce_soft_hinge(
+                    (shape1, first.1),
+                    (shape2, second.1),
+                    microseconds,
+                    compliance,
+                );
+                first_impulse.max(second_impulse)
+            }
+            Self::Rigid { first, second, .. } => {
+                let first_impulse = Self::enforce_hinge(
                     (shape1, first.0),
                     (shape2, second.0),
                     microseconds,
+                    tuning,
                     restitution_mulipiler,
                     friction_mulipiler,
                     static_friction_enabled,
                     dynamic_friction_enabled,
                 );
-                Self::enforce_hinge(
+                let second_impulse = Self::enforce_hinge(
                     (shape1, first.1),
                     (shape2, second.1),
                     microseconds,
+                    tuning,
                     restitution_mulipiler,
                     friction_mulipiler,
                     static_friction_enabled,
                     dynamic_friction_enabled,
                 );
+                first_impulse.max(second_impulse)
             }
+            Self::Spring {
+                first,
+                second,
+                rest_length,
+                stiffness,
+                damping,
+            } => Self::enforce_spring(
+                (shape1, first),
+                (shape2, second),
+                microseconds,
+                rest_length,
+                stiffness,
+                damping,
+            ),
+            Self::Rope {
+                first,
+                second,
+                max_length,
+            } => Self::enforce_rope(
+                (shape1, first),
+                (shape2, second),
+                microseconds,
+                tuning,
+                max_length,
+            ),
+            Self::Pulley {
+                first,
+                second,
+                anchors,
+                length,
+                ratio,
+            } => Self::enforce_pulley(
+                (shape1, first),
+                (shape2, second),
+                microseconds,
+                tuning,
+                anchors,
+                length,
+                ratio,
+            ),
         }
     }
 
+    fn enforce_pulley(
+        first: (&mut dyn Collidable, PointOnShape),
+        second: (&mut dyn Collidable, PointOnShape),
+        microseconds: f64,
+        tuning: &SolverTuning,
+        anchors: [Point; 2],
+        length: f64,
+        ratio: f64,
+    ) -> f64 {
+        let point1 = first.1.on(first.0);
+        let point2 = second.1.on(second.0);
+        let (side1, side2) = (anchors[0].to(point1), anchors[1].to(point2));
+        let (length1, length2) = (side1.norm(), side2.norm());
+        if length1 < f64::EPSILON || length2 < f64::EPSILON {
+            return 0.0;
+        }
+        let (direction1, direction2) = (side1 / length1, side2 / length2);
+
+        // an end that reached the wheel at its anchor stays out of the rope until it
+        // falls back, so the rope holds the other end as if it were tied to the wheel
+        let (shape1, shape2) = (first.0, second.0);
+        let at_wheel1 =
+            Self::stop_at_wheel(shape1, point1, direction1, length1, microseconds, tuning);
+        let at_wheel2 =
+            Self::stop_at_wheel(shape2, point2, direction2, length2, microseconds, tuning);
+        let excess = length1 + ratio * length2 - length;
+        if excess <= 0.0 {
+            return 0.0;
+        }
+        // how much either side counts towards the length of the rope
+        let weight1 = if at_wheel1 { 0.0 } else { 1.0 };
+        let weight2 = if at_wheel2 { 0.0 } else { ratio };
+
+        let first = shape1.collision_data_mut();
+        let second = shape2.collision_data_mut();
+        let first_offset = first.centroid.to(point1);
+        let second_offset = second.centroid.to(point2);
+        let first_velocity =
+            first.velocity - (first_offset * first.angular_velocity).perpendicular();
+        let second_velocity =
+            second.velocity - (second_offset * second.angular_velocity).perpendicular();
+        // how fast the rope would need to get longer
+        let lengthening =
+            weight1 * first_velocity.dot(direction1) + weight2 * second_velocity.dot(direction2);
+
+        let first_arm = first_offset.cross(direction1);
+        let second_arm = second_offset.cross(direction2);
+        let inverse_mass = weight1
+            * weight1
+            * (first.mass.recip() + first_arm * first_arm / first.inertia)
+            + weight2 * weight2 * (second.mass.recip() + second_arm * second_arm / second.inertia);
+        if inverse_mass == 0.0 {
+            return 0.0;
+        }
+
+        // the rope only ever pulls, towards the anchors
+        let impulse = (lengthening / inverse_mass).max(0.0);
+        first.velocity -= direction1 * (weight1 * impulse / first.mass);
+        first.angular_velocity -= weight1 * impulse * first_arm / first.inertia;
+        second.velocity -= direction2 * (weight2 * impulse / second.mass);
+        second.angular_velocity -= weight2 * impulse * second_arm / second.inertia;
+
+        // and pulls the ends back up to the anchors by part of the excess, like an overlap
+        let correction = excess.min(tuning.max_correction_per_microsecond * microseconds);
+        let (shift1, shift2) = (weight1 / first.mass, weight2 / second.mass);
+        let total = weight1 * shift1 + weight2 * shift2;
+        if total > 0.0 {
+            shape1.translate(-direction1 * (correction * shift1 / total));
+            shape2.translate(-direction2 * (correction * shift2 / total));
+        }
+        impulse
+    }
+
+    /// keeps the end of a pulley from coming closer to its anchor than the wheel
+    /// there, returning whether the end is at the wheel
+    fn stop_at_wheel(
+        shape: &mut dyn Collidable,
+        end: Point,
+        direction: Vector,
+        length: f64,
+        microseconds: f64,
+        tuning: &SolverTuning,
+    ) -> bool {
+        if length >= PULLEY_WHEEL_RADIUS {
+            return false;
+        }
+
+        let data = shape.collision_data_mut();
+        let offset = data.centroid.to(end);
+        let velocity = data.velocity - (offset * data.angular_velocity).perpendicular();
+        let approach = -velocity.dot(direction);
+        let arm = offset.cross(direction);
+        let inverse_mass = data.mass.recip() + arm * arm / data.inertia;
+        if approach > 0.0 && inverse_mass > 0.0 {
+            let impulse = approach / inverse_mass;
+            data.velocity += direction * (impulse / data.mass);
+            data.angular_velocity += impulse * arm / data.inertia;
+        }
+
+        if data.mass.is_finite() {
+            let correction = (PULLEY_WHEEL_RADIUS - length)
+                .min(tuning.max_correction_per_microsecond * microseconds);
+            shape.translate(direction * correction);
+        }
+        true
+    }
+
+    /// exchanges angular momentum between the shapes for the second one to turn
+    /// at the speed of the motor relative to the first one, up to its max torque
+    fn enforce_motor(
+        first: &mut dyn Collidable,
+        second: &mut dyn Collidable,
+        microseconds: f64,
+        motor: HingeMotor,
+    ) {
+        let first = first.collision_data_mut();
+        let second = second.collision_data_mut();
+        let inverse_inertia = first.inertia.recip() + second.inertia.recip();
+        if inverse_inertia == 0.0 {
+            return;
+        }
+
+        let error =
+            motor.target_angular_velocity() - (second.angular_velocity - first.angular_velocity);
+        let max_impulse = motor.max_torque * microseconds / 1_000_000.0;
+        let impulse = (error / inverse_inertia).clamp(-max_impulse, max_impulse);
+        first.angular_velocity -= impulse / first.inertia;
+        second.angular_velocity += impulse / second.inertia;
+    }
+
+    /// takes angular momentum out of the shapes turning relative to each other,
+    /// up to stopping them
+    fn enforce_damping(
+        first: &mut dyn Collidable,
+        second: &mut dyn Collidable,
+        microseconds: f64,
+        damping: f64,
+    ) {
+        let first = first.collision_data_mut();
+        let second = second.collision_data_mut();
+        let inverse_inertia = first.inertia.recip() + second.inertia.recip();
+        if inverse_inertia == 0.0 {
+            return;
+        }
+
+        let turning = second.angular_velocity - first.angular_velocity;
+        let radians_per_second = turning * MOVEMENT_COEFFICIENT * 1_000_000.0;
+        // never more than it takes to stop them, or it would turn them the other way
+        let stopping = (turning / inverse_inertia).abs();
+        let impulse =
+            (damping * radians_per_second * microseconds / 1_000_000.0).clamp(-stopping, stopping);
+        first.angular_velocity += impulse / first.inertia;
+        second.angular_velocity -= impulse / second.inertia;
+    }
+
+    fn enforce_rope(
+        first: (&mut dyn Collidable, PointOnShape),
+        second: (&mut dyn Collidable, PointOnShape),
+        microseconds: f64,
+        tuning: &SolverTuning,
+        max_length: f64,
+    ) -> f64 {
+        let point1 = first.1.on(first.0);
+        let point2 = second.1.on(second.0);
+        let translation = point2.to(point1);
+        let length = translation.norm();
+        if length <= max_length {
+            return 0.0;
+        }
+
+        // a taut rope pulls the points together like a hinge would, but it neither
+        // bounces them back nor holds them sideways
+        first.0.resolve_collision_with(
+            second.0,
+            Vertex {
+                point: translation * ((length - max_length) / length),
+                created_from: (point1, point2),
+            },
+            microseconds,
+            tuning,
+            0.0,
+            0.0,
+            false,
+            false,
+        )
+    }
+
+    fn enforce_spring(
+        first: (&mut dyn Collidable, PointOnShape),
+        second: (&mut dyn Collidable, PointOnShape),
+        microseconds: f64,
+        rest_length: f64,
+        stiffness: f64,
+        damping: f64,
+    ) -> f64 {
+        let point1 = first.1.on(first.0);
+        let point2 = second.1.on(second.0);
+        let along = point1.to(point2);
+        let length = along.norm();
+        if length < f64::EPSILON {
+            return 0.0;
+        }
+        let direction = along / length;
+
+        let first = first.0.collision_data_mut();
+        let second = second.0.collision_data_mut();
+        let first_offset = first.centroid.to(point1);
+        let second_offset = second.centroid.to(point2);
+        let first_velocity =
+            first.velocity - (first_offset * first.angular_velocity).perpendicular();
+        let second_velocity =
+            second.velocity - (second_offset * second.angular_velocity).perpendicular();
+        let stretching = (second_velocity - first_velocity).dot(direction);
+        let stretching_per_second = stretching * MOVEMENT_COEFFICIENT * 1_000_000.0;
+
+        // positive when pulling the points together
+        let force = stiffness * (length - rest_length) + damping * stretching_per_second;
+        let impulse = force * microseconds / 1_000_000.0;
+        if !impulse.is_finite() {
+            return 0.0;
+        }
+
+        first.velocity += direction * (impulse / first.mass);
+        first.angular_velocity += impulse * first_offset.cross(direction) / first.inertia;
+        second.velocity -= direction * (impulse / second.mass);
+        second.angular_velocity -= impulse * second_offset.cross(direction) / second.inertia;
+        impulse.abs()
+    }
+
+    /// pulls the two points together like a critically damped spring as stiff as the
+    /// compliance allows, returning the impulse it took
+    fn enforce_soft_hinge(
+        first: (&mut dyn Collidable, PointOnShape),
+        second: (&mut dyn Collidable, PointOnShape),
+        microseconds: f64,
+        compliance: f64,
+    ) -> f64 {
+        let point1 = first.1.on(first.0);
+        let point2 = second.1.on(second.0);
+        let separation = point1.to(point2);
+
+        let first = first.0.collision_data_mut();
+        let second = second.0.collision_data_mut();
+        let first_offset = first.centroid.to(point1);
+        let second_offset = second.centroid.to(point2);
+        let first_velocity =
+            first.velocity - (first_offset * first.angular_velocity).perpendicular();
+        let second_velocity =
+            second.velocity - (second_offset * second.angular_velocity).perpendicular();
+
+        // the soft constraints of Box2D: the spring oscillates at `frequency` in radians
+        // per second, and corrects this much of the velocity and of the separation
+        let inverse_mass = first.mass.recip() + second.mass.recip();
+        let distance_per_velocity = MOVEMENT_COEFFICIENT * 1_000_000.0;
+        let frequency = (inverse_mass * distance_per_velocity / compliance).sqrt();
+        let seconds = microseconds / 1_000_000.0;
+        let softness = seconds * frequency * (2.0 + seconds * frequency);
+        let mass_scale = softness / (1.0 + softness);
+        let bias_rate = frequency / (2.0 + seconds * frequency);
+
+        let error =
+            second_velocity - first_velocity + separation * (bias_rate / distance_per_velocity);
+        let length = error.norm();
+        if length < f64::EPSILON || !mass_scale.is_finite() {
+            return 0.0;
+        }
+        let direction = error / length;
+        let first_arm = first_offset.cross(direction);
+        let second_arm = second_offset.cross(direction);
+        let inverse_mass = inverse_mass
+            + first_arm * first_arm / first.inertia
+            + second_arm * second_arm / second.inertia;
+        if inverse_mass == 0.0 {
+            return 0.0;
+        }
+
+        let impulse = mass_scale * length / inverse_mass;
+        first.velocity += direction * (impulse / first.mass);
+        first.angular_velocity += impulse * first_arm / first.inertia;
+        second.velocity -= direction * (impulse / second.mass);
+        second.angular_velocity -= impulse * second_arm / second.inertia;
+        impulse
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn enforce_hinge(
         first: (&mut dyn Collidable, PointOnShape),
         second: (&mut dyn Collidable, PointOnShape),
         microseconds: f64,
+        tuning: &SolverTuning,
         restitution_mulipiler: f64,
         friction_mulipiler: f64,
         static_friction_enabled: bool,
         dynamic_friction_enabled: bool,
-    ) {
+    ) -> f64 {
         let point1 = first.1.on(first.0);
         let point2 = second.1.on(second.0);
         let translation = point2.to(point1);
-        if !translation.is_close_enough_to(Vector::ZERO) {
-            first.0.resolve_collision_with(
-                second.0,
-                Vertex {
-                    point: translation,
-                    created_from: (point1, point2),
-                },
-                microseconds,
-                restitution_mulipiler,
-                friction_mulipiler,
-                static_friction_enabled,
-                dynamic_friction_enabled,
-            )
+        if translation.is_close_enough_to(Vector::ZERO) {
+            return 0.0;
         }
+        first.0.resolve_collision_with(
+            second.0,
+            Vertex {
+                point: translation,
+                created_from: (point1, point2),
+            },
+            microseconds,
+            tuning,
+            restitution_mulipiler,
+            friction_mulipiler,
+            static_friction_enabled,
+            dynamic_friction_enabled,
+        )
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub enum Unbound {
-    Hinge(PointOnShape),
-    Rigid(PointOnShape),
+    Hinge {
+        #[serde(flatten)]
+        point: PointOnShape,
+        /// see [`Binding::Hinge`], missing from the bindings of snapshots taken
+        /// before hinges could be damped
+        #[serde(default)]
+        #[cfg_attr(feature = "wasm", tsify(optional))]
+        damping: f64,
+    },
+    Rigid {
+        #[serde(flatten)]
+        point: PointOnShape,
+        /// see [`Binding::Rigid`], missing from the bindings of snapshots taken
+        /// before rigid bindings could give
+        #[serde(default)]
+        #[cfg_attr(feature = "wasm", tsify(optional))]
+        compliance: f64,
+    },
 }
 
 impl Unbound {
-    pub fn new_hinge(shape: &(impl Collidable + ?Sized), at: Point) -> Self {
-        Self::Hinge(shape.create_point_reference(at))
+    /// where the binding is drawn, given the shape owning it
+    pub fn anchor(&self, shape: &(impl Collidable + ?Sized)) -> Point {
+        match self {
+            Self::Hinge { point, .. } | Self::Rigid { point, .. } => point.on(shape),
+        }
+    }
+
+    pub fn new_hinge(shape: &(impl Collidable + ?Sized), at: Point, damping: f64) -> Self {
+        Self::Hinge {
+            point: shape.create_point_reference(at),
+            damping,
+        }
     }
 
-    pub fn new_rigid(shape: &(impl Collidable + ?Sized), at: Point) -> Self {
-        Self::Rigid(shape.create_point_reference(at))
+    pub fn new_rigid(shape: &(impl Collidable + ?Sized), at: Point, compliance: f64) -> Self {
+        Self::Rigid {
+            point: shape.create_point_reference(at),
+            compliance,
+        }
     }
 }
 
@@ -172,7 +785,7 @@ mod test {
             (0.0, 1.0),
         };
 
-        let unbound = Unbound::new_hinge(&shape, Point(0.9, 0.9));
+        let unbound = Unbound::new_hinge(&shape, Point(0.9, 0.9), 0.0);
 
         assert!(Binding::try_bind(
             &shape,
@@ -198,4 +811,36 @@ mod test {
         )
         .is_none());
     }
+
+    #[test]
+    fn test_unbound_fields() {
+        let old: Unbound =
+            serde_json::from_str(r#"{"Rigid":{"angle_offset":0.5,"length_scale":0.25}}"#).unwrap();
+        let Unbound::Rigid { point, compliance } = old else {
+            panic!("not a rigid binding");
+        };
+        assert_eq!((point.angle_offset, point.length_scale), (0.5, 0.25));
+        assert_eq!(compliance, 0.0);
+
+        let shape = make_shape! { (0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0) };
+        let json =
+            serde_json::to_string(&Unbound::new_rigid(&shape, Point(0.9, 0.9), 1e-3)).unwrap();
+        let Ok(Unbound::Rigid { compliance, .. }) = serde_json::from_str(&json) else {
+            panic!("{json} is not a rigid binding");
+        };
+        assert_eq!(compliance, 1e-3);
+
+        let old: Unbound =
+            serde_json::from_str(r#"{"Hinge":{"angle_offset":0.5,"length_scale":0.25}}"#).unwrap();
+        let Unbound::Hinge { damping, .. } = old else {
+            panic!("not a hinge");
+        };
+        assert_eq!(damping, 0.0);
+        let json =
+            serde_json::to_string(&Unbound::new_hinge(&shape, Point(0.9, 0.9), 2.0)).unwrap();
+        let Ok(Unbound::Hinge { damping, .. }) = serde_json::from_str(&json) else {
+            panic!("{json} is not a hinge");
+        };
+        assert_eq!(damping, 2.0);
+    }
 }
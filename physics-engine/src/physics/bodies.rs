@@ -0,0 +1,162 @@
+//! Structure-of-arrays storage of the motion of the bodies
+//!
+//! The shapes own their geometry and their [`CollisionData`], which everything
+//! but integration reads and writes through [`Collidable`], one shape at a
+//! time. Integration instead gathers the motion of all entities into parallel
+//! arrays in the scratch arena, in the order of the entities, advances it in
+//! linear sweeps over those arrays, and moves the shapes by the result in a
+//! single pass at the end. Whatever runs after integration in the same step
+//! can read the arrays instead of borrowing every shape again, as long as no
+//! entity was removed or added in between.
+//!
+//! [`CollisionData`]: super::shape::CollisionData
+
+use bumpalo::{collections::Vec as ScratchVec, Bump};
+
+use super::{
+    time_zone::{time_scale_at, TimeZone},
+    Entity, GRAVITY_COEFFICIENT, MOVEMENT_COEFFICIENT,
+};
+use crate::{
+    geometry::{Point, Vector},
+    trace::span,
+};
+
+/// the motion of every entity, by its index in the entities
+pub(super) struct Bodies<'s> {
+    pub centroids: ScratchVec<'s, Point>,
+    pub velocities: ScratchVec<'s, Vector>,
+    pub angular_velocities: ScratchVec<'s, f64>,
+    /// how fast time passes for every entity, see [`super::time_zone`]
+    pub time_scales: ScratchVec<'s, f64>,
+    /// whether integration moves the entity, which the static ones it does not
+    pub is_dynamic: ScratchVec<'s, bool>,
+}
+
+impl<'s> Bodies<'s> {
+    pub fn gather(entities: &[Entity], scratch: &'s Bump) -> Self {
+        let mut bodies = Self {
+            centroids: ScratchVec::with_capacity_in(entities.len(), scratch),
+            velocities: ScratchVec::with_capacity_in(entities.len(), scratch),
+            angular_velocities: ScratchVec::with_capacity_in(entities.len(), scratch),
+            time_scales: ScratchVec::with_capacity_in(entities.len(), scratch),
+            is_dynamic: ScratchVec::with_capacity_in(entities.len(), scratch),
+        };
+        for entity in entities {
+            let mut shape = entity.shape.borrow_mut();
+            let data = shape.collision_data_mut();
+            bodies.centroids.push(data.centroid);
+            bodies.velocities.push(data.velocity);
+            bodies.angular_velocities.push(data.angular_velocity);
+            bodies.time_scales.push(1.0);
+            bodies.is_dynamic.push(!entity.is_static);
+        }
+        bodies
+    }
+
+    /// moves the dynamic entities by their velocities over `microseconds`,
+    /// scaled by the time zone each of them starts the step in, then
+    /// accelerates them by gravity. Updates the time scales of all entities
+    pub fn integrate(
+        &mut self,
+        entities: &mut [Entity],
+        microseconds: f64,
+        gravity: Vector,
+        time_zones: &[TimeZone],
+    ) {
+        span!(TRACE, "integrate", bodies = entities.len());
+        for (time_scale, &centroid) in self.time_scales.iter_mut().zip(&self.centroids) {
+            *time_scale = time_scale_at(time_zones, centroid);
+        }
+
+        let scratch = self.centroids.bump();
+        let mut translations = ScratchVec::with_capacity_in(entities.len(), scratch);
+        let mut rotations = ScratchVec::with_capacity_in(entities.len(), scratch);
+        for i in 0..self.centroids.len() {
+            if !self.is_dynamic[i] {
+                translations.push(Vector::ZERO);
+                rotations.push(0.0);
+                continue;
+            }
+            let microseconds = microseconds * self.time_scales[i];
+            let translation = self.velocities[i] * MOVEMENT_COEFFICIENT * microseconds;
+            translations.push(translation);
+            rotations.push(self.angular_velocities[i] * MOVEMENT_COEFFICIENT * microseconds);
+            self.centroids[i] += translation;
+            self.velocities[i] += gravity * (GRAVITY_COEFFICIENT * microseconds);
+        }
+
+        for (i, entity) in entities.iter_mut().enumerate() {
+            entity.time_scale = self.time_scales[i];
+            if !self.is_dynamic[i] {
+                continue;
+            }
+            let mut shape = entity.shape.borrow_mut();
+            shape.rotate(rotations[i]);
+            shape.translate(translations[i]);
+            shape.collision_data_mut().velocity = self.velocities[i];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        levels,
+        physics::{sandbox::SandboxConfig, shape::Circle, Engine},
+    };
+
+    #[test]
+    fn test_integrate() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_circle(Circle::new(Point(0.0, 5.0), 0.5), false);
+        engine.add_prefab_circle(Circle::new(Point(3.0, 5.0), 0.5), true);
+        engine.time_zones.push(
+            levels::TimeZone {
+                region: vec![
+                    Point(-1.0, 4.0),
+                    Point(1.0, 4.0),
+                    Point(1.0, 6.0),
+                    Point(-1.0, 6.0),
+                ],
+                time_scale: 0.5,
+            }
+            .into(),
+        );
+        for entity in &engine.entities {
+            let mut shape = entity.shape.borrow_mut();
+            let data = shape.collision_data_mut();
+            data.velocity = Point(1.0, 0.0);
+            data.angular_velocity = 2.0;
+        }
+
+        let scratch = Bump::new();
+        let mut bodies = Bodies::gather(&engine.entities, &scratch);
+        let gravity = Point(0.0, 1.0);
+        bodies.integrate(&mut engine.entities, 1_000.0, gravity, &engine.time_zones);
+
+        let [ball, slowed, fixed] = &engine.entities[..] else {
+            panic!("{} entities", engine.entities.len());
+        };
+        let seconds = MOVEMENT_COEFFICIENT * 1_000.0;
+        let mut shape = slowed.shape.borrow_mut();
+        assert_eq!(slowed.time_scale, 0.5);
+        assert!((shape.angle() - seconds).abs() < 1e-12);
+        let data = shape.collision_data_mut();
+        assert!(data.centroid.is_close_enough_to(Point(seconds * 0.5, 5.0)));
+        assert_eq!(data.centroid, bodies.centroids[1]);
+        let fall = GRAVITY_COEFFICIENT * 1_000.0 * 0.5;
+        assert!(data.velocity.is_close_enough_to(Point(1.0, fall)));
+        assert_eq!(data.velocity, bodies.velocities[1]);
+
+        assert_eq!(ball.time_scale, 1.0);
+        let ball = ball.shape.borrow_mut().collision_data_mut().centroid;
+        assert_eq!(ball, bodies.centroids[0]);
+        // the static circle keeps its place and its velocity
+        let mut fixed = fixed.shape.borrow_mut();
+        assert_eq!(fixed.angle(), 0.0);
+        assert_eq!(fixed.collision_data_mut().centroid, Point(3.0, 5.0));
+        assert_eq!(fixed.collision_data_mut().velocity, Point(1.0, 0.0));
+    }
+}
@@ -0,0 +1,121 @@
+//! Bombs and explosions
+//!
+//! A bomb is a small circle, which explodes once its fuse burns out or when
+//! a contact with it is too strong. The explosion pushes away everything
+//! around it and shatters fragile bodies in range, including other bombs,
+//! so bombs placed close together go off in a chain.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{game::GameEvent, ids::EntityId, Engine};
+use crate::{geometry::Point, levels, trace::event};
+
+pub const BOMB_RADIUS: f64 = 0.1;
+
+/// a bomb of the level that has not gone off yet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ArmedBomb {
+    id: EntityId,
+    /// `None` for bombs that only go off on impact
+    fuse_microseconds: Option<f64>,
+    blast_radius: f64,
+    blast_impulse: f64,
+}
+
+impl ArmedBomb {
+    pub fn new(id: EntityId, bomb: &levels::Bomb) -> Self {
+        Self {
+            id,
+            fuse_microseconds: bomb.fuse_seconds.map(|seconds| seconds * 1_000_000.0),
+            blast_radius: bomb.blast_radius,
+            blast_impulse: bomb.blast_impulse,
+        }
+    }
+
+    fn has_burnt_out(&self) -> bool {
+        self.fuse_microseconds.is_some_and(|fuse| fuse <= 0.0)
+    }
+}
+
+impl Engine {
+    /// Pushes every movable entity within `radius` of `center` away from it.
+    /// The impulse falls off linearly with the distance from the center,
+    /// fragile entities and bombs in range shatter regardless of it
+    pub fn explode(&mut self, center: Point, radius: f64, impulse: f64) {
+        for entity in &mut self.entities {
+            let is_bomb = self.bombs.iter().any(|bomb| bomb.id == entity.id);
+            let mut shape = entity.shape.borrow_mut();
+            let data = shape.collision_data_mut();
+            let offset = center.to(data.centroid);
+            let distance = offset.norm();
+
+            if distance > radius {
+                continue;
+            }
+
+            if is_bomb || entity.durability.breaking_impulse.is_some() {
+                entity.durability.is_shattered = true;
+            }
+
+            if !entity.is_static && distance > 0.0 {
+                let falloff = 1.0 - distance / radius;
+                data.velocity += offset.unit() * (impulse * falloff / data.mass);
+            }
+        }
+    }
+
+    /// burns the fuses of the bombs and sets off the ones that burnt out or were hit too hard,
+    /// along with any others caught in their explosions
+    pub(super) fn detonate_bombs(&mut self, microseconds: f64) {
+        self.bombs.iter_mut().for_each(|bomb| {
+            if let Some(fuse) = &mut bomb.fuse_microseconds {
+                *fuse -= microseconds;
+            }
+        });
+
+        // bombs that fell out of the level are gone for good
+        let entities = &self.entities;
+        self.bombs
+            .retain(|bomb| entities.iter().any(|entity| entity.id == bomb.id));
+
+        loop {
+            let entities = &self.entities;
+            let (detonating, armed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.bombs)
+                .into_iter()
+                .partition(|bomb| {
+                    bomb.has_burnt_out()
+                        || entities
+                            .iter()
+                            .any(|entity| entity.id == bomb.id && entity.durability.is_shattered)
+                });
+            self.bombs = armed;
+
+            if detonating.is_empty() {
+                break;
+            }
+
+            for bomb in detonating {
+                let Some(i) = self.slots.get(bomb.id) else {
+                    continue;
+                };
+                let center = self
+                    .remove_entity(i)
+                    .shape
+                    .borrow_mut()
+                    .collision_data_mut()
+                    .centroid;
+
+                event!(DEBUG, id = ?bomb.id, ?center, "bomb exploded");
+                self.explode(center, bomb.blast_radius, bomb.blast_impulse);
+                self.events.push(GameEvent::Exploded {
+                    id: bomb.id,
+                    center,
+                    radius: bomb.blast_radius,
+                });
+            }
+        }
+    }
+}
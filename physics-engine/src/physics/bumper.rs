@@ -0,0 +1,19 @@
+//! Pinball-style bumpers
+//!
+//! A bumper is any entity with a bump impulse. On top of the usual response
+//! to a contact, it kicks whatever touches it away from its centroid.
+
+use super::shape::Collidable;
+use crate::geometry::Vector;
+
+/// kicks `other` away from the centroid of `bumper`
+pub(super) fn bump(bumper: &mut dyn Collidable, other: &mut dyn Collidable, impulse: f64) {
+    let from = bumper.collision_data_mut().centroid;
+    let data = other.collision_data_mut();
+    let direction = from.to(data.centroid);
+    if direction.is_close_enough_to(Vector::ZERO) {
+        return;
+    }
+
+    data.velocity += direction.unit() * (impulse / data.mass);
+}
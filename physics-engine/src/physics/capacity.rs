@@ -0,0 +1,162 @@
+//! Expected sizes and hard caps of the engine
+//!
+//! The expected counts only reserve room up front, so that a level that is
+//! known to grow large does not reallocate its vectors while it is played. The
+//! caps bound what the player can draw: once one is reached, the drawing tools
+//! refuse to add more and report it with a [`GameEvent::CapReached`], rather
+//! than letting every step get slower with each shape. The config also sets how
+//! often the entities are reordered in memory, as described in [`super::slots`],
+//! and how long steps are split, as described in [`super::timestep`].
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    game::{GameEvent, Tool},
+    timestep::{DEFAULT_MAX_STEP_MICROSECONDS, DEFAULT_MAX_SUBSTEPS},
+    Engine,
+};
+use crate::levels::Level;
+
+/// Missing fields take their default values, which reserve nothing, cap nothing
+/// but the length of the steps, and keep the entities in the order of their ids
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct EngineConfig {
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub expected_circles: usize,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub expected_polygons: usize,
+    /// the most entities the engine holds, counting the ones of the level
+    /// and the main ball, beyond which no more shapes can be drawn
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub max_entities: Option<usize>,
+    /// the most bindings the engine holds, bound or not,
+    /// beyond which no more hinges or rigid bindings can be placed
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub max_bindings: Option<usize>,
+    /// the most particles the engine holds, beyond which no more are added
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub max_particles: Option<usize>,
+    /// every this many steps the entities are reordered along a space-filling
+    /// curve, so that the ones close to each other are also close in memory.
+    /// `None` keeps them in the order of their ids
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub spatial_order_interval: Option<u32>,
+    /// steps longer than this are split into substeps, `None` (`null`) to
+    /// simulate every step in one go however long it is
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub max_step_microseconds: Option<f64>,
+    /// the most substeps a step is split into, the rest of it is dropped
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub max_substeps: u32,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            expected_circles: 0,
+            expected_polygons: 0,
+            max_entities: None,
+            max_bindings: None,
+            max_particles: None,
+            spatial_order_interval: None,
+            max_step_microseconds: Some(DEFAULT_MAX_STEP_MICROSECONDS),
+            max_substeps: DEFAULT_MAX_SUBSTEPS,
+        }
+    }
+}
+
+impl Engine {
+    pub fn with_config(level: Level, config: EngineConfig) -> Self {
+        let mut engine = Engine::new(level);
+        engine.set_config(config);
+        engine
+    }
+
+    pub fn config(&self) -> EngineConfig {
+        self.config
+    }
+
+    /// reserves room for the expected counts on top of what is already there.
+    /// Lowering a cap removes nothing, it only stops new additions
+    pub fn set_config(&mut self, config: EngineConfig) {
+        let (circles, polygons) = (config.expected_circles, config.expected_polygons);
+        self.entities
+            .reserve((circles + polygons).saturating_sub(self.entities.len()));
+        self.circles
+            .reserve(circles.saturating_sub(self.circles.len()));
+        self.polygons
+            .reserve(polygons.saturating_sub(self.polygons.len()));
+        if config.spatial_order_interval != self.config.spatial_order_interval {
+            self.reset_spatial_order();
+        }
+        self.config = config;
+    }
+
+    /// whether `count` more uses of the drawing `tool` stay within the caps,
+    /// emitting [`GameEvent::CapReached`] if they do not
+    pub(super) fn has_room_for(&mut self, tool: Tool, count: usize) -> bool {
+        let (used, cap) = match tool {
+            Tool::Circle | Tool::Polygon => (self.entities.len(), self.config.max_entities),
+            Tool::Hinge | Tool::Rigid | Tool::Spring | Tool::Rope | Tool::Pulley => {
+                (self.binding_count(), self.config.max_bindings)
+            }
+            Tool::Erase => return true,
+        };
+
+        let has_room = cap.is_none_or(|cap| used + count <= cap);
+        if !has_room {
+            self.events.push(GameEvent::CapReached { tool });
+        }
+        has_room
+    }
+
+    fn binding_count(&self) -> usize {
+        self.entities
+            .iter()
+            .map(|entity| entity.bindings.len() + entity.unbound.len())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry::Point,
+        physics::{make_shape, sandbox::SandboxConfig},
+    };
+
+    #[test]
+    fn test_caps() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.set_config(EngineConfig {
+            max_entities: Some(2),
+            max_bindings: Some(1),
+            ..Default::default()
+        });
+
+        // the main ball takes the first place
+        let square = make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) };
+        assert!(engine.add_polygon(square.clone()).is_some());
+        assert!(engine.add_polygon(square).is_none());
+        assert!(engine.add_hinge(Point(1.5, 1.5), 0.0).is_some());
+        assert!(engine.add_rigid(Point(1.5, 1.5), None).is_none());
+
+        let message = engine.run_n_iterations(1, 2000.0);
+        assert_eq!(
+            message.events,
+            [
+                GameEvent::CapReached {
+                    tool: Tool::Polygon
+                },
+                GameEvent::CapReached { tool: Tool::Rigid },
+            ]
+        );
+        // the rejected shape did not count as a use of the tool
+        assert_eq!(engine.game.usage().polygons, 1);
+    }
+}
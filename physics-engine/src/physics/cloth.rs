@@ -0,0 +1,484 @@
+//! Cloth, strips of nodes held together by links of fixed length
+//!
+//! A cloth is a grid of nodes, every one of them linked to its neighbours in
+//! the row and the column and to the diagonal ones, so that it keeps its
+//! shape in both directions while still folding freely. Nodes can be pinned,
+//! either where they are in the level or to a point on a body, with which
+//! they move; the body does not feel the cloth hanging from it, nor collide
+//! with it. A pin to a body comes loose when the body is removed.
+//!
+//! The steps are split into substeps, in each of which the nodes move, and
+//! then, in turns, the links pull them back to their lengths and the nodes
+//! that are not pinned are pushed out of the entities as small circles,
+//! through the same solver the entities use. The velocities of the nodes
+//! follow from how far they moved in the end. Cloth does not collide with
+//! itself or other cloth, and like soft bodies, it is only subject to gravity
+//! at a distance.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    binding::PointOnShape,
+    ids::EntityId,
+    shape::{Circle, Collidable},
+    tuning::SolverTuning,
+    Engine, GRAVITY_COEFFICIENT, MOVEMENT_COEFFICIENT,
+};
+use crate::geometry::{Aabb, Point, Vector};
+
+/// the radius of the circles the nodes collide as
+pub const NODE_RADIUS: f64 = 0.015;
+/// the mass of a node, light enough for cloth to barely push the bodies it touches
+const NODE_MASS: f64 = 1e-4;
+/// the part of its velocity a node touching an entity loses per substep
+const NODE_FRICTION: f64 = 0.5;
+/// the longest substep, short enough for the links to stay stiff
+const MAX_SUBSTEP_MICROSECONDS: f64 = 500.0;
+/// how many times per substep the links pull the nodes back
+const LINK_ITERATIONS: usize = 4;
+
+/// A grid of nodes to make a cloth of. The rows run along `across`
+/// and follow each other along `down`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ClothSketch {
+    /// where the first node of the first row is
+    pub origin: Point,
+    /// from one node of a row to the next one
+    pub across: Vector,
+    /// from one row to the next one
+    pub down: Vector,
+    pub columns: usize,
+    pub rows: usize,
+    pub pins: Vec<ClothPin>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ClothPin {
+    pub column: usize,
+    pub row: usize,
+    /// the body the node moves with, `None` to pin it where it is
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub body: Option<EntityId>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum Anchor {
+    World(Point),
+    Body { id: EntityId, point: PointOnShape },
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Pin {
+    node: usize,
+    anchor: Anchor,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Link {
+    nodes: (usize, usize),
+    length: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Cloth {
+    id: EntityId,
+    columns: usize,
+    /// row by row
+    positions: Vec<Point>,
+    velocities: Vec<Vector>,
+    pinned: Vec<bool>,
+    links: Vec<Link>,
+    pins: Vec<Pin>,
+    /// where the nodes were at the start of the substep, kept to reuse its buffer
+    #[serde(skip)]
+    #[cfg_attr(feature = "wasm", tsify(type = "undefined"))]
+    previous: Vec<Point>,
+    /// whether the nodes touched an entity during the substep, kept likewise
+    #[serde(skip)]
+    #[cfg_attr(feature = "wasm", tsify(type = "undefined"))]
+    touching: Vec<bool>,
+}
+
+/// Where the nodes of a cloth are, for drawing it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ClothFrame {
+    pub id: EntityId,
+    /// the length of the rows
+    pub columns: usize,
+    /// row by row
+    pub nodes: Vec<Point>,
+}
+
+impl Cloth {
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    pub(super) fn translate(&mut self, translation: Vector) {
+        for position in &mut self.positions {
+            *position += translation;
+        }
+        for pin in &mut self.pins {
+            if let Anchor::World(point) = &mut pin.anchor {
+                *point += translation;
+            }
+        }
+    }
+
+    /// pulls the linked nodes back towards the lengths of their links,
+    /// moving only the nodes that are not pinned
+    fn pull_links(&mut self) {
+        for link in &self.links {
+            let (a, b) = link.nodes;
+            let weights = (
+                if self.pinned[a] { 0.0 } else { 1.0 },
+                if self.pinned[b] { 0.0 } else { 1.0 },
+            );
+            let offset = self.positions[a].to(self.positions[b]);
+            let length = offset.norm();
+            if length == 0.0 || weights.0 + weights.1 == 0.0 {
+                continue;
+            }
+            let correction = offset * ((length - link.length) / length / (weights.0 + weights.1));
+            self.positions[a] += correction * weights.0;
+            self.positions[b] -= correction * weights.1;
+        }
+    }
+}
+
+impl Engine {
+    /// `None` if the sketch has fewer than two rows or columns, or pins
+    /// a node it does not have or to a body that does not exist
+    pub fn add_cloth(&mut self, sketch: ClothSketch) -> Option<EntityId> {
+        let ClothSketch {
+            origin,
+            across,
+            down,
+            columns,
+            rows,
+            pins,
+        } = sketch;
+        if columns < 2 || rows < 2 {
+            return None;
+        }
+        let positions: Vec<_> = (0..rows)
+            .flat_map(|row| {
+                (0..columns).map(move |column| origin + across * column as f64 + down * row as f64)
+            })
+            .collect();
+
+        let mut pinned = vec![false; positions.len()];
+        let pins = pins
+            .into_iter()
+            .map(|pin| {
+                if pin.column >= columns || pin.row >= rows {
+                    return None;
+                }
+                let node = pin.row * columns + pin.column;
+                let position = positions[node];
+                let anchor = match pin.body {
+                    None => Anchor::World(position),
+                    Some(id) => {
+                        let shape = self.entities[self.slots.get(id)?].shape.borrow();
+                        let point = shape.create_point_reference(position);
+                        Anchor::Body { id, point }
+                    }
+                };
+                pinned[node] = true;
+                Some(Pin { node, anchor })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut links = vec![];
+        let mut link = |a: usize, b: usize| {
+            links.push(Link {
+                nodes: (a, b),
+                length: positions[a].to(positions[b]).norm(),
+            })
+        };
+        for row in 0..rows {
+            for column in 0..columns {
+                let node = row * columns + column;
+                if column + 1 < columns {
+                    link(node, node + 1);
+                }
+                if row + 1 < rows {
+                    link(node, node + columns);
+                }
+                if column + 1 < columns && row + 1 < rows {
+                    link(node, node + columns + 1);
+                    link(node + 1, node + columns);
+                }
+            }
+        }
+
+        let id = self.entity_ids.generate();
+        self.cloths.push(Cloth {
+            id,
+            columns,
+            velocities: vec![Vector::ZERO; positions.len()],
+            positions,
+            pinned,
+            links,
+            pins,
+            previous: vec![],
+            touching: vec![],
+        });
+        Some(id)
+    }
+
+    /// returns whether the cloth existed
+    pub fn remove_cloth(&mut self, id: EntityId) -> bool {
+        let count = self.cloths.len();
+        self.cloths.retain(|cloth| cloth.id != id);
+        self.cloths.len() < count
+    }
+
+    /// moves the cloth through the step and collides it with the entities,
+    /// removing the cloth that is entirely out of bounds like the entities
+    pub(super) fn step_cloths(&mut self, microseconds: f64, gravity: Vector) {
+        if self.cloths.is_empty() {
+            return;
+        }
+        let substeps = (microseconds / MAX_SUBSTEP_MICROSECONDS).ceil().max(1.0);
+        let microseconds = microseconds / substeps;
+        // nodes are light enough to be pushed out of the entities at once
+        let tuning = SolverTuning {
+            max_correction_per_microsecond: f64::INFINITY,
+            ..self.solver_tuning
+        };
+        let distance = MOVEMENT_COEFFICIENT * microseconds;
+
+        // taken out for the cloth to collide with the entities of the engine
+        let mut cloths = std::mem::take(&mut self.cloths);
+        for cloth in &mut cloths {
+            for _ in 0..substeps as u32 {
+                cloth.pins.retain(|pin| {
+                    let anchored = match pin.anchor {
+                        Anchor::World(_) => true,
+                        Anchor::Body { id, .. } => self.slots.get(id).is_some(),
+                    };
+                    cloth.pinned[pin.node] = anchored;
+                    anchored
+                });
+
+                cloth.previous.clone_from(&cloth.positions);
+                let moving = cloth.positions.iter_mut().zip(&mut cloth.velocities);
+                for (position, velocity) in moving {
+                    *velocity += gravity * (GRAVITY_COEFFICIENT * microseconds);
+                    *position += *velocity * distance;
+                }
+                for pin in &cloth.pins {
+                    cloth.positions[pin.node] = match pin.anchor {
+                        Anchor::World(point) => point,
+                        Anchor::Body { id, point } => {
+                            // the pins to removed bodies came loose above
+                            let slot = self.slots.get(id).unwrap();
+                            point.on(&*self.entities[slot].shape.borrow())
+                        }
+                    };
+                }
+
+                cloth.touching.clear();
+                cloth.touching.resize(cloth.positions.len(), false);
+                for _ in 0..LINK_ITERATIONS {
+                    cloth.pull_links();
+                    self.collide_cloth(cloth, microseconds, &tuning);
+                }
+
+                let moved = cloth.previous.iter().zip(&cloth.positions);
+                let nodes = cloth.velocities.iter_mut().zip(&cloth.touching);
+                for ((velocity, &touching), (previous, position)) in nodes.zip(moved) {
+                    *velocity = previous.to(*position) / distance;
+                    if touching {
+                        *velocity = *velocity * (1.0 - NODE_FRICTION);
+                    }
+                }
+            }
+        }
+        self.cloths = cloths;
+
+        if let Some(extent) = self.world_half_extent {
+            let origin = self.origin;
+            self.cloths.retain(|cloth| {
+                cloth
+                    .positions
+                    .iter()
+                    .any(|&position| (position + origin).1 > -extent)
+            });
+        }
+    }
+
+    /// pushes the nodes that are not pinned out of the entities, except for the
+    /// ones the cloth is pinned to, marking the nodes that touched one
+    fn collide_cloth(&self, cloth: &mut Cloth, microseconds: f64, tuning: &SolverTuning) {
+        let extent = Point(NODE_RADIUS, NODE_RADIUS);
+        let aabb = Aabb::around(cloth.positions.iter().copied());
+        let aabb = Aabb {
+            min: aabb.min - extent,
+            max: aabb.max + extent,
+        };
+        for entity in &self.entities {
+            let holds_cloth = cloth
+                .pins
+                .iter()
+                .any(|pin| matches!(pin.anchor, Anchor::Body { id, .. } if id == entity.id));
+            let mut shape = entity.shape.borrow_mut();
+            if holds_cloth || !shape.aabb().overlaps(&aabb) {
+                continue;
+            }
+            let nodes = cloth
+                .positions
+                .iter_mut()
+                .zip(&cloth.velocities)
+                .zip(&mut cloth.touching)
+                .zip(&cloth.pinned);
+            for (((position, &velocity), touching), &pinned) in nodes {
+                if pinned {
+                    continue;
+                }
+                let mut circle = Circle::new(*position, NODE_RADIUS);
+                let data = circle.collision_data_mut();
+                data.mass = NODE_MASS;
+                data.inertia = f64::INFINITY;
+                data.velocity = velocity;
+                let impulse = circle.collide(
+                    &mut *shape,
+                    microseconds,
+                    tuning,
+                    self.restitution_mulipiler,
+                    self.friction_mulipiler,
+                    self.static_friction_enabled,
+                    self.dynamic_friction_enabled,
+                );
+                *touching |= impulse.is_some();
+                *position = circle.collision_data_mut().centroid;
+            }
+        }
+    }
+
+    /// reuses the frames of the previous message and their buffers
+    pub(super) fn write_cloths(&self, frames: &mut Vec<ClothFrame>) {
+        frames.resize_with(self.cloths.len(), || ClothFrame {
+            id: EntityId(0),
+            columns: 0,
+            nodes: vec![],
+        });
+        for (frame, cloth) in frames.iter_mut().zip(&self.cloths) {
+            frame.id = cloth.id;
+            frame.columns = cloth.columns;
+            frame.nodes.clone_from(&cloth.positions);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig, shape::Circle};
+
+    /// a strip of 5 by 5 nodes 0.05 apart with its first row at y 0.2
+    fn curtain(pins: Vec<ClothPin>) -> ClothSketch {
+        ClothSketch {
+            origin: Point(-0.1, 0.2),
+            across: Point(0.05, 0.0),
+            down: Point(0.0, 0.05),
+            columns: 5,
+            rows: 5,
+            pins,
+        }
+    }
+
+    fn pin(column: usize, body: Option<EntityId>) -> ClothPin {
+        ClothPin {
+            column,
+            row: 0,
+            body,
+        }
+    }
+
+    #[test]
+    fn test_cloth_hangs_from_its_pins() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let id = engine
+            .add_cloth(curtain(vec![pin(0, None), pin(4, None)]))
+            .unwrap();
+        let message = engine.run_n_iterations(500, 2000.0);
+        let frame = &message.cloths[0];
+        assert_eq!((frame.id, frame.columns, frame.nodes.len()), (id, 5, 25));
+
+        // the pins held, and the cloth hangs below them without stretching much
+        assert_eq!(frame.nodes[0], Point(-0.1, 0.2));
+        assert_eq!(frame.nodes[4], Point(0.1, 0.2));
+        let bottom = frame.nodes[22];
+        assert!(bottom.1 > 0.35 && bottom.1 < 0.45, "{bottom:?}");
+        assert!(bottom.0.abs() < 1e-3);
+
+        assert!(engine.remove_cloth(id));
+        assert!(!engine.remove_cloth(id));
+        assert!(engine.run_iteration(2000.0).cloths.is_empty());
+    }
+
+    #[test]
+    fn test_cloth_collides_with_circles_and_polygons() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let peg = Point(0.12, 0.6);
+        engine.add_prefab_circle(Circle::new(peg, 0.05), true);
+        engine.add_prefab_polygon(
+            make_shape! { (-3.0, 0.8), (3.0, 0.8), (3.0, 1.0), (-3.0, 1.0) },
+            true,
+        );
+        engine.add_cloth(curtain(vec![])).unwrap();
+
+        // it falls onto the peg, slides off it and comes to rest on the floor
+        for _ in 0..500 {
+            let message = engine.run_iteration(2000.0);
+            for node in &message.cloths[0].nodes {
+                assert!(node.to(peg).norm() > 0.05 + NODE_RADIUS - 0.005, "{node:?}");
+                assert!(node.1 < 0.8 - NODE_RADIUS + 0.005, "{node:?}");
+            }
+        }
+        let nodes = &engine.cloths[0].positions;
+        assert!(nodes.iter().any(|node| node.1 > 0.75));
+        let speed = engine.cloths[0].velocities.iter().map(|v| v.norm());
+        assert!(speed.fold(0.0, f64::max) < 0.01);
+    }
+
+    #[test]
+    fn test_cloth_pinned_to_bodies() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let square = make_shape! { (-0.15, 0.1), (0.15, 0.1), (0.15, 0.2), (-0.15, 0.2) };
+        let body = engine.add_polygon(square).unwrap();
+        assert!(engine
+            .add_cloth(curtain(vec![pin(0, Some(EntityId(99)))]))
+            .is_none());
+        assert!(engine.add_cloth(curtain(vec![pin(5, None)])).is_none());
+        engine
+            .add_cloth(curtain(vec![pin(0, Some(body)), pin(4, Some(body))]))
+            .unwrap();
+
+        // the body falls freely, and the cloth falls with it
+        let message = engine.run_n_iterations(100, 2000.0);
+        let slot = engine.slots.get(body).unwrap();
+        let centroid = engine.body_states()[slot].centroid;
+        let first = message.cloths[0].nodes[0];
+        assert!(first.1 > 0.3);
+        let offset = first.to(centroid + Point(-0.1, 0.05)).norm();
+        assert!(offset < 1e-6, "{offset} {first:?} {centroid:?}");
+
+        // pins come loose with their body
+        engine.erase_at(centroid);
+        engine.run_n_iterations(10, 2000.0);
+        assert!(engine.cloths[0].pins.is_empty());
+        assert!(!engine.cloths[0].pinned.contains(&true));
+    }
+}
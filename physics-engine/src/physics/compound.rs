@@ -0,0 +1,104 @@
+//! Rigid bodies made of several convex polygons
+//!
+//! A hammer drawn as a handle and a head is one body, a [`Compound`] of the
+//! two, rather than two polygons held together by a rigid binding that gives
+//! a little under load. The parts share the velocity and the mass properties
+//! of the body, and collide separately, so the body may have any outline its
+//! parts add up to. The display message draws every part as a polygon of the
+//! entity of the body, in the color of the body.
+//!
+//! A compound takes a single polygon of the tool limits of the level.
+
+use super::{
+    game::Tool,
+    ids::EntityId,
+    shape::{Collidable, Compound, Polygon},
+    Engine, EntityCfg,
+};
+
+impl Engine {
+    /// adds a body of the convex parts drawn by the player, unless there are no parts,
+    /// the polygon limit of the level is reached or snapping leaves nothing of one
+    /// of the parts, see [`super::snap`]
+    pub fn add_compound(&mut self, mut parts: Vec<Polygon>) -> Option<EntityId> {
+        if parts.is_empty() {
+            return None;
+        }
+        for part in &mut parts {
+            if !self.snap_polygon(part) {
+                return None;
+            }
+            let mass = part.collision_data_mut().mass;
+            if !mass.is_finite() || mass <= 0.0 {
+                return None;
+            }
+        }
+        if !self.has_room_for(Tool::Polygon, 1) || !self.game.try_use(Tool::Polygon) {
+            return None;
+        }
+
+        let (id, weak) = self.add_entity(Compound::new(parts), EntityCfg::default());
+        self.compounds.push(self.painter.paint(weak));
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry::Point,
+        levels::Level,
+        physics::{make_shape, sandbox::SandboxConfig, shape::Circle, snapshot::Snapshot},
+    };
+
+    #[test]
+    fn test_compound() {
+        let mut level: Level = Engine::sandbox(SandboxConfig::default())
+            .export_level()
+            .unwrap();
+        level.polygons.clear();
+        level.initial_ball_position = Point(-3.0, 0.5);
+        level.terrain = vec![vec![Point(-4.0, 1.0), Point(4.0, 1.0)]];
+        let mut engine = Engine::new(level);
+        assert_eq!(engine.add_compound(vec![]), None);
+
+        // a cup, which no convex polygon could hold the ball in
+        let cup = engine
+            .add_compound(vec![
+                make_shape! { (-0.5, 0.8), (0.5, 0.8), (0.5, 0.9), (-0.5, 0.9) },
+                make_shape! { (-0.5, 0.3), (-0.4, 0.3), (-0.4, 0.8), (-0.5, 0.8) },
+                make_shape! { (0.4, 0.3), (0.5, 0.3), (0.5, 0.8), (0.4, 0.8) },
+            ])
+            .unwrap();
+        let ball = engine
+            .add_circle(Circle::new(Point(0.0, 0.5), 0.1))
+            .unwrap();
+        assert_eq!(engine.usage().polygons, 1);
+
+        let message = engine.run_n_iterations(1_000, 2_000.0);
+        let parts: Vec<_> = message
+            .polygon_entities
+            .iter()
+            .zip(&message.polygons)
+            .filter(|(&id, _)| id == cup)
+            .map(|(_, part)| part)
+            .collect();
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| part.color == parts[0].color));
+
+        // the ball rests on the bottom of the cup, between its walls
+        let states = engine.body_states();
+        let ball = states.iter().find(|state| state.id == ball).unwrap();
+        let cup = states.iter().find(|state| state.id == cup).unwrap();
+        assert!(ball.centroid.1 > 0.7, "{ball:?}");
+        assert!(ball.centroid.0.abs() < 0.3, "{ball:?}");
+        assert!(cup.angle.abs() < 0.01, "{cup:?}");
+
+        let mut restored = Engine::from(Snapshot::from(&engine));
+        assert_eq!(
+            restored.run_iteration(2_000.0).polygons.len(),
+            message.polygons.len()
+        );
+    }
+}
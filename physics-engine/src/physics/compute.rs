@@ -1,14 +1,29 @@
 use std::{
+    cell::RefCell,
+    collections::BinaryHeap,
     f64::consts::PI,
     panic::{self, RefUnwindSafe},
 };
 
 use super::shape::{Bounded, CollisionData, Polygon};
-use crate::geometry::{windows, Point, Vector};
+use crate::{
+    geometry::{windows, Point, Vector},
+    trace::event,
+};
 
 pub mod algorithm;
 pub mod minkowski;
 pub mod simplex;
+pub mod support;
+
+thread_local! {
+    // the edges of the polytope EPA expands, kept around so that it stops allocating
+    // once it has grown large enough
+    static EDGES: RefCell<BinaryHeap<simplex::Edge>> = const { RefCell::new(BinaryHeap::new()) };
+}
+
+/// where GJK starts searching when there is no earlier search to go on from
+pub const INITIAL_SEARCH_DIRECTION: Vector = Point(0.0, 1.0);
 
 /// returns the minimum translation vector necessary to resolve a collsion
 /// between `first` and `second`, or `None` if they are not colliding
@@ -16,39 +31,104 @@ pub fn collision(
     first: &(impl Bounded + ?Sized + RefUnwindSafe),
     second: &(impl Bounded + ?Sized + RefUnwindSafe),
 ) -> Option<simplex::Vertex> {
+    let mut search_direction = INITIAL_SEARCH_DIRECTION;
+    warm_started_collision(first, second, &mut search_direction)
+}
+
+/// like [`collision`], but GJK starts along `search_direction`, which is left holding
+/// the direction it ended with, see [`algorithm::gjk::eclosing_simplex`]. Passing it
+/// back in for the same pair of shapes in the next step warm starts GJK
+pub fn warm_started_collision(
+    first: &(impl Bounded + ?Sized + RefUnwindSafe),
+    second: &(impl Bounded + ?Sized + RefUnwindSafe),
+    search_direction: &mut Vector,
+) -> Option<simplex::Vertex> {
+    let initial_direction = *search_direction;
     // this is here bacause in some very rare cases there appear NaNs in the calculations.
     // The algorithms cannot work with NaNs and panics when attempting to compare them.
     // Since one of the last fixes these panics were not observed, but they might just be
     // very difficult to cause
-    panic::catch_unwind(|| {
+    let detected = panic::catch_unwind(|| {
         let difference = minkowski::Difference(first, second);
-        let initial_point = Point(0.0, 1.0);
-        let simplex = algorithm::gjk::eclosing_simplex(initial_point, difference)?;
+        let mut direction = initial_direction;
+        let collision = EDGES.with_borrow_mut(|edges| {
+            algorithm::gjk::eclosing_simplex(&mut direction, difference, edges)?;
+            Some(algorithm::epa::closest_point_of(edges, difference))
+        });
+        (collision, direction)
+    })
+    .map_err(|_| {
+        event!(
+            WARN,
+            "collision detection panicked, most likely due to a NaN"
+        )
+    });
 
-        Some(algorithm::epa::closest_point_of(simplex, difference))
+    // a degenerate direction would keep the next search from going anywhere
+    *search_direction = match detected {
+        Ok((_, direction))
+            if direction.0.is_finite()
+                && direction.1.is_finite()
+                && !direction.is_close_enough_to(Vector::ZERO) =>
+        {
+            direction
+        }
+        _ => INITIAL_SEARCH_DIRECTION,
+    };
+    detected.ok().and_then(|(collision, _)| collision)
+}
+
+/// like [`collision`], but only tells whether the shapes overlap,
+/// without computing how deep
+pub fn intersects(
+    first: &(impl Bounded + ?Sized + RefUnwindSafe),
+    second: &(impl Bounded + ?Sized + RefUnwindSafe),
+) -> bool {
+    panic::catch_unwind(|| {
+        let difference = minkowski::Difference(first, second);
+        EDGES.with_borrow_mut(|edges| {
+            let mut search_direction = INITIAL_SEARCH_DIRECTION;
+            algorithm::gjk::eclosing_simplex(&mut search_direction, difference, edges).is_some()
+        })
     })
-    .ok()
-    .flatten()
+    .unwrap_or(false)
 }
 
-/// computes the impulse resulting from a collision between
-/// `first` and `second`. The offsets are vectors from the centers
-/// of the shapes to the point of contact between them
-pub fn impulse(
-    first: CollisionData,
-    second: CollisionData,
+/// computes how much an impulse along `direction` at the point of contact between
+/// `first` and `second` changes their relative velocity along it. The offsets are
+/// vectors from the centers of the shapes to the point of contact between them
+pub fn inverse_effective_mass(
+    first: &CollisionData,
+    second: &CollisionData,
     first_offset: Vector,
     second_offset: Vector,
-    collision_normal: Vector,
+    direction: Vector,
+) -> f64 {
+    first.mass.recip() + second.mass.recip()
+        - direction.dot(
+            (first_offset.triple_product(direction) / first.inertia)
+                + (second_offset.triple_product(direction) / second.inertia),
+        )
+}
+
+/// computes the impulse along `direction` resulting from a collision
+/// with the given inverse effective mass along it
+pub fn impulse(
+    inverse_effective_mass: f64,
+    direction: Vector,
     relative_velocity: Vector,
     reflection_factor: f64,
 ) -> f64 {
-    -collision_normal.dot(relative_velocity * reflection_factor)
-        / (first.mass.recip() + second.mass.recip()
-            - collision_normal.dot(
-                (first_offset.triple_product(collision_normal) / first.inertia)
-                    + (second_offset.triple_product(collision_normal) / second.inertia),
-            ))
+    -direction.dot(relative_velocity * reflection_factor) / inverse_effective_mass
+}
+
+/// the signed area of the outline, positive for the winding of the polygons of the
+/// engine, the same as the mass of a polygon of unit density
+pub fn area(vertices: &[Point]) -> f64 {
+    windows::Looped::from(vertices.iter().cloned())
+        .map(|[first, second]| first.cross(second))
+        .sum::<f64>()
+        / 2.0
 }
 
 /// Wikipedia translated to Rust: [centroid of a polygon](https://en.wikipedia.org/wiki/Centroid#Of_a_polygon)
@@ -63,11 +143,18 @@ pub fn centroid(vertices: &[Point]) -> Point {
     combined_points / (3.0 * doubled_area)
 }
 
+/// the smallest width of a hull in any of its directions
+pub const MINIMAL_HULL_THICKNESS: f64 = 1e-3;
+
 /// wraps an at most `N` vertex hull around the provided collection of vertices
 /// I would love to put the `directions` array in a constant, but unfortunately
 /// Rust does not support generic const/statics. The static rvalue promotion hack
 /// is also not an option here due to the "complex" initalization scheme of the array
 ///
+/// The hull is the intersection of the supporting half-planes of the points in `N`
+/// evenly spaced directions, so unlike a hull made of the extreme points themselves
+/// it always contains every point, including the ones between sampled directions
+///
 /// Panics if the iterator is empty
 pub fn hull<const N: usize>(mut points: impl Iterator<Item = Point>) -> Polygon {
     let first = points
@@ -75,36 +162,97 @@ pub fn hull<const N: usize>(mut points: impl Iterator<Item = Point>) -> Polygon
         .expect("cannot create a hull from an empty set of verticies");
 
     let mut directions = [Vector::ZERO; N];
-    let mut maximally_extended_points = [first; N];
-    let mut maximally_extended_points_dots = [0.0; N];
+    let mut maximal_dots = [0.0; N];
 
     for i in 0..N {
         directions[i] = Point(1.0, 0.0).rotate((2 * i) as f64 * PI / N as f64);
-        maximally_extended_points_dots[i] = first.dot(directions[i]);
+        maximal_dots[i] = first.dot(directions[i]);
     }
 
     for point in points {
         for i in 0..N {
-            let new_dot = point.dot(directions[i]);
-            if new_dot > maximally_extended_points_dots[i] {
-                maximally_extended_points[i] = point;
-                maximally_extended_points_dots[i] = new_dot;
-            }
+            maximal_dots[i] = maximal_dots[i].max(point.dot(directions[i]));
         }
     }
+
+    // a hull of coincident or collinear points would have no area, and so no mass
+    // or centroid, so it is inflated to the minimal thickness in every direction
+    let thickness = (0..N)
+        .map(|i| maximal_dots[i] + maximal_dots[(i + N / 2) % N])
+        .fold(f64::INFINITY, f64::min);
+    if thickness < MINIMAL_HULL_THICKNESS {
+        let padding = (MINIMAL_HULL_THICKNESS - thickness) / 2.0;
+        maximal_dots.iter_mut().for_each(|dot| *dot += padding);
+    }
+
+    // each vertex lies where the supporting lines of two neighbouring directions cross
+    let vertices = (0..N).map(|i| {
+        let (first, second) = (directions[i], directions[(i + 1) % N]);
+        let (first_dot, second_dot) = (maximal_dots[i], maximal_dots[(i + 1) % N]);
+        let determinant = first.cross(second);
+
+        Point(
+            (first_dot * second.1 - second_dot * first.1) / determinant,
+            (first.0 * second_dot - second.0 * first_dot) / determinant,
+        )
+    });
+
     // filter out closely neighbouring vertices before creating the polygon
-    Polygon::new(maximally_extended_points.into_iter().fold(
-        Vec::<Point>::with_capacity(N),
-        |mut vertices, extended_point| match vertices.last() {
-            Some(vertex) if !vertex.is_close_enough_to(extended_point) => {
-                vertices.push(extended_point);
-                vertices
-            }
-            None => {
-                vertices.push(extended_point);
-                vertices
+    let mut vertices =
+        vertices.fold(
+            Vec::<Point>::with_capacity(N),
+            |mut vertices, vertex| match vertices.last() {
+                Some(last) if last.is_close_enough_to(vertex) => vertices,
+                _ => {
+                    vertices.push(vertex);
+                    vertices
+                }
+            },
+        );
+
+    if vertices.len() > 1 && vertices[0].is_close_enough_to(vertices[vertices.len() - 1]) {
+        vertices.pop();
+    }
+
+    Polygon::new(vertices)
+}
+
+/// the part of the convex polygon on the side of the line through `point`
+/// that `normal` points away from, empty if none of it is
+pub fn clip(vertices: &[Point], point: Point, normal: Vector) -> Vec<Point> {
+    let mut clipped = Vec::with_capacity(vertices.len() + 1);
+    for [start, end] in windows::Looped::from(vertices.iter().copied()) {
+        let (start_side, end_side) = (point.to(start).dot(normal), point.to(end).dot(normal));
+        if start_side <= 0.0 {
+            clipped.push(start);
+        }
+        if (start_side < 0.0 && end_side > 0.0) || (start_side > 0.0 && end_side < 0.0) {
+            clipped.push(start + start.to(end) * (start_side / (start_side - end_side)));
+        }
+    }
+    clipped
+}
+
+/// splits the convex polygon into the cells of the Voronoi diagram of the
+/// `seeds`, the parts of it closer to one seed than to all others, in the
+/// order of the seeds. Cells of seeds outside of the polygon may be empty,
+/// as are the ones of seeds repeating an earlier one
+pub fn voronoi_cells(vertices: &[Point], seeds: &[Point]) -> Vec<Vec<Point>> {
+    seeds
+        .iter()
+        .enumerate()
+        .map(|(i, &seed)| {
+            if seeds[..i].contains(&seed) {
+                return vec![];
             }
-            _ => vertices,
-        },
-    ))
+            let others = seeds
+                .iter()
+                .enumerate()
+                .filter(|&(j, &other)| j != i && other != seed);
+            others.fold(vertices.to_vec(), |cell, (_, &other)| {
+                // the bisector between the seeds bounds the cell
+                clip(&cell, (seed + other) / 2.0, seed.to(other))
+            })
+        })
+        .collect()
 }
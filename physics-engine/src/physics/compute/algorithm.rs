@@ -3,60 +3,86 @@ pub mod gjk {
 
     use super::super::simplex::{self, Simplex};
     use crate::{
-        geometry::Point,
-        physics::{compute::minkowski, shape::Bounded},
+        geometry::Vector,
+        physics::{compute::minkowski, perf, shape::Bounded},
     };
 
     /// 2D (GJK algorithm)[https://en.wikipedia.org/wiki/Gilbert%E2%80%93Johnson%E2%80%93Keerthi_distance_algorithm]
     ///
     /// Checks for a collision between to shapes by sampling their minkowski difference.
     /// If the samples form a simplex that encloses the origin, the two shapes collide and
-    /// the enclosing edges are returned.
+    /// the enclosing edges replace the contents of `edges`.
+    ///
+    /// The search starts along `search_direction`, which is left holding the direction
+    /// the search ended with. For shapes that barely moved, starting the next search
+    /// from there ends it within an iteration or two.
     pub fn eclosing_simplex(
-        initial_point: Point,
-        difference: minkowski::Difference<(impl Bounded + ?Sized), (impl Bounded + ?Sized)>,
-    ) -> Option<BinaryHeap<simplex::Edge>> {
+        search_direction: &mut Vector,
+        difference: minkowski::Difference<impl Bounded + ?Sized, impl Bounded + ?Sized>,
+        edges: &mut BinaryHeap<simplex::Edge>,
+    ) -> Option<()> {
         const MAX_ITERATION_COUNT: usize = 40;
 
-        let inital_point = difference.support_vector(initial_point);
+        let inital_point = difference.support_vector(*search_direction);
+        // nothing lies beyond the origin along the search direction, so it separates the shapes
+        if inital_point.point.dot(*search_direction) < 0.0 {
+            perf::count_gjk_iterations(1);
+            return None;
+        }
         let mut simplex = simplex::Partial::Point(inital_point);
-        let mut search_direction = -inital_point.point;
+        *search_direction = -inital_point.point;
         let mut iteration_count = 0;
 
-        Some(loop {
-            match simplex.try_to_enclose(difference.support_vector(search_direction)) {
+        edges.clear();
+        loop {
+            perf::count_gjk_iterations(1);
+            match simplex.try_to_enclose(difference.support_vector(*search_direction)) {
                 simplex::ClosureResult::NextDirection(direction) => {
-                    search_direction = direction;
+                    *search_direction = direction;
                     if iteration_count > MAX_ITERATION_COUNT {
                         return None;
                     }
                 }
                 simplex::ClosureResult::ExcludesOrigin => return None,
                 simplex::ClosureResult::IncludesOrigin(Simplex::Triangle(first, second, third)) => {
-                    break BinaryHeap::from([
+                    // the edges have to be wound counter-clockwise
+                    let (second, third) = if first
+                        .point
+                        .to(second.point)
+                        .cross(first.point.to(third.point))
+                        < 0.0
+                    {
+                        (third, second)
+                    } else {
+                        (second, third)
+                    };
+
+                    edges.extend([
                         simplex::Edge::try_new(first, second)?,
                         simplex::Edge::try_new(second, third)?,
                         simplex::Edge::try_new(third, first)?,
                     ]);
+                    return Some(());
                 }
                 simplex::ClosureResult::IncludesOrigin(Simplex::Line(first, second)) => {
                     let direction = first.point.to(second.point).perpendicular();
                     let third = difference.support_vector(direction);
                     let fourth = difference.support_vector(-direction);
 
-                    break BinaryHeap::from([
+                    edges.extend([
                         simplex::Edge::try_new(first, third)?,
                         simplex::Edge::try_new(third, second)?,
                         simplex::Edge::try_new(second, fourth)?,
                         simplex::Edge::try_new(fourth, first)?,
                     ]);
+                    return Some(());
                 }
                 simplex::ClosureResult::IncludesOrigin(Simplex::Point(_)) => {
                     return None;
                 }
             }
             iteration_count += 1;
-        })
+        }
     }
 }
 
@@ -72,32 +98,31 @@ pub mod epa {
     ///
     /// Finds the minimum translation vector by iteratively splitting the edge closest to the origin.
     pub fn closest_point_of(
-        mut simpex_edges: BinaryHeap<simplex::Edge>,
-        difference: minkowski::Difference<(impl Bounded + ?Sized), (impl Bounded + ?Sized)>,
+        simpex_edges: &mut BinaryHeap<simplex::Edge>,
+        difference: minkowski::Difference<impl Bounded + ?Sized, impl Bounded + ?Sized>,
     ) -> simplex::Vertex {
         const MAX_ITERATION_COUNT: usize = 40;
 
-        let mut prev_point = Point(f64::MAX, f64::MAX);
         let mut iteration_count = 0;
 
         loop {
             let edge = simpex_edges.pop().unwrap();
             let closest_point = edge.towards_segment * edge.distance_to_origin;
+            let new_vertex = difference.support_vector(edge.towards_segment);
 
-            if closest_point.is_close_enough_to(prev_point) || iteration_count > MAX_ITERATION_COUNT
-            {
+            // the closest edge lies on the boundary of the difference once the support point
+            // in its direction does not get any further. Comparing consecutive closest points
+            // instead stops early whenever two edges happen to be equally far from the origin
+            let expansion = new_vertex.point.dot(edge.towards_segment) - edge.distance_to_origin;
+            if expansion < EPSILON || iteration_count > MAX_ITERATION_COUNT {
                 return try_interpolate(&edge, closest_point, Axis::X)
                     .or_else(|| try_interpolate(&edge, closest_point, Axis::Y))
                     .unwrap_or(edge.segment.0);
             }
 
-            let new_vertex = difference.support_vector(edge.towards_segment);
-
             simpex_edges.push(simplex::Edge::new(edge.segment.0, new_vertex));
             simpex_edges.push(simplex::Edge::new(new_vertex, edge.segment.1));
 
-            prev_point = closest_point;
-
             iteration_count += 1;
         }
     }
@@ -145,8 +170,13 @@ pub mod epa {
 
 #[cfg(test)]
 mod test {
+    use std::collections::BinaryHeap;
+
     use super::{super::minkowski, gjk};
-    use crate::{geometry::Point, physics::make_shape};
+    use crate::{
+        geometry::Point,
+        physics::{make_shape, perf},
+    };
 
     #[test]
     fn gjk_collides_test() {
@@ -165,7 +195,10 @@ mod test {
         };
 
         let difference = minkowski::Difference(&first, &second);
-        assert!(gjk::eclosing_simplex(Point(1.0, 1.0), difference).is_some());
+        assert!(
+            gjk::eclosing_simplex(&mut Point(1.0, 1.0), difference, &mut BinaryHeap::new())
+                .is_some()
+        );
     }
 
     #[test]
@@ -185,6 +218,41 @@ mod test {
         };
 
         let difference = minkowski::Difference(&first, &second);
-        assert!(gjk::eclosing_simplex(Point(1.0, 1.0), difference).is_none());
+        assert!(
+            gjk::eclosing_simplex(&mut Point(1.0, 1.0), difference, &mut BinaryHeap::new())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn gjk_warm_start_test() {
+        let first = make_shape! {
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 2.0),
+            (0.0, 2.0),
+        };
+
+        let second = make_shape! {
+            (3.0, 0.5),
+            (5.0, 1.5),
+            (4.0, 3.0),
+        };
+
+        let difference = minkowski::Difference(&first, &second);
+        let mut search_direction = Point(0.0, 1.0);
+        perf::take_gjk_iterations();
+        assert!(
+            gjk::eclosing_simplex(&mut search_direction, difference, &mut BinaryHeap::new())
+                .is_none()
+        );
+        assert!(perf::take_gjk_iterations() > 1);
+
+        // starting where the last search ended finds the separating axis right away
+        assert!(
+            gjk::eclosing_simplex(&mut search_direction, difference, &mut BinaryHeap::new())
+                .is_none()
+        );
+        assert_eq!(perf::take_gjk_iterations(), 1);
     }
 }
@@ -10,7 +10,7 @@ pub struct Difference<'s, S1: ?Sized, S2: ?Sized>(pub &'s S1, pub &'s S2);
 impl<'s, S1: ?Sized, S2: ?Sized> Copy for Difference<'s, S1, S2> {}
 impl<'s, S1: ?Sized, S2: ?Sized> Clone for Difference<'s, S1, S2> {
     fn clone(&self) -> Self {
-        Self(self.0, self.1)
+        *self
     }
 }
 
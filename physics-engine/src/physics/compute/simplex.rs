@@ -17,7 +17,7 @@ pub enum Partial {
 }
 
 pub enum Simplex {
-    Point(Vertex),
+    Point(#[allow(dead_code)] Vertex),
     Line(Vertex, Vertex),
     Triangle(Vertex, Vertex, Vertex),
 }
@@ -113,36 +113,22 @@ impl Edge {
         Self::try_new(first, second).unwrap()
     }
 
+    /// the vertices must follow the counter-clockwise winding of the polytope,
+    /// so that the normal of the edge points outwards even when the origin lies on it
     pub fn try_new(first: Vertex, second: Vertex) -> Option<Self> {
-        if first.point.to(second.point).dot(-first.point) <= 0.0 {
-            return Some(Self::redundant(first, second));
-        }
-
-        if second.point.to(first.point).dot(-second.point) <= 0.0 {
-            return Some(Self::redundant(second, first));
-        }
-
-        let to_origin = first.point.triple_product(second.point).unit();
-        let distance_to_origin = -first.point.dot(to_origin);
+        let towards_segment = first.point.to(second.point).perpendicular().unit();
+        let distance_to_origin = first.point.dot(towards_segment);
 
         if distance_to_origin.is_nan() {
             None
         } else {
             Some(Self {
                 distance_to_origin,
-                towards_segment: -to_origin,
+                towards_segment,
                 segment: (first, second),
             })
         }
     }
-
-    fn redundant(primary: Vertex, redundant: Vertex) -> Self {
-        Self {
-            distance_to_origin: primary.point.dot(primary.point).sqrt(),
-            towards_segment: primary.point,
-            segment: (primary, redundant),
-        }
-    }
 }
 
 impl Eq for Edge {}
@@ -154,15 +140,16 @@ impl PartialEq for Edge {
 
 impl PartialOrd for Edge {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.distance_to_origin
-            .partial_cmp(&other.distance_to_origin)
-            .map(Ordering::reverse)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Edge {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        self.distance_to_origin
+            .partial_cmp(&other.distance_to_origin)
+            .map(Ordering::reverse)
+            .unwrap()
     }
 }
 
@@ -173,16 +160,40 @@ mod test {
     #[test]
     #[should_panic]
     fn test_edge() {
-        let first = Vertex {
+        let vertex = Vertex {
             point: Point(1.0, 1.0),
             created_from: (Point(1.0, 1.0), Point::ZERO),
         };
 
+        Edge::new(vertex, vertex);
+    }
+
+    #[test]
+    fn test_edge_through_origin() {
+        let first = Vertex {
+            point: Point(1.0, -1.0),
+            created_from: (Point(1.0, -1.0), Point::ZERO),
+        };
+
         let second = Vertex {
-            point: Point(-1.0, -1.0),
-            created_from: (Point(-1.0, -1.0), Point::ZERO),
+            point: Point(1.0, 1.0),
+            created_from: (Point(1.0, 1.0), Point::ZERO),
         };
 
-        Edge::new(first, second);
+        let edge = Edge::new(first, second);
+        assert_eq!(edge.distance_to_origin, 1.0);
+        assert_eq!(edge.towards_segment, Point(1.0, 0.0));
+
+        let edge = Edge::new(
+            first,
+            Vertex {
+                point: Point(-1.0, 1.0),
+                ..second
+            },
+        );
+        assert!(edge.distance_to_origin.abs() < 1e-12);
+        assert!(edge
+            .towards_segment
+            .is_close_enough_to(Point(1.0, 1.0).unit()));
     }
 }
@@ -0,0 +1,104 @@
+//! Support queries over the vertices of a polygon
+//!
+//! These dominate the narrowphase of polygons with many vertices, so with the
+//! `simd` feature they are vectorized with `core::simd`, which still requires
+//! a nightly toolchain. For wasm, the `wasm:simd` script of the frontend also
+//! enables `simd128` for the vectors to lower to, which leaves the other wasm
+//! builds running wherever wasm without SIMD does.
+
+#[cfg(feature = "simd")]
+use std::simd::prelude::*;
+
+use crate::geometry::{Point, Vector};
+
+#[cfg(feature = "simd")]
+const LANES: usize = 4;
+
+/// the point farthest along `direction`, the last one in case of a tie,
+/// panics if there are no points or some are NaN
+#[cfg(not(feature = "simd"))]
+pub fn farthest_point(points: &[Point], direction: Vector) -> Point {
+    *points
+        .iter()
+        .max_by(|&&p1, &&p2| direction.dot(p1).partial_cmp(&direction.dot(p2)).unwrap())
+        .unwrap()
+}
+
+/// the point farthest along `direction`, the last one in case of a tie,
+/// panics if there are no points or some are NaN
+#[cfg(feature = "simd")]
+pub fn farthest_point(points: &[Point], direction: Vector) -> Point {
+    assert!(!points.is_empty(), "support query of no points");
+    let chunks = points.chunks_exact(LANES);
+    let remainder = chunks.remainder();
+
+    let direction_x = Simd::splat(direction.0);
+    let direction_y = Simd::splat(direction.1);
+    let mut best_dots = Simd::<f64, LANES>::splat(f64::NEG_INFINITY);
+    let mut best_indices = Simd::<u64, LANES>::splat(0);
+    let mut indices = Simd::from_array(std::array::from_fn(|lane| lane as u64));
+    let mut has_nan = Mask::<i64, LANES>::splat(false);
+
+    for chunk in chunks {
+        let xs = Simd::from_array(std::array::from_fn(|lane| chunk[lane].0));
+        let ys = Simd::from_array(std::array::from_fn(|lane| chunk[lane].1));
+        let dots = xs * direction_x + ys * direction_y;
+
+        let is_farther = dots.simd_ge(best_dots);
+        has_nan |= dots.simd_ne(dots);
+        best_dots = is_farther.select(dots, best_dots);
+        best_indices = is_farther.select(indices, best_indices);
+        indices += Simd::splat(LANES as u64);
+    }
+    assert!(
+        !has_nan.any(),
+        "support query of a point with NaN coordinates"
+    );
+
+    // every lane holds its own indices, so ties have to go to the later index explicitly
+    let (mut best_dot, mut best_index) = best_dots
+        .to_array()
+        .into_iter()
+        .zip(best_indices.to_array().map(|index| index as usize))
+        .reduce(|best, lane| {
+            if lane.0 > best.0 || (lane.0 == best.0 && lane.1 > best.1) {
+                lane
+            } else {
+                best
+            }
+        })
+        .unwrap();
+
+    let remainder_start = points.len() - remainder.len();
+    for (index, &point) in remainder.iter().enumerate() {
+        let dot = direction.dot(point);
+        assert!(
+            !dot.is_nan(),
+            "support query of a point with NaN coordinates"
+        );
+        if dot >= best_dot {
+            best_dot = dot;
+            best_index = remainder_start + index;
+        }
+    }
+
+    points[best_index]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ties() {
+        // every vertex ties with another one along the axes
+        let points: Vec<_> = (0..7)
+            .map(|i| Point((i % 2) as f64, (i / 2) as f64))
+            .collect();
+
+        assert_eq!(farthest_point(&points, Point(1.0, 0.0)), Point(1.0, 2.0));
+        assert_eq!(farthest_point(&points, Point(0.0, 1.0)), Point(0.0, 3.0));
+        assert_eq!(farthest_point(&points, Point(-1.0, 0.0)), Point(0.0, 3.0));
+        assert_eq!(farthest_point(&points, Point(0.0, -1.0)), Point(1.0, 0.0));
+    }
+}
@@ -0,0 +1,219 @@
+//! Smoothed curves along ropes and chains
+//!
+//! A rope is a single binding between its ends and a chain is a row of
+//! rectangular links, and neither looks much like a rope drawn the way it is
+//! simulated. The display message carries points along a Catmull-Rom spline
+//! for every one of them, for the frontend to draw as a polyline instead. The
+//! spline of a chain passes through its ends and the hinges between its links,
+//! and the one of a rope through its ends and a point between them, which
+//! hangs below them along gravity while the rope is slack.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    binding::PointOnShape,
+    ids::{BindingId, EntityId},
+    DisplayMessage, Engine,
+};
+use crate::geometry::{Point, Vector};
+
+/// how many points of a curve there are from one point it passes through to the next
+const SAMPLES_PER_SEGMENT: usize = 8;
+
+/// The links of a chain, kept to draw a curve through them, see [`Engine::add_chain`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ChainPath {
+    /// from the first point to the last one
+    pub links: Vec<EntityId>,
+    /// between every link and the next one, held by the former
+    pub hinges: Vec<BindingId>,
+    /// the points of the chain every link spans, on the link
+    pub ends: Vec<[PointOnShape; 2]>,
+}
+
+/// Samples the uniform Catmull-Rom spline through the points into `curve`,
+/// doubling the first and the last point so that it reaches them
+fn catmull_rom(points: &[Point], curve: &mut Vec<Point>) {
+    curve.clear();
+    let Some(&first) = points.first() else {
+        return;
+    };
+    curve.push(first);
+    for index in 1..points.len() {
+        let p0 = points[index.saturating_sub(2)];
+        let p1 = points[index - 1];
+        let p2 = points[index];
+        let p3 = points[(index + 1).min(points.len() - 1)];
+        curve.extend((1..SAMPLES_PER_SEGMENT).map(|sample| {
+            let t = sample as f64 / SAMPLES_PER_SEGMENT as f64;
+            (p1 * 2.0
+                + (p2 - p0) * t
+                + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t.powi(2)
+                + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t.powi(3))
+                * 0.5
+        }));
+        curve.push(p2);
+    }
+}
+
+/// The middle of a rope `length` long between `ends`, as far below the middle of
+/// the ends along `down` as the bottom of the rope would be if it hung in two
+/// straight halves, or at it if the rope is taut or there is no gravity
+fn sagging_middle(ends: [Point; 2], length: f64, down: Vector) -> Point {
+    let middle = (ends[0] + ends[1]) * 0.5;
+    let span = ends[0].to(ends[1]).norm();
+    if span >= length || down == Vector::ZERO {
+        return middle;
+    }
+    middle + down.unit() * ((length.powi(2) - span.powi(2)).sqrt() * 0.5)
+}
+
+impl Engine {
+    /// writes the curves of the ropes already in the message and of the chains,
+    /// forgetting the chains none of the links of which are left
+    pub(super) fn write_curves(&mut self, message: &mut DisplayMessage) {
+        let down = self.gravity.vector(self.gravity_mulipiler);
+        message.rope_curves.clear();
+        for (&ends, &length) in message.ropes.iter().zip(&message.rope_lengths) {
+            let mut curve = vec![];
+            catmull_rom(
+                &[ends[0], sagging_middle(ends, length, down), ends[1]],
+                &mut curve,
+            );
+            message.rope_curves.push(curve);
+        }
+
+        self.chains.retain(|chain| {
+            chain
+                .links
+                .iter()
+                .any(|&link| self.slots.get(link).is_some())
+        });
+        message.chain_curves.clear();
+        message.chain_links.clear();
+        let mut anchors = vec![];
+        let mut write_run = |anchors: &mut Vec<Point>| {
+            if anchors.len() >= 2 {
+                let mut curve = vec![];
+                catmull_rom(anchors, &mut curve);
+                message.chain_curves.push(curve);
+            }
+            anchors.clear();
+        };
+        for chain in &self.chains {
+            // whether the previous link is still hinged to this one
+            let mut is_joined = false;
+            for (index, (&link, ends)) in chain.links.iter().zip(&chain.ends).enumerate() {
+                let Some(slot) = self.slots.get(link) else {
+                    write_run(&mut anchors);
+                    is_joined = false;
+                    continue;
+                };
+                let entity = &self.entities[slot];
+                let [start, end] =
+                    ends.map(|end| entity.shape.borrow().resolve_point_reference(end));
+                match anchors.last_mut() {
+                    // the hinge gives a little, so the ends of the links are not quite together
+                    Some(joint) if is_joined => *joint = (*joint + start) * 0.5,
+                    _ => {
+                        write_run(&mut anchors);
+                        anchors.push(start);
+                    }
+                }
+                anchors.push(end);
+                message.chain_links.push(link);
+                is_joined = chain
+                    .hinges
+                    .get(index)
+                    .is_some_and(|&hinge| entity.bindings.iter().any(|(id, ..)| *id == hinge));
+            }
+            write_run(&mut anchors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig, snapshot::Snapshot};
+
+    #[test]
+    fn test_catmull_rom() {
+        let points = [Point(0.0, 0.0), Point(1.0, 1.0), Point(2.0, 0.0)];
+        let mut curve = vec![];
+        catmull_rom(&points, &mut curve);
+        assert_eq!(curve.len(), 2 * SAMPLES_PER_SEGMENT + 1);
+        for (index, &point) in points.iter().enumerate() {
+            assert!(curve[index * SAMPLES_PER_SEGMENT].to(point).norm() < 1e-12);
+        }
+        // symmetric about the middle point, and above the straight line between the others
+        for sample in 1..SAMPLES_PER_SEGMENT {
+            let (left, right) = (curve[sample], curve[curve.len() - 1 - sample]);
+            assert!((left.0 + right.0 - 2.0).abs() < 1e-12);
+            assert!((left.1 - right.1).abs() < 1e-12);
+            assert!(left.1 > 0.0);
+        }
+
+        catmull_rom(&points[..2], &mut curve);
+        assert_eq!(curve.len(), SAMPLES_PER_SEGMENT + 1);
+        assert!(curve.iter().all(|point| (point.0 - point.1).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_curves() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (1.0, -2.2), (2.0, -2.2), (2.0, -2.0), (1.0, -2.0) },
+            true,
+        );
+        engine.add_prefab_polygon(
+            make_shape! { (3.0, -2.2), (4.0, -2.2), (4.0, -2.0), (3.0, -2.0) },
+            true,
+        );
+        engine
+            .add_rope(Point(1.5, -2.1), Point(3.5, -2.1), 4.0)
+            .unwrap();
+        let points: Vec<_> = (0..=4)
+            .map(|index| Point(1.5 + index as f64 * 0.5, -2.1))
+            .collect();
+        let chain = engine.add_chain(points, 0.05).unwrap();
+
+        // the slack rope hangs below its ends, as far as a rope folded in half would
+        let message = engine.run_iteration(2_000.0);
+        let [curve] = &message.rope_curves[..] else {
+            panic!("{:?} are not one rope", message.rope_curves);
+        };
+        assert_eq!(curve[0], message.ropes[0][0]);
+        assert_eq!(curve[curve.len() - 1], message.ropes[0][1]);
+        let bottom = curve[SAMPLES_PER_SEGMENT];
+        assert!(
+            (bottom.1 - (-2.1 + 12f64.sqrt() / 2.0)).abs() < 0.01,
+            "{bottom:?}"
+        );
+
+        // the chain is one curve through its ends and hinges
+        let [curve] = &message.chain_curves[..] else {
+            panic!("{:?} are not one chain", message.chain_curves);
+        };
+        assert_eq!(curve.len(), 4 * SAMPLES_PER_SEGMENT + 1);
+        assert!(curve[0].to(Point(1.5, -2.1)).norm() < 0.01);
+        assert!(curve[curve.len() - 1].to(Point(3.5, -2.1)).norm() < 0.01);
+        assert_eq!(message.chain_links, chain.links);
+
+        // removing a link splits the chain in two
+        assert_eq!(engine.erase(chain.links[1]), Some(chain.links[1]));
+        let message = engine.run_iteration(2_000.0);
+        let lengths: Vec<_> = message.chain_curves.iter().map(Vec::len).collect();
+        assert_eq!(
+            lengths,
+            vec![SAMPLES_PER_SEGMENT + 1, 2 * SAMPLES_PER_SEGMENT + 1]
+        );
+        assert_eq!(message.chain_links.len(), 3);
+
+        let restored = Engine::from(Snapshot::from(&engine));
+        assert_eq!(restored.chains.len(), 1);
+    }
+}
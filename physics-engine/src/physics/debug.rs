@@ -0,0 +1,190 @@
+//! Debug drawing of what the solver sees
+//!
+//! With debug drawing enabled, every display message carries a [`DebugDraw`]
+//! with the bounding box and velocity of every body, the contacts of the last
+//! step and the islands the bodies form. It is off by default, in which case
+//! the steps skip recording the contacts and the messages carry `None`, so
+//! all it costs is checking a flag. The engine never puts bodies to sleep, so
+//! there is no sleep state to show.
+
+use std::{collections::HashMap, os::raw::c_void};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{compute::simplex::Vertex, ids::EntityId, Engine, Entity};
+use crate::geometry::{Aabb, Point, Vector};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DebugBody {
+    pub id: EntityId,
+    pub aabb: Aabb,
+    pub velocity: Vector,
+    pub angular_velocity: f64,
+    /// bodies share an island when they touch or are bound together, directly
+    /// or through other bodies of it. `None` for static bodies, which hold up
+    /// everything resting on them without joining their islands
+    pub island: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DebugContact {
+    pub entities: (EntityId, EntityId),
+    /// where the first entity is touched
+    pub point: Point,
+    /// points from the first entity towards the second one
+    pub normal: Vector,
+    pub depth: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DebugDraw {
+    /// in the same order as they are simulated, starting with the main ball
+    pub bodies: Vec<DebugBody>,
+    /// the contacts detected during the last step, before they were resolved
+    pub contacts: Vec<DebugContact>,
+}
+
+/// the root of the set `i` is in, halving the path to it along the way
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// keeps the contacts of the step in `recorded`, if debug drawing is enabled
+pub(super) fn record_contacts(
+    recorded: &mut Option<Vec<DebugContact>>,
+    entities: &[Entity],
+    pairs: &[(usize, usize)],
+    contacts: &[Option<Vertex>],
+) {
+    let Some(recorded) = recorded else {
+        return;
+    };
+    recorded.clear();
+    for (&(i, j), contact) in pairs.iter().zip(contacts) {
+        if let Some(contact) = contact {
+            recorded.push(DebugContact {
+                entities: (entities[i].id, entities[j].id),
+                point: contact.created_from.0,
+                normal: contact.point.unit(),
+                depth: contact.point.norm(),
+            });
+        }
+    }
+}
+
+impl Engine {
+    pub fn set_debug_draw(&mut self, enabled: bool) {
+        self.debug_contacts = enabled.then(Vec::new);
+    }
+
+    pub(super) fn debug_draw(&self) -> Option<DebugDraw> {
+        let contacts = self.debug_contacts.as_ref()?;
+
+        let mut parents: Vec<usize> = (0..self.entities.len()).collect();
+        let mut join = |i: usize, j: usize| {
+            if !self.entities[i].is_static && !self.entities[j].is_static {
+                let (i, j) = (find(&mut parents, i), find(&mut parents, j));
+                parents[i.max(j)] = i.min(j);
+            }
+        };
+        for contact in contacts {
+            let slots = (
+                self.slots.get(contact.entities.0),
+                self.slots.get(contact.entities.1),
+            );
+            if let (Some(i), Some(j)) = slots {
+                join(i, j);
+            }
+        }
+        let address = |entity: &Entity| (&*entity.shape) as *const _ as *const c_void;
+        let slots: HashMap<_, _> = self
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(slot, entity)| (address(entity), slot))
+            .collect();
+        for (i, entity) in self.entities.iter().enumerate() {
+            for (_, _, target) in &entity.bindings {
+                if let Some(&j) = slots.get(&(target.as_ptr() as *const c_void)) {
+                    join(i, j);
+                }
+            }
+        }
+
+        // islands are numbered in the order of their first body
+        let mut islands = HashMap::new();
+        let bodies = self
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| {
+                let mut shape = entity.shape.borrow_mut();
+                let aabb = shape.aabb();
+                let data = shape.collision_data_mut();
+                let island = (!entity.is_static).then(|| {
+                    let next = islands.len() as u32;
+                    *islands.entry(find(&mut parents, i)).or_insert(next)
+                });
+                DebugBody {
+                    id: entity.id,
+                    aabb,
+                    velocity: data.velocity,
+                    angular_velocity: data.angular_velocity,
+                    island,
+                }
+            })
+            .collect();
+
+        Some(DebugDraw {
+            bodies,
+            contacts: contacts.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig};
+
+    #[test]
+    fn test_debug_draw() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        assert!(engine.run_iteration(2000.0).debug.is_none());
+
+        engine.add_prefab_polygon(
+            make_shape! { (-3.0, 1.0), (3.0, 1.0), (3.0, 1.2), (-3.0, 1.2) },
+            true,
+        );
+        // two stacks resting on the floor, one of them hinged to a square off to the side
+        for x in [-2.0, 1.0] {
+            engine.add_polygon(make_shape! { (x, 0.6), (x + 0.4, 0.6), (x + 0.4, 1.0), (x, 1.0) });
+        }
+        engine.add_hinge(Point(1.3, 0.65), 0.0);
+        engine.add_polygon(make_shape! { (1.2, 0.3), (1.6, 0.3), (1.6, 0.7), (1.2, 0.7) });
+        engine.set_debug_draw(true);
+
+        let debug = engine.run_n_iterations(20, 2000.0).debug.unwrap();
+        let islands: Vec<_> = debug.bodies.iter().map(|body| body.island).collect();
+        assert_eq!(islands[1], None);
+        assert_eq!(islands[3], islands[4]);
+        assert_ne!(islands[2], islands[3]);
+        // the normals point up from the floor, towards negative y
+        assert!(debug
+            .contacts
+            .iter()
+            .any(|contact| contact.entities.0 == EntityId(1) && contact.normal.1 < 0.0));
+
+        engine.set_debug_draw(false);
+        assert!(engine.run_iteration(2000.0).debug.is_none());
+    }
+}
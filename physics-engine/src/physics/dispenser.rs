@@ -0,0 +1,275 @@
+//! Dispensers emitting balls besides the main one, and what becomes of them
+//!
+//! A dispenser emits a ball every time its interval passes, or whenever
+//! [`Engine::dispense`] or the script triggers it, until it has emitted as many
+//! balls as its capacity. The engine keeps track of every ball emitted: a ball
+//! that leaves the world does not simply drop out of it like other bodies, it
+//! follows the policy of its dispenser instead, which respawns it at the
+//! dispenser, despawns it, or loses the level. The main ball leaves the world
+//! the same way, but always respawns at the start of the level.
+//!
+//! [`Engine::balls`] and the display message report the status of every ball
+//! emitted, including the ones no longer in play.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    ball,
+    game::{GameEvent, LossReason},
+    ids::{EntityId, RegionId},
+    Engine,
+};
+use crate::{
+    geometry::{Point, Vector},
+    levels::{self, OutOfBounds},
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Dispenser {
+    pub id: RegionId,
+    pub position: Point,
+    pub out_of_bounds: OutOfBounds,
+    /// how many more balls it emits, `None` for no limit
+    pub remaining: Option<u32>,
+    radius: f64,
+    density: f64,
+    color: Option<[f32; 3]>,
+    initial_velocity: Vector,
+    interval_microseconds: Option<f64>,
+    /// simulated time since it last emitted a ball on its own
+    elapsed_microseconds: f64,
+}
+
+impl Dispenser {
+    pub fn new(id: RegionId, dispenser: &levels::Dispenser) -> Self {
+        Self {
+            id,
+            position: dispenser.position,
+            out_of_bounds: dispenser.out_of_bounds,
+            remaining: dispenser.capacity,
+            radius: dispenser.radius,
+            density: dispenser.density,
+            color: dispenser.color,
+            initial_velocity: dispenser.initial_velocity,
+            interval_microseconds: dispenser
+                .interval_seconds
+                .map(|seconds| seconds * 1_000_000.0),
+            elapsed_microseconds: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum BallStatus {
+    InPlay,
+    /// it left the world and was removed, or was removed some other way
+    Despawned,
+    /// it left the world and lost the level
+    Lost,
+}
+
+/// A ball emitted by a dispenser
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TrackedBall {
+    pub id: EntityId,
+    pub dispenser: RegionId,
+    pub status: BallStatus,
+    /// how many times it left the world and started over at the dispenser
+    pub respawns: u32,
+}
+
+impl Engine {
+    pub fn balls(&self) -> &[TrackedBall] {
+        &self.balls
+    }
+
+    /// emits a ball from the dispenser, unless it has emitted as many as its
+    /// capacity or the engine holds as many entities as its config allows.
+    /// Returns the id of the ball
+    pub fn dispense(&mut self, id: RegionId) -> Option<EntityId> {
+        let is_full = self
+            .config
+            .max_entities
+            .is_some_and(|max| self.entities.len() >= max);
+        let dispenser = self
+            .dispensers
+            .iter_mut()
+            .find(|dispenser| dispenser.id == id)?;
+        if is_full || dispenser.remaining == Some(0) {
+            return None;
+        }
+
+        if let Some(remaining) = &mut dispenser.remaining {
+            *remaining -= 1;
+        }
+        let shape = ball::shape(
+            dispenser.position,
+            dispenser.radius,
+            dispenser.density,
+            dispenser.initial_velocity,
+        );
+        let color = dispenser.color;
+        let ball = self.add_ball(shape, color);
+        self.balls.push(TrackedBall {
+            id: ball,
+            dispenser: id,
+            status: BallStatus::InPlay,
+            respawns: 0,
+        });
+        self.events.push(GameEvent::Dispensed { id, ball });
+        Some(ball)
+    }
+
+    /// emits a ball from every dispenser whose interval passed
+    pub(super) fn run_dispensers(&mut self, microseconds: f64) {
+        for i in 0..self.dispensers.len() {
+            let dispenser = &mut self.dispensers[i];
+            let Some(interval) = dispenser.interval_microseconds.filter(|&it| it > 0.0) else {
+                continue;
+            };
+            dispenser.elapsed_microseconds += microseconds;
+            if dispenser.elapsed_microseconds >= interval {
+                dispenser.elapsed_microseconds -= interval;
+                let id = dispenser.id;
+                self.dispense(id);
+            }
+        }
+    }
+
+    /// whether a ball with its centroid at `centroid` left the world
+    pub(super) fn is_out_of_world(&self, centroid: Point) -> bool {
+        let in_level = centroid + self.origin;
+        self.world_half_extent
+            .is_some_and(|extent| in_level.0.abs() > extent || in_level.1 < -extent)
+    }
+
+    /// applies the policies of their dispensers to the balls that left the world
+    pub(super) fn check_balls(&mut self) {
+        for i in 0..self.balls.len() {
+            let TrackedBall {
+                id,
+                dispenser,
+                status,
+                ..
+            } = self.balls[i];
+            if status != BallStatus::InPlay {
+                continue;
+            }
+            let Some(slot) = self.slots.get(id) else {
+                self.balls[i].status = BallStatus::Despawned;
+                continue;
+            };
+            let centroid = self.entities[slot]
+                .shape
+                .borrow_mut()
+                .collision_data_mut()
+                .centroid;
+            if !self.is_out_of_world(centroid) {
+                continue;
+            }
+
+            let Some((position, velocity, out_of_bounds)) = self
+                .dispensers
+                .iter()
+                .find(|it| it.id == dispenser)
+                .map(|it| (it.position, it.initial_velocity, it.out_of_bounds))
+            else {
+                continue;
+            };
+            match out_of_bounds {
+                OutOfBounds::Respawn => {
+                    let mut shape = self.entities[slot].shape.borrow_mut();
+                    let data = shape.collision_data_mut();
+                    data.centroid = position;
+                    data.angular_velocity = 0.0;
+                    data.velocity = velocity;
+                    self.balls[i].respawns += 1;
+                    self.events.push(GameEvent::BallRespawned { id });
+                }
+                OutOfBounds::Despawn => {
+                    self.remove_entity(slot);
+                    self.balls[i].status = BallStatus::Despawned;
+                    self.events.push(GameEvent::BallDespawned { id });
+                }
+                OutOfBounds::Loss => {
+                    self.remove_entity(slot);
+                    self.balls[i].status = BallStatus::Lost;
+                    self.events.push(GameEvent::BallDespawned { id });
+                    self.events
+                        .extend(self.game.lose(LossReason::BallLost { id }));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{game::GameState, layer, sandbox::SandboxConfig, snapshot::Snapshot};
+
+    fn dispenser(interval_seconds: Option<f64>, out_of_bounds: OutOfBounds) -> levels::Dispenser {
+        levels::Dispenser {
+            position: Point(0.0, -1.0),
+            radius: 0.1,
+            density: 1.0,
+            color: None,
+            initial_velocity: Point(4.0, 0.0),
+            interval_seconds,
+            capacity: Some(2),
+            out_of_bounds,
+        }
+    }
+
+    #[test]
+    fn test_dispensers() {
+        let mut level = Engine::sandbox(SandboxConfig::default())
+            .export_level()
+            .unwrap();
+        level.world_half_extent = Some(2.0);
+        level.dispensers = vec![
+            dispenser(Some(0.1), OutOfBounds::Respawn),
+            dispenser(None, OutOfBounds::Despawn),
+            dispenser(None, OutOfBounds::Loss),
+        ];
+        let mut engine = Engine::new(level);
+        let [timed, despawning, losing] = [0, 1, 2].map(|i| engine.dispensers[i].id);
+
+        // the timed one emits a ball every 50 steps, up to its capacity
+        engine.run_n_iterations(100, 2_000.0);
+        assert_eq!(engine.balls().len(), 2);
+        assert_eq!(engine.dispense(timed), None);
+        let despawned = engine.dispense(despawning).unwrap();
+        assert_eq!(engine.render_layer(despawned), Some(layer::BALL));
+
+        let message = engine.run_n_iterations(500, 2_000.0);
+        assert_eq!(message.balls.len(), 3);
+        for ball in &message.balls[..2] {
+            assert_eq!(ball.status, BallStatus::InPlay);
+            assert!(ball.respawns > 0);
+            assert!(engine.slots.get(ball.id).is_some());
+        }
+        assert_eq!(message.balls[2].status, BallStatus::Despawned);
+        assert_eq!(engine.slots.get(despawned), None);
+        assert!(message
+            .events
+            .contains(&GameEvent::BallDespawned { id: despawned }));
+        assert_eq!(engine.game.state(), GameState::Playing);
+
+        let lost = engine.dispense(losing).unwrap();
+        let message = engine.run_n_iterations(500, 2_000.0);
+        assert_eq!(engine.balls()[3].status, BallStatus::Lost);
+        let reason = LossReason::BallLost { id: lost };
+        assert!(message.events.contains(&GameEvent::Lost { reason }));
+        assert_eq!(engine.game.state(), GameState::Lost { reason });
+
+        let restored = Engine::from(Snapshot::from(&engine));
+        assert_eq!(restored.balls(), engine.balls());
+        assert_eq!(restored.dispensers, engine.dispensers);
+    }
+}
@@ -0,0 +1,154 @@
+//! Finding where two runs of a level stop agreeing
+//!
+//! A replay that drifts away from the run it recorded only shows it once the
+//! drift is large enough to see, long after it started. While recording is
+//! enabled, the engine hashes every body after every step, the same way as
+//! [`Engine::state_hash`] hashes all of them, so that comparing the logs of a
+//! live run and its replay points at the first step and the first body on
+//! which they disagree.
+//!
+//! Recording costs a hash and a few bytes per body and step, and the log grows
+//! until recording is disabled, so it is meant for debugging sessions only.
+//! Both runs have to start recording at the same step for the step numbers to
+//! line up, the easiest way being to enable it right after loading the level.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{ids::EntityId, BodyState, Engine, Entity};
+
+/// The hash of every body after a step, ordered by id
+pub(super) type BodyHashes = Vec<(EntityId, u64)>;
+
+/// Where two runs diverge
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Divergence {
+    /// the number of steps recorded before the one after which the runs differ
+    pub step: u32,
+    /// the body with the lowest id that differs, or that exists in only one of the runs
+    pub entity: EntityId,
+}
+
+/// appends the hashes of the `entities` to the log, if recording is enabled
+pub(super) fn record_hashes(log: &mut Option<Vec<BodyHashes>>, entities: &[Entity]) {
+    let Some(log) = log else {
+        return;
+    };
+    let mut hashes: BodyHashes = entities
+        .iter()
+        .map(|entity| {
+            let state = BodyState::of(entity);
+            (state.id, state.hash(BodyState::OFFSET_BASIS))
+        })
+        .collect();
+    // the bodies are simulated in spatial order when that is enabled
+    hashes.sort_unstable_by_key(|&(id, _)| id);
+    log.push(hashes);
+}
+
+/// the first body that differs between the hashes of the same step
+fn first_difference(ours: &BodyHashes, theirs: &BodyHashes) -> Option<EntityId> {
+    let differing = ours
+        .iter()
+        .zip(theirs)
+        .find(|(ours, theirs)| ours != theirs)
+        .map(|(ours, theirs)| ours.0.min(theirs.0));
+    // when one run has every body of the other one, its extra bodies differ
+    differing.or_else(|| {
+        let common = ours.len().min(theirs.len());
+        ours.get(common).or(theirs.get(common)).map(|&(id, _)| id)
+    })
+}
+
+impl Engine {
+    /// starts recording the hashes of the bodies with a new log, or stops
+    /// recording and drops the log
+    pub fn set_divergence_recording(&mut self, enabled: bool) {
+        self.divergence_log = enabled.then(Vec::new);
+    }
+
+    /// the number of steps recorded since recording was enabled
+    pub fn recorded_steps(&self) -> u32 {
+        self.divergence_log
+            .as_ref()
+            .map_or(0, |log| log.len() as u32)
+    }
+
+    /// compares the steps both engines recorded, `None` if they agree on all
+    /// of them or either of them is not recording
+    pub fn first_divergence(&self, other: &Engine) -> Option<Divergence> {
+        let (ours, theirs) = (
+            self.divergence_log.as_ref()?,
+            other.divergence_log.as_ref()?,
+        );
+        ours.iter()
+            .zip(theirs)
+            .enumerate()
+            .find_map(|(step, (ours, theirs))| {
+                Some(Divergence {
+                    step: step as u32,
+                    entity: first_difference(ours, theirs)?,
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry::Point,
+        physics::{make_shape, sandbox::SandboxConfig, shape::Circle},
+    };
+
+    fn recording() -> Engine {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (-3.0, 1.0), (3.0, 1.0), (3.0, 1.2), (-3.0, 1.2) },
+            true,
+        );
+        engine.add_polygon(make_shape! { (1.0, 0.6), (1.4, 0.6), (1.4, 1.0), (1.0, 1.0) });
+        engine.set_divergence_recording(true);
+        engine
+    }
+
+    #[test]
+    fn test_first_divergence() {
+        let (mut live, mut replay) = (recording(), recording());
+        live.run_n_iterations(10, 2000.0);
+        replay.run_n_iterations(10, 2000.0);
+        assert_eq!(live.recorded_steps(), 10);
+        assert_eq!(live.first_divergence(&replay), None);
+
+        // the replay nudges the square, which drifts from then on
+        let slot = replay.slots.get(EntityId(2)).unwrap();
+        replay.entities[slot]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .velocity += Point(0.001, 0.0);
+        live.run_n_iterations(10, 2000.0);
+        replay.run_n_iterations(10, 2000.0);
+        let divergence = Divergence {
+            step: 10,
+            entity: EntityId(2),
+        };
+        assert_eq!(live.first_divergence(&replay), Some(divergence));
+
+        // a body missing from one of the runs
+        let mut extra = recording();
+        extra.add_circle(Circle::new(Point(-1.0, 0.0), 0.1));
+        let mut missing = recording();
+        extra.run_iteration(2000.0);
+        missing.run_iteration(2000.0);
+        assert_eq!(
+            extra.first_divergence(&missing).unwrap().entity,
+            EntityId(3)
+        );
+
+        live.set_divergence_recording(false);
+        assert_eq!(live.first_divergence(&replay), None);
+    }
+}
@@ -0,0 +1,422 @@
+//! Editing the level from within the engine
+//!
+//! The editor of the frontend works on the level the engine was loaded from
+//! instead of keeping a copy of its own. Every edit changes that level and
+//! loads the engine from it again, so the level restarts and whatever the
+//! player drew is gone, which is why the editor pauses the simulation while it
+//! is open. The host settings, like the config, the multipliers, snapping, the
+//! palette and debug drawing, carry over to the reloaded engine, and so do the
+//! origin and the run being recorded or traced. [`Engine::export_level`] hands
+//! out the edited level.
+//!
+//! Points are relative to the current origin, as everywhere else, and stored
+//! in the coordinates of the level. The ball start and the flags snap to the
+//! grid of [`super::snap`]. The engine keeps the ids it added the polygons and
+//! the circles of the level with, see [`LevelIds`], to find the entity of the
+//! level an id belongs to. As it adds them in the same order on every load,
+//! they keep their ids across the reloads.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    ids::EntityId,
+    shape::{Bounded, Polygon},
+    Engine, FLAG_SIZE,
+};
+use crate::{
+    geometry::Point,
+    levels::{self, Level},
+};
+
+/// The flags of an entity of the level that the editor toggles
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct EntityFlags {
+    pub is_static: bool,
+    pub is_bindable: bool,
+    pub is_erasable: bool,
+}
+
+impl<S> From<&levels::Entity<S>> for EntityFlags {
+    fn from(entity: &levels::Entity<S>) -> Self {
+        Self {
+            is_static: entity.is_static,
+            is_bindable: entity.is_bindable,
+            is_erasable: entity.is_erasable,
+        }
+    }
+}
+
+impl EntityFlags {
+    fn apply_to<S>(self, entity: &mut levels::Entity<S>) {
+        entity.is_static = self.is_static;
+        entity.is_bindable = self.is_bindable;
+        entity.is_erasable = self.is_erasable;
+    }
+}
+
+/// The ids of the entities of the level, by their index in the level
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct LevelIds {
+    pub polygons: Vec<EntityId>,
+    pub circles: Vec<EntityId>,
+}
+
+#[derive(Clone, Copy)]
+enum LevelEntity {
+    Polygon(usize),
+    Circle(usize),
+}
+
+impl LevelIds {
+    fn find(&self, id: EntityId) -> Option<LevelEntity> {
+        match self.polygons.iter().position(|&polygon| polygon == id) {
+            Some(i) => Some(LevelEntity::Polygon(i)),
+            None => self
+                .circles
+                .iter()
+                .position(|&circle| circle == id)
+                .map(LevelEntity::Circle),
+        }
+    }
+}
+
+fn is_finite(point: Point) -> bool {
+    point.0.is_finite() && point.1.is_finite()
+}
+
+/// the index of the last flag whose square covers `point`, as it is drawn on top
+fn flag_at(level: &Level, point: Point) -> Option<usize> {
+    level.flags_positions.iter().rposition(|&Point(x, y)| {
+        (x..=x + FLAG_SIZE).contains(&point.0) && (y..=y + FLAG_SIZE).contains(&point.1)
+    })
+}
+
+impl Engine {
+    /// the level with every edit applied, `None` for an engine restored
+    /// from a snapshot taken before the engine kept its level
+    pub fn export_level(&self) -> Option<Level> {
+        self.level.clone()
+    }
+
+    /// moves the spawn of the main ball, and the ball along with it.
+    /// Like all the editing methods, returns whether the level was changed
+    pub fn set_ball_start(&mut self, position: Point) -> bool {
+        let position = self.snap_point(position) + self.origin;
+        is_finite(position)
+            && self.edit(|level| {
+                level.initial_ball_position = position;
+                true
+            })
+    }
+
+    pub fn place_flag(&mut self, position: Point) -> bool {
+        let position = self.snap_point(position) + self.origin;
+        is_finite(position)
+            && self.edit(|level| {
+                level.flags_positions.push(position);
+                true
+            })
+    }
+
+    /// moves the flag under `from` as far as `to` is from `from`, so that
+    /// it stays under the pointer dragging it
+    pub fn move_flag(&mut self, from: Point, to: Point) -> bool {
+        let (from, to) = (from + self.origin, to + self.origin);
+        let Some((i, position)) = self.level.as_ref().and_then(|level| {
+            let i = flag_at(level, from)?;
+            Some((i, level.flags_positions[i]))
+        }) else {
+            return false;
+        };
+        let moved = self.snap_point(position + (to - from) - self.origin) + self.origin;
+        is_finite(moved)
+            && self.edit(|level| {
+                level.flags_positions[i] = moved;
+                true
+            })
+    }
+
+    pub fn remove_flag_at(&mut self, point: Point) -> bool {
+        let point = point + self.origin;
+        self.edit(|level| {
+            let Some(i) = flag_at(level, point) else {
+                return false;
+            };
+            level.flags_positions.remove(i);
+            true
+        })
+    }
+
+    /// the flags of an entity of the level, `None` for any other entity
+    pub fn entity_flags(&self, id: EntityId) -> Option<EntityFlags> {
+        let level = self.level.as_ref()?;
+        match self.level_ids.find(id)? {
+            LevelEntity::Polygon(i) => level.polygons.get(i).map(Into::into),
+            LevelEntity::Circle(i) => level.circles.get(i).map(Into::into),
+        }
+    }
+
+    /// changes the flags of an entity of the level,
+    /// the entities drawn by the player or added as prefabs cannot be edited
+    pub fn set_entity_flags(&mut self, id: EntityId, flags: EntityFlags) -> bool {
+        let Some(entity) = self.level_ids.find(id) else {
+            return false;
+        };
+        self.edit(|level| {
+            match entity {
+                LevelEntity::Polygon(i) => level
+                    .polygons
+                    .get_mut(i)
+                    .map(|polygon| flags.apply_to(polygon)),
+                LevelEntity::Circle(i) => level
+                    .circles
+                    .get_mut(i)
+                    .map(|circle| flags.apply_to(circle)),
+            }
+            .is_some()
+        })
+    }
+
+    /// adds a time zone, unless its region is empty or it has
+    /// a negative time scale, or anything about it is not finite
+    pub fn add_time_zone(&mut self, mut zone: levels::TimeZone) -> bool {
+        for vertex in &mut zone.region {
+            *vertex += self.origin;
+        }
+        let is_valid = !zone.region.is_empty()
+            && zone.region.iter().all(|&vertex| is_finite(vertex))
+            && zone.time_scale.is_finite()
+            && zone.time_scale >= 0.0;
+        is_valid
+            && self.edit(|level| {
+                level.time_zones.push(zone);
+                true
+            })
+    }
+
+    /// removes the last added time zone whose region covers `point`
+    pub fn remove_time_zone_at(&mut self, point: Point) -> bool {
+        let point = point + self.origin;
+        self.edit(|level| {
+            let Some(i) = level
+                .time_zones
+                .iter()
+                .rposition(|zone| Polygon::new(zone.region.clone()).includes(point))
+            else {
+                return false;
+            };
+            level.time_zones.remove(i);
+            true
+        })
+    }
+
+    /// applies `change` to the level, reloading the engine if it reports a change
+    fn edit(&mut self, change: impl FnOnce(&mut Level) -> bool) -> bool {
+        let Some(level) = &mut self.level else {
+            return false;
+        };
+        if !change(level) {
+            return false;
+        }
+
+        let mut engine = Engine::with_config(level.clone(), self.config);
+        engine.shift_origin(self.origin);
+        engine.origin_shift_distance = self.origin_shift_distance;
+        engine.restitution_mulipiler = self.restitution_mulipiler;
+        engine.friction_mulipiler = self.friction_mulipiler;
+        engine.gravity_mulipiler = self.gravity_mulipiler;
+        engine.static_friction_enabled = self.static_friction_enabled;
+        engine.dynamic_friction_enabled = self.dynamic_friction_enabled;
+        engine.solver_tuning = self.solver_tuning;
+        engine.snapping = self.snapping;
+        engine.spring_config = self.spring_config;
+        engine.painter.palette = self.painter.palette.clone();
+        engine.set_debug_draw(self.debug_contacts.is_some());
+        engine.set_divergence_recording(self.divergence_log.is_some());
+        engine.recorder = self.recorder.take();
+        if let Some(recorder) = &mut engine.recorder {
+            recorder.restart_level();
+        }
+        engine.tracer = self.tracer.take();
+        *self = engine;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry,
+        physics::{make_shape, sandbox::SandboxConfig},
+    };
+
+    fn level() -> Level {
+        let mut level = Engine::sandbox(SandboxConfig::default())
+            .export_level()
+            .unwrap();
+        level.polygons.push(levels::Entity {
+            shape: vec![
+                Point(-1.0, 1.0),
+                Point(1.0, 1.0),
+                Point(1.0, 1.2),
+                Point(-1.0, 1.2),
+            ],
+            is_static: true,
+            is_bindable: false,
+            hit_points: None,
+            breaking_impulse: None,
+            fracture_impulse: None,
+            bump_impulse: None,
+            is_ferrous: false,
+            is_erasable: false,
+            render_layer: 0,
+        });
+        level
+    }
+
+    #[test]
+    fn test_edit_level() {
+        let mut engine = Engine::new(level());
+        engine.set_gravity_multipier(0.5);
+        let floor = EntityId(1);
+        let flags = EntityFlags {
+            is_static: false,
+            is_bindable: true,
+            is_erasable: true,
+        };
+
+        assert!(engine.set_entity_flags(floor, flags));
+        assert!(!engine.set_entity_flags(EntityId(2), flags));
+        assert_eq!(engine.entity_flags(floor), Some(flags));
+        assert!(engine.describe_at(Point(0.0, 1.1)).is_erasable);
+
+        assert!(engine.place_flag(Point(2.0, 2.0)));
+        assert!(engine.move_flag(Point(2.05, 2.05), Point(3.05, 2.05)));
+        assert!(!engine.remove_flag_at(Point(2.05, 2.05)));
+        assert!(engine.set_ball_start(Point(0.5, -1.0)));
+        let zone = levels::TimeZone {
+            region: vec![Point(2.0, 2.0), Point(3.0, 2.0), Point(3.0, 3.0)],
+            time_scale: 0.5,
+        };
+        assert!(engine.add_time_zone(zone.clone()));
+        assert!(!engine.add_time_zone(levels::TimeZone {
+            time_scale: f64::NAN,
+            ..zone.clone()
+        }));
+
+        let level = engine.export_level().unwrap();
+        assert_eq!(level.flags_positions, [Point(3.0, 2.0)]);
+        assert_eq!(level.initial_ball_position, Point(0.5, -1.0));
+        assert_eq!(level.time_zones, [zone]);
+        assert!(level.polygons[0].is_erasable);
+        // the ball was moved and the settings kept
+        assert_eq!(engine.body_states()[0].centroid, Point(0.5, -1.0));
+        assert_eq!(engine.gravity_mulipiler, 0.5);
+
+        assert!(engine.remove_time_zone_at(Point(2.9, 2.1)));
+        assert!(engine.remove_flag_at(Point(3.05, 2.05)));
+        let level = engine.export_level().unwrap();
+        assert!(level.time_zones.is_empty() && level.flags_positions.is_empty());
+    }
+
+    #[test]
+    fn test_edit_circle_with_terrain() {
+        let mut level = level();
+        level.terrain = vec![vec![Point(-2.0, 2.0), Point(0.0, 2.5), Point(2.0, 2.0)]];
+        level.circles.push(levels::Entity {
+            shape: geometry::Circle {
+                center: Point(3.0, 0.0),
+                radius: 0.5,
+            },
+            is_static: true,
+            is_bindable: false,
+            hit_points: None,
+            breaking_impulse: None,
+            fracture_impulse: None,
+            bump_impulse: None,
+            is_ferrous: false,
+            is_erasable: false,
+            render_layer: 0,
+        });
+        let mut engine = Engine::new(level);
+        let entity_at = |engine: &Engine, point: Point| {
+            engine
+                .mass_properties()
+                .iter()
+                .find(|properties| properties.centroid.to(point).norm() < 1e-9)
+                .map(|properties| properties.id)
+                .unwrap()
+        };
+        let circle = entity_at(&engine, Point(3.0, 0.0));
+        let flags = EntityFlags {
+            is_static: true,
+            is_bindable: true,
+            is_erasable: true,
+        };
+
+        assert_eq!(
+            engine.entity_flags(circle),
+            Some(EntityFlags {
+                is_bindable: false,
+                is_erasable: false,
+                ..flags
+            })
+        );
+        assert!(engine.set_entity_flags(circle, flags));
+        assert_eq!(entity_at(&engine, Point(3.0, 0.0)), circle);
+        assert_eq!(engine.entity_flags(circle), Some(flags));
+        assert!(engine.export_level().unwrap().circles[0].is_erasable);
+        assert!(engine.describe_at(Point(3.0, 0.0)).is_erasable);
+        // the slabs of the terrain are not entities of the level the editor changes
+        let slab = entity_at(&engine, Point(-1.0, 2.25));
+        assert_eq!(engine.entity_flags(slab), None);
+        assert!(!engine.set_entity_flags(slab, flags));
+    }
+
+    #[test]
+    fn test_edit_keeps_origin_recording_and_trace() {
+        let mut engine = Engine::new(level());
+        let start = engine.export_level().unwrap().initial_ball_position;
+        engine.shift_origin(Point(1.0, 0.0));
+        engine.start_recording();
+        engine.start_trace(vec![EntityId(0)]);
+        assert!(engine.place_flag(Point(2.0, 2.0)));
+        engine.run_n_iterations(5, 16_000.0);
+
+        assert!(engine.move_flag(Point(2.05, 2.05), Point(3.05, 2.05)));
+        assert_eq!(engine.origin(), Point(1.0, 0.0));
+        assert_eq!(
+            engine.export_level().unwrap().flags_positions,
+            [Point(4.0, 2.0)]
+        );
+        assert_eq!(engine.body_states()[0].centroid, start - Point(1.0, 0.0));
+        engine.run_n_iterations(5, 16_000.0);
+
+        let run = engine.stop_recording().unwrap();
+        assert_eq!(run.frames.len(), 2);
+        // the flag moved without there being more of them
+        assert_eq!(run.frames[0].flags, Some(vec![Point(3.0, 2.0)]));
+        assert_eq!(run.frames[1].flags, Some(vec![Point(4.0, 2.0)]));
+        let trace = engine.stop_trace().unwrap();
+        assert_eq!(trace.samples.len(), 10);
+        assert_eq!(trace.samples[5].step, 5);
+    }
+
+    #[test]
+    fn test_edit_restarts_level() {
+        let mut engine = Engine::new(level());
+        engine.add_polygon(make_shape! { (2.0, 2.0), (3.0, 2.0), (3.0, 3.0), (2.0, 3.0) });
+        engine.run_n_iterations(10, 16_000.0);
+
+        assert!(engine.place_flag(Point(4.0, 4.0)));
+        assert_eq!(engine.entities.len(), 2);
+        assert_eq!(engine.game.elapsed_seconds(), 0.0);
+        let message = engine.run_iteration(16_000.0);
+        assert_eq!(message.flags_total, 1);
+    }
+}
@@ -0,0 +1,134 @@
+//! Energy accounting
+//!
+//! Velocities are integrated in their own units, so the energies come in
+//! units of the engine as well: the kinetic energy is `m v² / 2` plus
+//! `I ω² / 2`, and the potential energy is scaled to match it, measured from
+//! the origin of the level along the current gravity. Static bodies have
+//! neither.
+//!
+//! The energy dissipated is what the contacts and bindings take away from the
+//! bodies during the steps of a frame. It counts what the pair loop of every
+//! step changes, so a negative value means the solver made energy out of
+//! nothing, or a bumper kicked something. Fans, motors, springs and explosions
+//! work on the bodies outside of that loop and do not count. Where the energy
+//! of a contact goes is shared between its two bodies, so it is only totalled.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    ids::EntityId, shape::Collidable, Engine, Entity, GRAVITY_COEFFICIENT, MOVEMENT_COEFFICIENT,
+};
+use crate::geometry::{Point, Vector};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Energy {
+    pub kinetic: f64,
+    pub potential: f64,
+    /// taken away by the contacts and bindings since the previous message
+    pub dissipated: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BodyEnergy {
+    pub id: EntityId,
+    pub kinetic: f64,
+    pub potential: f64,
+}
+
+/// kinetic and potential energy of a body, zero for static ones
+fn energy_of(shape: &mut dyn Collidable, gravity: Vector, origin: Point) -> (f64, f64) {
+    let data = shape.collision_data_mut();
+    if !data.mass.is_finite() {
+        return (0.0, 0.0);
+    }
+    let kinetic = (data.mass * data.velocity.dot(data.velocity)
+        + data.inertia * data.angular_velocity.powi(2))
+        / 2.0;
+    let height = gravity.dot(data.centroid + origin);
+    let potential = -data.mass * GRAVITY_COEFFICIENT / MOVEMENT_COEFFICIENT * height;
+    (kinetic, potential)
+}
+
+/// the kinetic plus the potential energy of all the `entities`
+pub(super) fn mechanical_energy(entities: &[Entity], gravity: Vector, origin: Point) -> f64 {
+    entities
+        .iter()
+        .map(|entity| {
+            let (kinetic, potential) = energy_of(&mut *entity.shape.borrow_mut(), gravity, origin);
+            kinetic + potential
+        })
+        .sum()
+}
+
+impl Engine {
+    /// the energy of every body, in the same order as [`Engine::body_states`]
+    pub fn body_energies(&self) -> Vec<BodyEnergy> {
+        let gravity = self.gravity.vector(self.gravity_mulipiler);
+        self.entities
+            .iter()
+            .map(|entity| {
+                let (kinetic, potential) =
+                    energy_of(&mut *entity.shape.borrow_mut(), gravity, self.origin);
+                BodyEnergy {
+                    id: entity.id,
+                    kinetic,
+                    potential,
+                }
+            })
+            .collect()
+    }
+
+    /// the total energy, taking the energy dissipated since the previous message
+    pub(super) fn take_energy(&mut self) -> Energy {
+        let gravity = self.gravity.vector(self.gravity_mulipiler);
+        let (kinetic, potential) =
+            self.entities
+                .iter()
+                .fold((0.0, 0.0), |(kinetic, potential), entity| {
+                    let body = energy_of(&mut *entity.shape.borrow_mut(), gravity, self.origin);
+                    (kinetic + body.0, potential + body.1)
+                });
+        Energy {
+            kinetic,
+            potential,
+            dissipated: std::mem::take(&mut self.dissipated_energy),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig};
+
+    #[test]
+    fn test_energy() {
+        let mut engine = Engine::sandbox(SandboxConfig {
+            world_half_extent: None,
+            ..Default::default()
+        });
+        let start = engine.run_iteration(2000.0).energy;
+
+        // falling freely turns potential energy into kinetic energy
+        let falling = engine.run_n_iterations(100, 2000.0).energy;
+        assert!(falling.kinetic > 0.0);
+        assert_eq!(falling.dissipated, 0.0);
+        let total = |energy: Energy| energy.kinetic + energy.potential;
+        assert!((total(falling) - total(start)).abs() < 0.01 * falling.kinetic);
+
+        // landing on a floor takes most of it away
+        let y = engine.body_states()[0].centroid.1 + 0.15;
+        engine.add_prefab_polygon(
+            make_shape! { (-1.0, y), (1.0, y), (1.0, y + 0.2), (-1.0, y + 0.2) },
+            true,
+        );
+        let landed = engine.run_n_iterations(100, 2000.0).energy;
+        assert!(landed.dissipated > 0.5 * falling.kinetic);
+        assert!(landed.kinetic < 0.1 * falling.kinetic);
+        assert_eq!(engine.body_energies()[1].kinetic, 0.0);
+    }
+}
@@ -0,0 +1,95 @@
+//! Fans blowing on everything in a rectangle in front of them
+//!
+//! A fan has no body of its own, so nothing collides with it. The host
+//! turns fans on and off by their [`RegionId`].
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{ids::RegionId, Entity, GRAVITY_COEFFICIENT};
+use crate::{
+    geometry::{Point, Vector},
+    levels,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Fan {
+    pub id: RegionId,
+    /// the middle of the side of the rectangle the fan blows from
+    pub position: Point,
+    /// unit vector the fan blows towards
+    pub direction: Vector,
+    pub length: f64,
+    pub width: f64,
+    /// force in multiples of the weight of a unit of mass
+    pub strength: f64,
+    pub is_enabled: bool,
+}
+
+impl Fan {
+    pub fn new(id: RegionId, fan: &levels::Fan) -> Self {
+        Self {
+            id,
+            position: fan.position,
+            direction: fan.direction.unit(),
+            length: fan.length,
+            width: fan.width,
+            strength: fan.strength,
+            is_enabled: !fan.starts_disabled,
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        let offset = self.position.to(point);
+        let along = offset.dot(self.direction);
+        let across = offset.cross(self.direction).abs();
+
+        (0.0..=self.length).contains(&along) && across <= self.width / 2.0
+    }
+
+    /// pushes every movable entity with its centroid in front of the fan,
+    /// the lighter ones more
+    pub(super) fn blow(&self, entities: &[Entity], microseconds: f64) {
+        if !self.is_enabled {
+            return;
+        }
+
+        for entity in entities.iter().filter(|entity| !entity.is_static) {
+            let mut shape = entity.shape.borrow_mut();
+            let data = shape.collision_data_mut();
+            if self.contains(data.centroid) {
+                let microseconds = microseconds * entity.time_scale;
+                data.velocity += self.direction
+                    * (self.strength * GRAVITY_COEFFICIENT * microseconds / data.mass);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_contains() {
+        let fan = Fan::new(
+            RegionId(0),
+            &levels::Fan {
+                position: Point(0.0, 0.0),
+                direction: Point(0.0, -2.0),
+                length: 1.0,
+                width: 0.5,
+                strength: 1.0,
+                starts_disabled: false,
+            },
+        );
+
+        assert!(fan.contains(Point(0.0, -0.5)));
+        assert!(fan.contains(Point(0.2, -1.0)));
+        assert!(!fan.contains(Point(0.0, 0.5)));
+        assert!(!fan.contains(Point(0.3, -0.5)));
+        assert!(!fan.contains(Point(0.0, -1.1)));
+    }
+}
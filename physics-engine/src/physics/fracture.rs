@@ -0,0 +1,197 @@
+//! Brittle polygons breaking into pieces on impact
+//!
+//! A polygon with a fracture impulse breaks once the impulse of a single
+//! contact exceeds it. Like an entity that shatters, it is removed at the end
+//! of the step, but it is replaced with the cells of a Voronoi diagram of
+//! random points inside it, see [`compute::voronoi_cells`]. The points come
+//! from a generator seeded with the hash of the polygon as it broke, so that a
+//! replay breaks it into the same pieces.
+//!
+//! The pieces have the density of every other body, so together they weigh
+//! as much as the polygon did, and each of them moves on as the part of the
+//! polygon it was cut from: with the velocity the polygon had at its centroid
+//! and the angular velocity of the polygon. They take over the color, the
+//! render layer and the flags of the polygon, but not its bindings, which break with it, nor its
+//! durability, so they do not break any further.
+
+use std::rc::Rc;
+
+use super::{
+    compute,
+    ids::EntityId,
+    shape::{Collidable, Polygon},
+    splitmix64, BodyState, Durability, Engine, EntityCfg, WithColor,
+};
+use crate::geometry::Point;
+
+/// how many pieces a polygon breaks into, some of which may be too small to keep
+pub const PIECES: usize = 6;
+/// pieces with less area than this are dropped, they would only jitter around
+const MIN_PIECE_AREA: f64 = 1e-4;
+
+/// A piece of a polygon that broke, to be added once the polygon is removed
+pub(super) struct Piece {
+    polygon: Polygon,
+    color: [f32; 3],
+    is_erasable: bool,
+    is_bindable: bool,
+    render_layer: i32,
+}
+
+/// random points inside the convex polygon, each in a random one of the
+/// triangles between its centroid and its edges
+fn seeds(vertices: &[Point], centroid: Point, mut state: u64) -> Vec<Point> {
+    let mut random = || (splitmix64(&mut state) >> 11) as f64 / (1u64 << 53) as f64;
+    (0..PIECES)
+        .map(|_| {
+            let edge = (random() * vertices.len() as f64) as usize % vertices.len();
+            let (start, end) = (vertices[edge], vertices[(edge + 1) % vertices.len()]);
+            let (mut a, mut b) = (random(), random());
+            // folds the square onto the triangle below its diagonal
+            if a + b > 1.0 {
+                (a, b) = (1.0 - a, 1.0 - b);
+            }
+            centroid + centroid.to(start) * a + centroid.to(end) * b
+        })
+        .collect()
+}
+
+impl Engine {
+    /// makes the polygon break into pieces on any contact with an impulse above
+    /// `fracture_impulse`, or never if it is `None`. Returns whether the polygon exists
+    pub fn set_fracture_impulse(&mut self, id: EntityId, fracture_impulse: Option<f64>) -> bool {
+        let Some(slot) = self.slots.get(id) else {
+            return false;
+        };
+        let entity = &self.entities[slot];
+        let is_polygon = self.polygons.iter().any(|polygon| {
+            polygon.shape.as_ptr() as *const () == Rc::as_ptr(&entity.shape) as *const ()
+        });
+        if is_polygon {
+            self.entities[slot].durability.fracture_impulse = fracture_impulse;
+        }
+        is_polygon
+    }
+
+    /// the pieces of the polygons that fractured during the step
+    pub(super) fn fracture_polygons(&self) -> Vec<Piece> {
+        let mut pieces = vec![];
+        for entity in &self.entities {
+            if !entity.durability.is_fractured || entity.durability.is_shattered {
+                continue;
+            }
+            let Some(WithColor { color, shape }) = self.polygons.iter().find(|polygon| {
+                polygon.shape.as_ptr() as *const () == Rc::as_ptr(&entity.shape) as *const ()
+            }) else {
+                continue;
+            };
+            let Some(polygon) = shape.upgrade() else {
+                continue;
+            };
+            let seed = BodyState::of(entity).hash(BodyState::OFFSET_BASIS);
+            let mut polygon = polygon.borrow_mut();
+            let data = polygon.collision_data_mut().clone();
+            let seeds = seeds(polygon.vertices(), data.centroid, seed);
+
+            for cell in compute::voronoi_cells(polygon.vertices(), &seeds) {
+                if cell.len() < 3 {
+                    continue;
+                }
+                let mut piece = Polygon::new(cell);
+                let piece_data = piece.collision_data_mut();
+                if piece_data.mass < MIN_PIECE_AREA {
+                    continue;
+                }
+                let offset = data.centroid.to(piece_data.centroid);
+                piece_data.velocity =
+                    data.velocity - (offset * data.angular_velocity).perpendicular();
+                piece_data.angular_velocity = data.angular_velocity;
+                pieces.push(Piece {
+                    polygon: piece,
+                    color: *color,
+                    is_erasable: entity.is_erasable,
+                    is_bindable: entity.is_bindable,
+                    render_layer: entity.render_layer,
+                });
+            }
+        }
+        pieces
+    }
+
+    /// adds the pieces as dynamic polygons, after the polygons they came from are removed
+    pub(super) fn add_pieces(&mut self, pieces: Vec<Piece>) {
+        for piece in pieces {
+            let (_, weak) = self.insert_entity(
+                piece.polygon,
+                EntityCfg {
+                    is_erasable: piece.is_erasable,
+                    is_bindable: piece.is_bindable,
+                    is_static: false,
+                    durability: Durability::default(),
+                    bump_impulse: None,
+                    is_ferrous: false,
+                    render_layer: piece.render_layer,
+                },
+            );
+            self.polygons.push(WithColor {
+                color: piece.color,
+                shape: weak,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{game::GameEvent, make_shape, sandbox::SandboxConfig};
+
+    fn brittle_box() -> (Engine, EntityId) {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (-3.0, 1.0), (3.0, 1.0), (3.0, 1.2), (-3.0, 1.2) },
+            true,
+        );
+        let id = engine
+            .add_polygon(make_shape! { (1.0, 0.2), (1.4, 0.2), (1.4, 0.6), (1.0, 0.6) })
+            .unwrap();
+        assert!(engine.set_fracture_impulse(id, Some(1e-3)));
+        (engine, id)
+    }
+
+    #[test]
+    fn test_fracture() {
+        let (mut engine, id) = brittle_box();
+        let mass = |engine: &Engine| {
+            let dynamic = engine.entities[1..]
+                .iter()
+                .filter(|entity| !entity.is_static);
+            dynamic
+                .map(|entity| entity.shape.borrow_mut().collision_data_mut().mass)
+                .sum::<f64>()
+        };
+        let before = mass(&engine);
+        let count = engine.entities.len();
+
+        let events: Vec<_> = (0..400)
+            .flat_map(|_| engine.run_iteration(2000.0).events)
+            .collect();
+        assert_eq!(events, vec![GameEvent::Fractured { id }]);
+        assert!(engine.slots.get(id).is_none());
+        let pieces = engine.entities.len() + 1 - count;
+        assert!(pieces > 1 && pieces <= PIECES);
+        assert!((mass(&engine) - before).abs() < 1e-9);
+        // the pieces landed on the floor
+        for state in &engine.body_states()[count - 1..] {
+            assert!(state.aabb.max.1 < 1.01, "{state:?}");
+        }
+
+        // breaking again the same way
+        let (mut replay, _) = brittle_box();
+        replay.run_n_iterations(400, 2000.0);
+        assert_eq!(replay.state_hash(), engine.state_hash());
+
+        // only polygons fracture
+        assert!(!engine.set_fracture_impulse(EntityId(0), Some(1.0)));
+    }
+}
@@ -0,0 +1,894 @@
+//! A flat, serialization free encoding of a [`DisplayMessage`]
+//!
+//! The buffers live in wasm linear memory at addresses that only change
+//! when the data no longer fits, so the renderer can keep typed-array views
+//! over them and only needs to recreate the data view when its pointer changes.
+//!
+//! Only what is drawn, which there is a lot of and which moves every frame, is
+//! laid out in the buffers. The rest of the message, like the game state and the
+//! events, is kept in [`FrameDetails`] for the host to serialize alongside the
+//! generation.
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use crate::{
+    geometry::Point,
+    levels::{self, Laser},
+};
+
+use super::{
+    debug::DebugDraw,
+    dispenser::{Dispenser, TrackedBall},
+    energy::Energy,
+    fan::Fan,
+    game::{GameEvent, GameState, Usage},
+    ids::EntityId,
+    load::BindingLoad,
+    magnet::Magnet,
+    motor::Motor,
+    particles::ParticleKind,
+    spring::TorsionSpring,
+    trail::Trail,
+    DisplayMessage,
+};
+
+/// Number of `u32` words in the frame header
+pub const HEADER_LEN: usize = 41;
+
+/// Layout of the frame header. Every count is the number of items in
+/// the corresponding section of the data buffer, except for the counts
+/// of the flags left and of all flags, which are the ones of the message.
+///
+/// The data buffer holds `f32`s, with sections laid out one after another.
+/// Entity ids and render layers are stored as the bits of their `u32` and `i32`
+/// instead, for the renderer to read through a `Uint32Array` or an `Int32Array`
+/// over the same memory:
+/// - polygons: `r, g, b, vertex_count, x0, y0, x1, y1, ...`
+/// - circles: `r, g, b, x, y, radius`
+/// - flags: `vertex_count, x0, y0, x1, y1, ...`
+/// - rigid bindings, hinges, unbound rigid bindings, unbound hinges: `x, y`
+/// - soft bodies: `r, g, b, vertex_count, x0, y0, x1, y1, ...`
+/// - particles: `x, y`
+/// - particle kinds: `0` for sand, `1` for water
+/// - cloths: `id, columns, node_count, x0, y0, x1, y1, ...`, row by row
+/// - the entities of the polygons, circles, soft bodies, rigid bindings, hinges,
+///   unbound rigid bindings and unbound hinges: `id`, in the order of the items
+/// - the render layers of the polygons and circles: `layer`, in the order of the items
+/// - the targets of the rigid bindings and hinges: `id`, in the order of the items
+/// - springs: `x0, y0, x1, y1`, from the end on the entity holding the spring
+/// - the entities and the targets of the springs: `id`, in the order of the springs
+/// - ropes: `x0, y0, x1, y1`, like the springs
+/// - the max lengths of the ropes: `length`, in the order of the ropes
+/// - the entities and the targets of the ropes: `id`, in the order of the ropes
+/// - pulleys: `x0, y0, x1, y1, x2, y2, x3, y3`, from the end on the entity holding
+///   the pulley over its two anchors to the end on its target
+/// - the entities and the targets of the pulleys: `id`, in the order of the pulleys
+/// - the corner radii of the polygons: `radius`, one for every polygon
+/// - the curves along the ropes and the chains: `point_count, x0, y0, x1, y1, ...`
+/// - the links of the chains: `id`
+/// - whether the rigid bindings and hinges are stressed: `1` or `0`, in the order of the items
+pub mod header {
+    pub const GENERATION: usize = 0;
+    pub const DATA_LEN: usize = 1;
+    pub const POLYGONS: usize = 2;
+    pub const CIRCLES: usize = 3;
+    pub const FLAGS: usize = 4;
+    pub const RIGID_BINDINGS: usize = 5;
+    pub const HINGES: usize = 6;
+    pub const UNBOUND_RIGID_BINDINGS: usize = 7;
+    pub const UNBOUND_HINGES: usize = 8;
+    pub const FLAGS_REMAINING: usize = 9;
+    pub const FLAGS_TOTAL: usize = 10;
+    pub const SOFT_BODIES: usize = 11;
+    pub const PARTICLES: usize = 12;
+    pub const PARTICLE_KINDS: usize = 13;
+    pub const CLOTHS: usize = 14;
+    pub const POLYGON_ENTITIES: usize = 15;
+    pub const CIRCLE_ENTITIES: usize = 16;
+    pub const SOFT_BODY_ENTITIES: usize = 17;
+    pub const RIGID_BINDING_ENTITIES: usize = 18;
+    pub const HINGE_ENTITIES: usize = 19;
+    pub const UNBOUND_RIGID_BINDING_ENTITIES: usize = 20;
+    pub const UNBOUND_HINGE_ENTITIES: usize = 21;
+    pub const POLYGON_LAYERS: usize = 22;
+    pub const CIRCLE_LAYERS: usize = 23;
+    pub const RIGID_BINDING_TARGETS: usize = 24;
+    pub const HINGE_TARGETS: usize = 25;
+    pub const SPRINGS: usize = 26;
+    pub const SPRING_ENTITIES: usize = 27;
+    pub const SPRING_TARGETS: usize = 28;
+    pub const ROPES: usize = 29;
+    pub const ROPE_LENGTHS: usize = 30;
+    pub const ROPE_ENTITIES: usize = 31;
+    pub const ROPE_TARGETS: usize = 32;
+    pub const PULLEYS: usize = 33;
+    pub const PULLEY_ENTITIES: usize = 34;
+    pub const PULLEY_TARGETS: usize = 35;
+    pub const ROPE_CURVES: usize = 36;
+    pub const CHAIN_CURVES: usize = 37;
+    pub const CHAIN_LINKS: usize = 38;
+    pub const RIGID_BINDINGS_STRESSED: usize = 39;
+    pub const HINGES_STRESSED: usize = 40;
+}
+
+/// The fields of a [`DisplayMessage`] that are not laid out in the buffers,
+/// as of the last frame written
+#[derive(Clone, Debug, Default, Serialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi))]
+pub struct FrameDetails {
+    pub generation: u32,
+    pub state: GameState,
+    pub elapsed_seconds: f64,
+    pub attempt_seconds: f64,
+    pub usage: Usage,
+    pub trails: Vec<Trail>,
+    pub ghost: Vec<Point>,
+    pub fans: Vec<Fan>,
+    pub lasers: Vec<Laser>,
+    pub time_zones: Vec<levels::TimeZone>,
+    pub gravity_pickups: Vec<levels::GravityPickup>,
+    pub gravity_seconds_remaining: Option<f64>,
+    pub size_pickups: Vec<levels::SizePickup>,
+    pub ball_size_seconds_remaining: Option<f64>,
+    pub torsion_springs: Vec<TorsionSpring>,
+    pub motors: Vec<Motor>,
+    pub magnets: Vec<Magnet>,
+    pub dispensers: Vec<Dispenser>,
+    pub balls: Vec<TrackedBall>,
+    /// everything that happened since the previous frame, in order
+    pub events: Vec<GameEvent>,
+    pub origin: Point,
+    pub dropped_microseconds: f64,
+    pub debug: Option<DebugDraw>,
+    pub energy: Energy,
+    pub binding_loads: Vec<BindingLoad>,
+}
+
+impl FrameDetails {
+    fn write(&mut self, message: &DisplayMessage) {
+        self.state = message.state;
+        self.elapsed_seconds = message.elapsed_seconds;
+        self.attempt_seconds = message.attempt_seconds;
+        self.usage = message.usage;
+        self.trails.clone_from(&message.trails);
+        self.ghost.clone_from(&message.ghost);
+        self.fans.clone_from(&message.fans);
+        self.lasers.clone_from(&message.lasers);
+        self.time_zones.clone_from(&message.time_zones);
+        self.gravity_pickups.clone_from(&message.gravity_pickups);
+        self.gravity_seconds_remaining = message.gravity_seconds_remaining;
+        self.size_pickups.clone_from(&message.size_pickups);
+        self.ball_size_seconds_remaining = message.ball_size_seconds_remaining;
+        self.torsion_springs.clone_from(&message.torsion_springs);
+        self.motors.clone_from(&message.motors);
+        self.magnets.clone_from(&message.magnets);
+        self.dispensers.clone_from(&message.dispensers);
+        self.balls.clone_from(&message.balls);
+        self.events.clone_from(&message.events);
+        self.origin = message.origin;
+        self.dropped_microseconds = message.dropped_microseconds;
+        self.debug.clone_from(&message.debug);
+        self.energy = message.energy;
+        self.binding_loads.clone_from(&message.binding_loads);
+    }
+}
+
+pub struct SharedFrame {
+    header: [u32; HEADER_LEN],
+    data: Vec<f32>,
+    details: FrameDetails,
+}
+
+// arrays only derive `Default` up to 32 items, fewer than there are words in the header
+impl Default for SharedFrame {
+    fn default() -> Self {
+        Self {
+            header: [0; HEADER_LEN],
+            data: vec![],
+            details: FrameDetails::default(),
+        }
+    }
+}
+
+impl SharedFrame {
+    pub fn header_ptr(&self) -> *const u32 {
+        self.header.as_ptr()
+    }
+
+    pub fn data_ptr(&self) -> *const f32 {
+        self.data.as_ptr()
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.header[header::GENERATION]
+    }
+
+    /// the rest of the last frame written, see [`FrameDetails`]
+    pub fn details(&self) -> &FrameDetails {
+        &self.details
+    }
+
+    /// overwrites the frame with the contents of `message`
+    /// and bumps the generation counter
+    pub fn write(&mut self, message: &DisplayMessage) {
+        self.data.clear();
+
+        for polygon in &message.polygons {
+            self.data.extend_from_slice(&polygon.color);
+            self.push_path(&polygon.shape.vertices);
+        }
+
+        for circle in &message.circles {
+            self.data.extend_from_slice(&circle.color);
+            self.push_point(circle.shape.center);
+            self.data.push(circle.shape.radius as f32);
+        }
+
+        for flag in &message.flags {
+            self.push_path(&flag.vertices);
+        }
+
+        for points in [
+            &message.rigid_bindings,
+            &message.hinges,
+            &message.unbound_rigid_bindings,
+            &message.unbound_hinges,
+        ] {
+            points.iter().for_each(|&point| self.push_point(point));
+        }
+
+        for soft_body in &message.soft_bodies {
+            self.data.extend_from_slice(&soft_body.color);
+            self.push_path(&soft_body.shape.vertices);
+        }
+
+        self.data.extend(
+            message
+                .particles
+                .iter()
+                .map(|&coordinate| coordinate as f32),
+        );
+        self.data
+            .extend(message.particle_kinds.iter().map(|kind| match kind {
+                ParticleKind::Sand => 0.0,
+                ParticleKind::Water => 1.0,
+            }));
+
+        for cloth in &message.cloths {
+            self.push_id(cloth.id);
+            self.data.push(cloth.columns as f32);
+            self.data.push(cloth.nodes.len() as f32);
+            cloth.nodes.iter().for_each(|&node| self.push_point(node));
+        }
+
+        for ids in [
+            &message.polygon_entities,
+            &message.circle_entities,
+            &message.soft_body_entities,
+            &message.rigid_binding_entities,
+            &message.hinge_entities,
+            &message.unbound_rigid_binding_entities,
+            &message.unbound_hinge_entities,
+        ] {
+            ids.iter().for_each(|&id| self.push_id(id));
+        }
+
+        for layers in [&message.polygon_layers, &message.circle_layers] {
+            self.data
+                .extend(layers.iter().map(|&layer| f32::from_bits(layer as u32)));
+        }
+
+        for ids in [&message.rigid_binding_targets, &message.hinge_targets] {
+            ids.iter().for_each(|&id| self.push_id(id));
+        }
+
+        for ends in &message.springs {
+            ends.iter().for_each(|&end| self.push_point(end));
+        }
+        for ids in [&message.spring_entities, &message.spring_targets] {
+            ids.iter().for_each(|&id| self.push_id(id));
+        }
+
+        for ends in &message.ropes {
+            ends.iter().for_each(|&end| self.push_point(end));
+        }
+        self.data
+            .extend(message.rope_lengths.iter().map(|&length| length as f32));
+        for ids in [&message.rope_entities, &message.rope_targets] {
+            ids.iter().for_each(|&id| self.push_id(id));
+        }
+
+        for path in &message.pulleys {
+            path.iter().for_each(|&point| self.push_point(point));
+        }
+        for ids in [&message.pulley_entities, &message.pulley_targets] {
+            ids.iter().for_each(|&id| self.push_id(id));
+        }
+
+        for polygon in &message.polygons {
+            self.data.push(polygon.shape.corner_radius as f32);
+        }
+
+        for curve in message.rope_curves.iter().chain(&message.chain_curves) {
+            self.push_path(curve);
+        }
+        for &link in &message.chain_links {
+            self.push_id(link);
+        }
+
+        for stressed in [&message.rigid_binding_stressed, &message.hinge_stressed] {
+            self.data.extend(
+                stressed
+                    .iter()
+                    .map(|&stressed| f32::from(u8::from(stressed))),
+            );
+        }
+
+        let counts = [
+            (header::POLYGONS, message.polygons.len()),
+            (header::CIRCLES, message.circles.len()),
+            (header::FLAGS, message.flags.len()),
+            (header::RIGID_BINDINGS, message.rigid_bindings.len()),
+            (header::HINGES, message.hinges.len()),
+            (
+                header::UNBOUND_RIGID_BINDINGS,
+                message.unbound_rigid_bindings.len(),
+            ),
+            (header::UNBOUND_HINGES, message.unbound_hinges.len()),
+            (header::FLAGS_REMAINING, message.flags_remaining),
+            (header::FLAGS_TOTAL, message.flags_total),
+            (header::SOFT_BODIES, message.soft_bodies.len()),
+            (header::PARTICLES, message.particles.len() / 2),
+            (header::PARTICLE_KINDS, message.particle_kinds.len()),
+            (header::CLOTHS, message.cloths.len()),
+            (header::POLYGON_ENTITIES, message.polygon_entities.len()),
+            (header::CIRCLE_ENTITIES, message.circle_entities.len()),
+            (header::SOFT_BODY_ENTITIES, message.soft_body_entities.len()),
+            (
+                header::RIGID_BINDING_ENTITIES,
+                message.rigid_binding_entities.len(),
+            ),
+            (header::HINGE_ENTITIES, message.hinge_entities.len()),
+            (
+                header::UNBOUND_RIGID_BINDING_ENTITIES,
+                message.unbound_rigid_binding_entities.len(),
+            ),
+            (
+                header::UNBOUND_HINGE_ENTITIES,
+                message.unbound_hinge_entities.len(),
+            ),
+            (header::POLYGON_LAYERS, message.polygon_layers.len()),
+            (header::CIRCLE_LAYERS, message.circle_layers.len()),
+            (
+                header::RIGID_BINDING_TARGETS,
+                message.rigid_binding_targets.len(),
+            ),
+            (header::HINGE_TARGETS, message.hinge_targets.len()),
+            (header::SPRINGS, message.springs.len()),
+            (header::SPRING_ENTITIES, message.spring_entities.len()),
+            (header::SPRING_TARGETS, message.spring_targets.len()),
+            (header::ROPES, message.ropes.len()),
+            (header::ROPE_LENGTHS, message.rope_lengths.len()),
+            (header::ROPE_ENTITIES, message.rope_entities.len()),
+            (header::ROPE_TARGETS, message.rope_targets.len()),
+            (header::PULLEYS, message.pulleys.len()),
+            (header::PULLEY_ENTITIES, message.pulley_entities.len()),
+            (header::PULLEY_TARGETS, message.pulley_targets.len()),
+            (header::ROPE_CURVES, message.rope_curves.len()),
+            (header::CHAIN_CURVES, message.chain_curves.len()),
+            (header::CHAIN_LINKS, message.chain_links.len()),
+            (
+                header::RIGID_BINDINGS_STRESSED,
+                message.rigid_binding_stressed.len(),
+            ),
+            (header::HINGES_STRESSED, message.hinge_stressed.len()),
+            (header::DATA_LEN, self.data.len()),
+        ];
+
+        for (index, count) in counts {
+            self.header[index] = count as u32;
+        }
+        self.header[header::GENERATION] = self.generation().wrapping_add(1);
+        self.details.generation = self.generation();
+        self.details.write(message);
+    }
+
+    fn push_path(&mut self, vertices: &[Point]) {
+        self.data.push(vertices.len() as f32);
+        vertices.iter().for_each(|&vertex| self.push_point(vertex));
+    }
+
+    fn push_point(&mut self, Point(x, y): Point) {
+        self.data.push(x as f32);
+        self.data.push(y as f32);
+    }
+
+    fn push_id(&mut self, EntityId(id): EntityId) {
+        self.data.push(f32::from_bits(id));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry,
+        physics::{cloth::ClothFrame, game::LossReason, WithColor},
+    };
+
+    /// the data of a frame, read the way the renderer reads it
+    struct Reader<'a>(&'a [f32]);
+
+    impl Reader<'_> {
+        fn take(&mut self) -> f32 {
+            let (&first, rest) = self.0.split_first().expect("the frame ended early");
+            self.0 = rest;
+            first
+        }
+
+        fn color(&mut self) -> [f32; 3] {
+            [self.take(), self.take(), self.take()]
+        }
+
+        fn point(&mut self) -> Point {
+            Point(self.take().into(), self.take().into())
+        }
+
+        fn points(&mut self, count: usize) -> Vec<Point> {
+            (0..count).map(|_| self.point()).collect()
+        }
+
+        fn id(&mut self) -> EntityId {
+            EntityId(self.take().to_bits())
+        }
+
+        fn ids(&mut self, count: usize) -> Vec<EntityId> {
+            (0..count).map(|_| self.id()).collect()
+        }
+
+        fn flags(&mut self, count: usize) -> Vec<bool> {
+            (0..count).map(|_| self.take() == 1.0).collect()
+        }
+
+        fn layers(&mut self, count: usize) -> Vec<i32> {
+            (0..count).map(|_| self.take().to_bits() as i32).collect()
+        }
+
+        fn curve(&mut self) -> Vec<Point> {
+            let count = self.take() as usize;
+            self.points(count)
+        }
+
+        fn path(&mut self) -> geometry::Polygon {
+            geometry::Polygon {
+                vertices: self.curve(),
+                corner_radius: 0.0,
+                centroid: Point::ZERO,
+            }
+        }
+    }
+
+    /// decodes the frame back into a message, naming every field of it so that
+    /// a field added to the message without a place in the frame does not compile
+    fn read(frame: &SharedFrame) -> DisplayMessage {
+        let count = |index| frame.header[index] as usize;
+        let mut data = Reader(&frame.data[..count(header::DATA_LEN)]);
+        let mut polygons: Vec<WithColor<geometry::Polygon>> = (0..count(header::POLYGONS))
+            .map(|_| WithColor {
+                color: data.color(),
+                shape: data.path(),
+            })
+            .collect();
+        let circles = (0..count(header::CIRCLES))
+            .map(|_| WithColor {
+                color: data.color(),
+                shape: geometry::Circle {
+                    center: data.point(),
+                    radius: data.take().into(),
+                },
+            })
+            .collect();
+        let flags = (0..count(header::FLAGS)).map(|_| data.path()).collect();
+        let rigid_bindings = data.points(count(header::RIGID_BINDINGS));
+        let hinges = data.points(count(header::HINGES));
+        let unbound_rigid_bindings = data.points(count(header::UNBOUND_RIGID_BINDINGS));
+        let unbound_hinges = data.points(count(header::UNBOUND_HINGES));
+        let soft_bodies = (0..count(header::SOFT_BODIES))
+            .map(|_| WithColor {
+                color: data.color(),
+                shape: data.path(),
+            })
+            .collect();
+        let particles = (0..count(header::PARTICLES) * 2)
+            .map(|_| data.take().into())
+            .collect();
+        let particle_kinds = (0..count(header::PARTICLE_KINDS))
+            .map(|_| match data.take() {
+                0.0 => ParticleKind::Sand,
+                1.0 => ParticleKind::Water,
+                kind => panic!("{kind} is not a particle kind"),
+            })
+            .collect();
+        let cloths = (0..count(header::CLOTHS))
+            .map(|_| {
+                let id = data.id();
+                let columns = data.take() as usize;
+                let count = data.take() as usize;
+                ClothFrame {
+                    id,
+                    columns,
+                    nodes: data.points(count),
+                }
+            })
+            .collect();
+        let polygon_entities = data.ids(count(header::POLYGON_ENTITIES));
+        let circle_entities = data.ids(count(header::CIRCLE_ENTITIES));
+        let soft_body_entities = data.ids(count(header::SOFT_BODY_ENTITIES));
+        let rigid_binding_entities = data.ids(count(header::RIGID_BINDING_ENTITIES));
+        let hinge_entities = data.ids(count(header::HINGE_ENTITIES));
+        let unbound_rigid_binding_entities =
+            data.ids(count(header::UNBOUND_RIGID_BINDING_ENTITIES));
+        let unbound_hinge_entities = data.ids(count(header::UNBOUND_HINGE_ENTITIES));
+        let polygon_layers = data.layers(count(header::POLYGON_LAYERS));
+        let circle_layers = data.layers(count(header::CIRCLE_LAYERS));
+        let rigid_binding_targets = data.ids(count(header::RIGID_BINDING_TARGETS));
+        let hinge_targets = data.ids(count(header::HINGE_TARGETS));
+        let springs = (0..count(header::SPRINGS))
+            .map(|_| [data.point(), data.point()])
+            .collect();
+        let spring_entities = data.ids(count(header::SPRING_ENTITIES));
+        let spring_targets = data.ids(count(header::SPRING_TARGETS));
+        let ropes = (0..count(header::ROPES))
+            .map(|_| [data.point(), data.point()])
+            .collect();
+        let rope_lengths = (0..count(header::ROPE_LENGTHS))
+            .map(|_| data.take().into())
+            .collect();
+        let rope_entities = data.ids(count(header::ROPE_ENTITIES));
+        let rope_targets = data.ids(count(header::ROPE_TARGETS));
+        let pulleys = (0..count(header::PULLEYS))
+            .map(|_| [data.point(), data.point(), data.point(), data.point()])
+            .collect();
+        let pulley_entities = data.ids(count(header::PULLEY_ENTITIES));
+        let pulley_targets = data.ids(count(header::PULLEY_TARGETS));
+        for polygon in &mut polygons {
+            polygon.shape.corner_radius = data.take().into();
+        }
+        let rope_curves = (0..count(header::ROPE_CURVES))
+            .map(|_| data.curve())
+            .collect();
+        let chain_curves = (0..count(header::CHAIN_CURVES))
+            .map(|_| data.curve())
+            .collect();
+        let chain_links = data.ids(count(header::CHAIN_LINKS));
+        let rigid_binding_stressed = data.flags(count(header::RIGID_BINDINGS_STRESSED));
+        let hinge_stressed = data.flags(count(header::HINGES_STRESSED));
+        assert!(data.0.is_empty(), "{} values left over", data.0.len());
+
+        let details = frame.details().clone();
+        assert_eq!(details.generation, frame.generation());
+        DisplayMessage {
+            polygons,
+            polygon_entities,
+            polygon_layers,
+            circles,
+            circle_entities,
+            circle_layers,
+            soft_bodies,
+            soft_body_entities,
+            cloths,
+            particles,
+            particle_kinds,
+            flags,
+            rigid_bindings,
+            rigid_binding_entities,
+            rigid_binding_targets,
+            rigid_binding_stressed,
+            hinges,
+            hinge_entities,
+            hinge_targets,
+            hinge_stressed,
+            springs,
+            spring_entities,
+            spring_targets,
+            ropes,
+            rope_lengths,
+            rope_entities,
+            rope_targets,
+            rope_curves,
+            pulleys,
+            pulley_entities,
+            pulley_targets,
+            chain_curves,
+            chain_links,
+            unbound_rigid_bindings,
+            unbound_rigid_binding_entities,
+            unbound_hinges,
+            unbound_hinge_entities,
+            flags_remaining: count(header::FLAGS_REMAINING),
+            flags_total: count(header::FLAGS_TOTAL),
+            state: details.state,
+            elapsed_seconds: details.elapsed_seconds,
+            attempt_seconds: details.attempt_seconds,
+            usage: details.usage,
+            trails: details.trails,
+            ghost: details.ghost,
+            fans: details.fans,
+            lasers: details.lasers,
+            time_zones: details.time_zones,
+            gravity_pickups: details.gravity_pickups,
+            gravity_seconds_remaining: details.gravity_seconds_remaining,
+            size_pickups: details.size_pickups,
+            ball_size_seconds_remaining: details.ball_size_seconds_remaining,
+            torsion_springs: details.torsion_springs,
+            motors: details.motors,
+            magnets: details.magnets,
+            dispensers: details.dispensers,
+            balls: details.balls,
+            events: details.events,
+            origin: details.origin,
+            dropped_microseconds: details.dropped_microseconds,
+            debug: details.debug,
+            energy: details.energy,
+            binding_loads: details.binding_loads,
+        }
+    }
+
+    /// asserts that the message comes out of the frame as it went in, which
+    /// takes numbers the `f32`s of the frame hold exactly
+    fn assert_round_trip(message: DisplayMessage) {
+        let mut frame = SharedFrame::default();
+        frame.write(&DisplayMessage::default());
+        frame.write(&message);
+        assert_eq!(
+            serde_json::to_value(read(&frame)).unwrap(),
+            serde_json::to_value(message).unwrap()
+        );
+    }
+
+    fn square(x: f64) -> geometry::Polygon {
+        geometry::Polygon {
+            vertices: vec![
+                Point(x, 0.0),
+                Point(x + 1.0, 0.0),
+                Point(x + 1.0, 1.0),
+                Point(x, 1.0),
+            ],
+            corner_radius: 0.0,
+            centroid: Point(x + 0.5, 0.5),
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let message = DisplayMessage {
+            circles: vec![WithColor {
+                color: [0.5, 0.5, 0.5],
+                shape: geometry::Circle {
+                    center: Point(1.0, 2.0),
+                    radius: 0.1,
+                },
+            }],
+            hinges: vec![Point(3.0, 4.0)],
+            ..Default::default()
+        };
+
+        let mut frame = SharedFrame::default();
+        frame.write(&message);
+        frame.write(&message);
+
+        assert_eq!(frame.generation(), 2);
+        assert_eq!(frame.header[header::DATA_LEN], 8);
+        assert_eq!(frame.header[header::CIRCLES], 1);
+        assert_eq!(frame.header[header::HINGES], 1);
+        assert_eq!(&frame.data[3..], &[1.0, 2.0, 0.1, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_round_trip(DisplayMessage {
+            polygons: vec![WithColor {
+                color: [0.25, 0.5, 1.0],
+                shape: square(0.0),
+            }],
+            circles: vec![WithColor {
+                color: [1.0, 0.5, 0.25],
+                shape: geometry::Circle {
+                    center: Point(1.5, -2.0),
+                    radius: 0.125,
+                },
+            }],
+            flags: vec![square(2.0)],
+            rigid_bindings: vec![Point(0.5, 0.5)],
+            hinges: vec![Point(0.25, 0.75), Point(1.5, -2.0)],
+            unbound_rigid_bindings: vec![Point(-1.0, 3.0)],
+            unbound_hinges: vec![Point(4.0, 4.5)],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_flag_counts() {
+        assert_round_trip(DisplayMessage {
+            flags: vec![square(0.0)],
+            flags_remaining: 1,
+            flags_total: 3,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_soft_bodies() {
+        assert_round_trip(DisplayMessage {
+            soft_bodies: vec![WithColor {
+                color: [0.5, 0.75, 1.0],
+                shape: square(-1.5),
+            }],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_particles() {
+        assert_round_trip(DisplayMessage {
+            particles: vec![0.5, 1.0, -2.25, 3.0],
+            particle_kinds: vec![ParticleKind::Water, ParticleKind::Sand],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_cloths() {
+        assert_round_trip(DisplayMessage {
+            cloths: vec![ClothFrame {
+                id: EntityId(7),
+                columns: 2,
+                nodes: vec![
+                    Point(0.0, 0.0),
+                    Point(0.5, 0.0),
+                    Point(0.0, 0.5),
+                    Point(0.5, 0.5),
+                ],
+            }],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_entities() {
+        assert_round_trip(DisplayMessage {
+            polygons: vec![WithColor {
+                color: [0.25, 0.5, 1.0],
+                shape: square(0.0),
+            }],
+            polygon_entities: vec![EntityId(1)],
+            circle_entities: vec![EntityId(2), EntityId(3)],
+            soft_body_entities: vec![EntityId(4)],
+            rigid_binding_entities: vec![EntityId(5)],
+            hinge_entities: vec![EntityId(6)],
+            unbound_rigid_binding_entities: vec![EntityId(7)],
+            unbound_hinge_entities: vec![EntityId(u32::MAX)],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_layers() {
+        assert_round_trip(DisplayMessage {
+            polygon_layers: vec![0, -1],
+            circle_layers: vec![20],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_targets() {
+        assert_round_trip(DisplayMessage {
+            rigid_bindings: vec![Point(0.5, 0.5)],
+            rigid_binding_entities: vec![EntityId(1)],
+            rigid_binding_targets: vec![EntityId(2)],
+            hinge_targets: vec![EntityId(3), EntityId(1)],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_stressed() {
+        assert_round_trip(DisplayMessage {
+            rigid_bindings: vec![Point(0.5, 0.5)],
+            rigid_binding_stressed: vec![true],
+            hinges: vec![Point(1.0, 1.0), Point(2.0, 2.0)],
+            hinge_stressed: vec![false, true],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_springs() {
+        assert_round_trip(DisplayMessage {
+            springs: vec![[Point(0.0, 0.5), Point(1.0, -0.5)]],
+            spring_entities: vec![EntityId(1)],
+            spring_targets: vec![EntityId(2)],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_ropes() {
+        assert_round_trip(DisplayMessage {
+            ropes: vec![[Point(0.0, 0.5), Point(1.0, -0.5)]],
+            rope_lengths: vec![1.5],
+            rope_entities: vec![EntityId(1)],
+            rope_targets: vec![EntityId(2)],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_pulleys() {
+        assert_round_trip(DisplayMessage {
+            pulleys: vec![[
+                Point(0.0, 1.0),
+                Point(0.0, 0.0),
+                Point(1.0, 0.0),
+                Point(1.0, 1.5),
+            ]],
+            pulley_entities: vec![EntityId(1)],
+            pulley_targets: vec![EntityId(2)],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_corner_radii() {
+        assert_round_trip(DisplayMessage {
+            polygons: vec![
+                WithColor {
+                    color: [0.25, 0.5, 1.0],
+                    shape: geometry::Polygon {
+                        corner_radius: 0.125,
+                        ..square(0.0)
+                    },
+                },
+                WithColor {
+                    color: [1.0, 0.5, 0.25],
+                    shape: square(2.0),
+                },
+            ],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_curves() {
+        assert_round_trip(DisplayMessage {
+            rope_curves: vec![vec![Point(0.0, 0.0), Point(0.5, 0.25), Point(1.0, 0.0)]],
+            chain_curves: vec![
+                vec![Point(2.0, 0.0), Point(2.5, 0.0)],
+                vec![Point(3.0, 0.0), Point(3.5, 0.5), Point(4.0, 1.0)],
+            ],
+            chain_links: vec![EntityId(3), EntityId(5)],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_details() {
+        assert_round_trip(DisplayMessage {
+            state: GameState::Lost {
+                reason: LossReason::OutOfTime,
+            },
+            elapsed_seconds: 12.5,
+            attempt_seconds: 2.5,
+            ghost: vec![Point(1.0, 2.0), Point(1.5, 2.5)],
+            gravity_seconds_remaining: Some(1.5),
+            ball_size_seconds_remaining: Some(0.5),
+            events: vec![GameEvent::FlagCollected { remaining: 2 }],
+            origin: Point(100.0, 0.0),
+            dropped_microseconds: 250.0,
+            ..Default::default()
+        });
+    }
+}
@@ -0,0 +1,469 @@
+//! The outcome of a level
+//!
+//! The win and lose conditions are checked once per step. Every change of the
+//! state is reported exactly once as a [`GameEvent`], so the frontend can
+//! react to transitions without comparing consecutive frames.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    achievements::Achievement,
+    ids::{BindingId, EntityId, RegionId},
+};
+use crate::{
+    geometry::Point,
+    levels::{StarThreshold, ToolLimits},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum LossReason {
+    /// the time limit of the level passed before all flags were collected
+    OutOfTime,
+    /// a dispensed ball whose dispenser loses the level left the world
+    BallLost { id: EntityId },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum GameState {
+    #[default]
+    Playing,
+    /// all flags of the level have been collected
+    Won {
+        score: Score,
+    },
+    Lost {
+        reason: LossReason,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum GameEvent {
+    FlagCollected {
+        remaining: usize,
+    },
+    /// the trigger of the hint at this index of the level fired
+    Hint {
+        index: usize,
+    },
+    Won {
+        score: Score,
+    },
+    Lost {
+        reason: LossReason,
+    },
+    Achievement {
+        achievement: Achievement,
+    },
+    /// the entity ran out of hit points and was removed
+    Destroyed {
+        id: EntityId,
+    },
+    /// a contact with the fragile entity was too strong and it was removed
+    Shattered {
+        id: EntityId,
+    },
+    /// a contact with the brittle polygon was too strong and it broke into pieces
+    Fractured {
+        id: EntityId,
+    },
+    /// the player-drawn entity crossed a laser and was removed
+    Vaporized {
+        id: EntityId,
+    },
+    /// the binding was severed by a swipe of `cut_along`
+    Cut {
+        id: BindingId,
+    },
+    /// holding the entities of the binding together took more than its break impulse
+    BindingBroken {
+        id: BindingId,
+    },
+    /// the ball touched a pickup, which turned gravity for a number of seconds
+    GravityTurned {
+        rotation: f64,
+        seconds: f64,
+    },
+    /// the last gravity pickup wore off
+    GravityRestored,
+    /// something touched the bumper and was kicked away from it
+    Bumped {
+        id: EntityId,
+    },
+    /// the ball touched a pickup, which scaled it for a number of seconds
+    BallResized {
+        scale: f64,
+        seconds: f64,
+    },
+    /// the last size pickup wore off
+    BallSizeRestored,
+    /// the powered magnet touched the ferrous entity and picked it up
+    MagnetAttached {
+        id: EntityId,
+        target: EntityId,
+    },
+    /// the magnet was switched off and dropped what it held
+    MagnetReleased {
+        id: EntityId,
+    },
+    /// the bomb went off and was removed
+    Exploded {
+        id: EntityId,
+        center: Point,
+        radius: f64,
+    },
+    /// the dispenser emitted a ball
+    Dispensed {
+        id: RegionId,
+        ball: EntityId,
+    },
+    /// the dispensed ball left the world and started over at its dispenser
+    BallRespawned {
+        id: EntityId,
+    },
+    /// the dispensed ball left the world and was removed
+    BallDespawned {
+        id: EntityId,
+    },
+    /// the drawing tool was refused, as the engine holds as many entities
+    /// or bindings as its config allows
+    CapReached {
+        tool: Tool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum Tool {
+    Polygon,
+    Circle,
+    Hinge,
+    Rigid,
+    Spring,
+    Rope,
+    Pulley,
+    Erase,
+}
+
+/// How many times the player has used each tool since the level was loaded.
+/// Respawning the ball does not reset it, so it cannot be used to get around the limits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Usage {
+    pub polygons: u32,
+    pub circles: u32,
+    pub hinges: u32,
+    pub rigids: u32,
+    pub springs: u32,
+    pub ropes: u32,
+    pub pulleys: u32,
+    pub erases: u32,
+}
+
+impl Usage {
+    pub fn of(&self, tool: Tool) -> u32 {
+        match tool {
+            Tool::Polygon => self.polygons,
+            Tool::Circle => self.circles,
+            Tool::Hinge => self.hinges,
+            Tool::Rigid => self.rigids,
+            Tool::Spring => self.springs,
+            Tool::Rope => self.ropes,
+            Tool::Pulley => self.pulleys,
+            Tool::Erase => self.erases,
+        }
+    }
+
+    fn of_mut(&mut self, tool: Tool) -> &mut u32 {
+        match tool {
+            Tool::Polygon => &mut self.polygons,
+            Tool::Circle => &mut self.circles,
+            Tool::Hinge => &mut self.hinges,
+            Tool::Rigid => &mut self.rigids,
+            Tool::Spring => &mut self.springs,
+            Tool::Rope => &mut self.ropes,
+            Tool::Pulley => &mut self.pulleys,
+            Tool::Erase => &mut self.erases,
+        }
+    }
+
+    pub fn shapes_drawn(&self) -> u32 {
+        self.polygons + self.circles
+    }
+
+    pub fn bindings_placed(&self) -> u32 {
+        self.hinges + self.rigids + self.springs + self.ropes + self.pulleys
+    }
+}
+
+impl ToolLimits {
+    pub fn of(&self, tool: Tool) -> Option<u32> {
+        match tool {
+            Tool::Polygon => self.polygons,
+            Tool::Circle => self.circles,
+            Tool::Hinge => self.hinges,
+            Tool::Rigid => self.rigids,
+            Tool::Spring => self.springs,
+            Tool::Rope => self.ropes,
+            Tool::Pulley => self.pulleys,
+            Tool::Erase => self.erases,
+        }
+    }
+}
+
+/// The result of a won level
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Score {
+    /// simulated time from the start of the level to the win
+    pub seconds: f64,
+    pub usage: Usage,
+    /// between 0 and 3, the number of consecutive thresholds of the level met
+    pub stars: u8,
+}
+
+impl StarThreshold {
+    fn is_met_by(&self, seconds: f64, usage: Usage) -> bool {
+        fn within<T: PartialOrd>(value: T, bound: Option<T>) -> bool {
+            bound.is_none_or(|bound| value <= bound)
+        }
+
+        within(seconds, self.seconds)
+            && within(usage.shapes_drawn(), self.shapes_drawn)
+            && within(usage.erases, self.erases)
+            && within(usage.bindings_placed(), self.bindings_placed)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Game {
+    state: GameState,
+    /// simulated time since the start of the level
+    elapsed_microseconds: f64,
+    /// simulated time since the main ball was last (re)spawned
+    attempt_microseconds: f64,
+    /// number of times the main ball has respawned
+    failed_attempts: u32,
+    time_limit_microseconds: Option<f64>,
+    stars: [StarThreshold; 3],
+    tool_limits: ToolLimits,
+    usage: Usage,
+}
+
+impl Game {
+    pub fn new(
+        time_limit_seconds: Option<f64>,
+        stars: [StarThreshold; 3],
+        tool_limits: ToolLimits,
+    ) -> Self {
+        Self {
+            time_limit_microseconds: time_limit_seconds.map(|seconds| seconds * 1_000_000.0),
+            stars,
+            tool_limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.elapsed_microseconds / 1_000_000.0
+    }
+
+    pub fn attempt_seconds(&self) -> f64 {
+        self.attempt_microseconds / 1_000_000.0
+    }
+
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    /// counts a failed attempt and restarts the attempt timer,
+    /// the total time keeps running
+    pub fn restart_attempt(&mut self) {
+        self.failed_attempts += 1;
+        self.attempt_microseconds = 0.0;
+    }
+
+    /// loses the level for `reason`, returning the event of the transition
+    /// unless the game was already won or lost
+    pub fn lose(&mut self, reason: LossReason) -> Option<GameEvent> {
+        if self.state != GameState::Playing {
+            return None;
+        }
+        self.state = GameState::Lost { reason };
+        Some(GameEvent::Lost { reason })
+    }
+
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    pub fn tool_limits(&self) -> ToolLimits {
+        self.tool_limits
+    }
+
+    /// counts a use of `tool`, unless its limit has already been reached
+    pub fn try_use(&mut self, tool: Tool) -> bool {
+        let used = self.usage.of_mut(tool);
+        if self
+            .tool_limits
+            .of(tool)
+            .is_some_and(|limit| *used >= limit)
+        {
+            return false;
+        }
+
+        *used += 1;
+        true
+    }
+
+    fn score(&self) -> Score {
+        let seconds = self.elapsed_seconds();
+        let stars = self
+            .stars
+            .iter()
+            .take_while(|threshold| threshold.is_met_by(seconds, self.usage))
+            .count() as u8;
+
+        Score {
+            seconds,
+            usage: self.usage,
+            stars,
+        }
+    }
+
+    /// advances the clocks by a single step and checks the win and lose conditions,
+    /// returning the event of the transition if the state changed.
+    /// Once the game is won or lost, the state does not change anymore
+    pub fn advance(
+        &mut self,
+        microseconds: f64,
+        flags_remaining: usize,
+        flags_total: usize,
+    ) -> Option<GameEvent> {
+        self.elapsed_microseconds += microseconds;
+        self.attempt_microseconds += microseconds;
+
+        if self.state != GameState::Playing {
+            return None;
+        }
+
+        if flags_total > 0 && flags_remaining == 0 {
+            let score = self.score();
+            self.state = GameState::Won { score };
+            Some(GameEvent::Won { score })
+        } else if self
+            .time_limit_microseconds
+            .is_some_and(|limit| self.elapsed_microseconds > limit)
+        {
+            let reason = LossReason::OutOfTime;
+            self.state = GameState::Lost { reason };
+            Some(GameEvent::Lost { reason })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_won_once() {
+        let mut game = Game::new(None, Default::default(), Default::default());
+
+        assert_eq!(game.advance(500_000.0, 1, 1), None);
+        let Some(GameEvent::Won { score }) = game.advance(500_000.0, 0, 1) else {
+            panic!("the game should have been won");
+        };
+        assert_eq!(score.seconds, 1.0);
+        assert_eq!(score.stars, 3);
+        assert_eq!(game.advance(500_000.0, 0, 1), None);
+        assert_eq!(game.state(), GameState::Won { score });
+    }
+
+    #[test]
+    fn test_stars() {
+        let threshold = |seconds, shapes_drawn| StarThreshold {
+            seconds: Some(seconds),
+            shapes_drawn: Some(shapes_drawn),
+            ..Default::default()
+        };
+        let mut game = Game::new(
+            None,
+            [threshold(10.0, 5), threshold(5.0, 3), threshold(5.0, 1)],
+            Default::default(),
+        );
+        game.try_use(Tool::Polygon);
+        game.try_use(Tool::Circle);
+        game.try_use(Tool::Erase);
+
+        let Some(GameEvent::Won { score }) = game.advance(4_000_000.0, 0, 1) else {
+            panic!("the game should have been won");
+        };
+        assert_eq!(score.usage.shapes_drawn(), 2);
+        assert_eq!(score.usage.erases, 1);
+        assert_eq!(score.stars, 2);
+    }
+
+    #[test]
+    fn test_tool_limits() {
+        let mut game = Game::new(
+            None,
+            Default::default(),
+            ToolLimits {
+                hinges: Some(1),
+                ..Default::default()
+            },
+        );
+
+        assert!(game.try_use(Tool::Hinge));
+        assert!(!game.try_use(Tool::Hinge));
+        assert!(game.try_use(Tool::Rigid));
+        assert_eq!(game.usage().hinges, 1);
+    }
+
+    #[test]
+    fn test_out_of_time() {
+        let mut game = Game::new(Some(1.0), Default::default(), Default::default());
+
+        assert_eq!(game.advance(1_000_000.0, 1, 1), None);
+        assert_eq!(
+            game.advance(1.0, 1, 1),
+            Some(GameEvent::Lost {
+                reason: LossReason::OutOfTime
+            })
+        );
+        // collecting the last flag after losing does not win the game
+        assert_eq!(game.advance(1.0, 0, 1), None);
+    }
+
+    #[test]
+    fn test_no_flags() {
+        let mut game = Game::new(None, Default::default(), Default::default());
+        assert_eq!(game.advance(1_000_000.0, 0, 0), None);
+        assert_eq!(game.state(), GameState::Playing);
+    }
+
+    #[test]
+    fn test_timers() {
+        let mut game = Game::new(None, Default::default(), Default::default());
+        game.advance(1_500_000.0, 1, 1);
+        game.restart_attempt();
+        game.advance(500_000.0, 0, 1);
+        game.advance(500_000.0, 0, 1);
+
+        // both keep running after the win
+        assert_eq!(game.elapsed_seconds(), 2.5);
+        assert_eq!(game.attempt_seconds(), 1.0);
+    }
+}
@@ -0,0 +1,99 @@
+//! Trajectory of the main ball during the previous attempt
+//!
+//! The path is sampled at a fixed interval of simulated time. Once it grows
+//! too long every other point is dropped and the interval doubles, so the
+//! whole attempt is always covered by a bounded number of points.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use crate::geometry::{Point, Vector};
+
+/// at most this many points are kept per attempt
+pub const MAX_GHOST_POINTS: usize = 256;
+const INITIAL_SAMPLE_INTERVAL_MICROSECONDS: f64 = 20_000.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Ghost {
+    current: Vec<Point>,
+    previous: Vec<Point>,
+    sample_interval: f64,
+    since_last_sample: f64,
+}
+
+impl Default for Ghost {
+    fn default() -> Self {
+        Self {
+            // the path is bounded, so it can be allocated once per attempt
+            current: Vec::with_capacity(MAX_GHOST_POINTS + 1),
+            previous: vec![],
+            sample_interval: INITIAL_SAMPLE_INTERVAL_MICROSECONDS,
+            // so that the first call samples the starting position
+            since_last_sample: INITIAL_SAMPLE_INTERVAL_MICROSECONDS,
+        }
+    }
+}
+
+impl Ghost {
+    pub fn record(&mut self, microseconds: f64, position: Point) {
+        self.since_last_sample += microseconds;
+        if self.since_last_sample < self.sample_interval {
+            return;
+        }
+
+        self.since_last_sample = 0.0;
+        self.current.push(position);
+
+        if self.current.len() > MAX_GHOST_POINTS {
+            let mut i = 0;
+            self.current.retain(|_| {
+                i += 1;
+                i % 2 == 1
+            });
+            self.sample_interval *= 2.0;
+        }
+    }
+
+    /// makes the path recorded so far the ghost, and starts recording a new one
+    pub fn finish_attempt(&mut self) {
+        let previous = std::mem::take(&mut self.current);
+        *self = Self {
+            previous,
+            ..Default::default()
+        };
+    }
+
+    pub fn shift(&mut self, translation: Vector) {
+        for point in self.current.iter_mut().chain(&mut self.previous) {
+            *point += translation;
+        }
+    }
+
+    pub fn previous(&self) -> &[Point] {
+        &self.previous
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ghost() {
+        let mut ghost = Ghost::default();
+
+        for i in 0..1000 {
+            ghost.record(INITIAL_SAMPLE_INTERVAL_MICROSECONDS, Point(i as f64, 0.0));
+        }
+        assert!(ghost.previous().is_empty());
+
+        ghost.finish_attempt();
+        let path = ghost.previous();
+        assert!(path.len() <= MAX_GHOST_POINTS);
+        assert!(path.len() > MAX_GHOST_POINTS / 2);
+        assert_eq!(path[0], Point(0.0, 0.0));
+        assert!(path.last().unwrap().0 > 900.0);
+    }
+}
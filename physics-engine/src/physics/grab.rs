@@ -0,0 +1,128 @@
+//! Dragging entities with the pointer
+//!
+//! Grabbing ties the point of the entity under the pointer to the pointer.
+//! Every step the grab pushes that point towards the pointer fast enough to
+//! catch up within a fraction of a second, but with at most a few times the
+//! weight of the entity, so a dragged entity still collides with and pushes
+//! against whatever is in its way instead of passing through it. The grab
+//! ends with [`Engine::release_grab`] or with the entity.
+//!
+//! The grab follows the pointer of the host rather than anything in the level,
+//! so snapshots leave it out.
+
+use super::{
+    binding::PointOnShape, ids::EntityId, Engine, GRAVITY_COEFFICIENT, MOVEMENT_COEFFICIENT,
+};
+use crate::geometry::Point;
+
+/// how long the grabbed point takes to catch up with the pointer, in seconds
+const CATCH_UP_SECONDS: f64 = 0.1;
+/// the largest force of a grab, in multiples of the weight of the grabbed entity
+const MAX_FORCE_PER_WEIGHT: f64 = 5.0;
+
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Grab {
+    entity: EntityId,
+    point: PointOnShape,
+    pub(super) pointer: Point,
+}
+
+impl Engine {
+    /// grabs the entity under the point, unless it is static,
+    /// returning whether there is such an entity
+    pub fn grab(&mut self, point: Point) -> bool {
+        let Some(slot) = self.slot_at(point, |entity| !entity.is_static) else {
+            return false;
+        };
+        let entity = &self.entities[slot];
+        self.grab = Some(Grab {
+            entity: entity.id,
+            point: entity.shape.borrow().create_point_reference(point),
+            pointer: point,
+        });
+        true
+    }
+
+    /// moves the pointer the grabbed entity is dragged towards
+    pub fn move_grab(&mut self, point: Point) {
+        if let Some(grab) = &mut self.grab {
+            grab.pointer = point;
+        }
+    }
+
+    pub fn release_grab(&mut self) {
+        self.grab = None;
+    }
+
+    /// drags the grabbed entity towards the pointer, releasing it once it is removed
+    pub(super) fn drag_grabbed(&mut self, microseconds: f64) {
+        let Some(grab) = self.grab else {
+            return;
+        };
+        let Some(slot) = self.slots.get(grab.entity) else {
+            self.grab = None;
+            return;
+        };
+
+        let mut shape = self.entities[slot].shape.borrow_mut();
+        let point = grab.point.on(&*shape);
+        let data = shape.collision_data_mut();
+        let offset = data.centroid.to(point);
+        let velocity = data.velocity - (offset * data.angular_velocity).perpendicular();
+        let catch_up =
+            point.to(grab.pointer) / (MOVEMENT_COEFFICIENT * 1_000_000.0 * CATCH_UP_SECONDS);
+        let change = catch_up - velocity;
+        let length = change.norm();
+        if length < f64::EPSILON {
+            return;
+        }
+
+        let direction = change / length;
+        let arm = offset.cross(direction);
+        let inverse_mass = data.mass.recip() + arm * arm / data.inertia;
+        let max_impulse = MAX_FORCE_PER_WEIGHT * data.mass * GRAVITY_COEFFICIENT * microseconds;
+        let impulse = (length / inverse_mass).min(max_impulse);
+        data.velocity += direction * (impulse / data.mass);
+        data.angular_velocity += impulse * arm / data.inertia;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig, snapshot::Snapshot};
+
+    #[test]
+    fn test_grab() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (3.0, -3.0), (3.2, -3.0), (3.2, 1.0), (3.0, 1.0) },
+            true,
+        );
+        let block = engine
+            .add_polygon(make_shape! { (1.9, -1.1), (2.1, -1.1), (2.1, -0.9), (1.9, -0.9) })
+            .unwrap();
+        assert!(!engine.grab(Point(3.1, 0.0)));
+        assert!(engine.grab(Point(2.0, -1.0)));
+
+        // the block follows the pointer up against gravity
+        engine.move_grab(Point(2.0, -2.0));
+        engine.run_n_iterations(500, 2_000.0);
+        let centroid = |engine: &Engine| {
+            let slot = engine.slots.get(block).unwrap();
+            engine.body_states()[slot].centroid
+        };
+        assert!(centroid(&engine).to(Point(2.0, -2.0)).norm() < 0.05);
+
+        // but it does not pass through the wall
+        engine.move_grab(Point(4.0, -2.0));
+        engine.run_n_iterations(500, 2_000.0);
+        let Point(x, y) = centroid(&engine);
+        assert!((2.7..3.0).contains(&x) && y < -1.5, "{x} {y}");
+
+        assert!(Engine::from(Snapshot::from(&engine)).grab.is_none());
+        engine.erase(block);
+        engine.run_iteration(2_000.0);
+        assert!(engine.grab.is_none());
+    }
+}
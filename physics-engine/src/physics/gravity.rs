@@ -0,0 +1,138 @@
+//! Direction of gravity and the pickups that turn it
+//!
+//! Gravity points towards positive `y` by default. When the main ball touches
+//! a pickup, the pickup disappears and gravity turns by its rotation for its
+//! duration. A later pickup replaces the effect of an earlier one.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    compute,
+    game::GameEvent,
+    shape::{Circle, Collidable},
+};
+use crate::{
+    geometry::{Point, Vector},
+    levels::GravityPickup,
+};
+
+pub const PICKUP_RADIUS: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+struct Effect {
+    rotation: f64,
+    remaining_microseconds: f64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Gravity {
+    /// pickups the ball has not touched yet
+    pickups: Vec<GravityPickup>,
+    effect: Option<Effect>,
+}
+
+impl Gravity {
+    pub fn new(pickups: Vec<GravityPickup>) -> Self {
+        Self {
+            pickups,
+            effect: None,
+        }
+    }
+
+    pub fn pickups(&self) -> &[GravityPickup] {
+        &self.pickups
+    }
+
+    /// the gravity vector of the strength of the multiplier
+    pub fn vector(&self, multiplier: f64) -> Vector {
+        let rotation = self.effect.map_or(0.0, |effect| effect.rotation);
+        Point(0.0, multiplier).rotate(rotation)
+    }
+
+    pub fn seconds_remaining(&self) -> Option<f64> {
+        self.effect
+            .map(|effect| effect.remaining_microseconds / 1_000_000.0)
+    }
+
+    /// picks up every pickup the ball touches, the last one taking effect
+    pub fn collect(&mut self, ball: &dyn Collidable, events: &mut Vec<GameEvent>) {
+        self.pickups.retain(|pickup| {
+            let is_touched =
+                compute::intersects(ball, &Circle::new(pickup.position, PICKUP_RADIUS));
+            if is_touched {
+                self.effect = Some(Effect {
+                    rotation: pickup.rotation,
+                    remaining_microseconds: pickup.duration_seconds * 1_000_000.0,
+                });
+                events.push(GameEvent::GravityTurned {
+                    rotation: pickup.rotation,
+                    seconds: pickup.duration_seconds,
+                });
+            }
+            !is_touched
+        });
+    }
+
+    pub(super) fn shift(&mut self, translation: Vector) {
+        for pickup in &mut self.pickups {
+            pickup.position += translation;
+        }
+    }
+
+    /// runs down the current effect, turning gravity back once it is over
+    pub fn advance(&mut self, microseconds: f64, events: &mut Vec<GameEvent>) {
+        let Some(effect) = &mut self.effect else {
+            return;
+        };
+
+        effect.remaining_microseconds -= microseconds;
+        if effect.remaining_microseconds <= 0.0 {
+            self.effect = None;
+            events.push(GameEvent::GravityRestored);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn test_pickup() {
+        let mut gravity = Gravity::new(vec![GravityPickup {
+            position: Point(1.0, 0.0),
+            rotation: PI,
+            duration_seconds: 1.0,
+        }]);
+        let mut events = vec![];
+
+        gravity.collect(&Circle::new(Point(0.0, 0.0), 0.1), &mut events);
+        assert!(events.is_empty());
+        assert_eq!(gravity.vector(2.0), Point(0.0, 2.0));
+
+        gravity.collect(&Circle::new(Point(0.85, 0.0), 0.1), &mut events);
+        assert!(gravity.pickups().is_empty());
+        assert!(gravity.vector(2.0).is_close_enough_to(Point(0.0, -2.0)));
+
+        gravity.advance(600_000.0, &mut events);
+        assert_eq!(gravity.seconds_remaining(), Some(0.4));
+        gravity.advance(600_000.0, &mut events);
+        assert_eq!(gravity.seconds_remaining(), None);
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::GravityTurned {
+                    rotation: PI,
+                    seconds: 1.0
+                },
+                GameEvent::GravityRestored
+            ]
+        );
+    }
+}
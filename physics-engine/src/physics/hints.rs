@@ -0,0 +1,109 @@
+//! Tutorial hints defined by the level
+//!
+//! Every hint fires a single [`GameEvent::Hint`] the first time its trigger
+//! holds, the frontend looks up the text by the index in the level.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    game::{GameEvent, Tool},
+    shape::{Bounded, Polygon},
+};
+use crate::{
+    geometry::{Point, Vector},
+    levels::{Hint, HintTrigger},
+};
+
+/// hints that have not fired yet, with their indices in the level
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Hints(Vec<(usize, HintTrigger)>);
+
+impl Hints {
+    pub fn new(hints: Vec<Hint>) -> Self {
+        Self(
+            hints
+                .into_iter()
+                .map(|hint| hint.trigger)
+                .enumerate()
+                .collect(),
+        )
+    }
+
+    /// checks the triggers that depend on the state of the simulation
+    pub fn check(&mut self, ball: Point, failed_attempts: u32, events: &mut Vec<GameEvent>) {
+        self.fire(events, |trigger| match trigger {
+            HintTrigger::BallEnters(region) => Polygon::new(region.clone()).includes(ball),
+            HintTrigger::FailedAttempts(attempts) => failed_attempts >= *attempts,
+            HintTrigger::ToolSelected(_) => false,
+        })
+    }
+
+    pub(super) fn shift(&mut self, translation: Vector) {
+        for (_, trigger) in &mut self.0 {
+            if let HintTrigger::BallEnters(region) = trigger {
+                region.iter_mut().for_each(|point| *point += translation);
+            }
+        }
+    }
+
+    pub fn tool_selected(&mut self, tool: Tool, events: &mut Vec<GameEvent>) {
+        self.fire(
+            events,
+            |trigger| matches!(trigger, HintTrigger::ToolSelected(selected) if *selected == tool),
+        )
+    }
+
+    fn fire(&mut self, events: &mut Vec<GameEvent>, is_triggered: impl Fn(&HintTrigger) -> bool) {
+        self.0.retain(|(index, trigger)| {
+            let triggered = is_triggered(trigger);
+            if triggered {
+                events.push(GameEvent::Hint { index: *index });
+            }
+            !triggered
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hints() {
+        let hint = |trigger| Hint {
+            text: String::new(),
+            trigger,
+        };
+        let mut hints = Hints::new(vec![
+            hint(HintTrigger::BallEnters(vec![
+                Point(1.0, 1.0),
+                Point(2.0, 1.0),
+                Point(2.0, 2.0),
+                Point(1.0, 2.0),
+            ])),
+            hint(HintTrigger::FailedAttempts(2)),
+            hint(HintTrigger::ToolSelected(Tool::Hinge)),
+        ]);
+        let mut events = vec![];
+
+        hints.check(Point(0.0, 0.0), 1, &mut events);
+        hints.tool_selected(Tool::Rigid, &mut events);
+        assert!(events.is_empty());
+
+        hints.check(Point(1.5, 1.5), 2, &mut events);
+        hints.tool_selected(Tool::Hinge, &mut events);
+        hints.tool_selected(Tool::Hinge, &mut events);
+        hints.check(Point(1.5, 1.5), 2, &mut events);
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::Hint { index: 0 },
+                GameEvent::Hint { index: 1 },
+                GameEvent::Hint { index: 2 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Stable identifiers handed out to the host
+//!
+//! In the generated TypeScript declarations they are branded numbers,
+//! so the frontend cannot pass an id of one kind where another is expected.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct EntityId(
+    #[cfg_attr(
+        feature = "wasm",
+        tsify(type = "number & { readonly __brand: \"EntityId\" }")
+    )]
+    pub u32,
+);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BindingId(
+    #[cfg_attr(
+        feature = "wasm",
+        tsify(type = "number & { readonly __brand: \"BindingId\" }")
+    )]
+    pub u32,
+);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct RegionId(
+    #[cfg_attr(
+        feature = "wasm",
+        tsify(type = "number & { readonly __brand: \"RegionId\" }")
+    )]
+    pub u32,
+);
+
+/// Hands out ids of a single kind, never reusing one
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct IdGenerator {
+    next: u32,
+}
+
+impl IdGenerator {
+    pub fn generate<I: From<u32>>(&mut self) -> I {
+        let id = self.next;
+        self.next += 1;
+        id.into()
+    }
+}
+
+impl From<u32> for EntityId {
+    fn from(id: u32) -> Self {
+        Self(id)
+    }
+}
+
+impl From<u32> for BindingId {
+    fn from(id: u32) -> Self {
+        Self(id)
+    }
+}
+
+impl From<u32> for RegionId {
+    fn from(id: u32) -> Self {
+        Self(id)
+    }
+}
+
+#[cfg(all(test, feature = "wasm"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_branded_declarations() {
+        assert_eq!(
+            EntityId::DECL,
+            "export type EntityId = number & { readonly __brand: \"EntityId\" };"
+        );
+        assert!(BindingId::DECL.contains("__brand: \"BindingId\""));
+        assert!(RegionId::DECL.contains("__brand: \"RegionId\""));
+    }
+}
@@ -0,0 +1,238 @@
+//! Tracing the motion of selected entities, for plotting it elsewhere
+//!
+//! While tracing, every step adds a [`TraceSample`] for every traced entity
+//! that still exists, with where it is, how fast it moves and the sum of the
+//! impulses of the contacts it was in during the step. A trace exports to CSV
+//! or to JSON lines, which plotting tools read as they are, and which is what
+//! tuning friction and restitution needs. Positions are in the coordinates of
+//! the level. Impulses between two static entities are infinite and left out.
+
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{ids::EntityId, slots::Slots, Engine, Entity};
+use crate::geometry::{Point, Vector};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TraceSample {
+    /// steps since tracing started, starting at 0
+    pub step: u32,
+    /// simulated seconds since the start of the level
+    pub seconds: f64,
+    pub id: EntityId,
+    pub position: Point,
+    pub angle: f64,
+    pub velocity: Vector,
+    pub angular_velocity: f64,
+    pub contact_impulse: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum TraceFormat {
+    /// with a header line naming the columns
+    Csv,
+    /// one JSON object per sample and line
+    JsonLines,
+}
+
+const COLUMNS: [&str; 10] = [
+    "step",
+    "seconds",
+    "id",
+    "x",
+    "y",
+    "angle",
+    "velocity_x",
+    "velocity_y",
+    "angular_velocity",
+    "contact_impulse",
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Trace {
+    /// ordered by step, then in the order the entities were selected in
+    pub samples: Vec<TraceSample>,
+}
+
+impl Trace {
+    pub fn export(&self, format: TraceFormat) -> String {
+        let mut out = String::new();
+        if format == TraceFormat::Csv {
+            out.push_str(&COLUMNS.join(","));
+            out.push('\n');
+        }
+        for sample in &self.samples {
+            let values = [
+                sample.step as f64,
+                sample.seconds,
+                sample.id.0 as f64,
+                sample.position.0,
+                sample.position.1,
+                sample.angle,
+                sample.velocity.0,
+                sample.velocity.1,
+                sample.angular_velocity,
+                sample.contact_impulse,
+            ];
+            if format == TraceFormat::JsonLines {
+                out.push('{');
+            }
+            for (i, (column, value)) in COLUMNS.iter().zip(values).enumerate() {
+                // non-finite numbers are not valid JSON, and nothing reads them back alike
+                let value: &dyn std::fmt::Display = if value.is_finite() {
+                    &value
+                } else if format == TraceFormat::Csv {
+                    &""
+                } else {
+                    &"null"
+                };
+                let separator = if i == 0 { "" } else { "," };
+                // writing to a string cannot fail
+                let _ = match format {
+                    TraceFormat::Csv => write!(out, "{separator}{value}"),
+                    TraceFormat::JsonLines => write!(out, "{separator}\"{column}\":{value}"),
+                };
+            }
+            if format == TraceFormat::JsonLines {
+                out.push('}');
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// A trace being taken
+pub(super) struct Tracer {
+    trace: Trace,
+    ids: Vec<EntityId>,
+    /// the impulses of the entities of `ids` during the current step
+    impulses: Vec<f64>,
+    steps: u32,
+}
+
+impl Tracer {
+    /// adds the impulse of a contact between the two entities to theirs
+    pub(super) fn add_impulse(&mut self, entities: (EntityId, EntityId), impulse: f64) {
+        if !impulse.is_finite() {
+            return;
+        }
+        for (id, sum) in self.ids.iter().zip(&mut self.impulses) {
+            if *id == entities.0 || *id == entities.1 {
+                *sum += impulse;
+            }
+        }
+    }
+
+    /// adds the samples of the step that just ended
+    pub(super) fn record(
+        &mut self,
+        entities: &[Entity],
+        slots: &Slots,
+        seconds: f64,
+        origin: Point,
+    ) {
+        for (&id, impulse) in self.ids.iter().zip(&mut self.impulses) {
+            let contact_impulse = std::mem::take(impulse);
+            let Some(slot) = slots.get(id) else {
+                continue;
+            };
+            let mut shape = entities[slot].shape.borrow_mut();
+            let angle = shape.angle();
+            let data = shape.collision_data_mut();
+            self.trace.samples.push(TraceSample {
+                step: self.steps,
+                seconds,
+                id,
+                position: data.centroid + origin,
+                angle,
+                velocity: data.velocity,
+                angular_velocity: data.angular_velocity,
+                contact_impulse,
+            });
+        }
+        self.steps += 1;
+    }
+}
+
+impl Engine {
+    /// starts tracing the entities of `ids`, dropping the trace being taken
+    pub fn start_trace(&mut self, ids: Vec<EntityId>) {
+        self.tracer = Some(Box::new(Tracer {
+            trace: Trace::default(),
+            impulses: vec![0.0; ids.len()],
+            ids,
+            steps: 0,
+        }));
+    }
+
+    /// the trace taken since [`Engine::start_trace`], `None` if not tracing
+    pub fn stop_trace(&mut self) -> Option<Trace> {
+        self.tracer.take().map(|tracer| tracer.trace)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig};
+
+    #[test]
+    fn test_trace() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (-3.0, 0.2), (3.0, 0.2), (3.0, 0.4), (-3.0, 0.4) },
+            true,
+        );
+        engine.start_trace(vec![EntityId(0), EntityId(7)]);
+        engine.run_n_iterations(100, 2000.0);
+        let trace = engine.stop_trace().unwrap();
+        assert!(engine.stop_trace().is_none());
+
+        // only the ball exists, and it lands on the floor
+        assert_eq!(trace.samples.len(), 100);
+        assert_eq!(trace.samples[99].step, 99);
+        assert!(trace.samples[0].velocity.1 > 0.0);
+        assert!(trace
+            .samples
+            .iter()
+            .any(|sample| sample.contact_impulse > 0.0));
+
+        let csv = trace.export(TraceFormat::Csv);
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some(
+                "step,seconds,id,x,y,angle,velocity_x,velocity_y,angular_velocity,contact_impulse"
+            )
+        );
+        assert_eq!(lines.count(), 100);
+
+        let json = trace.export(TraceFormat::JsonLines);
+        let first: TraceSample = {
+            let line = json.lines().next().unwrap();
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["step"], 0.0);
+            TraceSample {
+                step: 0,
+                seconds: value["seconds"].as_f64().unwrap(),
+                id: EntityId(0),
+                position: Point(value["x"].as_f64().unwrap(), value["y"].as_f64().unwrap()),
+                angle: value["angle"].as_f64().unwrap(),
+                velocity: Point(
+                    value["velocity_x"].as_f64().unwrap(),
+                    value["velocity_y"].as_f64().unwrap(),
+                ),
+                angular_velocity: value["angular_velocity"].as_f64().unwrap(),
+                contact_impulse: value["contact_impulse"].as_f64().unwrap(),
+            }
+        };
+        assert_eq!(first, trace.samples[0]);
+    }
+}
@@ -0,0 +1,64 @@
+//! Laser barriers of a level
+//!
+//! Every step each laser erases the player-drawn shapes crossing it, and
+//! respawns the main ball if it is set to do so.
+
+use super::shape::Bounded;
+use crate::{
+    geometry::{Point, Vector},
+    levels::Laser,
+};
+
+impl Bounded for Laser {
+    fn support_vector(&self, direction: Vector) -> Point {
+        if self.start.dot(direction) >= self.end.dot(direction) {
+            self.start
+        } else {
+            self.end
+        }
+    }
+
+    fn includes(&self, point: Point) -> bool {
+        let segment = self.start.to(self.end);
+        let offset = self.start.to(point);
+        let along = offset.dot(segment) / segment.dot(segment);
+
+        (0.0..=1.0).contains(&along) && offset.cross(segment).abs() < 1e-9 * segment.norm()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{compute, make_shape, shape::Circle};
+
+    #[test]
+    fn test_crossing() {
+        let laser = Laser {
+            start: Point(0.0, 0.0),
+            end: Point(2.0, 0.0),
+            resets_ball: false,
+        };
+        let square = |y| {
+            make_shape! {
+                (0.5, y),
+                (1.0, y),
+                (1.0, y + 0.5),
+                (0.5, y + 0.5),
+            }
+        };
+
+        assert!(compute::intersects(&square(-0.25), &laser));
+        assert!(!compute::intersects(&square(0.25), &laser));
+        assert!(compute::intersects(
+            &Circle::new(Point(2.05, 0.0), 0.1),
+            &laser
+        ));
+        assert!(!compute::intersects(
+            &Circle::new(Point(2.2, 0.0), 0.1),
+            &laser
+        ));
+        assert!(laser.includes(Point(1.0, 0.0)));
+        assert!(!laser.includes(Point(3.0, 0.0)));
+    }
+}
@@ -0,0 +1,88 @@
+//! Render layers, telling the frontend what to draw on top of what
+//!
+//! Every entity is on a layer, which the display message lists next to its
+//! polygon or circle. Entities on higher layers are drawn on top of the ones
+//! on lower layers, and within a layer the order is up to the frontend. The
+//! entities of a level are on [`TERRAIN`] unless the level puts them
+//! elsewhere, what the player draws is on [`DRAWN`] and the main ball on
+//! [`BALL`], leaving room in between for the layers of a level. Pieces of a
+//! fractured polygon stay on its layer.
+
+use super::{ids::EntityId, Engine};
+
+/// the layer of the entities of a level that do not have one
+pub const TERRAIN: i32 = 0;
+/// the layer of everything the player draws or places
+pub const DRAWN: i32 = 10;
+/// the layer of the main ball
+pub const BALL: i32 = 20;
+
+impl Engine {
+    pub fn render_layer(&self, id: EntityId) -> Option<i32> {
+        let slot = self.slots.get(id)?;
+        Some(self.entities[slot].render_layer)
+    }
+
+    /// moves the entity to another layer, returns whether it exists
+    pub fn set_render_layer(&mut self, id: EntityId, render_layer: i32) -> bool {
+        let Some(slot) = self.slots.get(id) else {
+            return false;
+        };
+        self.entities[slot].render_layer = render_layer;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry::Point,
+        levels,
+        physics::{make_shape, sandbox::SandboxConfig, shape::Circle, snapshot::Snapshot},
+    };
+
+    #[test]
+    fn test_render_layers() {
+        let wall = |x: f64, render_layer| levels::Entity {
+            shape: vec![
+                Point(x, 0.0),
+                Point(x + 0.1, 0.0),
+                Point(x + 0.1, 1.0),
+                Point(x, 1.0),
+            ],
+            is_static: true,
+            is_bindable: false,
+            hit_points: None,
+            breaking_impulse: None,
+            fracture_impulse: None,
+            bump_impulse: None,
+            is_ferrous: false,
+            is_erasable: false,
+            render_layer,
+        };
+        let mut level = Engine::sandbox(SandboxConfig::default())
+            .export_level()
+            .unwrap();
+        level.polygons = vec![wall(-2.0, TERRAIN), wall(2.0, 15)];
+        let mut engine = Engine::new(level);
+        let drawn = engine
+            .add_polygon(make_shape! { (1.0, 1.0), (1.5, 1.0), (1.5, 1.5), (1.0, 1.5) })
+            .unwrap();
+        engine.add_circle(Circle::new(Point(-1.0, 1.0), 0.2));
+
+        let message = engine.run_iteration(2_000.0);
+        assert_eq!(message.polygon_layers, [TERRAIN, 15, DRAWN]);
+        assert_eq!(message.circle_layers, [BALL, DRAWN]);
+
+        assert!(engine.set_render_layer(drawn, -5));
+        assert_eq!(engine.render_layer(drawn), Some(-5));
+        let message = engine.run_iteration(2_000.0);
+        assert_eq!(message.polygon_layers, [TERRAIN, 15, -5]);
+
+        // layers are kept in snapshots
+        let restored = Engine::from(Snapshot::from(&engine));
+        assert_eq!(restored.render_layer(drawn), Some(-5));
+        assert_eq!(restored.render_layer(EntityId(0)), Some(BALL));
+    }
+}
@@ -0,0 +1,170 @@
+//! The loads the bindings carry
+//!
+//! Every step the solver reports the impulse it took to enforce every binding:
+//! what holds the points of a hinge or a rigid binding together, what a spring
+//! pushes or pulls with, and the tension of a taut rope or pulley. The torque
+//! of a hinge motor does not count. The display message reports the load of
+//! every binding enforced since the previous message, keyed by the id of the
+//! binding, including the bindings that broke under it.
+//!
+//! The largest impulse of a single step is the one the break impulse of a
+//! hinge or a rigid binding is compared against, so it depends on the length
+//! of the steps. It also alternates between steps for loads at rest, as the
+//! solver pulls the points a little too far one step and not at all the next.
+//! The force is the impulse per second averaged over all the steps, which does
+//! neither, a block hanging still loads its hinge with its weight.
+//!
+//! The stress of a binding that breaks is that largest impulse as a fraction
+//! of its break impulse, so that it reaches 1 as the binding breaks.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::ids::BindingId;
+
+/// the stress from which on a binding is reported as stressed, close to breaking
+pub const STRESSED: f64 = 0.8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BindingLoad {
+    pub id: BindingId,
+    /// the largest impulse the binding took in a single step
+    pub impulse: f64,
+    /// the impulse per second it took on average
+    pub force: f64,
+    /// the largest impulse as a fraction of the break impulse, `None` for the
+    /// bindings that do not break
+    pub stress: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Accumulated {
+    id: BindingId,
+    largest: f64,
+    total: f64,
+    microseconds: f64,
+    /// as of the last step recorded
+    break_impulse: Option<f64>,
+}
+
+impl Accumulated {
+    fn stress(&self) -> Option<f64> {
+        self.break_impulse.map(|max| self.largest / max)
+    }
+}
+
+/// The loads of the bindings since the last message, ordered by the ids of the
+/// bindings. It keeps its buffer between messages, not to allocate every frame
+#[derive(Clone, Debug, Default)]
+pub(super) struct BindingLoads(Vec<Accumulated>);
+
+impl BindingLoads {
+    /// records the impulse the binding took in a step of `microseconds`, along
+    /// with its break impulse, leaving out the infinite ones of static shapes
+    /// bound together
+    pub(super) fn record(
+        &mut self,
+        id: BindingId,
+        impulse: f64,
+        microseconds: f64,
+        break_impulse: Option<f64>,
+    ) {
+        if !impulse.is_finite() {
+            return;
+        }
+        let i = match self.0.binary_search_by_key(&id, |load| load.id) {
+            Ok(i) => i,
+            Err(i) => {
+                self.0.insert(
+                    i,
+                    Accumulated {
+                        id,
+                        largest: 0.0,
+                        total: 0.0,
+                        microseconds: 0.0,
+                        break_impulse,
+                    },
+                );
+                i
+            }
+        };
+        let load = &mut self.0[i];
+        load.largest = load.largest.max(impulse);
+        load.total += impulse;
+        load.microseconds += microseconds;
+        load.break_impulse = break_impulse;
+    }
+
+    /// the stress of the binding since the last message, see [`BindingLoad::stress`]
+    pub(super) fn stress(&self, id: BindingId) -> Option<f64> {
+        let i = self.0.binary_search_by_key(&id, |load| load.id).ok()?;
+        self.0[i].stress()
+    }
+
+    /// moves the loads into `loads`, starting over for the next message
+    pub(super) fn take_into(&mut self, loads: &mut Vec<BindingLoad>) {
+        loads.clear();
+        loads.extend(self.0.drain(..).map(|load| BindingLoad {
+            id: load.id,
+            impulse: load.largest,
+            force: if load.microseconds > 0.0 {
+                load.total * 1_000_000.0 / load.microseconds
+            } else {
+                0.0
+            },
+            stress: load.stress(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        geometry::Point,
+        physics::{make_shape, sandbox::SandboxConfig, Engine, GRAVITY_COEFFICIENT},
+    };
+
+    #[test]
+    fn test_binding_loads() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (2.0, -2.2), (3.0, -2.2), (3.0, -2.0), (2.0, -2.0) },
+            true,
+        );
+        let weight = engine
+            .add_polygon(make_shape! { (2.3, -1.0), (2.7, -1.0), (2.7, -0.6), (2.3, -0.6) })
+            .unwrap();
+        let rope = engine
+            .add_rope(Point(2.5, -0.8), Point(2.5, -2.1), 1.3)
+            .unwrap();
+        let slot = engine.slots.get(weight).unwrap();
+        let mass = engine.entities[slot]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .mass;
+
+        // hanging still, the rope carries the weight
+        engine.run_n_iterations(500, 2_000.0);
+        let message = engine.run_n_iterations(10, 2_000.0);
+        let [load] = message.binding_loads[..] else {
+            panic!("{:?} are not one load", message.binding_loads);
+        };
+        assert_eq!(load.id, rope);
+        let force = mass * GRAVITY_COEFFICIENT * 1_000_000.0;
+        assert!(
+            (load.force - force).abs() < 0.1 * force,
+            "{} {force}",
+            load.force
+        );
+        assert!(load.impulse >= load.force * 0.002);
+        assert_eq!(load.stress, None);
+
+        // the loads of a message are only the ones since the previous one
+        engine.erase(weight);
+        let message = engine.run_iteration(2_000.0);
+        assert!(message.binding_loads.is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+//! Electromagnets picking up ferrous entities
+//!
+//! While powered, a magnet rigidly binds the first ferrous entity it touches
+//! and holds on to it until it is switched off or the binding is otherwise
+//! removed, say when it breaks or is cut. Only then does it pick up another one.
+
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    binding::Binding,
+    compute,
+    game::GameEvent,
+    ids::{BindingId, EntityId},
+    Engine,
+};
+use crate::levels;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Magnet {
+    pub id: EntityId,
+    pub is_powered: bool,
+    /// the binding holding the entity the magnet picked up, if any
+    pub held: Option<BindingId>,
+}
+
+impl Magnet {
+    pub fn new(id: EntityId, magnet: &levels::Magnet) -> Self {
+        Self {
+            id,
+            is_powered: magnet.starts_powered,
+            held: None,
+        }
+    }
+}
+
+/// lets go of what the magnet holding on with the removed binding `id` holds, if any
+pub(super) fn release(magnets: &mut [Magnet], id: BindingId) {
+    for magnet in magnets {
+        if magnet.held == Some(id) {
+            magnet.held = None;
+        }
+    }
+}
+
+impl Engine {
+    /// switching a magnet off drops what it holds,
+    /// returns whether a magnet with the given id exists
+    pub fn set_magnet_powered(&mut self, id: EntityId, is_powered: bool) -> bool {
+        let Some(magnet) = self.magnets.iter_mut().find(|magnet| magnet.id == id) else {
+            return false;
+        };
+
+        magnet.is_powered = is_powered;
+        if let Some(held) = magnet.held.filter(|_| !is_powered) {
+            magnet.held = None;
+            for entity in &mut self.entities {
+                entity
+                    .bindings
+                    .retain(|(binding_id, ..)| *binding_id != held);
+            }
+            self.events.push(GameEvent::MagnetReleased { id });
+        }
+        true
+    }
+
+    /// binds every powered magnet with free hands to the first ferrous entity touching it,
+    /// dropping the magnets that were removed
+    pub(super) fn attract(&mut self) {
+        let (entities, slots) = (&mut self.entities, &self.slots);
+
+        self.magnets.retain_mut(|magnet| {
+            let Some(magnet_index) = slots.get(magnet.id) else {
+                return false;
+            };
+
+            // the binding goes away along with the held entity
+            magnet.held = magnet.held.filter(|&held| {
+                entities
+                    .iter()
+                    .any(|entity| entity.bindings.iter().any(|(id, ..)| *id == held))
+            });
+            if !magnet.is_powered || magnet.held.is_some() {
+                return true;
+            }
+
+            let magnet_shape = entities[magnet_index].shape.clone();
+            let Some(target_index) = entities.iter().position(|entity| {
+                entity.is_ferrous
+                    && entity.id != magnet.id
+                    && compute::intersects(&*magnet_shape.borrow(), &*entity.shape.borrow())
+            }) else {
+                return true;
+            };
+
+            // either entity could hold the binding, it goes to the one that
+            // comes first like the bindings placed by the player
+            let (first, second) = if magnet_index < target_index {
+                (magnet_index, target_index)
+            } else {
+                (target_index, magnet_index)
+            };
+            let second_shape = entities[second].shape.clone();
+            let at = entities[target_index]
+                .shape
+                .borrow_mut()
+                .collision_data_mut()
+                .centroid;
+            let binding = Binding::rigid_at(
+                &*entities[first].shape.borrow(),
+                &*second_shape.borrow(),
+                at,
+                0.0,
+            );
+
+            let id = self.binding_ids.generate();
+            entities[first]
+                .bindings
+                .push((id, binding, Rc::downgrade(&second_shape)));
+            magnet.held = Some(id);
+            self.events.push(GameEvent::MagnetAttached {
+                id: magnet.id,
+                target: entities[target_index].id,
+            });
+            true
+        });
+    }
+}
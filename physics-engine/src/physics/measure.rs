@@ -0,0 +1,111 @@
+//! Measuring the world, for the rulers of the editor and scripted win conditions
+//!
+//! A point is measured through a [`ShapePoint`], which refers to it relative
+//! to its entity the same way bindings refer to their anchors, so that it
+//! moves and turns along with the entity. A reference stops resolving once its
+//! entity is removed.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{binding::PointOnShape, ids::EntityId, Engine};
+use crate::geometry::{Point, Vector};
+
+/// A point fixed to an entity
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ShapePoint {
+    pub entity: EntityId,
+    pub point: PointOnShape,
+}
+
+impl Engine {
+    /// fixes `point` to the entity, wherever the point lies relative to it
+    pub fn point_on(&self, entity: EntityId, point: Point) -> Option<ShapePoint> {
+        let slot = self.slots.get(entity)?;
+        let shape = self.entities[slot].shape.borrow();
+        Some(ShapePoint {
+            entity,
+            point: shape.create_point_reference(point),
+        })
+    }
+
+    /// where the point is now, `None` if its entity no longer exists
+    pub fn resolve_point(&self, point: ShapePoint) -> Option<Point> {
+        let slot = self.slots.get(point.entity)?;
+        Some(point.point.on(&*self.entities[slot].shape.borrow()))
+    }
+
+    pub fn distance(&self, first: ShapePoint, second: ShapePoint) -> Option<f64> {
+        let (first, second) = (self.resolve_point(first)?, self.resolve_point(second)?);
+        Some(first.to(second).norm())
+    }
+
+    /// in radians, how far the entity turned since it was added
+    pub fn entity_angle(&self, id: EntityId) -> Option<f64> {
+        let slot = self.slots.get(id)?;
+        Some(self.entities[slot].shape.borrow().angle())
+    }
+
+    /// how far `first` could move along `direction` before the projections of
+    /// the two entities onto it meet, negative once they overlap along it.
+    /// `None` if either entity does not exist or the direction is zero
+    pub fn gap(&self, first: EntityId, second: EntityId, direction: Vector) -> Option<f64> {
+        let norm = direction.norm();
+        if norm == 0.0 || !norm.is_finite() {
+            return None;
+        }
+        let direction = direction / norm;
+
+        let (first, second) = (self.slots.get(first)?, self.slots.get(second)?);
+        let front = self.entities[first]
+            .shape
+            .borrow()
+            .support_vector(direction);
+        let back = self.entities[second]
+            .shape
+            .borrow()
+            .support_vector(-direction);
+        Some(front.to(back).dot(direction))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig};
+
+    #[test]
+    fn test_measure() {
+        let mut engine = Engine::sandbox(SandboxConfig {
+            world_half_extent: None,
+            ..Default::default()
+        });
+        let left = engine.add_prefab_polygon(
+            make_shape! { (-2.0, 1.0), (-0.5, 1.0), (-0.5, 1.2), (-2.0, 1.2) },
+            true,
+        );
+        let right = engine
+            .add_polygon(make_shape! { (0.3, 1.0), (2.0, 1.0), (2.0, 1.2), (0.3, 1.2) })
+            .unwrap();
+
+        assert!((engine.gap(left, right, Point(1.0, 0.0)).unwrap() - 0.8).abs() < 1e-9);
+        assert!(engine.gap(left, right, Point(0.0, 1.0)).unwrap() < 0.0);
+        assert_eq!(engine.gap(left, right, Point::ZERO), None);
+
+        let on_left = engine.point_on(left, Point(-0.5, 1.0)).unwrap();
+        let on_right = engine.point_on(right, Point(0.3, 1.0)).unwrap();
+        assert!((engine.distance(on_left, on_right).unwrap() - 0.8).abs() < 1e-9);
+
+        // the right plank falls, and the point on it along with it
+        engine.run_n_iterations(50, 2000.0);
+        let fallen = engine.resolve_point(on_right).unwrap();
+        assert!(fallen.1 > 1.0);
+        assert!(engine.distance(on_left, on_right).unwrap() > 0.8);
+        assert_eq!(engine.entity_angle(left), Some(0.0));
+
+        engine.erase(right);
+        assert_eq!(engine.resolve_point(on_right), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! Motors turning the shapes of a hinge against each other
+//!
+//! Like a torsion spring, a motor does not hold the entities together by
+//! itself and is placed alongside a hinge. Every step it exchanges just enough
+//! angular momentum between the two entities for the second one to spin at
+//! its speed relative to the first one.
+//!
+//! A hinge can also carry a motor of its own, which turns the entity drawn over
+//! the hinge relative to the one the hinge was placed on. Unlike the motors
+//! above, it only exerts up to its max torque, so a loaded wheel or windmill
+//! spins up gradually and may stall.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    binding::Binding,
+    ids::{BindingId, EntityId},
+    Engine, ANCHOR_PICK_RADIUS, MOVEMENT_COEFFICIENT,
+};
+use crate::geometry::Point;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Motor {
+    pub id: BindingId,
+    pub first: EntityId,
+    pub second: EntityId,
+    /// radians per second, positive turns from `+x` towards `+y`
+    pub speed: f64,
+}
+
+/// A motor built into a hinge
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct HingeMotor {
+    /// radians per second, positive turns from `+x` towards `+y`
+    pub speed: f64,
+    /// the most torque it exerts to reach its speed
+    pub max_torque: f64,
+}
+
+impl HingeMotor {
+    /// the angular velocity of the speed, in the units of the engine
+    pub(super) fn target_angular_velocity(&self) -> f64 {
+        self.speed / (MOVEMENT_COEFFICIENT * 1_000_000.0)
+    }
+}
+
+impl Engine {
+    /// adds a motor spinning `second` relative to `first`,
+    /// returns `None` if either entity does not exist
+    pub fn add_motor(
+        &mut self,
+        first: EntityId,
+        second: EntityId,
+        speed: f64,
+    ) -> Option<BindingId> {
+        let exists = |id| self.slots.get(id).is_some();
+        if !exists(first) || !exists(second) {
+            return None;
+        }
+
+        let id = self.binding_ids.generate();
+        self.motors.push(Motor {
+            id,
+            first,
+            second,
+            speed,
+        });
+        Some(id)
+    }
+
+    /// returns whether a motor with the given id exists
+    pub fn set_motor_speed(&mut self, id: BindingId, speed: f64) -> bool {
+        self.motors
+            .iter_mut()
+            .find(|motor| motor.id == id)
+            .map(|motor| motor.speed = speed)
+            .is_some()
+    }
+
+    /// puts a motor into the hinge nearest to the point, within [`ANCHOR_PICK_RADIUS`],
+    /// replacing the one it had. A zero max torque leaves the hinge turning freely.
+    /// Returns the id of the hinge, or `None` if there is no hinge there or the speed
+    /// or the max torque is not finite or the max torque is negative
+    pub fn set_hinge_motor(
+        &mut self,
+        point: Point,
+        speed: f64,
+        max_torque: f64,
+    ) -> Option<BindingId> {
+        if !(speed.is_finite() && max_torque.is_finite() && max_torque >= 0.0) {
+            return None;
+        }
+
+        let (slot, index) = self
+            .entities
+            .iter()
+            .enumerate()
+            .flat_map(|(slot, entity)| {
+                let shape = entity.shape.borrow();
+                entity
+                    .bindings
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (_, binding, _))| matches!(binding, Binding::Hinge { .. }))
+                    .map(|(index, (_, binding, _))| {
+                        (slot, index, binding.anchor(&*shape).to(point).norm())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|&(_, _, distance)| distance <= ANCHOR_PICK_RADIUS)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(slot, index, _)| (slot, index))?;
+
+        let (id, binding, _) = &mut self.entities[slot].bindings[index];
+        if let Binding::Hinge { motor, .. } = binding {
+            *motor = (max_torque > 0.0).then_some(HingeMotor { speed, max_torque });
+        }
+        Some(*id)
+    }
+
+    /// drives every motor to its speed, dropping the motors of removed entities
+    pub(super) fn enforce_motors(&mut self) {
+        let (entities, slots) = (&self.entities, &self.slots);
+        let find = |id| slots.get(id).map(|slot| &entities[slot]);
+
+        self.motors.retain(|motor| {
+            let (Some(first), Some(second)) = (find(motor.first), find(motor.second)) else {
+                return false;
+            };
+            let (mut first, mut second) = (first.shape.borrow_mut(), second.shape.borrow_mut());
+            let first = first.collision_data_mut();
+            let second = second.collision_data_mut();
+
+            let target = motor.speed / (MOVEMENT_COEFFICIENT * 1_000_000.0);
+            let error = target - (second.angular_velocity - first.angular_velocity);
+            let inverse_inertia = first.inertia.recip() + second.inertia.recip();
+            if inverse_inertia == 0.0 {
+                return true;
+            }
+            let impulse = error / inverse_inertia;
+            first.angular_velocity -= impulse / first.inertia;
+            second.angular_velocity += impulse / second.inertia;
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig, shape::Circle};
+
+    #[test]
+    fn test_hinge_motor() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (1.9, -1.1), (2.1, -1.1), (2.1, -0.9), (1.9, -0.9) },
+            true,
+        );
+        let hinge = engine.add_hinge(Point(2.0, -1.0), 0.0).unwrap();
+        let wheel = engine
+            .add_circle(Circle::new(Point(2.0, -1.0), 0.5))
+            .unwrap();
+        assert_eq!(engine.set_hinge_motor(Point(2.5, -1.0), 1.0, 1.0), None);
+        assert_eq!(engine.set_hinge_motor(Point(2.0, -1.0), 1.0, -1.0), None);
+
+        // a weak motor spins the wheel up gradually, a strong one right away
+        let speed = |engine: &Engine| {
+            let slot = engine.slots.get(wheel).unwrap();
+            engine.body_states()[slot].angular_velocity * MOVEMENT_COEFFICIENT * 1_000_000.0
+        };
+        assert_eq!(
+            engine.set_hinge_motor(Point(2.01, -1.0), 2.0, 1e-3),
+            Some(hinge)
+        );
+        engine.run_n_iterations(10, 2_000.0);
+        assert!((0.0..1.0).contains(&speed(&engine)), "{}", speed(&engine));
+        engine.set_hinge_motor(Point(2.0, -1.0), 2.0, 1e3);
+        engine.run_n_iterations(10, 2_000.0);
+        assert!((speed(&engine) - 2.0).abs() < 1e-6, "{}", speed(&engine));
+
+        engine.set_hinge_motor(Point(2.0, -1.0), 2.0, 0.0);
+        let Binding::Hinge { motor, .. } = engine.entities[1].bindings[0].1 else {
+            panic!("the hinge is gone");
+        };
+        assert_eq!(motor, None);
+    }
+}
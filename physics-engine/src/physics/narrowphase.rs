@@ -0,0 +1,341 @@
+//! Collision detection of the candidate pairs of a step
+//!
+//! Detection only reads the shapes, so it runs for every pair before any of
+//! them is resolved. With the `parallel` feature on native builds, the pairs
+//! are split among the threads of rayon; everywhere else they go through the
+//! same pipeline on a single thread. In wasm, the `threads` feature splits
+//! them among web workers instead, provided the host started a rayon pool on
+//! them (with `wasm-bindgen-rayon`, say) where SharedArrayBuffer is available.
+//! Where it is not, rayon falls back to the calling thread. Resolution stays
+//! sequential either way, as every contact changes the velocities the next
+//! one starts from.
+//!
+//! The contacts are thus detected where the shapes were at the start of the
+//! pass, not where the contacts resolved before them moved them. Resolving a
+//! contact pushes its shapes apart by at most `max_correction_per_microsecond`
+//! of [`SolverTuning`] over the step, 0.002 in a step of 2 ms, so a contact is
+//! off by that much for every contact of either of its shapes resolved before
+//! it, which the next step detects anyway. `test_stale_contacts` checks that
+//! against detecting every contact right before resolving it, like the pair
+//! loop did before detection was split off. The bindings enforced in the pass
+//! move their shapes too, the rigid ones by as little as they drifted apart.
+//!
+//! Everything the pair loop needs for a step lives in the scratch arena of the
+//! engine, which is reset at the start of every step rather than freed.
+//!
+//! GJK is warm started from the direction its search ended with for the same
+//! pair in the previous step. Shapes that are close without touching barely
+//! move between steps, so that direction usually still separates them and GJK
+//! stops after a single support query. Shapes in contact need a triangle around
+//! the origin either way, which takes GJK two iterations at best.
+//!
+//! [`SolverTuning`]: super::tuning::SolverTuning
+
+use std::{collections::HashMap, os::raw::c_void};
+
+use bumpalo::{collections::Vec as ScratchVec, Bump};
+#[cfg(any(
+    feature = "threads",
+    all(feature = "parallel", not(target_arch = "wasm32"))
+))]
+use rayon::prelude::*;
+
+use super::{
+    compute::{self, simplex::Vertex},
+    ids::EntityId,
+    perf,
+    shape::{self, Collidable},
+    Entity,
+};
+use crate::geometry::Vector;
+
+/// the last search direction of GJK for every pair that was a candidate in the last step
+#[derive(Default)]
+pub(super) struct WarmStarts(HashMap<(EntityId, EntityId), Vector>);
+
+impl WarmStarts {
+    /// sorted, so that equal caches serialize the same
+    pub fn to_vec(&self) -> Vec<(EntityId, EntityId, Vector)> {
+        let mut directions: Vec<_> = self
+            .0
+            .iter()
+            .map(|(&(first, second), &direction)| (first, second, direction))
+            .collect();
+        directions.sort_by_key(|&(first, second, _)| (first, second));
+        directions
+    }
+
+    pub fn from_vec(directions: Vec<(EntityId, EntityId, Vector)>) -> Self {
+        Self(
+            directions
+                .into_iter()
+                .map(|(first, second, direction)| ((first, second), direction))
+                .collect(),
+        )
+    }
+}
+
+/// every pair of entities whose bounding boxes and bounding circles overlap
+/// and that are not bound together, in either direction, as indices into `entities`,
+/// sorted by the first and then the second index, which is the order of their ids
+/// as long as the entities are in their canonical order.
+/// Drops the bindings to removed entities
+pub(super) fn candidate_pairs<'s>(
+    entities: &mut [Entity],
+    scratch: &'s Bump,
+) -> ScratchVec<'s, (usize, usize)> {
+    for this in entities.iter_mut() {
+        this.bindings
+            .retain(|(_, _, target)| target.strong_count() > 0);
+    }
+
+    let bounds = ScratchVec::from_iter_in(
+        entities.iter().map(|entity| {
+            let shape = entity.shape.borrow();
+            (shape.aabb(), shape.bounding_circle())
+        }),
+        scratch,
+    );
+
+    // removals may move the target of a binding before the entity holding it
+    let is_bound = |holder: &Entity, target: &Entity| {
+        holder.bindings.iter().any(|(_, _, bound)| {
+            std::ptr::eq(
+                bound.as_ptr() as *const c_void,
+                (&*target.shape) as *const _ as *const c_void,
+            )
+        })
+    };
+
+    let mut pairs = ScratchVec::new_in(scratch);
+    for (i, this) in entities.iter().enumerate() {
+        let (aabb, (center, radius)) = bounds[i];
+        for (j, other) in entities.iter().enumerate().skip(i + 1) {
+            // whichever of the two bounds is tighter depends on the shapes and their rotation
+            let (other_aabb, (other_center, other_radius)) = bounds[j];
+            let offset = center.to(other_center);
+            let reach = radius + other_radius;
+            if !aabb.overlaps(&other_aabb) || offset.dot(offset) > reach * reach {
+                continue;
+            }
+
+            if !is_bound(this, other) && !is_bound(other, this) {
+                pairs.push((i, j));
+            }
+        }
+    }
+
+    pairs
+}
+
+/// the contact of every pair, in the same order as the pairs.
+/// Replaces the warm starts with the ones of these pairs
+pub(super) fn contacts<'s>(
+    entities: &[Entity],
+    pairs: &[(usize, usize)],
+    warm_starts: &mut WarmStarts,
+    scratch: &'s Bump,
+) -> ScratchVec<'s, Option<Vertex>> {
+    let borrowed =
+        ScratchVec::from_iter_in(entities.iter().map(|entity| entity.shape.borrow()), scratch);
+    let shapes = ScratchVec::from_iter_in(
+        borrowed.iter().map(|shape| -> &dyn Collidable { &**shape }),
+        scratch,
+    );
+    // unlike the arena, a slice of them can be shared among threads
+    let shapes = shapes.as_slice();
+
+    // GJK counts its iterations per thread, so every pair takes the count of its own
+    let detect = |&(i, j): &(usize, usize), search_direction: &mut Vector| {
+        let contact = shape::contact(shapes[i], shapes[j], search_direction)
+            .filter(|contact| !contact.point.is_close_enough_to(Vector::ZERO));
+        (contact, perf::take_gjk_iterations())
+    };
+
+    let ids = |&(i, j): &(usize, usize)| (entities[i].id, entities[j].id);
+    let mut detected = ScratchVec::from_iter_in(
+        pairs.iter().map(|pair| {
+            let search_direction = warm_starts.0.get(&ids(pair)).copied();
+            (
+                None,
+                0,
+                search_direction.unwrap_or(compute::INITIAL_SEARCH_DIRECTION),
+            )
+        }),
+        scratch,
+    );
+    #[cfg(any(
+        feature = "threads",
+        all(feature = "parallel", not(target_arch = "wasm32"))
+    ))]
+    detected
+        .as_mut_slice()
+        .par_iter_mut()
+        .zip(pairs.par_iter())
+        .for_each(|((contact, gjk_iterations, search_direction), pair)| {
+            (*contact, *gjk_iterations) = detect(pair, search_direction)
+        });
+    #[cfg(not(any(
+        feature = "threads",
+        all(feature = "parallel", not(target_arch = "wasm32"))
+    )))]
+    detected.iter_mut().zip(pairs).for_each(
+        |((contact, gjk_iterations, search_direction), pair)| {
+            (*contact, *gjk_iterations) = detect(pair, search_direction)
+        },
+    );
+
+    // clearing keeps the capacity, so this stops allocating once every pair was seen
+    warm_starts.0.clear();
+    ScratchVec::from_iter_in(
+        detected.into_iter().zip(pairs).map(
+            |((contact, gjk_iterations, search_direction), pair)| {
+                perf::count_gjk_iterations(gjk_iterations);
+                warm_starts.0.insert(ids(pair), search_direction);
+                contact
+            },
+        ),
+        scratch,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        geometry::Point,
+        physics::{make_shape, sandbox::SandboxConfig, tuning::SolverTuning, Engine},
+    };
+
+    #[test]
+    fn test_candidate_pairs() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) });
+        engine.add_hinge(Point(1.5, 1.5), 0.0);
+        engine.add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) });
+        engine.add_polygon(make_shape! { (3.0, 3.0), (4.0, 3.0), (4.0, 4.0), (3.0, 4.0) });
+
+        engine.add_polygon(make_shape! { (1.5, 1.5), (3.5, 1.5), (3.5, 3.5), (1.5, 3.5) });
+
+        // only the bounding boxes of these overlap, at the corners
+        engine.add_polygon(make_shape! { (-3.0, -3.0), (-2.0, -3.0), (-3.0, -2.0) });
+        engine.add_polygon(make_shape! { (-1.1, -1.1), (-2.1, -1.1), (-1.1, -2.1) });
+
+        // the bound squares overlap, but are not candidates
+        let scratch = Bump::new();
+        let pairs = candidate_pairs(&mut engine.entities, &scratch);
+        assert_eq!(*pairs, [(1, 4), (2, 4), (3, 4)]);
+
+        let contacts = contacts(
+            &engine.entities,
+            &pairs,
+            &mut WarmStarts::default(),
+            &scratch,
+        );
+        assert!(contacts.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn test_stale_contacts() {
+        let mut engine = Engine::sandbox(SandboxConfig {
+            initial_ball_position: Point(-3.0, -3.0),
+            ..Default::default()
+        });
+        engine.add_prefab_polygon(
+            make_shape! { (-1.0, 1.0), (1.0, 1.0), (1.0, 1.2), (-1.0, 1.2) },
+            true,
+        );
+        for k in 0..5 {
+            let top = 1.0 - 0.31 * (k + 1) as f64;
+            engine.add_polygon(
+                make_shape! { (-0.15, top), (0.15, top), (0.15, top + 0.3), (-0.15, top + 0.3) },
+            );
+        }
+        let microseconds = 2_000.0;
+        engine.run_n_iterations(500, microseconds);
+
+        let scratch = Bump::new();
+        let pairs = candidate_pairs(&mut engine.entities, &scratch);
+        let stale = contacts(
+            &engine.entities,
+            &pairs,
+            &mut WarmStarts::default(),
+            &scratch,
+        );
+
+        // resolve the contacts of the stack detecting each right before resolving it
+        let tuning = SolverTuning::default();
+        let bound = tuning.max_correction_per_microsecond * microseconds;
+        let mut resolved = vec![0; engine.entities.len()];
+        let mut was_stale = false;
+        for (&(i, j), stale) in pairs.iter().zip(&*stale) {
+            let mut first = engine.entities[i].shape.borrow_mut();
+            let mut second = engine.entities[j].shape.borrow_mut();
+            let fresh = shape::contact(
+                &*first,
+                &*second,
+                &mut compute::INITIAL_SEARCH_DIRECTION.clone(),
+            )
+            .filter(|contact| !contact.point.is_close_enough_to(Vector::ZERO));
+
+            let point = |contact: Option<Vertex>| contact.map_or(Vector::ZERO, |c| c.point);
+            let offset = point(fresh).to(point(*stale)).norm();
+            let earlier = resolved[i] + resolved[j];
+            assert!(
+                offset <= earlier as f64 * bound + 1e-9,
+                "{offset} {earlier}"
+            );
+            was_stale |= earlier > 0 && stale.is_some();
+
+            if let Some(contact) = fresh {
+                first.resolve_collision_with(
+                    &mut *second,
+                    contact,
+                    microseconds,
+                    &tuning,
+                    1.0,
+                    1.0,
+                    true,
+                    true,
+                );
+                resolved[i] += 1;
+                resolved[j] += 1;
+            }
+        }
+        assert!(was_stale);
+    }
+
+    #[test]
+    fn test_warm_starts() {
+        let mut engine = Engine::sandbox(SandboxConfig {
+            initial_ball_position: Point(0.0, -3.0),
+            ..Default::default()
+        });
+        engine.add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) });
+        engine.add_polygon(make_shape! { (1.5, 1.9), (2.5, 1.9), (2.5, 2.9), (1.5, 2.9) });
+        // its bounding box overlaps the one of the first square, but the shapes do not touch
+        engine.add_polygon(make_shape! { (0.0, 0.0), (1.6, 0.0), (0.0, 1.6) });
+
+        let scratch = Bump::new();
+        let pairs = candidate_pairs(&mut engine.entities, &scratch);
+        let mut warm_starts = WarmStarts::default();
+        let mut detect = || {
+            perf::take_gjk_iterations();
+            let contacts = contacts(&engine.entities, &pairs, &mut warm_starts, &scratch);
+            let contacts: Vec<_> = contacts
+                .iter()
+                .map(|contact| contact.map(|c| c.point))
+                .collect();
+            (contacts, perf::take_gjk_iterations())
+        };
+
+        let (cold, cold_iterations) = detect();
+        let (warm, warm_iterations) = detect();
+        assert_eq!(warm, cold);
+        assert!(cold.iter().any(Option::is_some));
+        assert!(cold.iter().any(Option::is_none));
+        // nothing moved, so the search for the shapes that do not touch ends right away
+        assert!(warm_iterations < cold_iterations);
+        assert_eq!(warm_starts.0.len(), pairs.len());
+    }
+}
@@ -0,0 +1,128 @@
+//! Rebasing the coordinates of long levels
+//!
+//! Every position the engine takes or hands out is relative to its origin,
+//! which starts at the origin of the level. Shifting it moves everything the
+//! engine knows about by the same offset, so that the coordinates around the
+//! ball stay small even far into a scrolling level, where a frontend drawing
+//! in `f32` would otherwise lose precision. The world bounds stay where the
+//! level put them.
+
+use super::{shape::Collidable, Engine};
+use crate::geometry::{Point, Vector};
+
+impl Engine {
+    /// the position of the origin of the engine in the coordinates of the level
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    /// moves the origin by `offset`, which is relative to the current origin.
+    /// Everything keeps its place in the level, so its coordinates change by `-offset`
+    pub fn shift_origin(&mut self, offset: Vector) {
+        self.origin += offset;
+        let translation = -offset;
+
+        for entity in &mut self.entities {
+            entity.shape.borrow_mut().translate(translation);
+            for (_, binding, _) in &mut entity.bindings {
+                binding.translate(translation);
+            }
+        }
+        for flag in &mut self.flags {
+            flag.translate(translation);
+        }
+        for soft_body in &mut self.soft_bodies {
+            soft_body.shape.translate(translation);
+        }
+        self.particles.translate(translation);
+        for cloth in &mut self.cloths {
+            cloth.translate(translation);
+        }
+        for laser in &mut self.lasers {
+            laser.start += translation;
+            laser.end += translation;
+        }
+        for fan in &mut self.fans {
+            fan.position += translation;
+        }
+        for dispenser in &mut self.dispensers {
+            dispenser.position += translation;
+        }
+        for zone in &mut self.time_zones {
+            zone.shift(translation);
+        }
+        self.main_ball_starting_position += translation;
+        if let Some(grab) = &mut self.grab {
+            grab.pointer += translation;
+        }
+        self.gravity.shift(translation);
+        self.ball_size.shift(translation);
+        self.hints.shift(translation);
+        self.ghost.shift(translation);
+        self.trails.shift(translation);
+    }
+
+    /// shifts the origin onto the main ball whenever the ball gets farther
+    /// than `distance` from it, `None` to keep the origin where it is
+    pub fn set_origin_shift_distance(&mut self, distance: Option<f64>) {
+        self.origin_shift_distance = distance;
+    }
+
+    pub(super) fn follow_ball_with_origin(&mut self) {
+        let Some(distance) = self.origin_shift_distance else {
+            return;
+        };
+
+        let ball = self.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .centroid;
+        if ball.norm() > distance {
+            self.shift_origin(ball);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig, Engine};
+
+    #[test]
+    fn test_shift_origin() {
+        let mut shifted = Engine::sandbox(SandboxConfig::default());
+        let mut unshifted = Engine::sandbox(SandboxConfig::default());
+        for engine in [&mut shifted, &mut unshifted] {
+            engine.add_polygon(make_shape! { (-1.0, 1.0), (1.0, 1.0), (1.0, 1.2), (-1.0, 1.2) });
+            engine.add_hinge(Point(-0.9, 1.1), 0.0);
+            engine.add_hinge(Point(0.9, 1.1), 0.0);
+        }
+
+        shifted.shift_origin(Point(3.0, -2.0));
+        assert_eq!(shifted.origin(), Point(3.0, -2.0));
+
+        let shifted_message = shifted.run_n_iterations(30, 2000.0);
+        let message = unshifted.run_n_iterations(30, 2000.0);
+        assert_eq!(shifted_message.origin, Point(3.0, -2.0));
+        for (shifted, unshifted) in shifted_message.circles.iter().zip(&message.circles) {
+            let in_level = shifted.shape.center + Point(3.0, -2.0);
+            assert!(in_level.is_close_enough_to(unshifted.shape.center));
+        }
+    }
+
+    #[test]
+    fn test_follow_ball() {
+        let mut engine = Engine::sandbox(SandboxConfig {
+            world_half_extent: None,
+            ..Default::default()
+        });
+        engine.set_origin_shift_distance(Some(1.0));
+
+        // the ball falls freely, so the origin keeps catching up with it
+        let message = engine.run_n_iterations(500, 2000.0);
+        let ball = message.circles[0].shape.center;
+        assert!(ball.norm() <= 1.0);
+        assert!(message.origin.1 > 1.0);
+    }
+}
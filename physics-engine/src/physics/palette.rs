@@ -0,0 +1,160 @@
+//! The colors new shapes are painted with
+//!
+//! Every shape the engine adds gets a color picked at random from the palette,
+//! lightened or darkened by up to the shade variation so that neighbouring
+//! shapes of the same color still stand apart. The picks come from a generator
+//! that only depends on how many shapes the engine painted before, so two
+//! engines built the same way look the same, and a restored snapshot goes on
+//! painting where the original left off. An empty palette picks any color.
+//!
+//! The palette is a host setting, it carries over to the engine the level
+//! editor reloads, see [`super::editor`].
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{splitmix64, Engine, WithColor};
+
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Missing fields take their default values
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct Palette {
+    /// RGB colors with channels in `0.0..=1.0`
+    pub colors: Vec<[f32; 3]>,
+    /// how far a picked color may be lightened or darkened, in `0.0..=1.0`
+    pub shade_variation: f32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            colors: vec![
+                [0.93, 0.42, 0.37],
+                [0.98, 0.75, 0.33],
+                [0.55, 0.80, 0.45],
+                [0.40, 0.78, 0.70],
+                [0.33, 0.70, 0.82],
+                [0.45, 0.50, 0.85],
+                [0.75, 0.48, 0.80],
+                [0.95, 0.60, 0.70],
+            ],
+            shade_variation: 0.15,
+        }
+    }
+}
+
+/// The palette along with the number of shapes painted from it
+#[derive(Clone, Debug, Default)]
+pub(super) struct Painter {
+    pub(super) palette: Palette,
+    pub(super) painted: u32,
+}
+
+impl Painter {
+    pub(super) fn paint<S>(&mut self, shape: S) -> WithColor<S> {
+        let mut state = SEED.wrapping_add(u64::from(self.painted));
+        // consecutive counts would start out correlated otherwise
+        state = splitmix64(&mut state);
+        self.painted = self.painted.wrapping_add(1);
+        // the top 24 bits fill the mantissa of an f32 exactly
+        let mut random = || (splitmix64(&mut state) >> 40) as f32 / (1 << 24) as f32;
+
+        let colors = &self.palette.colors;
+        let color = if colors.is_empty() {
+            [random(), random(), random()]
+        } else {
+            let picked = colors[(random() * colors.len() as f32) as usize % colors.len()];
+            let variation = self.palette.shade_variation.clamp(0.0, 1.0);
+            let shade = (random() * 2.0 - 1.0) * variation;
+            picked.map(|channel| {
+                let channel = channel.clamp(0.0, 1.0);
+                if shade > 0.0 {
+                    channel + (1.0 - channel) * shade
+                } else {
+                    channel * (1.0 + shade)
+                }
+            })
+        };
+
+        WithColor { color, shape }
+    }
+}
+
+impl Engine {
+    pub fn palette(&self) -> Palette {
+        self.painter.palette.clone()
+    }
+
+    /// paints the shapes added from now on from `palette`, the ones already
+    /// there keep their colors
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.painter.palette = palette;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{sandbox::SandboxConfig, snapshot::Snapshot};
+
+    fn colors(painter: &mut Painter, count: usize) -> Vec<[f32; 3]> {
+        (0..count).map(|_| painter.paint(()).color).collect()
+    }
+
+    #[test]
+    fn test_palette() {
+        let mut painter = Painter::default();
+        let colors_of_palette = colors(&mut painter, 100);
+        let palette = Palette::default();
+        for color in &colors_of_palette {
+            // every color is a shade of one of the palette
+            assert!(palette.colors.iter().any(|picked| {
+                let lightened = (0..3).all(|i| {
+                    let shade = (color[i] - picked[i]) / (1.0 - picked[i]);
+                    (0.0..=0.15 + 1e-6).contains(&shade)
+                });
+                let darkened = (0..3).all(|i| {
+                    let shade = 1.0 - color[i] / picked[i];
+                    (0.0..=0.15 + 1e-6).contains(&shade)
+                });
+                lightened || darkened
+            }));
+        }
+        assert!(colors_of_palette.windows(2).all(|pair| pair[0] != pair[1]));
+        // the same picks, in the same order
+        assert_eq!(colors(&mut Painter::default(), 100), colors_of_palette);
+
+        let mut any = Painter {
+            palette: Palette {
+                colors: vec![],
+                ..Palette::default()
+            },
+            painted: 0,
+        };
+        let any_colors = colors(&mut any, 100);
+        assert!(any_colors
+            .iter()
+            .flatten()
+            .all(|channel| (0.0..1.0).contains(channel)));
+        assert!(any_colors.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn test_painting_continues_after_restore() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.set_palette(Palette {
+            colors: vec![[0.2, 0.4, 0.6], [0.8, 0.6, 0.4]],
+            shade_variation: 0.0,
+        });
+        let mut restored = Engine::from(Snapshot::from(&engine));
+        assert_eq!(restored.palette(), engine.palette());
+        assert_eq!(
+            colors(&mut restored.painter, 10),
+            colors(&mut engine.painter, 10)
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Particles, grains of sand or drops of water poured into the level
+//!
+//! Particles are small circles of [`PARTICLE_RADIUS`] simulated apart from the
+//! entities, as there are far too many of them to go through the pairs of the
+//! broad phase. Every step is split into substeps; in each of them the
+//! particles move, are pushed apart from the ones they overlap, found through
+//! a grid of cells as wide as a particle, and then collide with the entities
+//! through the same solver the entities use, so that they pour into containers
+//! and push bodies around as much as bodies push them. Sand keeps the grains
+//! from sliding along each other and piles up, water does not and spreads out.
+//!
+//! Like soft bodies, particles are only subject to gravity at a distance.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    shape::{Circle, Collidable},
+    tuning::SolverTuning,
+    Engine, GRAVITY_COEFFICIENT, MOVEMENT_COEFFICIENT,
+};
+use crate::geometry::{Aabb, Point, Vector};
+
+pub const PARTICLE_RADIUS: f64 = 0.01;
+/// the longest substep, short enough for falling particles not to slip between touching entities
+const MAX_SUBSTEP_MICROSECONDS: f64 = 500.0;
+/// how many times per substep the particles are pushed out of each other and the entities
+const ITERATIONS: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum ParticleKind {
+    Sand,
+    Water,
+}
+
+impl ParticleKind {
+    /// the part of the sliding between two touching particles that is undone
+    fn friction(self) -> f64 {
+        match self {
+            ParticleKind::Sand => 1.0,
+            ParticleKind::Water => 0.0,
+        }
+    }
+
+    /// multiplies the friction with the entities, which grows with the depth
+    /// of the contact and so is next to none for particles this small
+    fn friction_with_entities(self) -> f64 {
+        match self {
+            ParticleKind::Sand => 20.0,
+            ParticleKind::Water => 1.0,
+        }
+    }
+}
+
+/// The key of the grid cell a point is in, ordered by column, then row
+fn cell_key(Point(x, y): Point) -> u64 {
+    let cell = |coordinate: f64| {
+        let index = (coordinate / (2.0 * PARTICLE_RADIUS)).floor() as i32;
+        (index as i64 - i32::MIN as i64) as u64
+    };
+    (cell(x) << 32) | cell(y)
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Particles {
+    positions: Vec<Point>,
+    velocities: Vec<Vector>,
+    kinds: Vec<ParticleKind>,
+    /// the cell keys of the particles with their indices, sorted,
+    /// rebuilt every substep and kept to reuse its buffer
+    #[serde(skip)]
+    grid: Vec<(u64, u32)>,
+}
+
+impl Particles {
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub(super) fn translate(&mut self, translation: Vector) {
+        for position in &mut self.positions {
+            *position += translation;
+        }
+    }
+
+    fn push(&mut self, kind: ParticleKind, position: Point) {
+        self.positions.push(position);
+        self.velocities.push(Vector::ZERO);
+        self.kinds.push(kind);
+    }
+
+    fn rebuild_grid(&mut self) {
+        self.grid.clear();
+        let keys = self.positions.iter().map(|&position| cell_key(position));
+        self.grid.extend(keys.zip(0..));
+        self.grid.sort_unstable();
+    }
+
+    /// pushes overlapping particles apart, stopping them from moving into each other
+    /// and undoing some of their sliding along each other
+    fn separate(&mut self) {
+        let spacing = 2.0 * PARTICLE_RADIUS;
+        for i in 0..self.positions.len() {
+            let position = self.positions[i];
+            let key = cell_key(position);
+            for column in [key - (1 << 32), key, key + (1 << 32)] {
+                let start = self.grid.partition_point(|&(key, _)| key < column - 1);
+                let cells = self.grid[start..]
+                    .iter()
+                    .take_while(|&&(key, _)| key <= column + 1);
+                for &(_, j) in cells {
+                    let j = j as usize;
+                    if j <= i {
+                        continue;
+                    }
+                    let offset = self.positions[i].to(self.positions[j]);
+                    let distance = offset.norm();
+                    if distance >= spacing || distance == 0.0 {
+                        continue;
+                    }
+                    let normal = offset / distance;
+                    let push = normal * ((spacing - distance) / 2.0);
+                    let friction = self.kinds[i].friction().min(self.kinds[j].friction());
+                    let relative = self.velocities[j] - self.velocities[i];
+                    let approaching = relative.dot(normal).min(0.0);
+                    let sliding = relative - normal * relative.dot(normal);
+                    let change = (normal * approaching + sliding * friction) / 2.0;
+                    self.positions[i] -= push;
+                    self.positions[j] += push;
+                    self.velocities[i] += change;
+                    self.velocities[j] -= change;
+                }
+            }
+        }
+    }
+
+    /// applies gravity and moves the particles, then finds them in the grid again
+    fn advance(&mut self, microseconds: f64, gravity: Vector) {
+        for (position, velocity) in self.positions.iter_mut().zip(&mut self.velocities) {
+            *velocity += gravity * (GRAVITY_COEFFICIENT * microseconds);
+            *position += *velocity * (MOVEMENT_COEFFICIENT * microseconds);
+        }
+        self.rebuild_grid();
+    }
+}
+
+impl Engine {
+    /// adds particles at the positions, as many as fit within
+    /// [`super::capacity::EngineConfig::max_particles`], returning how many did
+    pub fn add_particles(
+        &mut self,
+        kind: ParticleKind,
+        positions: impl IntoIterator<Item = Point>,
+    ) -> usize {
+        let room = self
+            .config
+            .max_particles
+            .map_or(usize::MAX, |cap| cap.saturating_sub(self.particles.len()));
+        let mut added = 0;
+        for position in positions.into_iter().take(room) {
+            self.particles.push(kind, position);
+            added += 1;
+        }
+        added
+    }
+
+    /// fills the rectangle between the corners with particles packed in a grid,
+    /// returning how many were added
+    pub fn add_particle_block(
+        &mut self,
+        kind: ParticleKind,
+        corner: Point,
+        opposite: Point,
+    ) -> usize {
+        let aabb = Aabb::around([corner, opposite]);
+        let spacing = 2.0 * PARTICLE_RADIUS;
+        // sides of a multiple of the spacing are not shortened by rounding errors
+        let columns = ((aabb.max.0 - aabb.min.0) / spacing + 1e-9).floor() as usize;
+        let rows = ((aabb.max.1 - aabb.min.1) / spacing + 1e-9).floor() as usize;
+        let first = aabb.min + Point(PARTICLE_RADIUS, PARTICLE_RADIUS);
+        let positions = (0..rows).flat_map(|row| {
+            (0..columns)
+                .map(move |column| first + Point(column as f64 * spacing, row as f64 * spacing))
+        });
+        self.add_particles(kind, positions)
+    }
+
+    pub fn clear_particles(&mut self) {
+        self.particles = Particles::default();
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// moves the particles through the step and collides them with the
+    /// entities, removing the ones out of bounds like the entities
+    pub(super) fn step_particles(&mut self, microseconds: f64, gravity: Vector) {
+        if self.particles.is_empty() {
+            return;
+        }
+        let substeps = (microseconds / MAX_SUBSTEP_MICROSECONDS).ceil().max(1.0);
+        let microseconds = microseconds / substeps;
+        // overlaps are corrected at once, or the weight of a pile pushes its bottom through the floor
+        let tuning = SolverTuning {
+            max_correction_per_microsecond: f64::INFINITY,
+            ..self.solver_tuning
+        };
+
+        for _ in 0..substeps as u32 {
+            self.particles.advance(microseconds, gravity);
+            for _ in 0..ITERATIONS {
+                self.particles.separate();
+                self.collide_particles(microseconds, &tuning);
+            }
+        }
+
+        if let Some(extent) = self.world_half_extent {
+            let origin = self.origin;
+            let particles = &mut self.particles;
+            let mut i = 0;
+            while i < particles.positions.len() {
+                if (particles.positions[i] + origin).1 > -extent {
+                    i += 1;
+                    continue;
+                }
+                particles.positions.swap_remove(i);
+                particles.velocities.swap_remove(i);
+                particles.kinds.swap_remove(i);
+            }
+        }
+    }
+
+    /// collides the particles with the entities they are close to
+    fn collide_particles(&mut self, microseconds: f64, tuning: &SolverTuning) {
+        let extent = Point(PARTICLE_RADIUS, PARTICLE_RADIUS);
+        let particles = &mut self.particles;
+        for entity in &self.entities {
+            let mut shape = entity.shape.borrow_mut();
+            let aabb = shape.aabb();
+            let aabb = Aabb {
+                min: aabb.min - extent,
+                max: aabb.max + extent,
+            };
+            let moving = particles
+                .positions
+                .iter_mut()
+                .zip(&mut particles.velocities)
+                .zip(&particles.kinds);
+            for ((position, velocity), kind) in moving {
+                if !aabb.contains(*position) {
+                    continue;
+                }
+                let mut circle = Circle::new(*position, PARTICLE_RADIUS);
+                let data = circle.collision_data_mut();
+                data.inertia = f64::INFINITY;
+                data.velocity = *velocity;
+                circle.collide(
+                    &mut *shape,
+                    microseconds,
+                    tuning,
+                    self.restitution_mulipiler,
+                    self.friction_mulipiler * kind.friction_with_entities(),
+                    self.static_friction_enabled,
+                    self.dynamic_friction_enabled,
+                );
+                let data = circle.collision_data_mut();
+                *position = data.centroid;
+                *velocity = data.velocity;
+            }
+        }
+    }
+
+    /// writes the positions as `x, y` pairs and the kinds in the same order
+    pub(super) fn write_particles(&self, positions: &mut Vec<f64>, kinds: &mut Vec<ParticleKind>) {
+        positions.clear();
+        positions.extend(
+            self.particles
+                .positions
+                .iter()
+                .flat_map(|position| [position.0, position.1]),
+        );
+        kinds.clear();
+        kinds.extend_from_slice(&self.particles.kinds);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{capacity::EngineConfig, make_shape, sandbox::SandboxConfig};
+
+    /// a cup open at the top, 0.6 wide on the inside, with its floor at y 1.0
+    fn engine_with_cup() -> Engine {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        for wall in [
+            make_shape! { (-0.3, 1.0), (0.3, 1.0), (0.3, 1.1), (-0.3, 1.1) },
+            make_shape! { (-0.4, 0.0), (-0.3, 0.0), (-0.3, 1.1), (-0.4, 1.1) },
+            make_shape! { (0.3, 0.0), (0.4, 0.0), (0.4, 1.1), (0.3, 1.1) },
+        ] {
+            engine.add_prefab_polygon(wall, true);
+        }
+        engine
+    }
+
+    #[test]
+    fn test_particles_fill_a_cup() {
+        for kind in [ParticleKind::Sand, ParticleKind::Water] {
+            let mut engine = engine_with_cup();
+            let added = engine.add_particle_block(kind, Point(-0.2, 0.2), Point(0.2, 0.4));
+            assert_eq!(added, 20 * 10);
+
+            let message = engine.run_n_iterations(400, 2000.0);
+            assert_eq!(message.particles.len(), 2 * added);
+            assert_eq!(message.particle_kinds, vec![kind; added]);
+            for position in message.particles.chunks(2) {
+                // inside the cup, and not sunk into its floor
+                assert!(position[0].abs() < 0.3, "{kind:?} {position:?}");
+                assert!(
+                    position[1] < 1.0 && position[1] > 0.5,
+                    "{kind:?} {position:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sand_piles_up_and_water_spreads() {
+        let height = |kind| {
+            let mut engine = Engine::sandbox(SandboxConfig::default());
+            engine.add_prefab_polygon(
+                make_shape! { (-3.0, 1.0), (3.0, 1.0), (3.0, 1.2), (-3.0, 1.2) },
+                true,
+            );
+            engine.add_particle_block(kind, Point(-0.05, 0.4), Point(0.05, 0.8));
+            let message = engine.run_n_iterations(500, 2000.0);
+            let top = message.particles.chunks(2).map(|position| position[1]);
+            1.0 - top.fold(f64::INFINITY, f64::min)
+        };
+        assert!(height(ParticleKind::Sand) > height(ParticleKind::Water) + 0.02);
+    }
+
+    #[test]
+    fn test_particles_push_bodies() {
+        let mut engine = engine_with_cup();
+        let lid = engine
+            .add_polygon(make_shape! { (-0.29, 0.8), (0.29, 0.8), (0.29, 0.85), (-0.29, 0.85) })
+            .unwrap();
+        engine.add_particle_block(ParticleKind::Water, Point(-0.3, 0.86), Point(0.3, 1.0));
+        engine.run_n_iterations(200, 2000.0);
+        // too narrow a gap for the water to get past, the lid stays on top of it
+        let slot = engine.slots.get(lid).unwrap();
+        assert!(engine.body_states()[slot].centroid.1 < 0.9);
+    }
+
+    #[test]
+    fn test_max_particles() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.set_config(EngineConfig {
+            max_particles: Some(3),
+            ..Default::default()
+        });
+        let positions = [Point(0.0, 0.0), Point(0.1, 0.0)];
+        assert_eq!(engine.add_particles(ParticleKind::Sand, positions), 2);
+        assert_eq!(engine.add_particles(ParticleKind::Water, positions), 1);
+        assert_eq!(engine.particle_count(), 3);
+        engine.clear_particles();
+        assert_eq!(engine.particle_count(), 0);
+    }
+}
@@ -0,0 +1,61 @@
+//! Timings and counters of the last frame, for tuning the pair loop and solver
+
+use std::cell::Cell;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct PerfStats {
+    /// number of steps simulated during the frame
+    pub steps: u32,
+    pub integration_ms: f64,
+    /// narrowphase of the candidate pairs, then contact resolution
+    /// and binding enforcement, which are interleaved in the pair loop
+    pub pairs_ms: f64,
+    pub display_ms: f64,
+    pub total_ms: f64,
+    /// pairs passed on to the narrowphase, as their bounding boxes and circles overlap
+    pub pairs_tested: u32,
+    pub gjk_iterations: u32,
+    pub contacts_resolved: u32,
+    pub bindings_enforced: u32,
+}
+
+thread_local! {
+    // GJK runs too deep in `compute` to thread a counter through it
+    static GJK_ITERATIONS: Cell<u32> = const { Cell::new(0) };
+}
+
+pub fn count_gjk_iterations(count: u32) {
+    GJK_ITERATIONS.with(|iterations| iterations.set(iterations.get() + count));
+}
+
+pub fn take_gjk_iterations() -> u32 {
+    GJK_ITERATIONS.with(|iterations| iterations.replace(0))
+}
+
+/// milliseconds since an arbitrary, fixed point in time
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub fn now() -> f64 {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = performance)]
+        fn now() -> f64;
+    }
+
+    now()
+}
+
+/// milliseconds since an arbitrary, fixed point in time
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+pub fn now() -> f64 {
+    use std::{sync::OnceLock, time::Instant};
+
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
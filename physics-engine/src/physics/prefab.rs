@@ -0,0 +1,401 @@
+//! Ready-made contraptions built from several entities and bindings in one call
+//!
+//! Prefabs are placed by level designers and the editor rather than drawn by
+//! the player, so they do not count towards the tool usage of the level.
+
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    binding::Binding,
+    curve::ChainPath,
+    ids::{BindingId, EntityId},
+    shape::{Circle, Collidable, Polygon},
+    Engine, EntityCfg, MOVEMENT_COEFFICIENT,
+};
+use crate::{
+    geometry::{Point, Vector},
+    levels,
+};
+
+/// a plank this many times longer than it is thick rests on its pivot
+/// without sagging into it
+const PLANK_ASPECT_RATIO: f64 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Seesaw {
+    pub pivot: EntityId,
+    pub plank: EntityId,
+    pub hinge: BindingId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Catapult {
+    pub pivot: EntityId,
+    pub arm: EntityId,
+    pub bucket: EntityId,
+    pub hinge: BindingId,
+    pub rigid: BindingId,
+    pub spring: BindingId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Vehicle {
+    pub body: EntityId,
+    /// in the order of the wheel positions they were built from
+    pub wheels: Vec<EntityId>,
+    pub hinges: Vec<BindingId>,
+    pub motors: Vec<BindingId>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct WreckingBall {
+    pub anchor: EntityId,
+    pub rod: EntityId,
+    pub ball: EntityId,
+    pub hinge: BindingId,
+    pub rigid: BindingId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Chain {
+    /// from the first point to the last one
+    pub links: Vec<EntityId>,
+    /// between every link and the next one
+    pub hinges: Vec<BindingId>,
+    /// hinging the first and the last link to the entities under the ends of the chain
+    pub ends: [Option<BindingId>; 2],
+}
+
+/// how far the hinges of a chain give per unit of force. Holding every link to the
+/// next one stiffly, the solver lets a chain of more than a link or two come apart
+const CHAIN_COMPLIANCE: f64 = 0.001;
+
+/// how many times heavier than other shapes of its size a wrecking ball is
+pub const WRECKING_BALL_DENSITY: f64 = 10.0;
+
+/// how many times heavier than the ball the rod of a wrecking ball is
+const ROD_TO_BALL_MASS: f64 = 4.0;
+
+/// how far the spring of a catapult swings the arm up from its cocked position
+pub const CATAPULT_SWING: f64 = std::f64::consts::FRAC_PI_2;
+
+/// a rectangle of the given size centered on `center` and rotated by `angle`
+pub(super) fn rectangle(center: Point, length: f64, thickness: f64, angle: f64) -> Polygon {
+    let along: Vector = Point(length / 2.0, 0.0).rotate(angle);
+    let across: Vector = Point(0.0, thickness / 2.0).rotate(angle);
+
+    Polygon::new(vec![
+        center - along - across,
+        center + along - across,
+        center + along + across,
+        center - along + across,
+    ])
+}
+
+fn scale_mass(shape: &mut dyn Collidable, factor: f64) {
+    let data = shape.collision_data_mut();
+    data.mass *= factor;
+    data.inertia *= factor;
+}
+
+impl Engine {
+    pub(super) fn add_prefab_polygon(&mut self, polygon: Polygon, is_static: bool) -> EntityId {
+        let (id, weak) = self.add_entity(
+            polygon,
+            EntityCfg {
+                is_static,
+                ..Default::default()
+            },
+        );
+        self.polygons.push(self.painter.paint(weak));
+        id
+    }
+
+    pub(super) fn add_prefab_circle(&mut self, circle: Circle, is_static: bool) -> EntityId {
+        let (id, weak) = self.add_entity(
+            circle,
+            EntityCfg {
+                is_static,
+                ..Default::default()
+            },
+        );
+        self.circles.push(self.painter.paint(weak));
+        id
+    }
+
+    /// attaches a hinge to the last added entity, which binds to the next one added over it
+    pub(super) fn add_prefab_hinge(&mut self, at: Point) -> BindingId {
+        let id = self.binding_ids.generate();
+        self.entities
+            .last_mut()
+            .expect("the main ball is always present")
+            .add_hinge(id, at, 0.0);
+        id
+    }
+
+    /// hinges the entity in `slot` to the one in `target` at `at` with the compliance
+    /// of a chain, without waiting for either of them to be added over the other
+    fn bind_chain_hinge(&mut self, slot: usize, target: usize, at: Point) -> BindingId {
+        let id = self.binding_ids.generate();
+        let target = Rc::clone(&self.entities[target].shape);
+        let entity = &mut self.entities[slot];
+        let binding = Binding::hinge_at(
+            &*entity.shape.borrow(),
+            &*target.borrow(),
+            at,
+            CHAIN_COMPLIANCE,
+        );
+        entity.bindings.push((id, binding, Rc::downgrade(&target)));
+        id
+    }
+
+    /// attaches a rigid binding to the last added entity, which binds to the next one added over it
+    pub(super) fn add_prefab_rigid(&mut self, at: Point) -> BindingId {
+        let id = self.binding_ids.generate();
+        self.entities
+            .last_mut()
+            .expect("the main ball is always present")
+            .add_rigid(id, at, 0.0);
+        id
+    }
+
+    /// Adds a plank of the given length centered on a static pivot at `center`,
+    /// hinged to it and tilted by `angle`
+    pub fn add_seesaw(&mut self, center: Point, length: f64, angle: f64) -> Seesaw {
+        let thickness = length / PLANK_ASPECT_RATIO;
+
+        let pivot = self.add_prefab_circle(Circle::new(center, thickness), true);
+        let hinge = self.add_prefab_hinge(center);
+        let plank = self.add_prefab_polygon(rectangle(center, length, thickness, angle), false);
+
+        Seesaw {
+            pivot,
+            plank,
+            hinge,
+        }
+    }
+
+    /// Adds a cocked catapult with its pivot at `pivot_position`, an arm `size` long
+    /// pointing towards positive `x`, and a bucket at its far end. A torsion spring of
+    /// the given stiffness swings the arm up by [`CATAPULT_SWING`] once the simulation runs
+    pub fn add_catapult(&mut self, pivot_position: Point, size: f64, strength: f64) -> Catapult {
+        let thickness = size / PLANK_ASPECT_RATIO;
+        // the pivot sits a quarter of the way along the arm, leaving a long end to throw with
+        let arm_center = pivot_position + Point(size / 4.0, 0.0);
+        let bucket_width = size / 5.0;
+        let bucket_height = size / 10.0;
+        let bucket_center =
+            pivot_position + Point(size * 3.0 / 4.0 - bucket_width / 2.0, -bucket_height / 2.0);
+
+        let pivot = self.add_prefab_circle(Circle::new(pivot_position, thickness), true);
+        let hinge = self.add_prefab_hinge(pivot_position);
+        let arm = self.add_prefab_polygon(rectangle(arm_center, size, thickness, 0.0), false);
+
+        // the bucket overlaps the upper half of the arm, where the rigid binding holds it
+        let rigid =
+            self.add_prefab_rigid(Point(bucket_center.0, pivot_position.1 - thickness / 4.0));
+        let bucket = self.add_prefab_polygon(
+            rectangle(bucket_center, bucket_width, bucket_height, 0.0),
+            false,
+        );
+
+        let spring = self
+            .add_torsion_spring(pivot, arm, -CATAPULT_SWING, strength, strength / 10.0)
+            .expect("both entities have just been added");
+
+        Catapult {
+            pivot,
+            arm,
+            bucket,
+            hinge,
+            rigid,
+            spring,
+        }
+    }
+
+    /// Adds a wrecking ball hung from a static anchor on a rod, which it swings
+    /// around at the given speed along with the rod
+    pub fn add_wrecking_ball(&mut self, wrecking_ball: &levels::WreckingBall) -> WreckingBall {
+        let levels::WreckingBall {
+            anchor: anchor_position,
+            position,
+            radius,
+            swing_speed,
+        } = *wrecking_ball;
+        let thickness = radius / 4.0;
+        let along = anchor_position.to(position);
+
+        // bindings are soft and enforced in the order their entities were added. The parts
+        // are added from the ball up, leaving the static anchor the last word on where the
+        // rod is, and the rod outweighs the ball, or else the ball would drag it off both
+        let mut heavy_ball = Circle::new(position, radius);
+        scale_mass(&mut heavy_ball, WRECKING_BALL_DENSITY);
+        let ball_mass = heavy_ball.collision_data_mut().mass;
+        let ball = self.add_prefab_circle(heavy_ball, false);
+        let rigid = self.add_prefab_rigid(position);
+        // overhangs the anchor and the center of the ball, so that both bindings land on it
+        let mut rod = rectangle(
+            anchor_position + along * 0.5,
+            along.norm() + thickness,
+            thickness,
+            along.1.atan2(along.0),
+        );
+        let rod_mass = rod.collision_data_mut().mass;
+        scale_mass(&mut rod, ROD_TO_BALL_MASS * ball_mass / rod_mass);
+        let rod = self.add_prefab_polygon(rod, false);
+        let hinge = self.add_prefab_hinge(anchor_position);
+        let anchor = self.add_prefab_circle(Circle::new(anchor_position, thickness), true);
+
+        let angular_velocity = swing_speed / (MOVEMENT_COEFFICIENT * 1_000_000.0);
+        let moving = self.entities.len() - 3..self.entities.len() - 1;
+        for entity in &self.entities[moving] {
+            let mut shape = entity.shape.borrow_mut();
+            let data = shape.collision_data_mut();
+            data.velocity = -(anchor_position.to(data.centroid) * angular_velocity).perpendicular();
+            data.angular_velocity = angular_velocity;
+        }
+
+        WreckingBall {
+            anchor,
+            rod,
+            ball,
+            hinge,
+            rigid,
+        }
+    }
+
+    /// Adds a vehicle with the given body and a wheel hinged to it at each of
+    /// `wheel_positions`, every wheel driven by a motor at `motor_speed` radians
+    /// per second. Positive speeds drive the vehicle towards positive `x`. Returns
+    /// `None` for a body without an area, a radius that is not positive and finite,
+    /// or a wheel position or a speed that is not finite
+    pub fn add_vehicle(
+        &mut self,
+        mut body: Polygon,
+        wheel_positions: &[Point],
+        wheel_radius: f64,
+        motor_speed: f64,
+    ) -> Option<Vehicle> {
+        let body_mass = body.collision_data_mut().mass;
+        let is_valid = body_mass > 0.0
+            && body_mass.is_finite()
+            && wheel_radius > 0.0
+            && wheel_radius.is_finite()
+            && motor_speed.is_finite()
+            && wheel_positions
+                .iter()
+                .all(|position| position.0.is_finite() && position.1.is_finite());
+        if !is_valid {
+            return None;
+        }
+
+        let body = self.add_prefab_polygon(body, false);
+        let hinges: Vec<_> = wheel_positions
+            .iter()
+            .map(|&position| self.add_prefab_hinge(position))
+            .collect();
+        // each wheel binds the hinge at its center as it is added
+        let wheels: Vec<_> = wheel_positions
+            .iter()
+            .map(|&position| self.add_prefab_circle(Circle::new(position, wheel_radius), false))
+            .collect();
+        let motors = wheels
+            .iter()
+            .map(|&wheel| {
+                self.add_motor(body, wheel, motor_speed)
+                    .expect("both entities have just been added")
+            })
+            .collect();
+
+        Some(Vehicle {
+            body,
+            wheels,
+            hinges,
+            motors,
+        })
+    }
+
+    /// Adds a chain of links `link_thickness` thick from every point to the next,
+    /// hinged together at the points. The first and the last link are hinged to
+    /// the bindable entities under the ends of the chain, if there are any, so a
+    /// chain between two static shapes hangs like a rope bridge. The hinges are
+    /// bound from the start and give a little, see [`CHAIN_COMPLIANCE`]. Returns `None`
+    /// for fewer than two points, two consecutive points at the same place, or a
+    /// thickness or a point that is not finite
+    pub fn add_chain(&mut self, points: Vec<Point>, link_thickness: f64) -> Option<Chain> {
+        let is_valid = points.len() >= 2
+            && link_thickness > 0.0
+            && link_thickness.is_finite()
+            && points
+                .iter()
+                .all(|point| point.0.is_finite() && point.1.is_finite())
+            && points.windows(2).all(|pair| pair[0] != pair[1]);
+        if !is_valid {
+            return None;
+        }
+
+        // looked up before the links are there, or else they would be under the ends
+        let (first, last) = (points[0], points[points.len() - 1]);
+        let first_end = self.slot_at(first, |entity| entity.is_bindable);
+        let last_end = self.slot_at(last, |entity| entity.is_bindable);
+
+        // the links overhang the points, so that there is no gap between them where the chain bends
+        let links: Vec<_> = points
+            .windows(2)
+            .map(|pair| {
+                let along = pair[0].to(pair[1]);
+                let link = rectangle(
+                    pair[0] + along * 0.5,
+                    along.norm() + link_thickness,
+                    link_thickness,
+                    along.1.atan2(along.0),
+                );
+                self.add_prefab_polygon(link, false)
+            })
+            .collect();
+        let slots: Vec<_> = links
+            .iter()
+            .map(|&link| self.slots.get(link).expect("the link has just been added"))
+            .collect();
+
+        let hinges: Vec<_> = slots
+            .windows(2)
+            .zip(&points[1..])
+            .map(|(pair, &at)| self.bind_chain_hinge(pair[0], pair[1], at))
+            .collect();
+        let ends = [
+            first_end.map(|end| self.bind_chain_hinge(slots[0], end, first)),
+            last_end.map(|end| self.bind_chain_hinge(slots[slots.len() - 1], end, last)),
+        ];
+
+        // kept to draw a curve through the chain, see [`super::curve`]
+        let link_ends = slots
+            .iter()
+            .zip(points.windows(2))
+            .map(|(&slot, pair)| {
+                let link = self.entities[slot].shape.borrow();
+                [pair[0], pair[1]].map(|point| link.create_point_reference(point))
+            })
+            .collect();
+        self.chains.push(ChainPath {
+            links: links.clone(),
+            hinges: hinges.clone(),
+            ends: link_ends,
+        });
+
+        Some(Chain {
+            links,
+            hinges,
+            ends,
+        })
+    }
+}
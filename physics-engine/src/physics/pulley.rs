@@ -0,0 +1,128 @@
+//! Pulleys between entities
+//!
+//! A pulley is a rope tied to a point on each of two entities that runs over
+//! two anchors fixed in the world, so that lifting one entity lowers the
+//! other one. The ratio weighs the side of the second entity: with a ratio of
+//! two, the second entity rises half as far as the first one sinks. Like a
+//! rope, it only ever pulls, and a swipe of `cut_along` severs it anywhere
+//! along its length. An end pulled all the way up gets stuck at the wheel of
+//! its anchor, holding the other end where it is. The anchors move along with
+//! the origin, see [`super::origin`].
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{binding::Binding, game::Tool, ids::BindingId, Engine};
+use crate::geometry::Point;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct PulleySketch {
+    /// where the rope is tied to the first entity
+    pub first: Point,
+    /// where the rope is tied to the second entity
+    pub second: Point,
+    /// the anchor the rope runs over on the side of the first entity
+    pub first_anchor: Point,
+    /// the anchor the rope runs over on the side of the second entity
+    pub second_anchor: Point,
+    /// how much the side of the second entity weighs, one when missing
+    #[serde(default = "default_ratio")]
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub ratio: f64,
+}
+
+fn default_ratio() -> f64 {
+    1.0
+}
+
+impl Engine {
+    /// binds the entities under the two points with a pulley, whose rope is
+    /// exactly as long as it is in the sketch. Returns `None` for a ratio that
+    /// is not positive and finite, when either point is not on a bindable
+    /// entity or both are on the same one, or when the pulley limit of the level
+    /// or the binding cap of the engine is reached
+    pub fn add_pulley(&mut self, sketch: PulleySketch) -> Option<BindingId> {
+        let PulleySketch {
+            first,
+            second,
+            first_anchor,
+            second_anchor,
+            ratio,
+        } = sketch;
+        if !(ratio.is_finite() && ratio > 0.0) {
+            return None;
+        }
+
+        self.add_binding_between(Tool::Pulley, first, second, |first, second, [at, to]| {
+            Binding::Pulley {
+                first,
+                second,
+                anchors: [first_anchor, second_anchor],
+                length: first_anchor.to(at).norm() + ratio * second_anchor.to(to).norm(),
+                ratio,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig};
+
+    #[test]
+    fn test_pulley() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let heavy = engine
+            .add_polygon(make_shape! { (0.8, -1.0), (1.2, -1.0), (1.2, -0.6), (0.8, -0.6) })
+            .unwrap();
+        let light = engine
+            .add_polygon(make_shape! { (2.9, -0.9), (3.1, -0.9), (3.1, -0.7), (2.9, -0.7) })
+            .unwrap();
+        let sketch = PulleySketch {
+            first: Point(1.0, -0.8),
+            second: Point(3.0, -0.8),
+            first_anchor: Point(1.0, -2.0),
+            second_anchor: Point(3.0, -2.0),
+            ratio: 1.0,
+        };
+        assert_eq!(
+            engine.add_pulley(PulleySketch {
+                ratio: 0.0,
+                ..sketch
+            }),
+            None
+        );
+        let pulley = engine.add_pulley(sketch).unwrap();
+
+        // the heavy block sinks and lifts the light one, the rope keeps its length
+        let message = engine.run_n_iterations(50, 2_000.0);
+        let [first, first_anchor, second_anchor, second] = message.pulleys[0];
+        assert_eq!(
+            [first_anchor, second_anchor],
+            [Point(1.0, -2.0), Point(3.0, -2.0)]
+        );
+        assert_eq!(message.pulley_entities, vec![heavy]);
+        assert_eq!(message.pulley_targets, vec![light]);
+        assert!(first.1 > -0.7, "{first:?}");
+        assert!(second.1 < -0.9, "{second:?}");
+        let length = first_anchor.to(first).norm() + second_anchor.to(second).norm();
+        assert!((length - 2.4).abs() < 0.01, "{length}");
+
+        // until the light one gets stuck at the wheel
+        let message = engine.run_n_iterations(500, 2_000.0);
+        let [first, _, _, second] = message.pulleys[0];
+        assert!((second.1 + 1.95).abs() < 0.01, "{second:?}");
+        assert!((first.1 - 0.35).abs() < 0.01, "{first:?}");
+
+        engine.shift_origin(Point(1.0, 1.0));
+        let message = engine.run_iteration(2_000.0);
+        assert_eq!(message.pulleys[0][1], Point(0.0, -3.0));
+        assert_eq!(
+            engine.cut_along(&[Point(1.0, -3.5), Point(1.0, -2.5)]),
+            vec![pulley]
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! Recording runs as keyframes, to replay them without simulating them again
+//!
+//! While recording, every display message the engine writes adds a
+//! [`Keyframe`] to the run. A keyframe only holds the transforms of the bodies
+//! that moved since the previous one and the ids of the ones that are gone,
+//! while the shape of every body is kept once, in its own frame, so a resting
+//! level costs next to nothing per frame. Positions are in the coordinates of
+//! the level, so shifting the origin only changes the origin of the keyframes.
+//!
+//! A [`Player`] turns the run back into display messages, one per keyframe,
+//! with the shapes, the flags and the state of the game. Everything else in
+//! them, like trails or debug drawing, is left empty.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    os::raw::c_void,
+    rc::{Rc, Weak},
+};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    game::GameState,
+    ids::EntityId,
+    shape::{Collidable, Polygon},
+    shape_entities, DisplayMessage, Engine, Entity, WithColor, FLAG_SIZE,
+};
+use crate::geometry::{self, Point};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Transform {
+    pub id: EntityId,
+    pub centroid: Point,
+    pub angle: f64,
+}
+
+/// A shape centered on its centroid, turned by an angle of zero
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum RecordedShape {
+    Polygon {
+        vertices: Vec<Point>,
+        /// missing from runs recorded before polygons could be rounded
+        #[serde(default)]
+        corner_radius: f64,
+    },
+    Circle {
+        radius: f64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct RecordedBody {
+    pub id: EntityId,
+    /// the keyframe from which on the body has this shape, which changes
+    /// when a circle grows or shrinks
+    pub since_frame: u32,
+    pub color: [f32; 3],
+    /// missing from runs recorded before the entities had render layers
+    #[serde(default)]
+    pub render_layer: i32,
+    pub shape: RecordedShape,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Keyframe {
+    pub elapsed_seconds: f64,
+    pub state: GameState,
+    pub origin: Point,
+    /// the bodies that appeared or moved since the previous keyframe
+    pub moved: Vec<Transform>,
+    /// the bodies that disappeared since the previous keyframe
+    pub removed: Vec<EntityId>,
+    /// the corners of the flags left, only in the first keyframe, in the ones
+    /// after a flag was collected and in the first one after the level was edited
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flags: Option<Vec<Point>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct RecordedRun {
+    pub flags_total: usize,
+    /// ordered by the keyframe they start at
+    pub bodies: Vec<RecordedBody>,
+    pub frames: Vec<Keyframe>,
+}
+
+/// A run being recorded, along with what its last keyframe left behind
+#[derive(Default)]
+pub(super) struct Recorder {
+    run: RecordedRun,
+    transforms: HashMap<EntityId, Transform>,
+    radii: HashMap<EntityId, f64>,
+    /// how many flags there were in the previous keyframe, `None` before the
+    /// first one and after the level restarted, to record the flags again
+    flags: Option<usize>,
+}
+
+/// the transforms of the shapes that belong to an entity, in order,
+/// along with their colors and render layers
+fn transforms<'a, S: Collidable>(
+    shapes: &'a [WithColor<Weak<RefCell<S>>>],
+    entities: &'a HashMap<*const c_void, &Entity>,
+    origin: Point,
+) -> impl Iterator<Item = (Transform, [f32; 3], i32, Rc<RefCell<S>>)> + 'a {
+    shapes.iter().filter_map(move |colored| {
+        let entity = entities.get(&(colored.shape.as_ptr() as *const c_void))?;
+        let id = entity.id;
+        let shape = colored.shape.upgrade()?;
+        let transform = {
+            let mut shape = shape.borrow_mut();
+            Transform {
+                id,
+                centroid: shape.collision_data_mut().centroid + origin,
+                angle: shape.angle(),
+            }
+        };
+        Some((transform, colored.color, entity.render_layer, shape))
+    })
+}
+
+impl Recorder {
+    /// updates the transform of a body, returning whether it is a new one
+    fn track(&mut self, transform: Transform, moved: &mut Vec<Transform>) -> bool {
+        let previous = self.transforms.insert(transform.id, transform);
+        if previous != Some(transform) {
+            moved.push(transform);
+        }
+        previous.is_none()
+    }
+
+    /// makes the next keyframe record the flags, which an edit of the
+    /// level may have moved without changing how many there are
+    pub(super) fn restart_level(&mut self) {
+        self.flags = None;
+    }
+}
+
+impl Engine {
+    /// starts recording a new run, dropping the one being recorded
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(Box::default());
+    }
+
+    /// the run recorded since [`Engine::start_recording`], `None` if not recording
+    pub fn stop_recording(&mut self) -> Option<RecordedRun> {
+        self.recorder.take().map(|recorder| recorder.run)
+    }
+
+    /// adds the keyframe of the display message being written, if recording
+    pub(super) fn record_keyframe(&mut self) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        let frame = recorder.run.frames.len() as u32;
+        let entities = shape_entities(&self.entities);
+
+        let mut moved = vec![];
+        let mut seen = HashSet::new();
+        for (transform, color, render_layer, polygon) in
+            transforms(&self.polygons, &entities, self.origin)
+        {
+            seen.insert(transform.id);
+            if recorder.track(transform, &mut moved) {
+                let polygon = polygon.borrow();
+                let centroid = transform.centroid - self.origin;
+                let vertices = polygon
+                    .vertices()
+                    .iter()
+                    .map(|&vertex| centroid.to(vertex).rotate(-transform.angle))
+                    .collect();
+                recorder.run.bodies.push(RecordedBody {
+                    id: transform.id,
+                    since_frame: frame,
+                    color,
+                    render_layer,
+                    shape: RecordedShape::Polygon {
+                        vertices,
+                        corner_radius: polygon.corner_radius(),
+                    },
+                });
+            }
+        }
+        for (transform, color, render_layer, circle) in
+            transforms(&self.circles, &entities, self.origin)
+        {
+            seen.insert(transform.id);
+            recorder.track(transform, &mut moved);
+            let radius = circle.borrow().bounding_circle().1;
+            if recorder.radii.insert(transform.id, radius) != Some(radius) {
+                recorder.run.bodies.push(RecordedBody {
+                    id: transform.id,
+                    since_frame: frame,
+                    color,
+                    render_layer,
+                    shape: RecordedShape::Circle { radius },
+                });
+            }
+        }
+
+        let mut removed = vec![];
+        recorder.transforms.retain(|id, _| {
+            let is_removed = !seen.contains(id);
+            if is_removed {
+                removed.push(*id);
+            }
+            !is_removed
+        });
+        recorder.radii.retain(|id, _| seen.contains(id));
+        removed.sort_unstable();
+
+        let flags = (recorder.flags != Some(self.flags.len())).then(|| {
+            self.flags
+                .iter()
+                .map(|flag: &Polygon| flag.aabb().min + self.origin)
+                .collect()
+        });
+        recorder.flags = Some(self.flags.len());
+        recorder.run.flags_total = self.flags_total;
+        recorder.run.frames.push(Keyframe {
+            elapsed_seconds: self.game.elapsed_seconds(),
+            state: self.game.state(),
+            origin: self.origin,
+            moved,
+            removed,
+            flags,
+        });
+    }
+}
+
+/// Plays a recorded run back as display messages, one for every keyframe
+pub struct Player {
+    run: RecordedRun,
+    /// the keyframe the next message is made of
+    frame: usize,
+    /// the first body whose shape is not known yet
+    next_body: usize,
+    shapes: BTreeMap<EntityId, ([f32; 3], i32, RecordedShape)>,
+    transforms: BTreeMap<EntityId, Transform>,
+    flags: Vec<Point>,
+}
+
+impl Player {
+    pub fn new(run: RecordedRun) -> Self {
+        Self {
+            run,
+            frame: 0,
+            next_body: 0,
+            shapes: BTreeMap::new(),
+            transforms: BTreeMap::new(),
+            flags: vec![],
+        }
+    }
+
+    /// the number of keyframes, and so of messages
+    pub fn len(&self) -> usize {
+        self.run.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.run.frames.is_empty()
+    }
+
+    /// makes `frame` the keyframe of the next message, playing
+    /// the run again from its start to go back
+    pub fn seek(&mut self, frame: usize) {
+        if frame < self.frame {
+            *self = Self::new(std::mem::take(&mut self.run));
+        }
+        while self.frame < frame.min(self.len()) {
+            self.advance();
+        }
+    }
+
+    /// applies the next keyframe, which has to exist
+    fn advance(&mut self) {
+        let frame = &self.run.frames[self.frame];
+        let bodies = self.run.bodies[self.next_body..]
+            .iter()
+            .take_while(|body| body.since_frame as usize <= self.frame);
+        for body in bodies {
+            self.shapes
+                .insert(body.id, (body.color, body.render_layer, body.shape.clone()));
+            self.next_body += 1;
+        }
+        for &transform in &frame.moved {
+            self.transforms.insert(transform.id, transform);
+        }
+        for id in &frame.removed {
+            self.transforms.remove(id);
+            self.shapes.remove(id);
+        }
+        if let Some(flags) = &frame.flags {
+            self.flags.clone_from(flags);
+        }
+        self.frame += 1;
+    }
+
+    fn message(&self, frame: &Keyframe) -> DisplayMessage {
+        let mut message = DisplayMessage {
+            flags_remaining: self.flags.len(),
+            flags_total: self.run.flags_total,
+            state: frame.state,
+            elapsed_seconds: frame.elapsed_seconds,
+            origin: frame.origin,
+            ..Default::default()
+        };
+        for (id, transform) in &self.transforms {
+            let Some((color, render_layer, shape)) = self.shapes.get(id) else {
+                continue;
+            };
+            let centroid = transform.centroid - frame.origin;
+            match shape {
+                RecordedShape::Polygon {
+                    vertices,
+                    corner_radius,
+                } => {
+                    message.polygon_entities.push(*id);
+                    message.polygon_layers.push(*render_layer);
+                    message.polygons.push(WithColor {
+                        color: *color,
+                        shape: geometry::Polygon {
+                            vertices: vertices
+                                .iter()
+                                .map(|&vertex| centroid + vertex.rotate(transform.angle))
+                                .collect(),
+                            corner_radius: *corner_radius,
+                            centroid,
+                        },
+                    });
+                }
+                RecordedShape::Circle { radius } => {
+                    message.circle_entities.push(*id);
+                    message.circle_layers.push(*render_layer);
+                    message.circles.push(WithColor {
+                        color: *color,
+                        shape: geometry::Circle {
+                            center: centroid,
+                            radius: *radius,
+                        },
+                    });
+                }
+            }
+        }
+        message.flags.extend(self.flags.iter().map(|&corner| {
+            let Point(x, y) = corner - frame.origin;
+            Polygon::new(vec![
+                Point(x, y),
+                Point(x + FLAG_SIZE, y),
+                Point(x + FLAG_SIZE, y + FLAG_SIZE),
+                Point(x, y + FLAG_SIZE),
+            ])
+            .into()
+        }));
+        message
+    }
+}
+
+impl Iterator for Player {
+    type Item = DisplayMessage;
+
+    fn next(&mut self) -> Option<DisplayMessage> {
+        let frame = self.run.frames.get(self.frame)?.clone();
+        self.advance();
+        Some(self.message(&frame))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig};
+
+    #[test]
+    fn test_record_and_play() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (-3.0, 1.0), (3.0, 1.0), (3.0, 1.2), (-3.0, 1.2) },
+            true,
+        );
+        let square = engine
+            .add_polygon(make_shape! { (1.0, -0.5), (1.4, -0.5), (1.4, -0.1), (1.0, -0.1) })
+            .unwrap();
+        engine.start_recording();
+
+        let mut messages = vec![];
+        for _ in 0..20 {
+            messages.push(engine.run_n_iterations(5, 2000.0));
+        }
+        engine.erase(square);
+        messages.push(engine.run_iteration(2000.0));
+        let run = engine.stop_recording().unwrap();
+        assert_eq!(run.frames.len(), 21);
+        assert_eq!(run.bodies.len(), 3);
+        // the floor does not move after the first keyframe
+        assert!(run.frames[1]
+            .moved
+            .iter()
+            .all(|moved| moved.id != EntityId(1)));
+        assert_eq!(run.frames[20].removed, [square]);
+
+        let close = |a: Point, b: Point| a.to(b).norm() < 1e-9;
+        let mut player =
+            Player::new(serde_json::from_str(&serde_json::to_string(&run).unwrap()).unwrap());
+        assert_eq!(player.len(), 21);
+        for (played, message) in player.by_ref().zip(&messages) {
+            assert_eq!(played.polygons.len(), message.polygons.len());
+            for (played, polygon) in played.polygons.iter().zip(&message.polygons) {
+                assert_eq!(played.color, polygon.color);
+                for (&a, &b) in played.shape.vertices.iter().zip(&polygon.shape.vertices) {
+                    assert!(close(a, b));
+                }
+            }
+            assert!(close(
+                played.circles[0].shape.center,
+                message.circles[0].shape.center
+            ));
+            assert_eq!(played.elapsed_seconds, message.elapsed_seconds);
+        }
+        assert!(player.next().is_none());
+
+        player.seek(3);
+        let played = player.next().unwrap();
+        assert!(close(
+            played.circles[0].shape.center,
+            messages[3].circles[0].shape.center
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+//! Ropes between entities
+//!
+//! A rope binds two points on different entities like a spring does, but it
+//! only ever pulls: the points move freely as long as they are at most its
+//! max length apart, and a taut rope holds them there the way a hinge holds
+//! its points together, without bouncing them back. A swipe of `cut_along`
+//! severs it anywhere along its length.
+
+use super::{binding::Binding, game::Tool, ids::BindingId, Engine};
+use crate::geometry::Point;
+
+impl Engine {
+    /// binds the entities under the two points with a rope between the points,
+    /// pulling them together if they are further apart than `max_length`. Returns
+    /// `None` for a negative or infinite length, when either point is not on a
+    /// bindable entity or both are on the same one, or when the rope limit of the
+    /// level or the binding cap of the engine is reached
+    pub fn add_rope(&mut self, first: Point, second: Point, max_length: f64) -> Option<BindingId> {
+        if !(max_length.is_finite() && max_length >= 0.0) {
+            return None;
+        }
+        self.add_binding_between(Tool::Rope, first, second, |first, second, _| {
+            Binding::Rope {
+                first,
+                second,
+                max_length,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig};
+
+    #[test]
+    fn test_rope() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let ceiling = engine.add_prefab_polygon(
+            make_shape! { (2.0, -2.2), (3.0, -2.2), (3.0, -2.0), (2.0, -2.0) },
+            true,
+        );
+        let weight = engine
+            .add_polygon(make_shape! { (2.3, -1.0), (2.7, -1.0), (2.7, -0.6), (2.3, -0.6) })
+            .unwrap();
+        assert_eq!(
+            engine.add_rope(Point(2.5, -0.8), Point(2.5, -2.1), f64::NAN),
+            None
+        );
+        let rope = engine
+            .add_rope(Point(2.5, -0.8), Point(2.5, -2.1), 1.5)
+            .unwrap();
+
+        // the weight falls until the rope is taut, and hangs from it
+        let message = engine.run_iteration(2_000.0);
+        let [[bottom, top]] = message.ropes[..] else {
+            panic!("{:?} are not one rope", message.ropes);
+        };
+        assert!((top.to(bottom).norm() - 1.3).abs() < 0.01);
+        assert_eq!(message.rope_lengths, vec![1.5]);
+        assert_eq!(message.rope_entities, vec![weight]);
+        assert_eq!(message.rope_targets, vec![ceiling]);
+
+        let message = engine.run_n_iterations(1_000, 2_000.0);
+        let slot = engine.slots.get(weight).unwrap();
+        let state = &engine.body_states()[slot];
+        assert!(state.velocity.norm() < 1e-2, "{state:?}");
+        let [[bottom, top]] = message.ropes[..] else {
+            panic!("{:?} are not one rope", message.ropes);
+        };
+        assert!((top.to(bottom).norm() - 1.5).abs() < 0.01);
+
+        assert_eq!(
+            engine.cut_along(&[Point(2.0, -1.5), Point(3.0, -1.5)]),
+            vec![rope]
+        );
+        assert!(engine.run_iteration(2_000.0).ropes.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! Free play without a level to beat
+//!
+//! A sandbox has no flags and no time limit, so it can be neither won nor
+//! lost. The main ball is still there to play with, but the world can be made
+//! unbounded so that nothing ever leaves it.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{tuning::SolverTuning, Engine, WORLD_HALF_EXTENT};
+use crate::{
+    geometry::Point,
+    levels::{Level, ToolLimits},
+};
+
+/// Missing fields take their default values
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct SandboxConfig {
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub initial_ball_position: Point,
+    /// half the side of the square the main ball respawns when leaving,
+    /// `None` (`null`) for a world without bounds
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub world_half_extent: Option<f64>,
+    /// every tool is unlimited by default
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub tool_limits: ToolLimits,
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub solver_tuning: SolverTuning,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            initial_ball_position: Point(0.0, 0.0),
+            world_half_extent: Some(WORLD_HALF_EXTENT),
+            tool_limits: ToolLimits::default(),
+            solver_tuning: SolverTuning::default(),
+        }
+    }
+}
+
+impl Engine {
+    pub fn sandbox(config: SandboxConfig) -> Self {
+        let mut engine = Engine::new(Level {
+            initial_ball_position: config.initial_ball_position,
+            main_ball: Default::default(),
+            circles: vec![],
+            polygons: vec![],
+            flags_positions: vec![],
+            time_limit: None,
+            stars: Default::default(),
+            tool_limits: config.tool_limits,
+            rigid_compliance: 0.0,
+            terrain: vec![],
+            hints: vec![],
+            bombs: vec![],
+            fans: vec![],
+            lasers: vec![],
+            time_zones: vec![],
+            gravity_pickups: vec![],
+            size_pickups: vec![],
+            wrecking_balls: vec![],
+            magnets: vec![],
+            dispensers: vec![],
+            world_half_extent: config.world_half_extent,
+            script: None,
+        });
+        engine.solver_tuning = config.solver_tuning;
+        engine
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields() {
+        let config: SandboxConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config, SandboxConfig::default());
+
+        let config: SandboxConfig = serde_json::from_str(r#"{"world_half_extent":null}"#).unwrap();
+        assert_eq!(config.world_half_extent, None);
+    }
+
+    #[test]
+    fn test_unbounded() {
+        let ball_x_after_blast = |world_half_extent| {
+            let mut engine = Engine::sandbox(SandboxConfig {
+                world_half_extent,
+                ..Default::default()
+            });
+            engine.explode(Point(-0.2, 0.0), 1.0, 1e-2);
+            let message = engine.run_n_iterations(500, 2_000.0);
+            assert!(message.events.is_empty());
+            message.circles[0].shape.center.0
+        };
+
+        // the ball is blown out of the default world and respawns
+        assert!(ball_x_after_blast(Some(WORLD_HALF_EXTENT)) < WORLD_HALF_EXTENT);
+        assert!(ball_x_after_blast(None) > WORLD_HALF_EXTENT);
+    }
+}
@@ -0,0 +1,390 @@
+//! Level logic scripted in [Rhai](https://rhai.rs), with the `scripting` feature
+//!
+//! A level may bring the source of a script, whose top level runs once when
+//! the level is loaded, and which defines any of these hooks:
+//!
+//! - `fn on_step(world, seconds)` after every step, with the simulated
+//!   seconds since the start of the level
+//! - `fn on_contact(world, first, second, impulse)` for every contact that
+//!   pushed two entities apart during the step
+//! - `fn on_flag(world, remaining)` whenever the main ball collects a flag
+//!
+//! The hooks see the engine through `world` only: `world.seconds`,
+//! `world.exists(id)`, `world.position(id)`, `world.velocity(id)` and
+//! `world.angle(id)` query the entities, which are `()` for ids that do not
+//! exist, while `world.apply_impulse(id, x, y)`, `world.spawn_circle(x, y,
+//! radius)`, `world.spawn_polygon([[x, y], ..])` and `world.dispense(id)`,
+//! which triggers the dispenser with the region id, act on it once the hook
+//! returns. Positions are in the coordinates of the level, the main ball has
+//! the id 0, and spawned entities count towards the entity cap of the config.
+//!
+//! Rhai functions cannot see the variables of the script, so what the hooks
+//! remember goes into `this`, an object map kept for as long as the level.
+//! A hook that runs too long or fails stops the script for good, and
+//! [`Engine::script_error`] says why. Scripts cannot print, nor reach anything
+//! but the world.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use rhai::{Array, CallFnOptions, Dynamic, Map, Scope, AST, FLOAT, INT};
+
+use super::{
+    game::{GameEvent, Tool},
+    ids::{EntityId, RegionId},
+    shape::{Circle, Collidable, Polygon},
+    Engine,
+};
+use crate::geometry::{Point, Vector};
+
+/// operations a hook may take before it is stopped, which keeps an endless
+/// loop from freezing the level
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+#[derive(Clone, Copy)]
+struct Body {
+    position: Point,
+    velocity: Vector,
+    angle: f64,
+}
+
+enum Command {
+    Impulse(EntityId, Vector),
+    SpawnCircle(Point, f64),
+    SpawnPolygon(Vec<Point>),
+    Dispense(RegionId),
+}
+
+#[derive(Default)]
+struct WorldState {
+    seconds: f64,
+    bodies: HashMap<EntityId, Body>,
+    commands: Vec<Command>,
+}
+
+/// What the hooks see of the engine, and act on it through
+#[derive(Clone, Default)]
+struct World(Rc<RefCell<WorldState>>);
+
+impl World {
+    fn body<T: Into<Dynamic>>(&self, id: INT, get: impl Fn(&Body) -> T) -> Dynamic {
+        let state = self.0.borrow();
+        u32::try_from(id)
+            .ok()
+            .and_then(|id| state.bodies.get(&EntityId(id)))
+            .map_or(Dynamic::UNIT, |body| get(body).into())
+    }
+
+    fn command(&mut self, command: Command) {
+        self.0.borrow_mut().commands.push(command);
+    }
+}
+
+fn pair(point: Point) -> Array {
+    vec![Dynamic::from_float(point.0), Dynamic::from_float(point.1)]
+}
+
+/// `[x, y]` as a point, `None` for anything else
+fn point(value: Dynamic) -> Option<Point> {
+    let pair = value.try_cast::<Array>()?;
+    let coordinate = |value: &Dynamic| {
+        value
+            .as_float()
+            .ok()
+            .or_else(|| value.as_int().ok().map(|value| value as FLOAT))
+    };
+    match &pair[..] {
+        [x, y] => Some(Point(coordinate(x)?, coordinate(y)?)),
+        _ => None,
+    }
+}
+
+fn rhai_engine() -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine
+        .register_type_with_name::<World>("World")
+        .register_get("seconds", |world: &mut World| world.0.borrow().seconds)
+        .register_fn("exists", |world: &mut World, id: INT| {
+            !world.body(id, |_| ()).is_unit()
+        })
+        .register_fn("position", |world: &mut World, id: INT| {
+            world.body(id, |body| pair(body.position))
+        })
+        .register_fn("velocity", |world: &mut World, id: INT| {
+            world.body(id, |body| pair(body.velocity))
+        })
+        .register_fn("angle", |world: &mut World, id: INT| {
+            world.body(id, |body| body.angle)
+        })
+        .register_fn(
+            "apply_impulse",
+            |world: &mut World, id: INT, x: FLOAT, y: FLOAT| {
+                if let Ok(id) = u32::try_from(id) {
+                    world.command(Command::Impulse(EntityId(id), Point(x, y)));
+                }
+            },
+        )
+        .register_fn(
+            "spawn_circle",
+            |world: &mut World, x: FLOAT, y: FLOAT, radius: FLOAT| {
+                world.command(Command::SpawnCircle(Point(x, y), radius));
+            },
+        )
+        .register_fn("spawn_polygon", |world: &mut World, vertices: Array| {
+            if let Some(vertices) = vertices.into_iter().map(point).collect() {
+                world.command(Command::SpawnPolygon(vertices));
+            }
+        })
+        .register_fn("dispense", |world: &mut World, id: INT| {
+            if let Ok(id) = u32::try_from(id) {
+                world.command(Command::Dispense(RegionId(id)));
+            }
+        });
+    engine
+}
+
+/// A loaded script, with the state its hooks keep
+pub(super) struct LevelScript {
+    engine: rhai::Engine,
+    ast: AST,
+    this: Dynamic,
+    world: World,
+    /// the contacts of the step, only kept if the script has `on_contact`
+    contacts: Option<Vec<(EntityId, EntityId, f64)>>,
+}
+
+impl LevelScript {
+    /// compiles the script and runs its top level
+    fn load(source: &str) -> Result<Box<Self>, String> {
+        let engine = rhai_engine();
+        let ast = engine.compile(source).map_err(|error| error.to_string())?;
+        engine
+            .run_ast_with_scope(&mut Scope::new(), &ast)
+            .map_err(|error| error.to_string())?;
+
+        let has_on_contact = ast
+            .iter_functions()
+            .any(|function| function.name == "on_contact" && function.params.len() == 4);
+        Ok(Box::new(Self {
+            engine,
+            ast,
+            this: Map::new().into(),
+            world: World::default(),
+            contacts: has_on_contact.then(Vec::new),
+        }))
+    }
+
+    /// keeps a contact of the step for `on_contact`
+    pub(super) fn contact(&mut self, first: EntityId, second: EntityId, impulse: f64) {
+        if let Some(contacts) = &mut self.contacts {
+            if impulse.is_finite() && impulse > 0.0 {
+                contacts.push((first, second, impulse));
+            }
+        }
+    }
+
+    /// calls the hook if the script defines it
+    fn call<const N: usize>(&mut self, name: &str, args: [Dynamic; N]) -> Result<(), String> {
+        let is_defined = self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == N + 1);
+        if !is_defined {
+            return Ok(());
+        }
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        let world = Dynamic::from(self.world.clone());
+        let args: Vec<_> = [world].into_iter().chain(args).collect();
+        self.engine
+            .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, name, args)
+            .map(|_| ())
+            .map_err(|error| error.to_string())
+    }
+}
+
+/// loads the script of a level, returning either it or why it could not be loaded
+pub(super) fn load(source: Option<&str>) -> (Option<Box<LevelScript>>, Option<String>) {
+    match source.map(LevelScript::load) {
+        None => (None, None),
+        Some(Ok(script)) => (Some(script), None),
+        Some(Err(error)) => (None, Some(error)),
+    }
+}
+
+impl Engine {
+    /// why the script of the level stopped, or could not be loaded
+    pub fn script_error(&self) -> Option<&str> {
+        self.script_error.as_deref()
+    }
+
+    /// runs the hooks of the script for the step that just ended, `events`
+    /// being the index of the first event of the step
+    pub(super) fn run_script(&mut self, events: usize) {
+        let Some(mut script) = self.script.take() else {
+            return;
+        };
+        {
+            let mut world = script.world.0.borrow_mut();
+            world.seconds = self.game.elapsed_seconds();
+            world.bodies.clear();
+            for entity in &self.entities {
+                let mut shape = entity.shape.borrow_mut();
+                let angle = shape.angle();
+                let data = shape.collision_data_mut();
+                let body = Body {
+                    position: data.centroid + self.origin,
+                    velocity: data.velocity,
+                    angle,
+                };
+                world.bodies.insert(entity.id, body);
+            }
+        }
+
+        let flags = self.events[events..]
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::FlagCollected { remaining } => Some(*remaining as INT),
+                _ => None,
+            });
+        let flags: Vec<_> = flags.collect();
+        let contacts = script.contacts.as_mut().map(std::mem::take);
+
+        let result = (|| {
+            for remaining in flags {
+                script.call("on_flag", [remaining.into()])?;
+            }
+            for (first, second, impulse) in contacts.into_iter().flatten() {
+                let (first, second) = (first.0 as INT, second.0 as INT);
+                script.call("on_contact", [first.into(), second.into(), impulse.into()])?;
+            }
+            let seconds = self.game.elapsed_seconds();
+            script.call("on_step", [seconds.into()])
+        })();
+
+        let commands = std::mem::take(&mut script.world.0.borrow_mut().commands);
+        for command in commands {
+            self.run_command(command);
+        }
+        match result {
+            Ok(()) => self.script = Some(script),
+            Err(error) => self.script_error = Some(error),
+        }
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Impulse(id, impulse) => {
+                let Some(slot) = self.slots.get(id) else {
+                    return;
+                };
+                let mut shape = self.entities[slot].shape.borrow_mut();
+                let data = shape.collision_data_mut();
+                if data.mass.is_finite() && impulse.0.is_finite() && impulse.1.is_finite() {
+                    data.velocity += impulse / data.mass;
+                }
+            }
+            Command::SpawnCircle(center, radius) => {
+                let is_valid = center.0.is_finite() && center.1.is_finite() && radius > 0.0;
+                if is_valid && radius.is_finite() && self.has_room_for(Tool::Circle, 1) {
+                    let circle = Circle::new(center - self.origin, radius);
+                    self.add_prefab_circle(circle, false);
+                }
+            }
+            Command::SpawnPolygon(vertices) => {
+                let vertices: Vec<_> = vertices.into_iter().map(|v| v - self.origin).collect();
+                let is_finite = vertices.iter().all(|v| v.0.is_finite() && v.1.is_finite());
+                if vertices.len() < 3 || !is_finite {
+                    return;
+                }
+                let mut polygon = Polygon::new(vertices);
+                let mass = polygon.collision_data_mut().mass;
+                if mass.is_finite() && mass > 0.0 && self.has_room_for(Tool::Polygon, 1) {
+                    self.add_prefab_polygon(polygon, false);
+                }
+            }
+            Command::Dispense(id) => {
+                self.dispense(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{geometry::Point, levels, physics::sandbox::SandboxConfig};
+
+    fn scripted(script: &str) -> Engine {
+        let mut level = Engine::sandbox(SandboxConfig::default())
+            .export_level()
+            .unwrap();
+        level.polygons.push(levels::Entity {
+            shape: vec![
+                Point(-1.0, 0.2),
+                Point(1.0, 0.2),
+                Point(1.0, 0.4),
+                Point(-1.0, 0.4),
+            ],
+            is_static: true,
+            is_bindable: false,
+            hit_points: None,
+            breaking_impulse: None,
+            fracture_impulse: None,
+            bump_impulse: None,
+            is_ferrous: false,
+            is_erasable: false,
+            render_layer: 0,
+        });
+        level.script = Some(script.to_owned());
+        Engine::new(level)
+    }
+
+    #[test]
+    fn test_script_hooks() {
+        let mut engine = scripted(
+            r#"
+            fn on_step(world, seconds) {
+                if this.spawned == () {
+                    world.spawn_polygon([[2, -1], [2.4, -1], [2.4, -0.6], [2, -0.6]]);
+                    this.spawned = true;
+                }
+                if seconds > 0.2 && this.kicked == () {
+                    world.apply_impulse(0, 0.01, 0.0);
+                    this.kicked = true;
+                }
+            }
+            fn on_contact(world, first, second, impulse) {
+                this.contacts = (this.contacts ?? 0) + 1;
+                if this.contacts == 1 {
+                    world.spawn_circle(-2.0, -1.0, 0.2);
+                }
+            }
+            "#,
+        );
+        engine.run_n_iterations(200, 2000.0);
+        assert_eq!(engine.script_error(), None);
+        // the ball, the floor, the box of the first step and the circle of the first contact
+        assert_eq!(engine.entities.len(), 4);
+        let ball = engine.body_states()[0];
+        // kicked to the right
+        assert!(ball.centroid.0 > 0.0 && ball.velocity.0 > 0.0);
+    }
+
+    #[test]
+    fn test_script_errors() {
+        let engine = scripted("fn on_step(world, seconds) {");
+        assert!(engine.script_error().is_some());
+
+        let mut engine = scripted("fn on_step(world, seconds) { loop {} }");
+        engine.run_iteration(2000.0);
+        assert!(engine.script_error().is_some());
+        // a failed script is not run again
+        engine.run_iteration(2000.0);
+        assert_eq!(engine.entities.len(), 2);
+    }
+}
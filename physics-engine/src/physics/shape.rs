@@ -1,18 +1,23 @@
-use std::{panic::RefUnwindSafe, time::Duration};
+use std::panic::RefUnwindSafe;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
 use crate::{
-    geometry::{Point, Vector},
+    geometry::{Aabb, Point, Vector},
     physics::compute,
+    trace::span,
 };
 
-use super::{
-    binding::PointOnShape, compute::simplex::Vertex, GRAVITY_COEFFICIENT, MOVEMENT_COEFFICIENT,
-};
+use super::{binding::PointOnShape, compute::simplex::Vertex, tuning::SolverTuning};
 
 mod circle;
+mod compound;
 mod polygon;
 
 pub use circle::Circle;
+pub use compound::Compound;
 pub use polygon::Polygon;
 
 pub trait Bounded {
@@ -20,23 +25,41 @@ pub trait Bounded {
     fn includes(&self, point: Point) -> bool;
 }
 
-pub trait Collidable: Bounded + RefUnwindSafe {
+/// `Sync` so that the narrowphase can read shapes from several threads
+pub trait Collidable: Bounded + RefUnwindSafe + Sync {
     fn rotate(&mut self, angle: f64);
     fn translate(&mut self, translation: Vector);
+    /// scales the shape about its centroid, along with its mass and inertia
+    fn scale(&mut self, factor: f64);
     fn collision_data_mut(&mut self) -> &mut CollisionData;
+    /// bounding box of the shape, kept up to date by `translate`, `rotate` and `scale`
+    fn aabb(&self) -> Aabb;
+    /// center and radius of a circle around the shape, centered at its centroid.
+    /// Unlike the bounding box, it does not grow as the shape rotates
+    fn bounding_circle(&self) -> (Point, f64);
+    /// total rotation since the shape was created
+    fn angle(&self) -> f64;
+    /// the convex parts collisions are detected against, `None` for a shape that
+    /// is convex itself
+    fn parts(&self) -> Option<&[Polygon]> {
+        None
+    }
 
+    /// returns the normal impulse exchanged between the shapes
+    #[allow(clippy::too_many_arguments)]
     fn resolve_collision_with(
         &mut self,
         other: &mut dyn Collidable,
         collision: Vertex,
         microseconds: f64,
+        tuning: &SolverTuning,
         restitution_mulipiler: f64,
         friction_mulipiler: f64,
         static_friction_enabled: bool,
         dynamic_friction_enabled: bool,
-    ) {
-        const RESTITUTION: f64 = 0.2;
-        let restitution = restitution_mulipiler * RESTITUTION;
+    ) -> f64 {
+        span!(TRACE, "solver");
+        let restitution = restitution_mulipiler * tuning.restitution;
 
         let first = self.collision_data_mut();
         let second = other.collision_data_mut();
@@ -50,40 +73,35 @@ pub trait Collidable: Bounded + RefUnwindSafe {
             second.velocity - (second_offset * second.angular_velocity).perpendicular();
         let relative_velocity = second_velocity - first_velocity;
 
-        let impulse = compute::impulse(
-            first.clone(),
-            second.clone(),
-            first_offset,
-            second_offset,
-            normal,
-            relative_velocity,
-            restitution + 1.0,
-        );
+        let normal_mass =
+            compute::inverse_effective_mass(first, second, first_offset, second_offset, normal);
+        let impulse = compute::impulse(normal_mass, normal, relative_velocity, restitution + 1.0);
 
         if impulse > 0.0 {
             let friction_normal = -normal.perpendicular();
-
-            let static_friction_impulse = compute::impulse(
-                first.clone(),
-                second.clone(),
+            let friction_mass = compute::inverse_effective_mass(
+                first,
+                second,
                 first_offset,
                 second_offset,
                 friction_normal,
-                relative_velocity,
-                1.0,
             );
 
-            let friction_impulse = if static_friction_impulse > impulse * friction_mulipiler * 1e-4
+            let static_friction_impulse =
+                compute::impulse(friction_mass, friction_normal, relative_velocity, 1.0);
+
+            let friction_impulse = if static_friction_impulse
+                > impulse * friction_mulipiler * tuning.static_friction_threshold
             {
                 if dynamic_friction_enabled {
                     compute::impulse(
-                        first.clone(),
-                        second.clone(),
-                        first_offset,
-                        second_offset,
+                        friction_mass,
                         friction_normal,
                         relative_velocity,
-                        (50.0 * collision.point.norm() * friction_mulipiler).min(1.0),
+                        (tuning.dynamic_friction_per_depth
+                            * collision.point.norm()
+                            * friction_mulipiler)
+                            .min(1.0),
                     )
                 } else {
                     0.0
@@ -112,7 +130,11 @@ pub trait Collidable: Bounded + RefUnwindSafe {
         }
 
         if first.mass.is_finite() || second.mass.is_finite() {
-            let translation = normal * collision.point.norm().min(1e-6 * microseconds);
+            let translation = normal
+                * collision
+                    .point
+                    .norm()
+                    .min(tuning.max_correction_per_microsecond * microseconds);
             let i1 = first.mass.recip();
             let i2 = second.mass.recip();
             let i_sum = i1 + i2;
@@ -120,58 +142,120 @@ pub trait Collidable: Bounded + RefUnwindSafe {
             self.translate(-translation * (i1 / i_sum));
             other.translate(translation * (i2 / i_sum));
         }
+
+        impulse.max(0.0)
     }
 
+    /// returns the normal impulse exchanged between the shapes,
+    /// or `None` if they were not in contact
+    #[allow(clippy::too_many_arguments)]
     fn collide(
         &mut self,
         other: &mut dyn Collidable,
         microseconds: f64,
+        tuning: &SolverTuning,
         restitution_mulipiler: f64,
         friction_mulipiler: f64,
         static_friction_enabled: bool,
         dynamic_friction_enabled: bool,
-    ) {
-        let Some(collision) = compute::collision(self, other) else {
-            return;
-        };
+    ) -> Option<f64> {
+        span!(TRACE, "narrowphase");
+        let mut search_direction = compute::INITIAL_SEARCH_DIRECTION;
+        let collision = contact(self, other, &mut search_direction)?;
 
         if collision.point.is_close_enough_to(Vector::ZERO) {
-            return;
+            return None;
         }
 
-        self.resolve_collision_with(
+        Some(self.resolve_collision_with(
             other,
             collision,
             microseconds,
+            tuning,
             restitution_mulipiler,
             friction_mulipiler,
             static_friction_enabled,
             dynamic_friction_enabled,
-        );
+        ))
     }
 
     fn resolve_point_reference(&self, point_ref: PointOnShape) -> Point;
     fn create_point_reference(&self, point: Point) -> PointOnShape;
+}
 
-    fn update_position(&mut self, microseconds: f64, gravity_multiplier: f64) {
-        let velocity = self.collision_data_mut().velocity;
-        let angular_velocity = self.collision_data_mut().angular_velocity;
+/// the contact of the shapes like [`compute::warm_started_collision`], between the
+/// parts of shapes made of several, see [`Collidable::parts`]. The parts that overlap
+/// the deepest make the contact, and leave the search direction of their pair
+pub(super) fn contact<F, S>(first: &F, second: &S, search_direction: &mut Vector) -> Option<Vertex>
+where
+    F: Collidable + ?Sized,
+    S: Collidable + ?Sized,
+{
+    let (first_parts, second_parts) = match (first.parts(), second.parts()) {
+        (None, None) => return compute::warm_started_collision(first, second, search_direction),
+        parts => parts,
+    };
 
-        self.collision_data_mut().velocity +=
-            Point(0.0, gravity_multiplier * GRAVITY_COEFFICIENT * microseconds);
-        self.rotate(angular_velocity * MOVEMENT_COEFFICIENT * microseconds);
-        self.translate(velocity * MOVEMENT_COEFFICIENT * microseconds);
+    let initial_direction = *search_direction;
+    let mut deepest = None;
+    match (first_parts, second_parts) {
+        (Some(first_parts), Some(second_parts)) => {
+            for first in first_parts {
+                for second in second_parts {
+                    deepen(&mut deepest, first, second, initial_direction);
+                }
+            }
+        }
+        (Some(first_parts), None) => {
+            for first in first_parts {
+                deepen(&mut deepest, first, second, initial_direction);
+            }
+        }
+        (None, Some(second_parts)) => {
+            for second in second_parts {
+                deepen(&mut deepest, first, second, initial_direction);
+            }
+        }
+        (None, None) => unreachable!(),
     }
+    let (contact, direction) = deepest?;
+    *search_direction = direction;
+    Some(contact)
 }
 
-pub trait Shape: Collidable + Clone + Into<Self::Underlying> {
-    type Underlying;
+/// keeps the contact of the two convex shapes in `deepest` if they overlap
+/// deeper than the contact already there, along with its search direction
+fn deepen<F, S>(
+    deepest: &mut Option<(Vertex, Vector)>,
+    first: &F,
+    second: &S,
+    search_direction: Vector,
+) where
+    F: Collidable + ?Sized,
+    S: Collidable + ?Sized,
+{
+    if !first.aabb().overlaps(&second.aabb()) {
+        return;
+    }
+    let mut direction = search_direction;
+    let Some(contact) = compute::warm_started_collision(first, second, &mut direction) else {
+        return;
+    };
+    if deepest.is_none_or(|(deepest, _)| contact.point.norm() > deepest.point.norm()) {
+        *deepest = Some((contact, direction));
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct CollisionData {
     pub centroid: Point,
+    // static shapes have infinite mass and inertia, which JSON cannot represent
+    #[serde(with = "super::snapshot::infinite_as_null")]
+    #[cfg_attr(feature = "wasm", tsify(type = "number | null"))]
     pub mass: f64,
+    #[serde(with = "super::snapshot::infinite_as_null")]
+    #[cfg_attr(feature = "wasm", tsify(type = "number | null"))]
     pub inertia: f64,
     pub velocity: Vector,
     pub angular_velocity: f64,
@@ -1,15 +1,15 @@
 use crate::{
-    geometry::{self, Point, Vector},
+    geometry::{self, Aabb, Point, Vector},
     physics::binding::PointOnShape,
 };
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
-use super::{Bounded, Collidable, CollisionData, Shape};
+use super::{Bounded, Collidable, CollisionData};
 
-impl Shape for Circle {
-    type Underlying = geometry::Circle;
-}
-
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Circle {
     radius: f64,
     angle: f64,
@@ -48,6 +48,22 @@ impl Collidable for Circle {
         &mut self.collision_properties
     }
 
+    fn aabb(&self) -> Aabb {
+        let extent = Point(self.radius, self.radius);
+        Aabb {
+            min: self.collision_properties.centroid - extent,
+            max: self.collision_properties.centroid + extent,
+        }
+    }
+
+    fn bounding_circle(&self) -> (Point, f64) {
+        (self.collision_properties.centroid, self.radius)
+    }
+
+    fn angle(&self) -> f64 {
+        self.angle
+    }
+
     fn translate(&mut self, translation: Vector) {
         self.collision_properties.centroid += translation;
     }
@@ -56,6 +72,12 @@ impl Collidable for Circle {
         self.angle += angle;
     }
 
+    fn scale(&mut self, factor: f64) {
+        self.radius *= factor;
+        self.collision_properties.mass *= factor.powi(2);
+        self.collision_properties.inertia *= factor.powi(4);
+    }
+
     fn resolve_point_reference(&self, point_ref: PointOnShape) -> Point {
         (Point(self.radius, 0.0).rotate(point_ref.angle_offset + self.angle)
             * point_ref.length_scale)
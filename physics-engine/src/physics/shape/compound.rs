@@ -0,0 +1,244 @@
+use crate::{
+    geometry::{Aabb, Point, Vector},
+    physics::binding::PointOnShape,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{Bounded, Collidable, CollisionData, Polygon};
+
+/// A rigid body made of convex polygons, like the handle and the head of a hammer,
+/// moving as one. Collisions are detected against every part separately, so the
+/// body need not be convex, see [`Collidable::parts`]. The parts keep their own
+/// mass properties, the ones of the body combine them about their common centroid
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(from = "CompoundFields")]
+pub struct Compound {
+    parts: Vec<Polygon>,
+    collision_properties: CollisionData,
+    angle: f64,
+    /// derived from the parts, so it is recomputed rather than deserialized
+    #[serde(skip)]
+    aabb: Aabb,
+    /// distance of the farthest vertex from the centroid, derived like the bounding box
+    #[serde(skip)]
+    radius: f64,
+}
+
+/// the serialized fields of a [`Compound`]
+#[derive(Deserialize)]
+struct CompoundFields {
+    parts: Vec<Polygon>,
+    collision_properties: CollisionData,
+    angle: f64,
+}
+
+impl From<CompoundFields> for Compound {
+    fn from(fields: CompoundFields) -> Self {
+        Self {
+            aabb: Compound::aabb_of(&fields.parts),
+            radius: Compound::radius(fields.collision_properties.centroid, &fields.parts),
+            parts: fields.parts,
+            collision_properties: fields.collision_properties,
+            angle: fields.angle,
+        }
+    }
+}
+
+impl Compound {
+    /// the parts must not be empty, and must all have a positive mass
+    pub fn new(mut parts: Vec<Polygon>) -> Self {
+        let mass: f64 = parts
+            .iter_mut()
+            .map(|part| part.collision_data_mut().mass)
+            .sum();
+        let centroid = parts
+            .iter_mut()
+            .map(|part| {
+                let data = part.collision_data_mut();
+                data.centroid * (data.mass / mass)
+            })
+            .fold(Point::ZERO, |sum, weighted| sum + weighted);
+        // the parallel axis theorem moves the inertia of every part to the common centroid
+        let inertia = parts
+            .iter_mut()
+            .map(|part| {
+                let data = part.collision_data_mut();
+                let offset = centroid.to(data.centroid);
+                data.inertia + data.mass * offset.dot(offset)
+            })
+            .sum();
+
+        Self {
+            aabb: Self::aabb_of(&parts),
+            radius: Self::radius(centroid, &parts),
+            parts,
+            collision_properties: CollisionData {
+                centroid,
+                mass,
+                inertia,
+                velocity: Vector::ZERO,
+                angular_velocity: 0.0,
+            },
+            angle: 0.0,
+        }
+    }
+
+    fn aabb_of(parts: &[Polygon]) -> Aabb {
+        Aabb::around(parts.iter().flat_map(|part| {
+            let aabb = part.aabb();
+            [aabb.min, aabb.max]
+        }))
+    }
+
+    fn radius(centroid: Point, parts: &[Polygon]) -> f64 {
+        parts
+            .iter()
+            .flat_map(|part| {
+                let rounding = part.corner_radius();
+                part.vertices()
+                    .iter()
+                    .map(move |&vertex| centroid.to(vertex).norm() + rounding)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// moves every part so that it keeps its offset from the centroid,
+    /// after the offset is changed by `change`
+    fn move_parts(&mut self, change: impl Fn(Vector) -> Vector) {
+        let centroid = self.collision_properties.centroid;
+        for part in &mut self.parts {
+            let offset = centroid.to(part.collision_data_mut().centroid);
+            part.translate(change(offset) - offset);
+        }
+    }
+}
+
+impl Bounded for Compound {
+    /// the support of the hull of the parts
+    fn support_vector(&self, direction: Vector) -> Point {
+        self.parts
+            .iter()
+            .map(|part| part.support_vector(direction))
+            .max_by(|first, second| first.dot(direction).total_cmp(&second.dot(direction)))
+            .expect("a compound of no parts")
+    }
+
+    fn includes(&self, point: Point) -> bool {
+        self.parts.iter().any(|part| part.includes(point))
+    }
+}
+
+impl Collidable for Compound {
+    fn rotate(&mut self, angle: f64) {
+        for part in &mut self.parts {
+            part.rotate(angle);
+        }
+        self.move_parts(|offset| offset.rotate(angle));
+        self.aabb = Self::aabb_of(&self.parts);
+
+        self.angle += angle;
+    }
+
+    fn translate(&mut self, translation: Vector) {
+        for part in &mut self.parts {
+            part.translate(translation);
+        }
+        self.collision_properties.centroid += translation;
+        self.aabb.min += translation;
+        self.aabb.max += translation;
+    }
+
+    fn scale(&mut self, factor: f64) {
+        for part in &mut self.parts {
+            part.scale(factor);
+        }
+        self.move_parts(|offset| offset * factor);
+        self.aabb = Self::aabb_of(&self.parts);
+        self.radius *= factor;
+
+        self.collision_properties.mass *= factor.powi(2);
+        self.collision_properties.inertia *= factor.powi(4);
+    }
+
+    fn collision_data_mut(&mut self) -> &mut CollisionData {
+        &mut self.collision_properties
+    }
+
+    fn aabb(&self) -> Aabb {
+        self.aabb
+    }
+
+    fn bounding_circle(&self) -> (Point, f64) {
+        (self.collision_properties.centroid, self.radius)
+    }
+
+    fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    fn parts(&self) -> Option<&[Polygon]> {
+        Some(&self.parts)
+    }
+
+    fn resolve_point_reference(&self, point_ref: PointOnShape) -> Point {
+        (self
+            .collision_properties
+            .centroid
+            .to(self.parts[0].vertices()[0])
+            .rotate(point_ref.angle_offset)
+            * point_ref.length_scale)
+            + self.collision_properties.centroid
+    }
+
+    fn create_point_reference(&self, point: Point) -> PointOnShape {
+        let to_first_vertex = self
+            .collision_properties
+            .centroid
+            .to(self.parts[0].vertices()[0]);
+        let to_point = self.collision_properties.centroid.to(point);
+        PointOnShape {
+            angle_offset: to_first_vertex.angle_to(to_point),
+            length_scale: to_point.norm() / to_first_vertex.norm(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::make_shape;
+
+    #[test]
+    fn test_mass_properties() {
+        let halves = || {
+            vec![
+                make_shape! { (0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0) },
+                make_shape! { (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0) },
+            ]
+        };
+        let mut whole = make_shape! { (0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0) };
+        let mut compound = Compound::new(halves());
+        let expected = whole.collision_data_mut().clone();
+        let data = compound.collision_data_mut();
+        assert!(data.centroid.to(expected.centroid).norm() < 1e-12);
+        assert!((data.mass - expected.mass).abs() < 1e-12);
+        assert!((data.inertia - expected.inertia).abs() < 1e-12);
+
+        compound.rotate(std::f64::consts::FRAC_PI_2);
+        compound.scale(2.0);
+        whole.rotate(std::f64::consts::FRAC_PI_2);
+        whole.scale(2.0);
+        assert!(compound.aabb().min.to(whole.aabb().min).norm() < 1e-9);
+        assert!(compound.aabb().max.to(whole.aabb().max).norm() < 1e-9);
+        assert!((compound.bounding_circle().1 - whole.bounding_circle().1).abs() < 1e-9);
+        assert!(compound.includes(Point(1.0, 0.5)));
+        assert!(!compound.includes(Point(1.0, 3.0)));
+
+        let json = serde_json::to_string(&compound).unwrap();
+        let deserialized: Compound = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.aabb(), compound.aabb());
+    }
+}
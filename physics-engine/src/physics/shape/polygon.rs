@@ -1,24 +1,65 @@
 use crate::{
-    geometry::{self, windows, Point, Vector},
+    geometry::{self, windows, Aabb, Point, Vector},
     physics::{binding::PointOnShape, compute},
 };
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
 use super::{Bounded, Collidable, CollisionData};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(from = "PolygonFields")]
 pub struct Polygon {
     vertices: Vec<Point>,
+    /// how far the outline is rounded out beyond the vertices and the edges,
+    /// see [`Polygon::with_corner_radius`]
+    corner_radius: f64,
     collision_properties: CollisionData,
     angle: f64,
+    /// derived from the vertices, so it is recomputed rather than deserialized
+    #[serde(skip)]
+    aabb: Aabb,
+    /// distance of the farthest vertex from the centroid, derived like the bounding box
+    #[serde(skip)]
+    radius: f64,
+}
+
+/// the serialized fields of a [`Polygon`]
+#[derive(Deserialize)]
+struct PolygonFields {
+    vertices: Vec<Point>,
+    /// missing from polygons serialized before they could be rounded
+    #[serde(default)]
+    corner_radius: f64,
+    collision_properties: CollisionData,
+    angle: f64,
+}
+
+impl From<PolygonFields> for Polygon {
+    fn from(fields: PolygonFields) -> Self {
+        Self {
+            aabb: Polygon::aabb_of(&fields.vertices, fields.corner_radius),
+            radius: Polygon::radius(fields.collision_properties.centroid, &fields.vertices)
+                + fields.corner_radius,
+            vertices: fields.vertices,
+            corner_radius: fields.corner_radius,
+            collision_properties: fields.collision_properties,
+            angle: fields.angle,
+        }
+    }
 }
 
 impl Polygon {
     pub fn new(vertices: Vec<Point>) -> Self {
-        let centroid = compute::centroid(&vertices);
-        let (inertia, mass) = Self::intertia_and_mass(centroid, &vertices);
+        let (centroid, mass, inertia) = Self::mass_properties(&vertices);
 
         Self {
+            aabb: Aabb::around(vertices.iter().copied()),
+            radius: Self::radius(centroid, &vertices),
             vertices,
+            corner_radius: 0.0,
             collision_properties: CollisionData {
                 mass,
                 inertia,
@@ -30,6 +71,63 @@ impl Polygon {
         }
     }
 
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    /// rounds the outline out by `corner_radius`, to the points that far from the
+    /// polygon, so that its corners roll and slide over others like circles do.
+    /// The mass and the inertia stay the ones of the vertices
+    pub fn with_corner_radius(mut self, corner_radius: f64) -> Self {
+        self.radius += corner_radius - self.corner_radius;
+        self.corner_radius = corner_radius;
+        self.aabb = Self::aabb_of(&self.vertices, corner_radius);
+        self
+    }
+
+    pub fn corner_radius(&self) -> f64 {
+        self.corner_radius
+    }
+
+    /// replaces the outline of the polygon, recomputing its centroid, mass and inertia.
+    /// The density is kept, so weighted polygons stay as heavy per area and static ones
+    /// stay static, and so are the velocities and the total rotation
+    pub fn set_vertices(&mut self, vertices: Vec<Point>) {
+        let (_, unit_mass, _) = Self::mass_properties(&self.vertices);
+        let density = self.collision_properties.mass / unit_mass;
+
+        let (centroid, mass, inertia) = Self::mass_properties(&vertices);
+        self.aabb = Self::aabb_of(&vertices, self.corner_radius);
+        self.radius = Self::radius(centroid, &vertices) + self.corner_radius;
+        self.vertices = vertices;
+        self.collision_properties.centroid = centroid;
+        self.collision_properties.mass = mass * density;
+        self.collision_properties.inertia = inertia * density;
+    }
+
+    fn aabb_of(vertices: &[Point], corner_radius: f64) -> Aabb {
+        let aabb = Aabb::around(vertices.iter().copied());
+        let extent = Point(corner_radius, corner_radius);
+        Aabb {
+            min: aabb.min - extent,
+            max: aabb.max + extent,
+        }
+    }
+
+    fn radius(centroid: Point, vertices: &[Point]) -> f64 {
+        vertices
+            .iter()
+            .map(|&vertex| centroid.to(vertex).norm())
+            .fold(0.0, f64::max)
+    }
+
+    /// centroid, mass and inertia of an outline of unit density
+    fn mass_properties(vertices: &[Point]) -> (Point, f64, f64) {
+        let centroid = compute::centroid(vertices);
+        let (inertia, mass) = Self::intertia_and_mass(centroid, vertices);
+        (centroid, mass, inertia)
+    }
+
     fn intertia_and_mass(centroid: Point, vertices: &[Point]) -> (f64, f64) {
         let centroid_norm_squared = centroid.dot(centroid);
         let (inertia_sum, mass_sum) = windows::Looped::from(
@@ -61,11 +159,12 @@ impl Polygon {
 
 impl Bounded for Polygon {
     fn support_vector(&self, direction: Vector) -> Vector {
-        *self
-            .vertices
-            .iter()
-            .max_by(|&&p1, &&p2| direction.dot(p1).partial_cmp(&direction.dot(p2)).unwrap())
-            .unwrap()
+        let farthest = compute::support::farthest_point(&self.vertices, direction);
+        if self.corner_radius > 0.0 {
+            farthest + direction.unit() * self.corner_radius
+        } else {
+            farthest
+        }
     }
 
     fn includes(&self, point: Point) -> bool {
@@ -73,7 +172,10 @@ impl Bounded for Polygon {
         for [p1, p2] in windows::Looped::from(self.vertices.iter().copied()) {
             let next = p1.to(p2).perpendicular().dot(p1.to(point));
             if last * next < 0.0 {
-                return false;
+                // outside the vertices, though maybe not outside the rounding
+                return self.corner_radius > 0.0
+                    && windows::Looped::from(self.vertices.iter().copied())
+                        .any(|[p1, p2]| point.distance_to_segment(p1, p2) <= self.corner_radius);
             }
 
             last = next;
@@ -88,6 +190,7 @@ impl Collidable for Polygon {
             let offset = self.collision_properties.centroid.to(*v);
             *v = offset.rotate(angle) + self.collision_properties.centroid;
         });
+        self.aabb = Self::aabb_of(&self.vertices, self.corner_radius);
 
         self.angle += angle;
     }
@@ -95,12 +198,39 @@ impl Collidable for Polygon {
     fn translate(&mut self, translation: Vector) {
         self.vertices.iter_mut().for_each(|v| *v += translation);
         self.collision_properties.centroid += translation;
+        self.aabb.min += translation;
+        self.aabb.max += translation;
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.vertices.iter_mut().for_each(|v| {
+            let offset = self.collision_properties.centroid.to(*v);
+            *v = offset * factor + self.collision_properties.centroid;
+        });
+        self.corner_radius *= factor;
+        self.aabb = Self::aabb_of(&self.vertices, self.corner_radius);
+        self.radius *= factor;
+
+        self.collision_properties.mass *= factor.powi(2);
+        self.collision_properties.inertia *= factor.powi(4);
     }
 
     fn collision_data_mut(&mut self) -> &mut CollisionData {
         &mut self.collision_properties
     }
 
+    fn aabb(&self) -> Aabb {
+        self.aabb
+    }
+
+    fn bounding_circle(&self) -> (Point, f64) {
+        (self.collision_properties.centroid, self.radius)
+    }
+
+    fn angle(&self) -> f64 {
+        self.angle
+    }
+
     fn resolve_point_reference(&self, point_ref: PointOnShape) -> Point {
         (self
             .collision_properties
@@ -125,6 +255,7 @@ impl From<Polygon> for geometry::Polygon {
     fn from(shape: Polygon) -> Self {
         Self {
             vertices: shape.vertices,
+            corner_radius: shape.corner_radius,
             centroid: shape.collision_properties.centroid,
         }
     }
@@ -133,6 +264,7 @@ impl From<Polygon> for geometry::Polygon {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::physics::make_shape;
 
     #[test]
     fn test_includes() {
@@ -146,4 +278,97 @@ mod test {
         assert!(polygon.includes(Point(0.2, 0.4)));
         assert!(!polygon.includes(Point(0.2, 0.6)));
     }
+
+    #[test]
+    fn test_aabb() {
+        let mut polygon = Polygon::new(vec![
+            Point(0.0, 0.0),
+            Point(2.0, 0.0),
+            Point(2.0, 1.0),
+            Point(0.0, 1.0),
+        ]);
+        polygon.translate(Point(1.0, 1.0));
+        polygon.rotate(std::f64::consts::FRAC_PI_2);
+        polygon.scale(2.0);
+
+        let expected = Aabb::around(polygon.vertices.iter().copied());
+        assert_eq!(polygon.aabb(), expected);
+        assert!((expected.max.0 - expected.min.0 - 2.0).abs() < 1e-9);
+        assert!((expected.max.1 - expected.min.1 - 4.0).abs() < 1e-9);
+
+        let json = serde_json::to_string(&polygon).unwrap();
+        let deserialized: Polygon = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.aabb(), expected);
+    }
+
+    #[test]
+    fn test_set_vertices() {
+        let square = |side: f64| {
+            vec![
+                Point(0.0, 0.0),
+                Point(side, 0.0),
+                Point(side, side),
+                Point(0.0, side),
+            ]
+        };
+        let mut polygon = Polygon::new(square(1.0));
+        polygon.collision_data_mut().mass *= 3.0;
+        polygon.collision_data_mut().inertia *= 3.0;
+        polygon.collision_data_mut().velocity = Point(1.0, 0.0);
+
+        polygon.set_vertices(square(2.0));
+        let expected = Polygon::new(square(2.0));
+        let data = polygon.collision_data_mut();
+        assert_eq!(data.centroid, Point(1.0, 1.0));
+        assert_eq!(data.mass, 3.0 * expected.collision_properties.mass);
+        assert_eq!(data.inertia, 3.0 * expected.collision_properties.inertia);
+        assert_eq!(data.velocity, Point(1.0, 0.0));
+        assert_eq!(polygon.aabb(), Aabb::around(square(2.0)));
+
+        polygon.collision_data_mut().mass = f64::INFINITY;
+        polygon.collision_data_mut().inertia = f64::INFINITY;
+        polygon.set_vertices(square(1.0));
+        assert_eq!(polygon.collision_data_mut().mass, f64::INFINITY);
+        assert_eq!(polygon.collision_data_mut().inertia, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_corner_radius() {
+        let square = || {
+            Polygon::new(vec![
+                Point(0.0, 0.0),
+                Point(1.0, 0.0),
+                Point(1.0, 1.0),
+                Point(0.0, 1.0),
+            ])
+        };
+        let mut rounded = square().with_corner_radius(0.25);
+        let diagonal = Point(1.0, 1.0);
+        let corner = Point(1.0, 1.0) + diagonal.unit() * 0.25;
+        assert!(rounded.support_vector(diagonal).is_close_enough_to(corner));
+        assert!(rounded.includes(Point(1.2, 0.5)));
+        assert!(rounded.includes(Point(1.15, 1.15)));
+        assert!(!rounded.includes(Point(1.2, 1.2)));
+        assert!(!square().includes(Point(1.2, 0.5)));
+        assert_eq!(rounded.aabb().min, Point(-0.25, -0.25));
+
+        // it touches a shape near the middle of an edge before the vertices do
+        let beside = make_shape! { (1.1, 0.4), (2.0, 0.4), (2.0, 0.6), (1.1, 0.6) };
+        assert!(compute::collision(&rounded, &beside).is_some());
+        assert!(compute::collision(&square(), &beside).is_none());
+
+        rounded.scale(2.0);
+        assert_eq!(rounded.corner_radius(), 0.5);
+        assert!((rounded.bounding_circle().1 - (2.0f64.sqrt() + 0.5)).abs() < 1e-12);
+        let json = serde_json::to_string(&rounded).unwrap();
+        let deserialized: Polygon = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.aabb(), rounded.aabb());
+        // polygons serialized before they could be rounded are sharp
+        let json = serde_json::to_string(&square())
+            .unwrap()
+            .replace(",\"corner_radius\":0.0", "");
+        assert!(!json.contains("corner_radius"));
+        let sharp: Polygon = serde_json::from_str(&json).unwrap();
+        assert_eq!(sharp.corner_radius(), 0.0);
+    }
 }
@@ -0,0 +1,144 @@
+//! Pickups that grow or shrink the main ball for a while
+//!
+//! When the main ball touches a pickup, the pickup disappears and the ball is
+//! scaled to the size of the pickup for its duration, along with its mass and
+//! inertia. A later pickup replaces the effect of an earlier one.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    compute,
+    game::GameEvent,
+    gravity::PICKUP_RADIUS,
+    shape::{Circle, Collidable},
+};
+use crate::{geometry::Vector, levels::SizePickup};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+struct Effect {
+    scale: f64,
+    remaining_microseconds: f64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BallSize {
+    /// pickups the ball has not touched yet
+    pickups: Vec<SizePickup>,
+    effect: Option<Effect>,
+}
+
+impl BallSize {
+    pub fn new(pickups: Vec<SizePickup>) -> Self {
+        Self {
+            pickups,
+            effect: None,
+        }
+    }
+
+    pub fn pickups(&self) -> &[SizePickup] {
+        &self.pickups
+    }
+
+    /// the size of the ball relative to its original size
+    pub fn scale(&self) -> f64 {
+        self.effect.map_or(1.0, |effect| effect.scale)
+    }
+
+    pub fn seconds_remaining(&self) -> Option<f64> {
+        self.effect
+            .map(|effect| effect.remaining_microseconds / 1_000_000.0)
+    }
+
+    /// picks up every pickup the ball touches, the last one taking effect
+    pub fn collect(&mut self, ball: &mut dyn Collidable, events: &mut Vec<GameEvent>) {
+        let mut scale = self.scale();
+        self.pickups.retain(|pickup| {
+            let is_touched =
+                compute::intersects(&*ball, &Circle::new(pickup.position, PICKUP_RADIUS));
+            if is_touched {
+                ball.scale(pickup.scale / scale);
+                scale = pickup.scale;
+                self.effect = Some(Effect {
+                    scale: pickup.scale,
+                    remaining_microseconds: pickup.duration_seconds * 1_000_000.0,
+                });
+                events.push(GameEvent::BallResized {
+                    scale: pickup.scale,
+                    seconds: pickup.duration_seconds,
+                });
+            }
+            !is_touched
+        });
+    }
+
+    pub(super) fn shift(&mut self, translation: Vector) {
+        for pickup in &mut self.pickups {
+            pickup.position += translation;
+        }
+    }
+
+    /// runs down the current effect, scaling the ball back once it is over
+    pub fn advance(
+        &mut self,
+        microseconds: f64,
+        ball: &mut dyn Collidable,
+        events: &mut Vec<GameEvent>,
+    ) {
+        let Some(effect) = &mut self.effect else {
+            return;
+        };
+
+        effect.remaining_microseconds -= microseconds;
+        if effect.remaining_microseconds <= 0.0 {
+            ball.scale(effect.scale.recip());
+            self.effect = None;
+            events.push(GameEvent::BallSizeRestored);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::{self, Point};
+
+    #[test]
+    fn test_pickup() {
+        let mut size = BallSize::new(vec![SizePickup {
+            position: Point(1.0, 0.0),
+            scale: 2.0,
+            duration_seconds: 1.0,
+        }]);
+        let mut ball = Circle::new(Point(0.0, 0.0), 0.1);
+        let mass = ball.collision_data_mut().mass;
+        let mut events = vec![];
+
+        size.collect(&mut ball, &mut events);
+        assert!(events.is_empty());
+
+        ball.translate(Point(0.85, 0.0));
+        size.collect(&mut ball, &mut events);
+        assert!(size.pickups().is_empty());
+        assert_eq!(size.scale(), 2.0);
+        assert_eq!(geometry::Circle::from(ball.clone()).radius, 0.2);
+        assert_eq!(ball.collision_data_mut().mass, mass * 4.0);
+
+        size.advance(1_200_000.0, &mut ball, &mut events);
+        assert_eq!(size.scale(), 1.0);
+        assert_eq!(geometry::Circle::from(ball.clone()).radius, 0.1);
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::BallResized {
+                    scale: 2.0,
+                    seconds: 1.0
+                },
+                GameEvent::BallSizeRestored
+            ]
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Finding entities by their id, and the order they are processed in
+//!
+//! The slots map the id of every entity to its index in the vector of
+//! entities. Removing a single entity swaps the last one into its place, so
+//! only the slot of that one changes and the removal takes constant time.
+//! Bindings point to their targets rather than to indices, so moving an entity
+//! does not affect them. The removals of a step go through `retain`, which
+//! keeps the order, and the slots are renumbered once afterwards instead.
+//!
+//! The canonical order of the entities is by their id. Every step starts by
+//! restoring it, once for however many entities were removed since the last
+//! step, so that the pairs are resolved and the bindings enforced in the same
+//! order no matter how the entities happen to be stored. Point queries pick
+//! the entity with the lowest id, as they may run between the steps.
+//!
+//! With a spatial order interval in the config, the entities are instead
+//! placed along a Z-order curve over their centroids every so many steps, and
+//! ordered by their place on it, then by their id, so that the entities close
+//! to each other are next to each other in the vector. The shapes themselves
+//! stay wherever they were allocated behind their `Rc`s, so whether this pays
+//! off depends on the level; the `spatial_order` benchmark measures it. The
+//! places only change at those steps, so the order still follows from the
+//! state of the world alone. Entities added in between come
+//! after the placed ones, and the main ball stays first either way.
+
+use std::collections::HashMap;
+
+use super::{ids::EntityId, Engine, Entity};
+use crate::geometry::Point;
+
+/// the order key of the entities that were not placed along the curve
+pub(super) const UNPLACED: u32 = u32::MAX;
+
+/// bits of either coordinate on the curve, so that no place equals [`UNPLACED`]
+const CURVE_BITS: u32 = 15;
+
+#[derive(Default)]
+pub(super) struct Slots(HashMap<EntityId, usize>);
+
+impl Slots {
+    pub fn of(entities: &[Entity]) -> Self {
+        Self(
+            entities
+                .iter()
+                .enumerate()
+                .map(|(slot, entity)| (entity.id, slot))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<usize> {
+        self.0.get(&id).copied()
+    }
+
+    pub fn insert(&mut self, id: EntityId, slot: usize) {
+        self.0.insert(id, slot);
+    }
+}
+
+impl Engine {
+    /// removes the entity in `slot`, moving the last entity into it
+    pub(super) fn remove_entity(&mut self, slot: usize) -> Entity {
+        let removed = self.entities.swap_remove(slot);
+        self.slots.0.remove(&removed.id);
+        if let Some(moved) = self.entities.get(slot) {
+            self.slots.insert(moved.id, slot);
+        }
+        removed
+    }
+
+    /// to be called after removing entities in any other way than [`Self::remove_entity`]
+    pub(super) fn renumber_slots(&mut self) {
+        // nothing was removed if the counts still match
+        if self.slots.0.len() != self.entities.len() {
+            self.rebuild_slots();
+        }
+    }
+
+    /// places the entities along the curve when it is time to, and sorts them
+    /// if that or a removal changed their order
+    pub(super) fn restore_canonical_order(&mut self) {
+        if let Some(interval) = self.config.spatial_order_interval {
+            if self.steps_since_reorder == 0 {
+                self.place_along_curve();
+            }
+            self.steps_since_reorder = (self.steps_since_reorder + 1) % interval.max(1);
+        }
+
+        let Some((_main_ball, rest)) = self.entities.split_first_mut() else {
+            return;
+        };
+        let key = |entity: &Entity| (entity.order_key, entity.id);
+        if !rest.is_sorted_by_key(key) {
+            rest.sort_unstable_by_key(key);
+            self.rebuild_slots();
+        }
+    }
+
+    /// back to the order of the ids, placing the entities again at the next step
+    pub(super) fn reset_spatial_order(&mut self) {
+        for entity in &mut self.entities {
+            entity.order_key = UNPLACED;
+        }
+        self.steps_since_reorder = 0;
+    }
+
+    fn place_along_curve(&mut self) {
+        let Some((_main_ball, rest)) = self.entities.split_first_mut() else {
+            return;
+        };
+        let centroid = |entity: &Entity| entity.shape.borrow_mut().collision_data_mut().centroid;
+
+        let (mut min, mut max) = (
+            Point(f64::INFINITY, f64::INFINITY),
+            Point(f64::NEG_INFINITY, f64::NEG_INFINITY),
+        );
+        for point in rest.iter().map(centroid) {
+            min = Point(min.0.min(point.0), min.1.min(point.1));
+            max = Point(max.0.max(point.0), max.1.max(point.1));
+        }
+        let cells = (1 << CURVE_BITS) as f64 - 1.0;
+        let extent = (max.0 - min.0).max(max.1 - min.1);
+        let scale = if extent > 0.0 { cells / extent } else { 0.0 };
+
+        for entity in rest {
+            let point = centroid(entity);
+            // casting saturates, and takes NaN to 0
+            let cell = |value: f64, min: f64| ((value - min) * scale) as u32;
+            entity.order_key =
+                interleave(cell(point.0, min.0)) | interleave(cell(point.1, min.1)) << 1;
+        }
+    }
+
+    fn rebuild_slots(&mut self) {
+        self.slots.0.clear();
+        let slots = self.entities.iter().enumerate();
+        self.slots
+            .0
+            .extend(slots.map(|(slot, entity)| (entity.id, slot)));
+    }
+
+    /// the slot of the entity with the lowest id of those under `point` that pass `filter`
+    pub(super) fn slot_at(&self, point: Point, filter: impl Fn(&Entity) -> bool) -> Option<usize> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| entity.includes(point) && filter(entity))
+            .min_by_key(|(_, entity)| entity.id)
+            .map(|(slot, _)| slot)
+    }
+}
+
+/// spreads the low [`CURVE_BITS`] bits of `value` to every other bit
+fn interleave(value: u32) -> u32 {
+    let mut value = value.min((1 << CURVE_BITS) - 1);
+    value = (value | value << 8) & 0x00ff_00ff;
+    value = (value | value << 4) & 0x0f0f_0f0f;
+    value = (value | value << 2) & 0x3333_3333;
+    (value | value << 1) & 0x5555_5555
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use super::*;
+    use crate::{
+        geometry::Point,
+        physics::{capacity::EngineConfig, make_shape, narrowphase, sandbox::SandboxConfig},
+    };
+
+    #[test]
+    fn test_remove_entity() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let erased = engine
+            .add_polygon(make_shape! { (4.0, 4.0), (5.0, 4.0), (5.0, 5.0), (4.0, 5.0) })
+            .unwrap();
+        let holder = engine
+            .add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) })
+            .unwrap();
+        engine.add_hinge(Point(1.5, 1.5), 0.0);
+        let target = engine
+            .add_polygon(make_shape! { (1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0) })
+            .unwrap();
+
+        assert_eq!(engine.erase(erased), Some(erased));
+        assert_eq!(engine.slots.get(erased), None);
+        assert_eq!(engine.slots.get(target), Some(1));
+        assert_eq!(engine.slots.get(holder), Some(2));
+
+        // the target of the binding now comes before the entity holding it,
+        // and still does not collide with it
+        let scratch = Bump::new();
+        let pairs = narrowphase::candidate_pairs(&mut engine.entities, &scratch);
+        assert!(!pairs.contains(&(1, 2)));
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let build = || {
+            let mut engine = Engine::sandbox(SandboxConfig::default());
+            engine.add_prefab_polygon(
+                make_shape! { (-3.0, 6.0), (3.0, 6.0), (3.0, 7.0), (-3.0, 7.0) },
+                true,
+            );
+            for i in 0..4 {
+                let y = 1.0 + i as f64 * 0.9;
+                engine.add_polygon(
+                    make_shape! { (0.0, y), (1.0, y), (1.0, y + 1.0), (0.0, y + 1.0) },
+                );
+            }
+            engine.add_polygon(make_shape! { (-2.0, 5.0), (2.0, 5.0), (2.0, 5.5), (-2.0, 5.5) });
+            engine
+        };
+        let mut engine = build();
+        let mut reordered = build();
+        reordered.entities[1..].reverse();
+        reordered.rebuild_slots();
+
+        // the lowest id under the point, wherever it is stored
+        let lowest = engine.describe_at(Point(0.5, 1.95)).entity;
+        assert_eq!(reordered.describe_at(Point(0.5, 1.95)).entity, lowest);
+
+        let message = engine.run_n_iterations(200, 2000.0);
+        let reordered_message = reordered.run_n_iterations(200, 2000.0);
+        // the colors are random, the shapes have to match exactly
+        let shapes = |message: &crate::physics::DisplayMessage| {
+            let shapes: Vec<_> = message
+                .polygons
+                .iter()
+                .map(|polygon| &polygon.shape)
+                .collect();
+            serde_json::to_string(&shapes).unwrap()
+        };
+        assert_eq!(shapes(&reordered_message), shapes(&message));
+    }
+
+    #[test]
+    fn test_spatial_order() {
+        let build = || {
+            let mut engine = Engine::sandbox(SandboxConfig {
+                world_half_extent: None,
+                ..Default::default()
+            });
+            for (x, y) in [
+                (10.0, 10.0),
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (0.0, 10.0),
+                (0.6, 0.6),
+            ] {
+                engine.add_polygon(
+                    make_shape! { (x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0) },
+                );
+            }
+            engine.set_config(EngineConfig {
+                spatial_order_interval: Some(10),
+                ..Default::default()
+            });
+            engine
+        };
+        let mut engine = build();
+        let mut reordered = build();
+        reordered.entities[1..].reverse();
+        reordered.rebuild_slots();
+
+        let message = engine.run_n_iterations(25, 2000.0);
+        let reordered_message = reordered.run_n_iterations(25, 2000.0);
+
+        let corners: Vec<_> = engine.entities[1..]
+            .iter()
+            .map(|entity| {
+                let aabb = entity.shape.borrow().aabb();
+                (aabb.min.0.round(), aabb.min.1.round())
+            })
+            .collect();
+        assert_eq!(
+            corners,
+            [
+                (0.0, 0.0),
+                (1.0, 1.0),
+                (10.0, 0.0),
+                (0.0, 10.0),
+                (10.0, 10.0)
+            ]
+        );
+        for (slot, entity) in engine.entities.iter().enumerate() {
+            assert_eq!(engine.slots.get(entity.id), Some(slot));
+        }
+
+        let shapes = |message: &crate::physics::DisplayMessage| {
+            let shapes: Vec<_> = message
+                .polygons
+                .iter()
+                .map(|polygon| &polygon.shape)
+                .collect();
+            serde_json::to_string(&shapes).unwrap()
+        };
+        assert_eq!(shapes(&reordered_message), shapes(&message));
+    }
+}
@@ -0,0 +1,136 @@
+//! Snapping what is drawn to a grid and to angle increments
+//!
+//! Both are off by default. With a grid, circles are centered on the grid
+//! point closest to them, and the vertices of polygons and the anchors of
+//! bindings move to theirs. With an angle increment, polygons are first
+//! turned about their centroid until their first edge lies at a multiple of
+//! it, so the grid has the last word on where the vertices end up. A polygon
+//! that snapping flattens into a line or a point is not added at all.
+//!
+//! The grid is fixed in the coordinates of the level, so shifting the origin
+//! does not move it. The level editor snaps the ball start and the flags to the
+//! same grid, so that they line up with the shapes. Batches are added as given.
+//!
+//! The edge distance only applies to the strokes of [`super::assist`].
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    shape::{Collidable, Polygon},
+    Engine,
+};
+use crate::geometry::Point;
+
+/// Missing fields take their default values, which snap nothing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct Snapping {
+    /// spacing of the grid, `None` (`null`) to leave the positions as they are
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub grid: Option<f64>,
+    /// in radians, `None` (`null`) to leave the polygons turned as they are
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub angle_increment: Option<f64>,
+    /// points of assisted strokes this close to the edge of a shape move onto it,
+    /// `None` (`null`) to leave them where the grid puts them
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub edge_distance: Option<f64>,
+}
+
+/// `value` rounded to a multiple of `step`, if that is a positive number
+pub(super) fn round_to(value: f64, step: Option<f64>) -> f64 {
+    match step {
+        Some(step) if step > 0.0 && step.is_finite() => (value / step).round() * step,
+        _ => value,
+    }
+}
+
+impl Engine {
+    pub fn snapping(&self) -> Snapping {
+        self.snapping
+    }
+
+    pub fn set_snapping(&mut self, snapping: Snapping) {
+        self.snapping = snapping;
+    }
+
+    /// the grid point closest to `point`
+    pub(super) fn snap_point(&self, point: Point) -> Point {
+        let Point(x, y) = point + self.origin;
+        let grid = self.snapping.grid;
+        Point(round_to(x, grid), round_to(y, grid)) - self.origin
+    }
+
+    /// returns whether the snapped polygon still has an area
+    pub(super) fn snap_polygon(&self, polygon: &mut Polygon) -> bool {
+        if self.snapping == Snapping::default() {
+            return true;
+        }
+
+        let mut vertices = polygon.vertices().to_vec();
+        if let [first, second, ..] = vertices[..] {
+            let edge = first.to(second);
+            let angle = edge.1.atan2(edge.0);
+            let turn = round_to(angle, self.snapping.angle_increment) - angle;
+            let centroid = polygon.collision_data_mut().centroid;
+            for vertex in &mut vertices {
+                *vertex = centroid + centroid.to(*vertex).rotate(turn);
+            }
+        }
+        for vertex in &mut vertices {
+            *vertex = self.snap_point(*vertex);
+        }
+        vertices.dedup();
+        if vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+        if vertices.len() < 3 {
+            return false;
+        }
+
+        polygon.set_vertices(vertices);
+        let mass = polygon.collision_data_mut().mass;
+        mass.is_finite() && mass > 0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig, shape::Circle};
+
+    #[test]
+    fn test_snapping() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.set_snapping(Snapping {
+            grid: Some(0.5),
+            angle_increment: Some(std::f64::consts::FRAC_PI_4),
+            ..Snapping::default()
+        });
+
+        // a square turned by a little less than 45 degrees
+        let turned = make_shape! { (1.0, 0.0), (1.7, 0.75), (0.95, 1.45), (0.25, 0.7) };
+        let id = engine.add_polygon(turned).unwrap();
+        let slot = engine.slots.get(id).unwrap();
+        let shape = engine.entities[slot].shape.borrow();
+        assert!(shape.includes(Point(1.0, 1.0)));
+        assert_eq!(shape.aabb().min, Point(0.0, 0.0));
+        assert_eq!(shape.aabb().max, Point(1.5, 1.5));
+        drop(shape);
+
+        let circle = engine
+            .add_circle(Circle::new(Point(2.2, 2.3), 0.2))
+            .unwrap();
+        let slot = engine.slots.get(circle).unwrap();
+        let center = engine.entities[slot].shape.borrow().bounding_circle().0;
+        assert_eq!(center, Point(2.0, 2.5));
+
+        // too small for the grid
+        let sliver = make_shape! { (3.0, 3.0), (3.1, 3.0), (3.1, 3.1), (3.0, 3.1) };
+        assert_eq!(engine.add_polygon(sliver), None);
+        assert_eq!(engine.usage().polygons, 1);
+    }
+}
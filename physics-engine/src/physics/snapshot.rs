@@ -0,0 +1,615 @@
+//! (De)serialization of the whole engine state
+//!
+//! The engine itself is a graph of `Rc<RefCell<dyn Collidable>>` connected by
+//! weak pointers, which serde cannot represent. It is instead flattened into a
+//! [`Snapshot`], in which the shapes have concrete types and bindings refer to
+//! their targets by index, and the graph is rebuilt from it when deserializing.
+//!
+//! Everything a step carries over to the next one is part of the snapshot,
+//! down to the warm starts of GJK, the trails and the ghost, so that a restored
+//! engine picks up exactly where the original one was instead of visibly
+//! settling again. Only the scratch arena, the pending events and the
+//! performance counters start afresh.
+
+use std::{cell::RefCell, collections::HashMap, os::raw::c_void, rc::Rc};
+
+use bumpalo::Bump;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    achievements::Achievements,
+    binding::{Binding, Unbound},
+    bomb::ArmedBomb,
+    capacity::EngineConfig,
+    cloth::Cloth,
+    curve::ChainPath,
+    dispenser::{Dispenser, TrackedBall},
+    editor::LevelIds,
+    fan::Fan,
+    game::Game,
+    ghost::Ghost,
+    gravity::Gravity,
+    hints::Hints,
+    ids::{BindingId, EntityId, IdGenerator},
+    load::BindingLoads,
+    magnet::Magnet,
+    motor::Motor,
+    narrowphase::WarmStarts,
+    palette::{Painter, Palette},
+    particles::Particles,
+    perf::PerfStats,
+    shape::{Circle, Collidable, Compound, Polygon},
+    size::BallSize,
+    slots::{Slots, UNPLACED},
+    snap::Snapping,
+    soft_body::SoftBody,
+    spring::{SpringConfig, TorsionSpring},
+    time_zone::TimeZone,
+    trail::{Trail, Trails},
+    tuning::SolverTuning,
+    Durability, Engine, Entity, EntityCfg, WithColor,
+};
+use crate::{
+    geometry::{Point, Vector},
+    levels::{Laser, Level, MainBall},
+};
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum ShapeSnapshot {
+    Circle(Circle),
+    Polygon(Polygon),
+    Compound(Compound),
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct EntitySnapshot {
+    pub id: EntityId,
+    pub shape: WithColor<ShapeSnapshot>,
+    pub is_erasable: bool,
+    pub is_bindable: bool,
+    pub is_static: bool,
+    pub hit_points: Option<f64>,
+    pub breaking_impulse: Option<f64>,
+    /// missing from snapshots taken before polygons could fracture
+    #[serde(default)]
+    pub fracture_impulse: Option<f64>,
+    pub bump_impulse: Option<f64>,
+    pub is_ferrous: bool,
+    /// missing from snapshots taken before the entities had render layers,
+    /// which puts all of them on [`super::layer::TERRAIN`]
+    #[serde(default)]
+    pub render_layer: i32,
+    /// missing from snapshots taken before the entities could be ordered spatially
+    #[serde(default = "unplaced")]
+    pub order_key: u32,
+    pub unbound: Vec<(BindingId, Unbound)>,
+    /// bindings together with the indices of the entities they are bound to
+    pub bindings: Vec<(BindingId, Binding, usize)>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Snapshot {
+    pub entities: Vec<EntitySnapshot>,
+    pub main_ball_starting_position: Point,
+    /// missing from snapshots taken before the main ball could be configured
+    #[serde(default)]
+    pub main_ball: MainBall,
+    pub world_half_extent: Option<f64>,
+    /// missing from snapshots taken before the origin could be shifted
+    #[serde(default)]
+    pub origin: Point,
+    #[serde(default)]
+    pub origin_shift_distance: Option<f64>,
+    pub flags: Vec<Polygon>,
+    pub flags_total: usize,
+    pub restitution_mulipiler: f64,
+    pub friction_mulipiler: f64,
+    pub gravity_mulipiler: f64,
+    pub static_friction_enabled: bool,
+    pub dynamic_friction_enabled: bool,
+    /// missing from snapshots taken before the tuning could be changed
+    #[serde(default)]
+    pub solver_tuning: SolverTuning,
+    #[serde(default)]
+    pub config: EngineConfig,
+    #[serde(default)]
+    pub snapping: Snapping,
+    /// missing from snapshots taken before the shapes were painted from a palette
+    #[serde(default)]
+    pub palette: Palette,
+    #[serde(default)]
+    pub colors_painted: u32,
+    #[serde(default)]
+    pub steps_since_reorder: u32,
+    pub entity_ids: IdGenerator,
+    pub binding_ids: IdGenerator,
+    pub game: Game,
+    pub hints: Hints,
+    pub achievements: Achievements,
+    pub bombs: Vec<ArmedBomb>,
+    pub fans: Vec<Fan>,
+    pub lasers: Vec<Laser>,
+    pub time_zones: Vec<TimeZone>,
+    pub gravity: Gravity,
+    pub ball_size: BallSize,
+    pub springs: Vec<TorsionSpring>,
+    /// missing from snapshots taken before springs could be placed between two points
+    #[serde(default)]
+    pub spring_config: SpringConfig,
+    pub motors: Vec<Motor>,
+    pub magnets: Vec<Magnet>,
+    #[serde(default)]
+    pub dispensers: Vec<Dispenser>,
+    #[serde(default)]
+    pub balls: Vec<TrackedBall>,
+    #[serde(default)]
+    pub soft_bodies: Vec<WithColor<SoftBody>>,
+    #[serde(default)]
+    pub particles: Particles,
+    #[serde(default)]
+    pub cloths: Vec<Cloth>,
+    /// missing from snapshots taken before curves were drawn through chains
+    #[serde(default)]
+    pub chains: Vec<ChainPath>,
+    /// the bindings placed with the tools, which can be cut, missing from
+    /// snapshots taken before cutting was limited to them
+    #[serde(default)]
+    pub placed_bindings: Vec<BindingId>,
+    pub region_ids: IdGenerator,
+    /// where GJK starts searching for every pair of entities, so that a restored
+    /// engine continues exactly like the original one. It is all the contact state
+    /// there is: bodies never sleep and contacts keep no manifolds between steps
+    #[serde(default)]
+    pub warm_starts: Vec<(EntityId, EntityId, Vector)>,
+    /// `None` in snapshots taken before the trails were kept,
+    /// in which case only the main ball leaves one, as in a new engine
+    #[serde(default)]
+    pub trails: Option<Vec<Trail>>,
+    #[serde(default)]
+    pub ghost: Ghost,
+    /// the level being edited, see [`super::editor`]
+    #[serde(default)]
+    pub level: Option<Level>,
+    /// missing from snapshots taken before the ids of the entities of the
+    /// level were kept, whose entities cannot be edited then
+    #[serde(default)]
+    pub level_ids: LevelIds,
+}
+
+fn unplaced() -> u32 {
+    UNPLACED
+}
+
+fn address<T: ?Sized>(shape: *const RefCell<T>) -> *const c_void {
+    shape as *const c_void
+}
+
+impl From<&Engine> for Snapshot {
+    fn from(engine: &Engine) -> Self {
+        let indices: HashMap<_, _> = engine
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (address(Rc::as_ptr(&entity.shape)), i))
+            .collect();
+
+        let mut shapes: Vec<Option<WithColor<ShapeSnapshot>>> =
+            engine.entities.iter().map(|_| None).collect();
+
+        for WithColor { color, shape } in &engine.circles {
+            if let Some(&i) = indices.get(&address(shape.as_ptr())) {
+                if let Some(circle) = shape.upgrade() {
+                    shapes[i] = Some(WithColor {
+                        color: *color,
+                        shape: ShapeSnapshot::Circle(circle.borrow().clone()),
+                    });
+                }
+            }
+        }
+
+        for WithColor { color, shape } in &engine.polygons {
+            if let Some(&i) = indices.get(&address(shape.as_ptr())) {
+                if let Some(polygon) = shape.upgrade() {
+                    shapes[i] = Some(WithColor {
+                        color: *color,
+                        shape: ShapeSnapshot::Polygon(polygon.borrow().clone()),
+                    });
+                }
+            }
+        }
+
+        for WithColor { color, shape } in &engine.compounds {
+            if let Some(&i) = indices.get(&address(shape.as_ptr())) {
+                if let Some(compound) = shape.upgrade() {
+                    shapes[i] = Some(WithColor {
+                        color: *color,
+                        shape: ShapeSnapshot::Compound(compound.borrow().clone()),
+                    });
+                }
+            }
+        }
+
+        let entities = engine
+            .entities
+            .iter()
+            .zip(shapes)
+            .map(|(entity, shape)| EntitySnapshot {
+                id: entity.id,
+                shape: shape.expect("every entity is a circle, a polygon or a compound"),
+                is_erasable: entity.is_erasable,
+                is_bindable: entity.is_bindable,
+                is_static: entity.is_static,
+                hit_points: entity.durability.hit_points,
+                breaking_impulse: entity.durability.breaking_impulse,
+                fracture_impulse: entity.durability.fracture_impulse,
+                bump_impulse: entity.bump_impulse,
+                is_ferrous: entity.is_ferrous,
+                render_layer: entity.render_layer,
+                order_key: entity.order_key,
+                unbound: entity.unbound.clone(),
+                bindings: entity
+                    .bindings
+                    .iter()
+                    .filter_map(|(id, binding, target)| {
+                        Some((*id, *binding, *indices.get(&address(target.as_ptr()))?))
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Snapshot {
+            entities,
+            main_ball_starting_position: engine.main_ball_starting_position,
+            main_ball: engine.main_ball,
+            world_half_extent: engine.world_half_extent,
+            origin: engine.origin,
+            origin_shift_distance: engine.origin_shift_distance,
+            flags: engine.flags.clone(),
+            flags_total: engine.flags_total,
+            restitution_mulipiler: engine.restitution_mulipiler,
+            friction_mulipiler: engine.friction_mulipiler,
+            gravity_mulipiler: engine.gravity_mulipiler,
+            static_friction_enabled: engine.static_friction_enabled,
+            dynamic_friction_enabled: engine.dynamic_friction_enabled,
+            solver_tuning: engine.solver_tuning,
+            config: engine.config,
+            snapping: engine.snapping,
+            palette: engine.painter.palette.clone(),
+            colors_painted: engine.painter.painted,
+            steps_since_reorder: engine.steps_since_reorder,
+            entity_ids: engine.entity_ids,
+            binding_ids: engine.binding_ids,
+            game: engine.game.clone(),
+            hints: engine.hints.clone(),
+            achievements: engine.achievements.clone(),
+            bombs: engine.bombs.clone(),
+            fans: engine.fans.clone(),
+            lasers: engine.lasers.clone(),
+            time_zones: engine.time_zones.clone(),
+            gravity: engine.gravity.clone(),
+            ball_size: engine.ball_size.clone(),
+            springs: engine.springs.clone(),
+            spring_config: engine.spring_config,
+            motors: engine.motors.clone(),
+            magnets: engine.magnets.clone(),
+            dispensers: engine.dispensers.clone(),
+            balls: engine.balls.clone(),
+            soft_bodies: engine.soft_bodies.clone(),
+            particles: engine.particles.clone(),
+            cloths: engine.cloths.clone(),
+            chains: engine.chains.clone(),
+            placed_bindings: engine.placed_bindings.clone(),
+            region_ids: engine.region_ids,
+            warm_starts: engine.warm_starts.to_vec(),
+            trails: Some(engine.trails.to_vec()),
+            ghost: engine.ghost.clone(),
+            level: engine.level.clone(),
+            level_ids: engine.level_ids.clone(),
+        }
+    }
+}
+
+impl From<Snapshot> for Engine {
+    fn from(snapshot: Snapshot) -> Self {
+        let has_trails = snapshot.trails.is_some();
+        // the script starts over, as what its hooks kept is not saved
+        #[cfg(feature = "scripting")]
+        let (script, script_error) = super::script::load(
+            snapshot
+                .level
+                .as_ref()
+                .and_then(|level| level.script.as_deref()),
+        );
+        let mut engine = Engine {
+            entities: Vec::with_capacity(snapshot.entities.len()),
+            slots: Slots::default(),
+            steps_since_reorder: snapshot.steps_since_reorder,
+            polygons: vec![],
+            circles: vec![],
+            compounds: vec![],
+            main_ball_starting_position: snapshot.main_ball_starting_position,
+            main_ball: snapshot.main_ball,
+            world_half_extent: snapshot.world_half_extent,
+            origin: snapshot.origin,
+            origin_shift_distance: snapshot.origin_shift_distance,
+            flags: snapshot.flags,
+            flags_total: snapshot.flags_total,
+            restitution_mulipiler: snapshot.restitution_mulipiler,
+            friction_mulipiler: snapshot.friction_mulipiler,
+            gravity_mulipiler: snapshot.gravity_mulipiler,
+            static_friction_enabled: snapshot.static_friction_enabled,
+            dynamic_friction_enabled: snapshot.dynamic_friction_enabled,
+            solver_tuning: snapshot.solver_tuning,
+            config: snapshot.config,
+            snapping: snapshot.snapping,
+            painter: Painter {
+                palette: snapshot.palette,
+                painted: snapshot.colors_painted,
+            },
+            entity_ids: snapshot.entity_ids,
+            binding_ids: snapshot.binding_ids,
+            game: snapshot.game,
+            events: vec![],
+            hints: snapshot.hints,
+            achievements: snapshot.achievements,
+            bombs: snapshot.bombs,
+            fans: snapshot.fans,
+            lasers: snapshot.lasers,
+            time_zones: snapshot.time_zones,
+            gravity: snapshot.gravity,
+            ball_size: snapshot.ball_size,
+            springs: snapshot.springs,
+            spring_config: snapshot.spring_config,
+            grab: None,
+            motors: snapshot.motors,
+            magnets: snapshot.magnets,
+            dispensers: snapshot.dispensers,
+            balls: snapshot.balls,
+            soft_bodies: snapshot.soft_bodies,
+            particles: snapshot.particles,
+            cloths: snapshot.cloths,
+            chains: snapshot.chains,
+            placed_bindings: snapshot.placed_bindings,
+            region_ids: snapshot.region_ids,
+            ghost: snapshot.ghost,
+            trails: snapshot.trails.map(Trails::from_vec).unwrap_or_default(),
+            perf: PerfStats::default(),
+            scratch: Bump::new(),
+            warm_starts: WarmStarts::from_vec(snapshot.warm_starts),
+            debug_contacts: None,
+            dissipated_energy: 0.0,
+            binding_loads: BindingLoads::default(),
+            divergence_log: None,
+            recorder: None,
+            tracer: None,
+            #[cfg(feature = "scripting")]
+            script,
+            #[cfg(feature = "scripting")]
+            script_error,
+            level: snapshot.level,
+            level_ids: snapshot.level_ids,
+        };
+
+        let mut bindings = Vec::with_capacity(snapshot.entities.len());
+
+        for entity in snapshot.entities {
+            let WithColor { color, shape } = entity.shape;
+            let shape: Rc<RefCell<dyn Collidable>> = match shape {
+                ShapeSnapshot::Circle(circle) => {
+                    let circle = Rc::new(RefCell::new(circle));
+                    engine.circles.push(WithColor {
+                        color,
+                        shape: Rc::downgrade(&circle),
+                    });
+                    circle
+                }
+                ShapeSnapshot::Polygon(polygon) => {
+                    let polygon = Rc::new(RefCell::new(polygon));
+                    engine.polygons.push(WithColor {
+                        color,
+                        shape: Rc::downgrade(&polygon),
+                    });
+                    polygon
+                }
+                ShapeSnapshot::Compound(compound) => {
+                    let compound = Rc::new(RefCell::new(compound));
+                    engine.compounds.push(WithColor {
+                        color,
+                        shape: Rc::downgrade(&compound),
+                    });
+                    compound
+                }
+            };
+
+            let mut restored = Entity::new(
+                entity.id,
+                shape,
+                EntityCfg {
+                    is_erasable: entity.is_erasable,
+                    is_bindable: entity.is_bindable,
+                    is_static: entity.is_static,
+                    durability: Durability::new(
+                        entity.hit_points,
+                        entity.breaking_impulse,
+                        entity.fracture_impulse,
+                    ),
+                    bump_impulse: entity.bump_impulse,
+                    is_ferrous: entity.is_ferrous,
+                    render_layer: entity.render_layer,
+                },
+            );
+            restored.unbound = entity.unbound;
+            restored.order_key = entity.order_key;
+            engine.entities.push(restored);
+            bindings.push(entity.bindings);
+        }
+
+        for (i, entity_bindings) in bindings.into_iter().enumerate() {
+            let targets = entity_bindings
+                .into_iter()
+                .filter_map(|(id, binding, target)| {
+                    let target = Rc::downgrade(&engine.entities.get(target)?.shape);
+                    Some((id, binding, target))
+                })
+                .collect();
+            engine.entities[i].bindings = targets;
+        }
+        engine.slots = Slots::of(&engine.entities);
+        if !has_trails {
+            if let Some(main_ball) = engine.entities.first() {
+                engine.trails.track(main_ball.id);
+            }
+        }
+
+        engine
+    }
+}
+
+impl Serialize for Engine {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Snapshot::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Engine {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Snapshot::deserialize(deserializer).map(Engine::from)
+    }
+}
+
+/// Serializes infinite values as `null`, and `null` back as infinity
+pub(crate) mod infinite_as_null {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        Some(*value)
+            .filter(|value| value.is_finite())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Option::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        levels::Entity,
+        physics::{make_shape, sandbox::SandboxConfig, WORLD_HALF_EXTENT},
+    };
+
+    #[test]
+    fn test_roundtrip() {
+        let mut engine = Engine::new(Level {
+            initial_ball_position: Point(0.0, 0.0),
+            main_ball: Default::default(),
+            circles: vec![],
+            polygons: vec![Entity {
+                shape: vec![
+                    Point(-1.0, 1.0),
+                    Point(1.0, 1.0),
+                    Point(1.0, 1.2),
+                    Point(-1.0, 1.2),
+                ],
+                is_static: true,
+                is_bindable: true,
+                hit_points: None,
+                breaking_impulse: None,
+                fracture_impulse: None,
+                bump_impulse: None,
+                is_ferrous: false,
+                is_erasable: false,
+                render_layer: 0,
+            }],
+            flags_positions: vec![Point(0.5, 0.5)],
+            time_limit: None,
+            stars: Default::default(),
+            tool_limits: Default::default(),
+            rigid_compliance: 0.0,
+            terrain: vec![],
+            hints: vec![],
+            bombs: vec![],
+            fans: vec![],
+            lasers: vec![],
+            time_zones: vec![],
+            gravity_pickups: vec![],
+            size_pickups: vec![],
+            wrecking_balls: vec![],
+            magnets: vec![],
+            dispensers: vec![],
+            world_half_extent: Some(WORLD_HALF_EXTENT),
+            script: None,
+        });
+        engine.add_hinge(Point(0.5, 1.1), 0.0);
+        engine.add_polygon(make_shape! {
+            (0.4, 1.0),
+            (0.6, 1.0),
+            (0.6, 1.2),
+            (0.4, 1.2),
+        });
+        engine.run_n_iterations(5, 16_000.0);
+
+        let json = serde_json::to_string(&engine).unwrap();
+        let mut restored: Engine = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.entities.len(), engine.entities.len());
+        assert_eq!(restored.state_hash(), engine.state_hash());
+        assert_eq!(restored.entities[1].bindings.len(), 1);
+        assert!(restored.entities[1]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .mass
+            .is_infinite());
+
+        let message = engine.run_iteration(16_000.0);
+        let restored_message = restored.run_iteration(16_000.0);
+        assert_eq!(message.hinges.len(), 1);
+        assert_eq!(restored_message.hinges, message.hinges);
+        assert_eq!(
+            restored_message.polygons[1].shape.vertices,
+            message.polygons[1].shape.vertices
+        );
+    }
+
+    #[test]
+    fn test_restored_engine_continues() {
+        let level: Level = ron::from_str(include_str!("../../levels/stack.ron")).unwrap();
+        let mut engine = Engine::new(level);
+        engine.run_n_iterations(300, 2000.0);
+
+        let json = serde_json::to_string(&engine).unwrap();
+        let mut restored: Engine = serde_json::from_str(&json).unwrap();
+
+        // the display messages carry the trails along with every shape
+        for _ in 0..100 {
+            let message = engine.run_n_iterations(2, 2000.0);
+            let restored_message = restored.run_n_iterations(2, 2000.0);
+            assert_eq!(
+                serde_json::to_string(&restored_message).unwrap(),
+                serde_json::to_string(&message).unwrap()
+            );
+        }
+        assert_eq!(restored.state_hash(), engine.state_hash());
+    }
+
+    #[test]
+    fn test_snapshot_without_trails() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.run_n_iterations(3, 2000.0);
+
+        let mut snapshot = Snapshot::from(&engine);
+        snapshot.trails = None;
+        let mut restored = Engine::from(snapshot);
+        let message = restored.run_iteration(2000.0);
+        assert_eq!(message.trails.len(), 1);
+        assert_eq!(message.trails[0].id, restored.entities[0].id);
+    }
+}
@@ -0,0 +1,345 @@
+//! Soft bodies, blobs of point masses held in shape by springs
+//!
+//! A soft body is made of nodes along the outline it was drawn with, spaced
+//! at most [`NODE_SPACING`] apart, plus one node at its centroid, which share
+//! the mass of the outline evenly. Springs run along the outline, from every
+//! node of the outline to the centroid and to the node after the next one,
+//! which keeps the blob from folding up.
+//!
+//! The nodes are integrated on their own, next to the rigid bodies. The steps
+//! are split into substeps short enough for stiff springs to stay stable, and
+//! in every substep the nodes of the outline collide with the rigid bodies as
+//! small circles, through the same solver the rigid bodies use, so that the
+//! blob and the bodies push each other around. Soft bodies do not collide
+//! with each other or themselves, and nothing but gravity acts on them at a
+//! distance: fans, magnets, explosions and time zones leave them alone.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    ids::EntityId,
+    shape::{Circle, Collidable, Polygon},
+    Engine, WithColor, GRAVITY_COEFFICIENT, MOVEMENT_COEFFICIENT,
+};
+use crate::geometry::{self, Aabb, Point, Vector};
+
+/// the longest edge of the outline of a soft body, longer ones are split
+pub const NODE_SPACING: f64 = 0.05;
+/// the radius of the circles the nodes of the outline collide as
+pub const NODE_RADIUS: f64 = 0.02;
+/// the longest substep, short enough for the default stiffness
+const MAX_SUBSTEP_MICROSECONDS: f64 = 500.0;
+
+/// How a soft body resists being deformed. Both are per unit of mass,
+/// so that blobs of any size wobble alike. Missing fields take their defaults
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct SoftBodyConfig {
+    /// the velocity a spring gains per second per unit it is stretched by
+    pub stiffness: f64,
+    /// the part of the velocity along a spring it loses per second
+    pub damping: f64,
+}
+
+impl Default for SoftBodyConfig {
+    fn default() -> Self {
+        Self {
+            stiffness: 2000.0,
+            damping: 60.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Node {
+    position: Point,
+    velocity: Vector,
+    mass: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Spring {
+    nodes: (usize, usize),
+    rest_length: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SoftBody {
+    id: EntityId,
+    /// the nodes of the outline, in order, followed by the one at the centroid
+    nodes: Vec<Node>,
+    springs: Vec<Spring>,
+    config: SoftBodyConfig,
+}
+
+/// the vertices of the outline with every edge split into pieces of at most `NODE_SPACING`
+fn subdivide(outline: &[Point]) -> Vec<Point> {
+    let edges = outline.iter().zip(outline.iter().cycle().skip(1));
+    edges
+        .flat_map(|(&start, &end)| {
+            // edges of a multiple of the spacing are not split once more by rounding errors
+            let pieces = (start.to(end).norm() / NODE_SPACING - 1e-9).ceil().max(1.0) as usize;
+            (0..pieces).map(move |i| start + start.to(end) * (i as f64 / pieces as f64))
+        })
+        .collect()
+}
+
+impl SoftBody {
+    /// a soft body without an id yet, `None` if the outline has no area
+    fn new(outline: Vec<Point>, config: SoftBodyConfig) -> Option<Self> {
+        let mut polygon = Polygon::new(outline);
+        let data = polygon.collision_data_mut().clone();
+        if !data.mass.is_finite() || data.mass <= 0.0 {
+            return None;
+        }
+
+        let mut positions = subdivide(polygon.vertices());
+        let n = positions.len();
+        positions.push(data.centroid);
+        let mass = data.mass / positions.len() as f64;
+        let nodes: Vec<_> = positions
+            .into_iter()
+            .map(|position| Node {
+                position,
+                velocity: Vector::ZERO,
+                mass,
+            })
+            .collect();
+
+        let pairs = (0..n).flat_map(|i| [(i, (i + 1) % n), (i, n), (i, (i + 2) % n)]);
+        let springs = pairs
+            .map(|(a, b)| Spring {
+                nodes: (a, b),
+                rest_length: nodes[a].position.to(nodes[b].position).norm(),
+            })
+            .collect();
+
+        Some(Self {
+            id: EntityId(0),
+            nodes,
+            springs,
+            config,
+        })
+    }
+
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    /// the nodes of the outline, which are all of them but the last one
+    fn outline(&self) -> impl Iterator<Item = Point> + '_ {
+        let outline = &self.nodes[..self.nodes.len() - 1];
+        outline.iter().map(|node| node.position)
+    }
+
+    fn centroid(&self) -> Point {
+        self.nodes[self.nodes.len() - 1].position
+    }
+
+    pub(super) fn translate(&mut self, translation: Vector) {
+        for node in &mut self.nodes {
+            node.position += translation;
+        }
+    }
+
+    /// applies gravity and the springs, then moves the nodes
+    fn integrate(&mut self, microseconds: f64, gravity: Vector) {
+        let seconds = microseconds / 1_000_000.0;
+        for node in &mut self.nodes {
+            node.velocity += gravity * (GRAVITY_COEFFICIENT * microseconds);
+        }
+        for spring in &self.springs {
+            let (a, b) = spring.nodes;
+            let offset = self.nodes[a].position.to(self.nodes[b].position);
+            let length = offset.norm();
+            if length == 0.0 {
+                continue;
+            }
+            let direction = offset / length;
+            let closing = (self.nodes[b].velocity - self.nodes[a].velocity).dot(direction);
+            let pull = self.config.stiffness * (length - spring.rest_length)
+                + self.config.damping * closing;
+            self.nodes[a].velocity += direction * (pull * seconds);
+            self.nodes[b].velocity -= direction * (pull * seconds);
+        }
+        for node in &mut self.nodes {
+            node.position += node.velocity * (MOVEMENT_COEFFICIENT * microseconds);
+        }
+    }
+}
+
+impl Engine {
+    /// adds a soft body filling the outline, `None` if the outline has no area
+    pub fn add_soft_body(
+        &mut self,
+        outline: Vec<Point>,
+        config: SoftBodyConfig,
+    ) -> Option<EntityId> {
+        let mut soft_body = SoftBody::new(outline, config)?;
+        soft_body.id = self.entity_ids.generate();
+        let id = soft_body.id;
+        let soft_body = self.painter.paint(soft_body);
+        self.soft_bodies.push(soft_body);
+        Some(id)
+    }
+
+    /// returns whether the soft body existed
+    pub fn remove_soft_body(&mut self, id: EntityId) -> bool {
+        let count = self.soft_bodies.len();
+        self.soft_bodies
+            .retain(|soft_body| soft_body.shape.id != id);
+        self.soft_bodies.len() < count
+    }
+
+    /// moves the soft bodies through the step and collides them with the rigid
+    /// bodies, removing the ones out of bounds like the rigid bodies
+    pub(super) fn step_soft_bodies(&mut self, microseconds: f64, gravity: Vector) {
+        if self.soft_bodies.is_empty() {
+            return;
+        }
+        let substeps = (microseconds / MAX_SUBSTEP_MICROSECONDS).ceil().max(1.0);
+        let microseconds = microseconds / substeps;
+
+        for WithColor { shape: body, .. } in &mut self.soft_bodies {
+            for _ in 0..substeps as u32 {
+                body.integrate(microseconds, gravity);
+
+                let extent = Point(NODE_RADIUS, NODE_RADIUS);
+                let aabb = Aabb::around(body.outline());
+                let aabb = Aabb {
+                    min: aabb.min - extent,
+                    max: aabb.max + extent,
+                };
+                let n = body.nodes.len() - 1;
+                for entity in &self.entities {
+                    let mut shape = entity.shape.borrow_mut();
+                    if !shape.aabb().overlaps(&aabb) {
+                        continue;
+                    }
+                    for node in &mut body.nodes[..n] {
+                        let mut circle = Circle::new(node.position, NODE_RADIUS);
+                        let data = circle.collision_data_mut();
+                        data.mass = node.mass;
+                        data.inertia = f64::INFINITY;
+                        data.velocity = node.velocity;
+                        circle.collide(
+                            &mut *shape,
+                            microseconds,
+                            &self.solver_tuning,
+                            self.restitution_mulipiler,
+                            self.friction_mulipiler,
+                            self.static_friction_enabled,
+                            self.dynamic_friction_enabled,
+                        );
+                        let data = circle.collision_data_mut();
+                        node.position = data.centroid;
+                        node.velocity = data.velocity;
+                    }
+                }
+            }
+        }
+
+        let origin = self.origin;
+        if let Some(extent) = self.world_half_extent {
+            self.soft_bodies
+                .retain(|soft_body| (soft_body.shape.centroid() + origin).1 > -extent);
+        }
+    }
+
+    pub(super) fn write_soft_bodies(
+        &self,
+        soft_bodies: &mut Vec<WithColor<geometry::Polygon>>,
+        ids: &mut Vec<EntityId>,
+    ) {
+        ids.clear();
+        ids.extend(
+            self.soft_bodies
+                .iter()
+                .map(|soft_body| soft_body.shape.id()),
+        );
+        soft_bodies.clear();
+        soft_bodies.extend(self.soft_bodies.iter().map(|soft_body| WithColor {
+            color: soft_body.color,
+            shape: geometry::Polygon {
+                vertices: soft_body.shape.outline().collect(),
+                corner_radius: 0.0,
+                centroid: soft_body.shape.centroid(),
+            },
+        }));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig};
+
+    fn square(x: f64, y: f64, side: f64) -> Vec<Point> {
+        vec![
+            Point(x, y),
+            Point(x + side, y),
+            Point(x + side, y + side),
+            Point(x, y + side),
+        ]
+    }
+
+    #[test]
+    fn test_soft_body_lands_and_keeps_its_shape() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        engine.add_prefab_polygon(
+            make_shape! { (-3.0, 1.0), (3.0, 1.0), (3.0, 1.2), (-3.0, 1.2) },
+            true,
+        );
+        let id = engine
+            .add_soft_body(square(1.0, 0.2, 0.4), SoftBodyConfig::default())
+            .unwrap();
+        assert_eq!(engine.soft_bodies[0].shape.nodes.len(), 4 * 8 + 1);
+
+        let message = engine.run_n_iterations(500, 2000.0);
+        let blob = &message.soft_bodies[0].shape;
+        let aabb = Aabb::around(blob.vertices.iter().copied());
+        // resting on the floor, squashed a little at most
+        assert!((aabb.max.1 - (1.0 - NODE_RADIUS)).abs() < 0.02);
+        assert!(aabb.max.1 - aabb.min.1 > 0.3);
+        assert!(aabb.max.0 - aabb.min.0 < 0.5);
+
+        assert!(engine.remove_soft_body(id));
+        assert!(!engine.remove_soft_body(id));
+        assert!(engine.run_iteration(2000.0).soft_bodies.is_empty());
+    }
+
+    #[test]
+    fn test_soft_body_pushes_rigid_bodies() {
+        // a plank thrown up into a blob slows down faster than without it
+        let plank_velocity = |with_blob: bool| {
+            let mut engine = Engine::sandbox(SandboxConfig::default());
+            let plank = engine
+                .add_polygon(make_shape! { (-1.0, 1.0), (1.0, 1.0), (1.0, 1.1), (-1.0, 1.1) })
+                .unwrap();
+            let slot = engine.slots.get(plank).unwrap();
+            engine.entities[slot]
+                .shape
+                .borrow_mut()
+                .collision_data_mut()
+                .velocity = Point(0.0, -1.0);
+            if with_blob {
+                engine.add_soft_body(square(-0.2, 0.4, 0.4), SoftBodyConfig::default());
+            }
+            engine.run_n_iterations(50, 2000.0);
+            engine.body_states()[slot].velocity.1
+        };
+        assert!(plank_velocity(true) > plank_velocity(false) + 0.05);
+
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let line = vec![Point::ZERO, Point(1.0, 0.0), Point(2.0, 0.0)];
+        assert!(engine
+            .add_soft_body(line, SoftBodyConfig::default())
+            .is_none());
+    }
+}
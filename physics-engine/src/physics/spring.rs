@@ -0,0 +1,213 @@
+//! Springs between entities
+//!
+//! A torsion spring drives the angle of the second entity relative to the
+//! first one towards its rest angle. It does not hold the entities together
+//! by itself, so it is placed alongside a hinge.
+//!
+//! A spring binding pulls two points on different entities together when
+//! stretched beyond its rest length and pushes them apart when compressed.
+//! Like the other bindings it is held by one of the entities, goes away with
+//! either of them and is severed by a swipe of `cut_along` across it. The player
+//! places it between two points, and it takes its stiffness, damping and rest
+//! length from the [`SpringConfig`] of the engine at the time.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{
+    binding::Binding,
+    game::Tool,
+    ids::{BindingId, EntityId},
+    Engine,
+};
+use crate::geometry::Point;
+
+/// How the spring bindings placed from now on behave,
+/// missing fields take their default values
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct SpringConfig {
+    /// force per unit of length away from the rest length
+    pub stiffness: f64,
+    /// force per unit of length per second the ends move apart or together
+    pub damping: f64,
+    /// the length the spring relaxes at, `None` (`null`) for the distance
+    /// between the two points it is placed at
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub rest_length: Option<f64>,
+}
+
+impl Default for SpringConfig {
+    fn default() -> Self {
+        Self {
+            stiffness: 10.0,
+            damping: 0.025,
+            rest_length: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TorsionSpring {
+    pub id: BindingId,
+    pub first: EntityId,
+    pub second: EntityId,
+    /// angle of the second entity relative to the first one the spring relaxes at
+    pub rest_angle: f64,
+    /// torque per radian away from the rest angle
+    pub stiffness: f64,
+    /// torque per radian per second of relative angular velocity
+    pub damping: f64,
+}
+
+impl Engine {
+    /// adds a torsion spring relaxed at the current relative angle of the two entities
+    /// plus `angle_offset`, returns `None` if either entity does not exist
+    pub fn add_torsion_spring(
+        &mut self,
+        first: EntityId,
+        second: EntityId,
+        angle_offset: f64,
+        stiffness: f64,
+        damping: f64,
+    ) -> Option<BindingId> {
+        let angle_of = |id| {
+            self.entities
+                .iter()
+                .find(|entity| entity.id == id)
+                .map(|entity| entity.shape.borrow().angle())
+        };
+        let rest_angle = angle_of(second)? - angle_of(first)? + angle_offset;
+
+        let id = self.binding_ids.generate();
+        self.springs.push(TorsionSpring {
+            id,
+            first,
+            second,
+            rest_angle,
+            stiffness,
+            damping,
+        });
+        Some(id)
+    }
+
+    pub fn spring_config(&self) -> SpringConfig {
+        self.spring_config
+    }
+
+    /// configures the springs placed from now on, the ones already placed keep
+    /// their configuration. Returns whether the config is valid, with finite
+    /// values that are not negative
+    pub fn set_spring_config(&mut self, config: SpringConfig) -> bool {
+        let is_valid = |value: f64| value.is_finite() && value >= 0.0;
+        if !(is_valid(config.stiffness)
+            && is_valid(config.damping)
+            && config.rest_length.is_none_or(is_valid))
+        {
+            return false;
+        }
+        self.spring_config = config;
+        true
+    }
+
+    /// binds the entities under the two points with a spring between the points,
+    /// unless either point is not on a bindable entity, both are on the same one,
+    /// or the spring limit of the level or the binding cap of the engine is reached
+    pub fn add_spring(&mut self, first: Point, second: Point) -> Option<BindingId> {
+        let SpringConfig {
+            stiffness,
+            damping,
+            rest_length,
+        } = self.spring_config;
+        self.add_binding_between(Tool::Spring, first, second, |first, second, [at, to]| {
+            Binding::Spring {
+                first,
+                second,
+                rest_length: rest_length.unwrap_or(at.to(to).norm()),
+                stiffness,
+                damping,
+            }
+        })
+    }
+
+    /// applies the torque of every spring, dropping the springs of removed entities
+    pub(super) fn enforce_springs(&mut self, microseconds: f64) {
+        let seconds = microseconds / 1_000_000.0;
+        let (entities, slots) = (&self.entities, &self.slots);
+        let find = |id| slots.get(id).map(|slot| &entities[slot]);
+
+        self.springs.retain(|spring| {
+            let (Some(first), Some(second)) = (find(spring.first), find(spring.second)) else {
+                return false;
+            };
+            let (mut first, mut second) = (first.shape.borrow_mut(), second.shape.borrow_mut());
+
+            let angle = second.angle() - first.angle();
+            let first_data = first.collision_data_mut();
+            let second_data = second.collision_data_mut();
+            let angular_velocity = second_data.angular_velocity - first_data.angular_velocity;
+
+            let torque =
+                -spring.stiffness * (angle - spring.rest_angle) - spring.damping * angular_velocity;
+            first_data.angular_velocity -= torque * seconds / first_data.inertia;
+            second_data.angular_velocity += torque * seconds / second_data.inertia;
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{make_shape, sandbox::SandboxConfig, snapshot::Snapshot};
+
+    #[test]
+    fn test_spring() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let ceiling = engine.add_prefab_polygon(
+            make_shape! { (2.0, -2.2), (3.0, -2.2), (3.0, -2.0), (2.0, -2.0) },
+            true,
+        );
+        let weight = engine
+            .add_polygon(make_shape! { (2.3, -1.0), (2.7, -1.0), (2.7, -0.6), (2.3, -0.6) })
+            .unwrap();
+        assert!(!engine.set_spring_config(SpringConfig {
+            stiffness: -1.0,
+            ..SpringConfig::default()
+        }));
+        let config = SpringConfig {
+            rest_length: Some(1.0),
+            ..SpringConfig::default()
+        };
+        assert!(engine.set_spring_config(config));
+        assert_eq!(engine.add_spring(Point(2.5, -0.8), Point(2.6, -0.7)), None);
+        assert_eq!(engine.add_spring(Point(2.5, -0.8), Point(5.0, 5.0)), None);
+        let spring = engine
+            .add_spring(Point(2.5, -0.8), Point(2.5, -2.1))
+            .unwrap();
+
+        let message = engine.run_n_iterations(1_500, 2_000.0);
+        let slot = engine.slots.get(weight).unwrap();
+        let state = &engine.body_states()[slot];
+        // it comes to rest stretched by its weight over the stiffness, 0.16 * 9.81 / 10
+        assert!(state.velocity.norm() < 1e-3);
+        let [[bottom, top]] = message.springs[..] else {
+            panic!("{:?} are not one spring", message.springs);
+        };
+        assert!((top.to(bottom).norm() - 1.157).abs() < 0.01);
+        assert_eq!(bottom, state.centroid);
+        assert_eq!(message.spring_entities, vec![weight]);
+        assert_eq!(message.spring_targets, vec![ceiling]);
+
+        let restored = Engine::from(Snapshot::from(&engine));
+        assert_eq!(restored.spring_config(), config);
+        assert_eq!(
+            engine.cut_along(&[Point(2.0, -1.5), Point(3.0, -1.5)]),
+            vec![spring]
+        );
+        assert!(engine.run_iteration(2_000.0).springs.is_empty());
+    }
+}
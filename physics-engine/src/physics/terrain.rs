@@ -0,0 +1,92 @@
+//! Terrain of a level made of polylines
+//!
+//! The engine only collides convex shapes, so ground, ramps and caves that are
+//! not convex would have to be pieced together from convex polygons. A chain of
+//! terrain is a polyline instead: every edge of it becomes a static slab
+//! [`TERRAIN_THICKNESS`] thick along the edge, which bodies collide with from
+//! either side. The slabs overhang the points of the chain by half their
+//! thickness, closing the gaps between them where the chain bends, and static
+//! shapes never collide with each other where they overlap.
+//!
+//! The slabs are entities of their own on the [`TERRAIN`] layer, drawn as
+//! polygons and bound to like the polygons of the level, but they cannot be
+//! erased.
+
+use super::{layer::TERRAIN, prefab::rectangle, Engine, EntityCfg};
+use crate::geometry::Point;
+
+/// how thick the edges of the terrain are, bodies rest half of it above the chain
+pub const TERRAIN_THICKNESS: f64 = 0.05;
+
+impl Engine {
+    /// adds a slab along every edge of the chain, skipping the points that repeat
+    /// the previous one, so a chain of fewer than two different points adds nothing
+    pub(super) fn add_terrain(&mut self, chain: &[Point]) {
+        let mut points = chain.to_vec();
+        points.dedup();
+        for edge in points.windows(2) {
+            let along = edge[0].to(edge[1]);
+            let slab = rectangle(
+                edge[0] + along * 0.5,
+                along.norm() + TERRAIN_THICKNESS,
+                TERRAIN_THICKNESS,
+                along.1.atan2(along.0),
+            );
+            let (_, weak) = self.add_entity(
+                slab,
+                EntityCfg {
+                    is_static: true,
+                    is_erasable: false,
+                    render_layer: TERRAIN,
+                    ..Default::default()
+                },
+            );
+            self.polygons.push(self.painter.paint(weak));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{levels::Level, physics::sandbox::SandboxConfig};
+
+    #[test]
+    fn test_terrain() {
+        let mut level: Level = Engine::sandbox(SandboxConfig::default())
+            .export_level()
+            .unwrap();
+        level.polygons.clear();
+        level.initial_ball_position = Point(-0.8, -1.0);
+        // a valley, which a convex polygon could not hold the ball in
+        level.terrain = vec![
+            vec![
+                Point(-2.0, -1.0),
+                Point(-1.0, 0.0),
+                Point(-1.0, 0.0),
+                Point(0.0, 0.5),
+                Point(1.0, 0.0),
+                Point(2.0, -1.0),
+            ],
+            vec![Point(3.0, 3.0)],
+        ];
+        let mut engine = Engine::new(level.clone());
+        let message = engine.run_iteration(2_000.0);
+        assert_eq!(message.polygons.len(), 4);
+        assert!(message.polygon_layers.iter().all(|&layer| layer == TERRAIN));
+        assert_eq!(engine.erase_at(Point(0.0, 0.5)), None);
+
+        // the ball rolls down into the bottom of the valley, and stays there
+        engine.run_n_iterations(2_000, 2_000.0);
+        let ball = engine.body_states()[0];
+        let radius = level.main_ball.radius;
+        // touching both slopes, which fall 1 in 2
+        let rest = 0.5 - (radius + TERRAIN_THICKNESS / 2.0) * 1.25f64.sqrt();
+        assert!(ball.centroid.0.abs() < 0.1, "{ball:?}");
+        assert!(
+            (ball.centroid.1 - rest).abs() < 0.1 * radius,
+            "{ball:?} {rest}"
+        );
+        assert_eq!(Engine::new(level).export_level().unwrap().terrain.len(), 2);
+    }
+}
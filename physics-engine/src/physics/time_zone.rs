@@ -0,0 +1,79 @@
+//! Bullet-time zones, in which time passes slower
+//!
+//! Every body integrates with the time scale of the slowest zone its centroid
+//! lies in. A contact between bodies running at different speeds is resolved
+//! at the slower of the two, so that the positional correction does not shove
+//! a slowed body further in a single step than it could move by itself.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::shape::{Bounded, Collidable, Polygon};
+use crate::{geometry, levels};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TimeZone {
+    region: Polygon,
+    time_scale: f64,
+}
+
+impl From<levels::TimeZone> for TimeZone {
+    fn from(zone: levels::TimeZone) -> Self {
+        Self {
+            region: Polygon::new(zone.region),
+            time_scale: zone.time_scale,
+        }
+    }
+}
+
+impl From<&TimeZone> for levels::TimeZone {
+    fn from(zone: &TimeZone) -> Self {
+        Self {
+            region: geometry::Polygon::from(zone.region.clone()).vertices,
+            time_scale: zone.time_scale,
+        }
+    }
+}
+
+impl TimeZone {
+    pub(super) fn shift(&mut self, translation: geometry::Vector) {
+        self.region.translate(translation);
+    }
+}
+
+/// the time scale at `point`, 1 outside of every zone
+pub fn time_scale_at(zones: &[TimeZone], point: geometry::Point) -> f64 {
+    zones
+        .iter()
+        .filter(|zone| zone.region.includes(point))
+        .map(|zone| zone.time_scale)
+        .fold(1.0, f64::min)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::Point;
+
+    #[test]
+    fn test_time_scale_at() {
+        let zone = |x: f64, time_scale| {
+            TimeZone::from(levels::TimeZone {
+                region: vec![
+                    Point(x, 0.0),
+                    Point(x + 2.0, 0.0),
+                    Point(x + 2.0, 2.0),
+                    Point(x, 2.0),
+                ],
+                time_scale,
+            })
+        };
+        let zones = [zone(0.0, 0.5), zone(1.0, 0.25)];
+
+        assert_eq!(time_scale_at(&zones, Point(-1.0, 1.0)), 1.0);
+        assert_eq!(time_scale_at(&zones, Point(0.5, 1.0)), 0.5);
+        assert_eq!(time_scale_at(&zones, Point(1.5, 1.0)), 0.25);
+    }
+}
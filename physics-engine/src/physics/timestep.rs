@@ -0,0 +1,103 @@
+//! Keeping long steps from blowing up the simulation
+//!
+//! A frontend usually steps the engine by the time since its last frame, which
+//! grows huge whenever the tab stalls or the device cannot keep up. A single
+//! step that long lets shapes tunnel through each other and bindings explode,
+//! so a step longer than the maximum of the config is split into substeps.
+//! The substeps are capped too, as catching up on all of the time would only
+//! make the next frame later still. Whatever is left over is dropped, and the
+//! display message reports it so the host can tell that it happened.
+
+use super::capacity::EngineConfig;
+
+/// a step of 1/30 of a second, the longest frame that still looks animated
+pub const DEFAULT_MAX_STEP_MICROSECONDS: f64 = 1_000_000.0 / 30.0;
+pub const DEFAULT_MAX_SUBSTEPS: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Substeps {
+    pub count: u32,
+    pub microseconds: f64,
+    /// the part of the step that is not simulated
+    pub dropped_microseconds: f64,
+}
+
+/// the equal substeps a step of `microseconds` is simulated in.
+/// Steps of a negative or NaN length are dropped entirely, without reporting them
+pub(super) fn substeps(config: &EngineConfig, microseconds: f64) -> Substeps {
+    if microseconds.is_nan() || microseconds < 0.0 {
+        return Substeps {
+            count: 0,
+            microseconds: 0.0,
+            dropped_microseconds: 0.0,
+        };
+    }
+
+    let max = match config.max_step_microseconds {
+        Some(max) if microseconds > max && max > 0.0 => max,
+        _ => {
+            return Substeps {
+                count: 1,
+                microseconds,
+                dropped_microseconds: 0.0,
+            }
+        }
+    };
+
+    let needed = (microseconds / max).ceil();
+    let count = needed.min(config.max_substeps.max(1) as f64);
+    // an infinite step cannot be divided, so it gets substeps of the maximum
+    let substep = if needed.is_finite() {
+        microseconds / needed
+    } else {
+        max
+    };
+    Substeps {
+        count: count as u32,
+        microseconds: substep,
+        dropped_microseconds: microseconds - count * substep,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::{sandbox::SandboxConfig, Engine};
+
+    #[test]
+    fn test_substeps() {
+        let config = EngineConfig {
+            max_step_microseconds: Some(10_000.0),
+            max_substeps: 3,
+            ..Default::default()
+        };
+        let split = |microseconds| substeps(&config, microseconds);
+
+        assert_eq!(split(8_000.0).count, 1);
+        assert_eq!(split(8_000.0).dropped_microseconds, 0.0);
+        assert_eq!(
+            split(25_000.0),
+            Substeps {
+                count: 3,
+                microseconds: 25_000.0 / 3.0,
+                dropped_microseconds: 0.0,
+            }
+        );
+        // a stall of a second only catches up on three substeps of 10 ms
+        let stall = split(1_000_000.0);
+        assert_eq!((stall.count, stall.microseconds), (3, 10_000.0));
+        assert_eq!(stall.dropped_microseconds, 970_000.0);
+        assert_eq!(split(f64::INFINITY).count, 3);
+        assert_eq!(split(f64::NAN).count, 0);
+    }
+
+    #[test]
+    fn test_stalled_frame() {
+        let mut engine = Engine::sandbox(SandboxConfig::default());
+        let message = engine.run_iteration(2_000_000.0);
+        assert_eq!(engine.perf_stats().steps, DEFAULT_MAX_SUBSTEPS);
+        let simulated = DEFAULT_MAX_SUBSTEPS as f64 * DEFAULT_MAX_STEP_MICROSECONDS;
+        assert!((message.dropped_microseconds - (2_000_000.0 - simulated)).abs() < 1e-6);
+        assert!((message.elapsed_seconds - simulated / 1e6).abs() < 1e-9);
+    }
+}
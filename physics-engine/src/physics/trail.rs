@@ -0,0 +1,98 @@
+//! Recent positions of selected entities, recorded once per step
+//!
+//! Sampling on the simulation clock rather than on every rendered frame keeps
+//! the trails the same length no matter the framerate of the frontend.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::{ids::EntityId, Entity};
+use crate::geometry::{Point, Vector};
+
+/// number of steps a trail spans
+pub const TRAIL_LENGTH: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Trail {
+    pub id: EntityId,
+    /// from the oldest to the most recent position
+    pub points: Vec<Point>,
+}
+
+#[derive(Default)]
+pub(super) struct Trails(Vec<(EntityId, VecDeque<Point>)>);
+
+impl Trails {
+    pub fn track(&mut self, id: EntityId) {
+        if !self.0.iter().any(|(tracked, _)| *tracked == id) {
+            self.0.push((id, VecDeque::with_capacity(TRAIL_LENGTH)));
+        }
+    }
+
+    pub fn untrack(&mut self, id: EntityId) {
+        self.0.retain(|(tracked, _)| *tracked != id);
+    }
+
+    /// appends the current positions of the tracked entities,
+    /// forgetting the trails of ones that no longer exist
+    pub(super) fn record(&mut self, entities: &[Entity]) {
+        self.0.retain_mut(|(id, points)| {
+            let Some(entity) = entities.iter().find(|entity| entity.id == *id) else {
+                return false;
+            };
+
+            if points.len() == TRAIL_LENGTH {
+                points.pop_front();
+            }
+            points.push_back(entity.shape.borrow_mut().collision_data_mut().centroid);
+            true
+        });
+    }
+
+    pub fn to_vec(&self) -> Vec<Trail> {
+        let mut trails = vec![];
+        self.write_display(&mut trails);
+        trails
+    }
+
+    pub fn from_vec(trails: Vec<Trail>) -> Self {
+        Self(
+            trails
+                .into_iter()
+                .map(|Trail { id, points }| {
+                    let mut tracked = VecDeque::with_capacity(TRAIL_LENGTH);
+                    tracked.extend(points);
+                    (id, tracked)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn shift(&mut self, translation: Vector) {
+        for (_, points) in &mut self.0 {
+            points.iter_mut().for_each(|point| *point += translation);
+        }
+    }
+
+    /// overwrites `trails` with the tracked ones, reusing their point buffers
+    pub fn write_display(&self, trails: &mut Vec<Trail>) {
+        trails.truncate(self.0.len());
+        for (index, (id, points)) in self.0.iter().enumerate() {
+            if index == trails.len() {
+                trails.push(Trail {
+                    id: *id,
+                    points: Vec::with_capacity(TRAIL_LENGTH),
+                });
+            }
+
+            let trail = &mut trails[index];
+            trail.id = *id;
+            trail.points.clear();
+            trail.points.extend(points.iter().copied());
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Tolerances of the contact solver
+//!
+//! The defaults are what the levels were designed with. They can be changed
+//! at runtime through [`Engine::set_solver_tuning`] or the sandbox config,
+//! e.g. to pin the behavior of a test or to experiment without recompiling.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use super::Engine;
+
+/// Missing fields take their default values
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct SolverTuning {
+    /// fraction of the normal velocity kept by a bounce,
+    /// before the restitution multiplier
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub restitution: f64,
+    /// friction holds the shapes together (static friction) as long as the impulse
+    /// needed for it is at most this fraction of the normal impulse,
+    /// before the friction multiplier
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub static_friction_threshold: f64,
+    /// dynamic friction grows with the penetration depth by this factor,
+    /// up to stopping the sliding completely
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub dynamic_friction_per_depth: f64,
+    /// upper bound of the positional correction of an overlap per microsecond
+    #[cfg_attr(feature = "wasm", tsify(optional))]
+    pub max_correction_per_microsecond: f64,
+}
+
+impl Default for SolverTuning {
+    fn default() -> Self {
+        Self {
+            restitution: 0.2,
+            static_friction_threshold: 1e-4,
+            dynamic_friction_per_depth: 50.0,
+            max_correction_per_microsecond: 1e-6,
+        }
+    }
+}
+
+impl Engine {
+    pub fn solver_tuning(&self) -> SolverTuning {
+        self.solver_tuning
+    }
+
+    pub fn set_solver_tuning(&mut self, tuning: SolverTuning) {
+        self.solver_tuning = tuning;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields() {
+        let tuning: SolverTuning = serde_json::from_str(r#"{"restitution":0.5}"#).unwrap();
+        assert_eq!(
+            tuning,
+            SolverTuning {
+                restitution: 0.5,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    geometry::Point,
+    levels::Level,
+    physics::{self, compute, game::GameState, ids::EntityId, instrument::TraceFormat, shape},
+};
+
+fn invalid(error: serde_json::Error) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// Physical state of a single body, see [`physics::BodyState`]
+#[pyclass(get_all, frozen)]
+pub struct BodyState {
+    id: u32,
+    is_static: bool,
+    x: f64,
+    y: f64,
+    angle: f64,
+    velocity_x: f64,
+    velocity_y: f64,
+    angular_velocity: f64,
+}
+
+impl From<physics::BodyState> for BodyState {
+    fn from(state: physics::BodyState) -> Self {
+        Self {
+            id: state.id.0,
+            is_static: state.is_static,
+            x: state.centroid.0,
+            y: state.centroid.1,
+            angle: state.angle,
+            velocity_x: state.velocity.0,
+            velocity_y: state.velocity.1,
+            angular_velocity: state.angular_velocity,
+        }
+    }
+}
+
+// the engine is built out of `Rc`s, so it has to stay on the thread that created it
+#[pyclass(unsendable)]
+pub struct Engine(physics::Engine);
+
+#[pymethods]
+impl Engine {
+    /// creates an engine from a level serialized as JSON
+    #[new]
+    fn new(level_json: &str) -> PyResult<Self> {
+        let level: Level = serde_json::from_str(level_json).map_err(invalid)?;
+        Ok(Engine(physics::Engine::new(level)))
+    }
+
+    #[staticmethod]
+    fn from_snapshot(snapshot_json: &str) -> PyResult<Self> {
+        serde_json::from_str(snapshot_json)
+            .map(Engine)
+            .map_err(invalid)
+    }
+
+    fn snapshot(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(invalid)
+    }
+
+    /// the JSON of the edited level, `None` if the snapshot the engine
+    /// was restored from did not keep it
+    fn export_level(&self) -> PyResult<Option<String>> {
+        self.0
+            .export_level()
+            .map(|level| serde_json::to_string(&level).map_err(invalid))
+            .transpose()
+    }
+
+    /// advances the simulation by `count` steps and returns whether the level is won
+    fn run_n_iterations(&mut self, count: u32, time_step_microseconds: f64) -> bool {
+        self.0.run_n_iterations(count, time_step_microseconds);
+        self.is_won()
+    }
+
+    fn is_won(&self) -> bool {
+        matches!(self.0.game_state(), GameState::Won { .. })
+    }
+
+    fn state_hash(&self) -> u64 {
+        self.0.state_hash()
+    }
+
+    fn start_trace(&mut self, ids: Vec<u32>) {
+        self.0.start_trace(ids.into_iter().map(EntityId).collect());
+    }
+
+    /// the trace taken since `start_trace` as `"csv"` or `"jsonl"`, `None` if not tracing
+    fn stop_trace(&mut self, format: &str) -> PyResult<Option<String>> {
+        let format = match format {
+            "csv" => TraceFormat::Csv,
+            "jsonl" => TraceFormat::JsonLines,
+            _ => return Err(PyValueError::new_err("the format is either csv or jsonl")),
+        };
+        Ok(self.0.stop_trace().map(|trace| trace.export(format)))
+    }
+
+    fn start_recording(&mut self) {
+        self.0.start_recording();
+    }
+
+    /// the JSON of the keyframes recorded since `start_recording`, `None` if not recording
+    fn stop_recording(&mut self) -> PyResult<Option<String>> {
+        self.0
+            .stop_recording()
+            .map(|run| serde_json::to_string(&run).map_err(invalid))
+            .transpose()
+    }
+
+    /// hashes every body after every step, to compare with another engine
+    fn set_divergence_recording(&mut self, enabled: bool) {
+        self.0.set_divergence_recording(enabled);
+    }
+
+    /// the first step and entity id on which the recordings of the two engines differ
+    fn first_divergence(&self, other: PyRef<'_, Self>) -> Option<(u32, u32)> {
+        self.0
+            .first_divergence(&other.0)
+            .map(|divergence| (divergence.step, divergence.entity.0))
+    }
+
+    fn body_states(&self) -> Vec<BodyState> {
+        self.0.body_states().into_iter().map(Into::into).collect()
+    }
+
+    /// the JSON of the kinetic and potential energy of every body
+    fn body_energies(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0.body_energies()).map_err(invalid)
+    }
+
+    fn entity_angle(&self, id: u32) -> Option<f64> {
+        self.0.entity_angle(EntityId(id))
+    }
+
+    /// how far the first entity could move along the direction before touching
+    /// the second one along it, negative once they overlap along it
+    fn gap(&self, first: u32, second: u32, x: f64, y: f64) -> Option<f64> {
+        self.0.gap(EntityId(first), EntityId(second), Point(x, y))
+    }
+
+    fn add_circle(&mut self, x: f64, y: f64, radius: f64) -> Option<u32> {
+        self.0
+            .add_circle(shape::Circle::new(Point(x, y), radius))
+            .map(|id| id.0)
+    }
+
+    #[pyo3(signature = (vertices, corner_radius=0.0))]
+    fn add_polygon(
+        &mut self,
+        vertices: Vec<(f64, f64)>,
+        corner_radius: f64,
+    ) -> PyResult<Option<u32>> {
+        if vertices.is_empty() {
+            return Err(PyValueError::new_err("a polygon needs at least one vertex"));
+        }
+
+        let hull = compute::hull::<24>(vertices.into_iter().map(|(x, y)| Point(x, y)));
+        Ok(self
+            .0
+            .add_polygon(hull.with_corner_radius(corner_radius))
+            .map(|id| id.0))
+    }
+
+    /// adds one body of the hulls of the parts
+    fn add_compound(&mut self, parts: Vec<Vec<(f64, f64)>>) -> PyResult<Option<u32>> {
+        if parts.iter().any(Vec::is_empty) {
+            return Err(PyValueError::new_err("a part needs at least one vertex"));
+        }
+
+        let hulls = parts
+            .into_iter()
+            .map(|part| compute::hull::<24>(part.into_iter().map(|(x, y)| Point(x, y))))
+            .collect();
+        Ok(self.0.add_compound(hulls).map(|id| id.0))
+    }
+
+    /// takes a JSON batch and returns the JSON of the added ids,
+    /// or `None` if nothing was added
+    fn add_shapes(&mut self, batch_json: &str) -> PyResult<Option<String>> {
+        let batch = serde_json::from_str(batch_json).map_err(invalid)?;
+        self.0
+            .add_shapes(batch)
+            .map(|added| serde_json::to_string(&added).map_err(invalid))
+            .transpose()
+    }
+
+    fn erase_at(&mut self, x: f64, y: f64) -> Option<u32> {
+        self.0.erase_at(Point(x, y)).map(|id| id.0)
+    }
+
+    #[pyo3(signature = (x, y, damping=0.0))]
+    fn add_hinge(&mut self, x: f64, y: f64, damping: f64) -> Option<u32> {
+        self.0.add_hinge(Point(x, y), damping).map(|id| id.0)
+    }
+
+    #[pyo3(signature = (x, y, compliance=None))]
+    fn add_rigid(&mut self, x: f64, y: f64, compliance: Option<f64>) -> Option<u32> {
+        self.0.add_rigid(Point(x, y), compliance).map(|id| id.0)
+    }
+
+    fn set_gravity_multipier(&mut self, value: f64) {
+        self.0.set_gravity_multipier(value);
+    }
+
+    fn set_restitution_multipier(&mut self, value: f64) {
+        self.0.set_restitution_multipier(value);
+    }
+
+    fn set_friction_multipier(&mut self, value: f64) {
+        self.0.set_friction_multipier(value);
+    }
+
+    fn set_static_friction(&mut self, enabled: bool) {
+        self.0.set_static_friction(enabled);
+    }
+
+    fn set_dynamic_friction(&mut self, enabled: bool) {
+        self.0.set_dynamic_friction(enabled);
+    }
+
+    /// missing fields of the JSON object take their default values
+    fn set_solver_tuning(&mut self, tuning_json: &str) -> PyResult<()> {
+        let tuning = serde_json::from_str(tuning_json).map_err(invalid)?;
+        self.0.set_solver_tuning(tuning);
+        Ok(())
+    }
+
+    /// missing fields of the JSON object take their default values
+    fn set_config(&mut self, config_json: &str) -> PyResult<()> {
+        let config = serde_json::from_str(config_json).map_err(invalid)?;
+        self.0.set_config(config);
+        Ok(())
+    }
+
+    /// missing fields of the JSON object take their default values
+    fn set_snapping(&mut self, snapping_json: &str) -> PyResult<()> {
+        let snapping = serde_json::from_str(snapping_json).map_err(invalid)?;
+        self.0.set_snapping(snapping);
+        Ok(())
+    }
+
+    fn origin(&self) -> (f64, f64) {
+        let origin = self.0.origin();
+        (origin.0, origin.1)
+    }
+
+    fn shift_origin(&mut self, x: f64, y: f64) {
+        self.0.shift_origin(Point(x, y));
+    }
+
+    #[pyo3(signature = (distance))]
+    fn set_origin_shift_distance(&mut self, distance: Option<f64>) {
+        self.0.set_origin_shift_distance(distance);
+    }
+}
+
+#[pymodule]
+fn physics_engine(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Engine>()?;
+    module.add_class::<BodyState>()?;
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Thin wrappers around `tracing`, which expand to nothing unless
+//! the `tracing` feature is enabled
+
+/// enters a span at the given level, e.g. `span!(DEBUG, "step")`,
+/// that lasts until the end of the enclosing block
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($level:ident, $($arg:tt)*) => {
+        let _span = tracing::span!(tracing::Level::$level, $($arg)*).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($level:ident, $($arg:tt)*) => {};
+}
+
+/// emits an event at the given level, e.g. `event!(WARN, "message")`
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($level:ident, $($arg:tt)*) => {
+        tracing::event!(tracing::Level::$level, $($arg)*)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    ($level:ident, $($arg:tt)*) => {
+        ()
+    };
+}
+
+pub(crate) use event;
+pub(crate) use span;
@@ -0,0 +1,703 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    geometry::{self, Point},
+    levels::{self, MainBall, ToolLimits},
+    physics::{
+        self,
+        assist::{AssistedShape, Stroke},
+        batch::{AddedShapes, ShapeBatch},
+        capacity::EngineConfig,
+        cloth::ClothSketch,
+        compute,
+        divergence::Divergence,
+        editor::EntityFlags,
+        energy::BodyEnergy,
+        frame::{FrameDetails, SharedFrame},
+        game::Tool,
+        ids::{BindingId, EntityId, RegionId},
+        instrument::TraceFormat,
+        measure::ShapePoint,
+        palette::Palette,
+        particles::ParticleKind,
+        perf::PerfStats,
+        prefab::{Catapult, Chain, Seesaw, Vehicle, WreckingBall},
+        pulley::PulleySketch,
+        recording::{self, RecordedRun},
+        sandbox::SandboxConfig,
+        shape::{self, Bounded},
+        snap::Snapping,
+        snapshot::Snapshot,
+        soft_body::SoftBodyConfig,
+        spring::SpringConfig,
+        tuning::SolverTuning,
+        BindingInfo, DisplayMessage, MassProperties, PointDescription,
+    },
+};
+
+/// The last frame is kept around between calls so that its buffers can be reused,
+/// and serialized from behind a reference
+#[wasm_bindgen]
+pub struct Engine(Box<physics::Engine>, Box<SharedFrame>, DisplayMessage);
+
+type DisplayMessageJs = <DisplayMessage as Tsify>::JsType;
+type FrameDetailsJs = <FrameDetails as Tsify>::JsType;
+
+#[derive(Serialize, Deserialize, Tsify, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+#[derive(Serialize, Deserialize, Tsify, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PolygonList {
+    polygons: Vec<Polygon>,
+}
+
+#[derive(Serialize, Deserialize, Tsify, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Swipe {
+    points: Vec<Point>,
+}
+
+#[derive(Serialize, Deserialize, Tsify, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct VehicleSketch {
+    body: Vec<Point>,
+    wheels: Vec<Point>,
+}
+
+#[derive(Serialize, Deserialize, Tsify, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MassPropertiesList {
+    bodies: Vec<MassProperties>,
+}
+
+#[derive(Serialize, Deserialize, Tsify, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct BodyEnergyList {
+    bodies: Vec<BodyEnergy>,
+}
+
+#[derive(Serialize, Deserialize, Tsify, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct BindingInfoList {
+    bindings: Vec<BindingInfo>,
+}
+
+fn init_hooks() {
+    console_error_panic_hook::set_once();
+
+    // forwards spans and events to the browser console. Fails if a subscriber is
+    // already set, which is the case for every engine after the first one
+    #[cfg(feature = "tracing")]
+    let _ = tracing_wasm::try_set_as_global_default();
+}
+
+#[wasm_bindgen]
+impl Engine {
+    pub fn create(init: levels::Level) -> Self {
+        init_hooks();
+        Engine(
+            Box::new(physics::Engine::new(init)),
+            Box::default(),
+            DisplayMessage::default(),
+        )
+    }
+
+    pub fn create_with_config(init: levels::Level, config: EngineConfig) -> Self {
+        init_hooks();
+        Engine(
+            Box::new(physics::Engine::with_config(init, config)),
+            Box::default(),
+            DisplayMessage::default(),
+        )
+    }
+
+    pub fn sandbox(config: SandboxConfig) -> Self {
+        init_hooks();
+        Engine(
+            Box::new(physics::Engine::sandbox(config)),
+            Box::default(),
+            DisplayMessage::default(),
+        )
+    }
+
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        init_hooks();
+        Engine(
+            Box::new(snapshot.into()),
+            Box::default(),
+            DisplayMessage::default(),
+        )
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        (&*self.0).into()
+    }
+
+    pub fn run_iteration(&mut self, time_step_microseconds: f64) -> DisplayMessageJs {
+        self.run_n_iterations(1, time_step_microseconds)
+    }
+
+    pub fn run_n_iterations(
+        &mut self,
+        count: u32,
+        time_step_microseconds: f64,
+    ) -> DisplayMessageJs {
+        self.0
+            .run_n_iterations_into(count, time_step_microseconds, &mut self.2);
+        self.2.into_js().unwrap_throw()
+    }
+
+    pub fn perf_stats(&self) -> PerfStats {
+        self.0.perf_stats()
+    }
+
+    pub fn state_hash(&self) -> u64 {
+        self.0.state_hash()
+    }
+
+    /// traces the motion of the entities with the ids, see `physics::instrument`
+    pub fn start_trace(&mut self, ids: Vec<u32>) {
+        self.0.start_trace(ids.into_iter().map(EntityId).collect());
+    }
+
+    /// the trace taken since `start_trace`, exported to the format
+    pub fn stop_trace(&mut self, format: TraceFormat) -> Option<String> {
+        self.0.stop_trace().map(|trace| trace.export(format))
+    }
+
+    /// why the script of the level stopped, see `physics::script`
+    #[cfg(feature = "scripting")]
+    pub fn script_error(&self) -> Option<String> {
+        self.0.script_error().map(str::to_owned)
+    }
+
+    pub fn start_recording(&mut self) {
+        self.0.start_recording();
+    }
+
+    /// the keyframes recorded since `start_recording`, see `physics::recording`
+    pub fn stop_recording(&mut self) -> Option<RecordedRun> {
+        self.0.stop_recording()
+    }
+
+    /// hashes every body after every step, see `physics::divergence`
+    pub fn set_divergence_recording(&mut self, enabled: bool) {
+        self.0.set_divergence_recording(enabled);
+    }
+
+    pub fn recorded_steps(&self) -> u32 {
+        self.0.recorded_steps()
+    }
+
+    /// the first step and body on which the recordings of the two engines differ
+    pub fn first_divergence(&self, other: &Engine) -> Option<Divergence> {
+        self.0.first_divergence(&other.0)
+    }
+
+    pub fn mass_properties(&self) -> MassPropertiesList {
+        MassPropertiesList {
+            bodies: self.0.mass_properties(),
+        }
+    }
+
+    /// the energy of every body, the totals come with every display message
+    pub fn body_energies(&self) -> BodyEnergyList {
+        BodyEnergyList {
+            bodies: self.0.body_energies(),
+        }
+    }
+
+    /// adds what the solver saw to the display messages, see `physics::debug`
+    pub fn set_debug_draw(&mut self, enabled: bool) {
+        self.0.set_debug_draw(enabled);
+    }
+
+    /// Like `run_iteration`, but instead of returning the frame it writes it
+    /// into the shared frame buffers, and returns their new generation with
+    /// the rest of the frame, see `physics::frame`
+    pub fn run_iteration_shared(&mut self, time_step_microseconds: f64) -> FrameDetailsJs {
+        self.0
+            .run_n_iterations_into(1, time_step_microseconds, &mut self.2);
+        self.1.write(&self.2);
+        self.1.details().into_js().unwrap_throw()
+    }
+
+    /// Address of the shared frame header in wasm memory. It never changes
+    /// during the lifetime of the engine
+    pub fn frame_header_ptr(&self) -> *const u32 {
+        self.1.header_ptr()
+    }
+
+    /// Address of the shared frame data in wasm memory. It may change
+    /// whenever the generation does
+    pub fn frame_data_ptr(&self) -> *const f32 {
+        self.1.data_ptr()
+    }
+
+    pub fn add_circle(&mut self, x: f64, y: f64, radius: f64) -> Option<EntityId> {
+        self.0
+            .add_circle(shape::Circle::new(geometry::Point(x, y), radius))
+    }
+
+    /// adds the hull of the polygon, with its corners rounded by `corner_radius`
+    pub fn add_polygon(
+        &mut self,
+        polygon: Polygon,
+        corner_radius: Option<f64>,
+    ) -> Option<EntityId> {
+        let hull = compute::hull::<24>(polygon.vertices.into_iter());
+        self.0
+            .add_polygon(hull.with_corner_radius(corner_radius.unwrap_or(0.0)))
+    }
+
+    /// adds one body of the hulls of the parts
+    pub fn add_compound(&mut self, parts: PolygonList) -> Option<EntityId> {
+        if parts.polygons.iter().any(|part| part.vertices.is_empty()) {
+            return None;
+        }
+        self.0.add_compound(
+            parts
+                .polygons
+                .into_iter()
+                .map(|part| compute::hull::<24>(part.vertices.into_iter()))
+                .collect(),
+        )
+    }
+
+    /// the shape the stroke through the points turns into, to preview it while drawing
+    pub fn assist(&self, swipe: Swipe, stroke: Stroke) -> Option<AssistedShape> {
+        self.0.assist(&swipe.points, stroke)
+    }
+
+    pub fn add_assisted(&mut self, swipe: Swipe, stroke: Stroke) -> Option<EntityId> {
+        self.0.add_assisted(&swipe.points, stroke)
+    }
+
+    /// adds all shapes of the batch, or none of them if any is invalid
+    pub fn add_shapes(&mut self, batch: ShapeBatch) -> Option<AddedShapes> {
+        self.0.add_shapes(batch)
+    }
+
+    /// the polygon is the outline of the soft body as drawn, not its hull
+    pub fn add_soft_body(&mut self, polygon: Polygon, config: SoftBodyConfig) -> Option<EntityId> {
+        self.0.add_soft_body(polygon.vertices, config)
+    }
+
+    pub fn remove_soft_body(&mut self, id: EntityId) -> bool {
+        self.0.remove_soft_body(id)
+    }
+
+    pub fn add_cloth(&mut self, sketch: ClothSketch) -> Option<EntityId> {
+        self.0.add_cloth(sketch)
+    }
+
+    pub fn remove_cloth(&mut self, id: EntityId) -> bool {
+        self.0.remove_cloth(id)
+    }
+
+    /// fills the rectangle with particles, returning how many were added
+    pub fn add_particle_block(
+        &mut self,
+        kind: ParticleKind,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> usize {
+        self.0.add_particle_block(
+            kind,
+            geometry::Point(x, y),
+            geometry::Point(x + width, y + height),
+        )
+    }
+
+    pub fn clear_particles(&mut self) {
+        self.0.clear_particles();
+    }
+
+    pub fn add_seesaw(&mut self, x: f64, y: f64, length: f64, angle: f64) -> Seesaw {
+        self.0.add_seesaw(geometry::Point(x, y), length, angle)
+    }
+
+    pub fn add_catapult(&mut self, x: f64, y: f64, size: f64, strength: f64) -> Catapult {
+        self.0.add_catapult(geometry::Point(x, y), size, strength)
+    }
+
+    pub fn add_wrecking_ball(&mut self, wrecking_ball: levels::WreckingBall) -> WreckingBall {
+        self.0.add_wrecking_ball(&wrecking_ball)
+    }
+
+    pub fn add_vehicle(
+        &mut self,
+        sketch: VehicleSketch,
+        wheel_radius: f64,
+        motor_speed: f64,
+    ) -> Option<Vehicle> {
+        if sketch.body.is_empty() {
+            return None;
+        }
+        self.0.add_vehicle(
+            compute::hull::<24>(sketch.body.into_iter()),
+            &sketch.wheels,
+            wheel_radius,
+            motor_speed,
+        )
+    }
+
+    /// a chain of links from every point of the swipe to the next
+    pub fn add_chain(&mut self, swipe: Swipe, link_thickness: f64) -> Option<Chain> {
+        self.0.add_chain(swipe.points, link_thickness)
+    }
+
+    pub fn set_magnet_powered(&mut self, id: EntityId, is_powered: bool) -> bool {
+        self.0.set_magnet_powered(id, is_powered)
+    }
+
+    pub fn set_motor_speed(&mut self, id: BindingId, speed: f64) -> bool {
+        self.0.set_motor_speed(id, speed)
+    }
+
+    pub fn tool_limits(&self) -> ToolLimits {
+        self.0.tool_limits()
+    }
+
+    pub fn point_on(&self, entity: EntityId, x: f64, y: f64) -> Option<ShapePoint> {
+        self.0.point_on(entity, geometry::Point(x, y))
+    }
+
+    pub fn resolve_point(&self, point: ShapePoint) -> Option<Point> {
+        self.0.resolve_point(point)
+    }
+
+    pub fn distance(&self, first: ShapePoint, second: ShapePoint) -> Option<f64> {
+        self.0.distance(first, second)
+    }
+
+    pub fn entity_angle(&self, id: EntityId) -> Option<f64> {
+        self.0.entity_angle(id)
+    }
+
+    pub fn gap(&self, first: EntityId, second: EntityId, x: f64, y: f64) -> Option<f64> {
+        self.0.gap(first, second, geometry::Point(x, y))
+    }
+
+    pub fn describe_at(&self, x: f64, y: f64) -> PointDescription {
+        self.0.describe_at(geometry::Point(x, y))
+    }
+
+    pub fn bindings(&self) -> BindingInfoList {
+        BindingInfoList {
+            bindings: self.0.bindings(),
+        }
+    }
+
+    pub fn remove_binding(&mut self, id: BindingId) -> bool {
+        self.0.remove_binding(id)
+    }
+
+    pub fn grab(&mut self, x: f64, y: f64) -> bool {
+        self.0.grab(geometry::Point(x, y))
+    }
+
+    pub fn move_grab(&mut self, x: f64, y: f64) {
+        self.0.move_grab(geometry::Point(x, y));
+    }
+
+    pub fn release_grab(&mut self) {
+        self.0.release_grab();
+    }
+
+    pub fn erase_at(&mut self, x: f64, y: f64) -> Option<EntityId> {
+        self.0.erase_at(geometry::Point(x, y))
+    }
+
+    pub fn erase(&mut self, id: EntityId) -> Option<EntityId> {
+        self.0.erase(id)
+    }
+
+    /// `damping` defaults to none
+    pub fn add_hinge(&mut self, x: f64, y: f64, damping: Option<f64>) -> Option<BindingId> {
+        self.0
+            .add_hinge(geometry::Point(x, y), damping.unwrap_or(0.0))
+    }
+
+    /// `compliance` defaults to the one of the level
+    pub fn add_rigid(&mut self, x: f64, y: f64, compliance: Option<f64>) -> Option<BindingId> {
+        self.0.add_rigid(geometry::Point(x, y), compliance)
+    }
+
+    pub fn add_spring(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) -> Option<BindingId> {
+        self.0
+            .add_spring(geometry::Point(x1, y1), geometry::Point(x2, y2))
+    }
+
+    pub fn add_rope(
+        &mut self,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        max_length: f64,
+    ) -> Option<BindingId> {
+        self.0
+            .add_rope(geometry::Point(x1, y1), geometry::Point(x2, y2), max_length)
+    }
+
+    pub fn add_pulley(&mut self, sketch: PulleySketch) -> Option<BindingId> {
+        self.0.add_pulley(sketch)
+    }
+
+    pub fn spring_config(&self) -> SpringConfig {
+        self.0.spring_config()
+    }
+
+    pub fn set_spring_config(&mut self, config: SpringConfig) -> bool {
+        self.0.set_spring_config(config)
+    }
+
+    pub fn set_hinge_motor(
+        &mut self,
+        x: f64,
+        y: f64,
+        speed: f64,
+        max_torque: f64,
+    ) -> Option<BindingId> {
+        self.0
+            .set_hinge_motor(geometry::Point(x, y), speed, max_torque)
+    }
+
+    pub fn set_hinge_damping(&mut self, id: BindingId, damping: f64) -> bool {
+        self.0.set_hinge_damping(id, damping)
+    }
+
+    pub fn set_break_impulse(&mut self, id: BindingId, impulse: Option<f64>) -> bool {
+        self.0.set_break_impulse(id, impulse)
+    }
+
+    /// returns the number of severed bindings, their ids are reported as events
+    pub fn cut_along(&mut self, swipe: Swipe) -> usize {
+        self.0.cut_along(&swipe.points).len()
+    }
+
+    pub fn select_tool(&mut self, tool: Tool) {
+        self.0.select_tool(tool);
+    }
+
+    pub fn explode(&mut self, x: f64, y: f64, radius: f64, impulse: f64) {
+        self.0.explode(geometry::Point(x, y), radius, impulse);
+    }
+
+    pub fn set_fan_enabled(&mut self, id: RegionId, enabled: bool) -> bool {
+        self.0.set_fan_enabled(id, enabled)
+    }
+
+    /// the status of the ball is reported in the display messages
+    pub fn dispense(&mut self, id: RegionId) -> Option<EntityId> {
+        self.0.dispense(id)
+    }
+
+    pub fn set_breaking_impulse(&mut self, id: EntityId, breaking_impulse: Option<f64>) -> bool {
+        self.0.set_breaking_impulse(id, breaking_impulse)
+    }
+
+    pub fn set_fracture_impulse(&mut self, id: EntityId, fracture_impulse: Option<f64>) -> bool {
+        self.0.set_fracture_impulse(id, fracture_impulse)
+    }
+
+    pub fn render_layer(&self, id: EntityId) -> Option<i32> {
+        self.0.render_layer(id)
+    }
+
+    pub fn set_render_layer(&mut self, id: EntityId, render_layer: i32) -> bool {
+        self.0.set_render_layer(id, render_layer)
+    }
+
+    pub fn main_ball(&self) -> MainBall {
+        self.0.main_ball()
+    }
+
+    pub fn set_main_ball(&mut self, main_ball: MainBall) -> bool {
+        self.0.set_main_ball(main_ball)
+    }
+
+    pub fn set_trail(&mut self, id: EntityId, enabled: bool) {
+        self.0.set_trail(id, enabled);
+    }
+
+    pub fn set_gravity_multipier(&mut self, value: f64) {
+        self.0.set_gravity_multipier(value);
+    }
+
+    pub fn set_restitution_multipier(&mut self, value: f64) {
+        self.0.set_restitution_multipier(value);
+    }
+
+    pub fn set_friction_multipier(&mut self, value: f64) {
+        self.0.set_friction_multipier(value);
+    }
+
+    pub fn set_static_friction(&mut self, enabled: bool) {
+        self.0.set_static_friction(enabled);
+    }
+
+    pub fn set_dynamic_friction(&mut self, enabled: bool) {
+        self.0.set_dynamic_friction(enabled);
+    }
+
+    pub fn solver_tuning(&self) -> SolverTuning {
+        self.0.solver_tuning()
+    }
+
+    pub fn set_solver_tuning(&mut self, tuning: SolverTuning) {
+        self.0.set_solver_tuning(tuning);
+    }
+
+    pub fn set_config(&mut self, config: EngineConfig) {
+        self.0.set_config(config);
+    }
+
+    pub fn snapping(&self) -> Snapping {
+        self.0.snapping()
+    }
+
+    pub fn set_snapping(&mut self, snapping: Snapping) {
+        self.0.set_snapping(snapping);
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.0.palette()
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.0.set_palette(palette);
+    }
+
+    /// the display messages carry the origin, so there is no getter for it
+    pub fn shift_origin(&mut self, x: f64, y: f64) {
+        self.0.shift_origin(geometry::Point(x, y));
+    }
+
+    pub fn set_origin_shift_distance(&mut self, distance: Option<f64>) {
+        self.0.set_origin_shift_distance(distance);
+    }
+
+    pub fn export_level(&self) -> Option<levels::Level> {
+        self.0.export_level()
+    }
+
+    /// the editing methods restart the level, see `physics::editor`
+    pub fn set_ball_start(&mut self, x: f64, y: f64) -> bool {
+        self.0.set_ball_start(geometry::Point(x, y))
+    }
+
+    pub fn place_flag(&mut self, x: f64, y: f64) -> bool {
+        self.0.place_flag(geometry::Point(x, y))
+    }
+
+    pub fn move_flag(&mut self, from_x: f64, from_y: f64, to_x: f64, to_y: f64) -> bool {
+        self.0
+            .move_flag(geometry::Point(from_x, from_y), geometry::Point(to_x, to_y))
+    }
+
+    pub fn remove_flag_at(&mut self, x: f64, y: f64) -> bool {
+        self.0.remove_flag_at(geometry::Point(x, y))
+    }
+
+    pub fn entity_flags(&self, id: EntityId) -> Option<EntityFlags> {
+        self.0.entity_flags(id)
+    }
+
+    pub fn set_entity_flags(&mut self, id: EntityId, flags: EntityFlags) -> bool {
+        self.0.set_entity_flags(id, flags)
+    }
+
+    pub fn add_time_zone(&mut self, zone: levels::TimeZone) -> bool {
+        self.0.add_time_zone(zone)
+    }
+
+    pub fn remove_time_zone_at(&mut self, x: f64, y: f64) -> bool {
+        self.0.remove_time_zone_at(geometry::Point(x, y))
+    }
+}
+
+/// Plays a recorded run back without simulating it
+#[wasm_bindgen]
+pub struct Player(recording::Player);
+
+#[wasm_bindgen]
+impl Player {
+    pub fn create(run: RecordedRun) -> Self {
+        init_hooks();
+        Player(recording::Player::new(run))
+    }
+
+    /// the number of frames in the run
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn seek(&mut self, frame: usize) {
+        self.0.seek(frame);
+    }
+
+    /// the next frame, `undefined` after the last one
+    pub fn next_frame(&mut self) -> Option<DisplayMessageJs> {
+        self.0
+            .next()
+            .map(|message| message.into_js().unwrap_throw())
+    }
+}
+
+/// whether the point is inside the polygon, tested like the engine tests its
+/// polygons, all of which are convex
+#[wasm_bindgen]
+pub fn polygon_includes(polygon: Polygon, point: Point) -> bool {
+    !polygon.vertices.is_empty() && shape::Polygon::new(polygon.vertices).includes(point)
+}
+
+/// the signed area, positive for the winding of the polygons of the engine
+#[wasm_bindgen]
+pub fn polygon_area(polygon: Polygon) -> f64 {
+    compute::area(&polygon.vertices)
+}
+
+/// `undefined` for a polygon without an area
+#[wasm_bindgen]
+pub fn polygon_centroid(polygon: Polygon) -> Option<Point> {
+    let centroid = compute::centroid(&polygon.vertices);
+    (centroid.0.is_finite() && centroid.1.is_finite()).then_some(centroid)
+}
+
+/// the convex polygon the engine wraps around the points of a drawn stroke,
+/// `undefined` for a stroke without points
+#[wasm_bindgen]
+pub fn convex_hull(swipe: Swipe) -> Option<Polygon> {
+    if swipe.points.is_empty() {
+        return None;
+    }
+    let hull = compute::hull::<24>(swipe.points.into_iter());
+    Some(Polygon {
+        vertices: hull.vertices().to_vec(),
+    })
+}
+
+/// where the two segments cross, `undefined` if they do not or are parallel
+#[wasm_bindgen]
+pub fn segment_intersection(
+    start: Point,
+    end: Point,
+    other_start: Point,
+    other_end: Point,
+) -> Option<Point> {
+    Point::segment_intersection(start, end, other_start, other_end)
+}
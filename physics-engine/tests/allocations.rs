@@ -0,0 +1,66 @@
+//! Heap allocations of the engine once it reached a steady state.
+//!
+//! Native only, as it replaces the global allocator, and without the `parallel`
+//! feature, as the job queues of rayon allocate on their own. Keep a single
+//! test in this file, since the count is shared by every thread of the process.
+
+#![cfg(not(any(target_arch = "wasm32", feature = "parallel")))]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use physics_engine::{
+    levels::Level,
+    physics::{DisplayMessage, Engine},
+};
+
+const STEP_MICROSECONDS: f64 = 2_000.0;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations_of(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn steps_do_not_allocate() {
+    let level: Level = ron::from_str(include_str!("../levels/stack.ron")).unwrap();
+    let mut engine = Engine::new(level);
+    let mut message = DisplayMessage::default();
+    engine.run_n_iterations_into(600, STEP_MICROSECONDS, &mut message);
+
+    // both build one display message, so any difference comes from the steps
+    let one_step = allocations_of(|| {
+        engine.run_n_iterations_into(1, STEP_MICROSECONDS, &mut message);
+    });
+    let many_steps = allocations_of(|| {
+        engine.run_n_iterations_into(100, STEP_MICROSECONDS, &mut message);
+    });
+
+    assert_eq!(many_steps, one_step);
+}
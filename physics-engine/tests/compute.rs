@@ -0,0 +1,243 @@
+//! Property based tests of the geometric and collision computations.
+//!
+//! Native only, proptest's fork and timeout machinery has no wasm support.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use physics_engine::{
+    geometry::{self, Point, Vector},
+    physics::{
+        compute,
+        shape::{Circle, Collidable, CollisionData, Polygon},
+        tuning::SolverTuning,
+    },
+};
+use proptest::prelude::*;
+
+// the hull is built from intersecting lines, so its vertices carry some rounding error
+const TOLERANCE: f64 = 1e-9;
+
+fn point() -> impl Strategy<Value = Point> {
+    (-10.0..10.0, -10.0..10.0).prop_map(|(x, y)| Point(x, y))
+}
+
+fn unit_vector() -> impl Strategy<Value = Vector> {
+    (0.0..std::f64::consts::TAU).prop_map(|angle| Point(angle.cos(), angle.sin()))
+}
+
+fn area(vertices: &[Point]) -> f64 {
+    let shifted = vertices.iter().cycle().skip(1);
+    vertices
+        .iter()
+        .zip(shifted)
+        .map(|(&first, &second)| first.cross(second))
+        .sum::<f64>()
+        / 2.0
+}
+
+fn vertices(polygon: Polygon) -> Vec<Point> {
+    geometry::Polygon::from(polygon).vertices
+}
+
+/// a hull around a point cloud that is not too thin to have a meaningful mass
+fn polygon() -> impl Strategy<Value = Polygon> {
+    (prop::collection::vec(point(), 1..30), point(), 0.1..3.0).prop_filter_map(
+        "degenerate hull",
+        |(points, center, scale)| {
+            let polygon =
+                compute::hull::<24>(points.into_iter().map(|point| center + point * scale));
+            (area(&vertices(polygon.clone())) > 1e-2).then_some(polygon)
+        },
+    )
+}
+
+#[derive(Debug)]
+enum Shape {
+    Polygon(Polygon),
+    Circle(Circle),
+}
+
+impl Shape {
+    fn as_mut(&mut self) -> &mut dyn Collidable {
+        match self {
+            Shape::Polygon(polygon) => polygon,
+            Shape::Circle(circle) => circle,
+        }
+    }
+
+    fn as_ref(&self) -> &dyn Collidable {
+        match self {
+            Shape::Polygon(polygon) => polygon,
+            Shape::Circle(circle) => circle,
+        }
+    }
+}
+
+fn shape() -> impl Strategy<Value = Shape> {
+    prop_oneof![
+        polygon().prop_map(Shape::Polygon),
+        (point(), 0.1..5.0).prop_map(|(center, radius)| Shape::Circle(Circle::new(center, radius))),
+    ]
+}
+
+fn collision_data() -> impl Strategy<Value = CollisionData> {
+    (point(), 1e-2..1e2, 1e-2..1e2, point(), -10.0..10.0).prop_map(
+        |(centroid, mass, inertia, velocity, angular_velocity)| CollisionData {
+            centroid,
+            mass,
+            inertia,
+            velocity,
+            angular_velocity,
+        },
+    )
+}
+
+fn is_finite(point: Point) -> bool {
+    point.0.is_finite() && point.1.is_finite()
+}
+
+proptest! {
+    #[test]
+    fn hull_is_convex_and_contains_its_points(
+        points in prop::collection::vec(point(), 1..50),
+    ) {
+        let hull = vertices(compute::hull::<24>(points.iter().cloned()));
+        prop_assert!(!hull.is_empty());
+
+        let edges = hull
+            .iter()
+            .zip(hull.iter().cycle().skip(1))
+            .map(|(&start, &end)| (start, start.to(end)));
+
+        for (i, (start, edge)) in edges.clone().enumerate() {
+            let (_, next) = edges.clone().cycle().nth(i + 1).unwrap();
+            prop_assert!(edge.cross(next) >= -TOLERANCE, "hull turns clockwise at {i}");
+
+            for &point in &points {
+                prop_assert!(
+                    edge.cross(start.to(point)) >= -TOLERANCE * (1.0 + edge.norm()),
+                    "{point:?} lies outside of the hull"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn farthest_point_matches_max_by(
+        points in prop::collection::vec(point(), 1..70),
+        direction in unit_vector(),
+    ) {
+        let expected = *points
+            .iter()
+            .max_by(|p1, p2| direction.dot(**p1).total_cmp(&direction.dot(**p2)))
+            .unwrap();
+        prop_assert_eq!(compute::support::farthest_point(&points, direction), expected);
+    }
+
+    #[test]
+    fn collision_is_finite(first in shape(), second in shape()) {
+        if let Some(collision) = compute::collision(first.as_ref(), second.as_ref()) {
+            prop_assert!(is_finite(collision.point));
+            prop_assert!(is_finite(collision.created_from.0));
+            prop_assert!(is_finite(collision.created_from.1));
+        }
+    }
+
+    #[test]
+    fn impulse_is_finite(
+        first in collision_data(),
+        second in collision_data(),
+        first_offset in point(),
+        second_offset in point(),
+        normal in unit_vector(),
+        relative_velocity in point(),
+        reflection_factor in 1.0..2.0,
+    ) {
+        let inverse_effective_mass = compute::inverse_effective_mass(
+            &first,
+            &second,
+            first_offset,
+            second_offset,
+            normal,
+        );
+        let impulse = compute::impulse(
+            inverse_effective_mass,
+            normal,
+            relative_velocity,
+            reflection_factor,
+        );
+        prop_assert!(impulse.is_finite());
+    }
+
+    #[test]
+    fn penetration_is_minimal(
+        first in polygon(),
+        second in polygon(),
+        direction in unit_vector(),
+    ) {
+        let Some(collision) = compute::collision(&first, &second) else {
+            return Ok(());
+        };
+        let depth = collision.point.norm();
+
+        // pushing the shapes apart along any other direction takes at least as far
+        let difference = compute::minkowski::Difference(&first, &second);
+        let reach = difference.support_vector(direction).point.dot(direction);
+        prop_assert!(reach >= depth - 1e-6 * (1.0 + depth), "{reach} < {depth}");
+
+        // and the translation ends on the boundary of the difference
+        if depth > TOLERANCE {
+            let normal = collision.point * (1.0 / depth);
+            let boundary = difference.support_vector(normal).point.dot(normal);
+            prop_assert!((boundary - depth).abs() <= 1e-6 * (1.0 + depth));
+        }
+    }
+
+    #[test]
+    fn colliding_shapes_separate(mut first in shape(), mut second in shape()) {
+        let Some(collision) = compute::collision(first.as_ref(), second.as_ref()) else {
+            return Ok(());
+        };
+        let depth = collision.point.norm();
+
+        // a step long enough for the positional correction to push the whole overlap out
+        let microseconds = 1e6 * (1.0 + depth);
+        first.as_mut().collide(
+            second.as_mut(),
+            microseconds,
+            &SolverTuning::default(),
+            1.0,
+            1.0,
+            true,
+            true,
+        );
+
+        // EPA approximates curved boundaries with a bounded number of edges,
+        // so a small part of the overlap may remain
+        let remaining = compute::collision(first.as_ref(), second.as_ref())
+            .map_or(0.0, |collision| collision.point.norm());
+        prop_assert!(remaining < 1e-2 * depth + 1e-9, "{remaining} of {depth} remains");
+    }
+
+    #[test]
+    fn area_and_centroid_match_the_mass_properties(mut polygon in polygon()) {
+        let data = polygon.collision_data_mut().clone();
+        let outline = vertices(polygon);
+        prop_assert!((compute::area(&outline) - data.mass).abs() < TOLERANCE * data.mass.max(1.0));
+        prop_assert!(compute::centroid(&outline).to(data.centroid).norm() < TOLERANCE);
+    }
+
+    #[test]
+    fn voronoi_cells_cover_the_polygon(
+        polygon in polygon(),
+        seeds in prop::collection::vec(point(), 1..10),
+    ) {
+        let vertices = vertices(polygon);
+        let cells = compute::voronoi_cells(&vertices, &seeds);
+        prop_assert_eq!(cells.len(), seeds.len());
+
+        let total = area(&vertices);
+        let sum = cells.iter().map(|cell| area(cell)).sum::<f64>();
+        prop_assert!((sum - total).abs() <= 1e-6 * (1.0 + total), "{sum} != {total}");
+    }
+}
@@ -0,0 +1,86 @@
+//! Canonical levels simulated for a while, checking invariants of the result.
+//!
+//! Runs natively with `cargo test`, and in the browser or node with `wasm-pack test`.
+
+use physics_engine::{
+    levels::Level,
+    physics::{BodyState, Engine},
+};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+// the engine is tuned for pixel sized worlds, unit sized levels need smaller steps
+const STEP_MICROSECONDS: f64 = 2_000.0;
+
+const LEVELS: [(&str, &str); 3] = [
+    ("ramp", include_str!("../levels/ramp.ron")),
+    ("resting", include_str!("../levels/resting.ron")),
+    ("stack", include_str!("../levels/stack.ron")),
+];
+
+fn load(name: &str) -> Engine {
+    let (_, source) = LEVELS
+        .iter()
+        .find(|(level, _)| *level == name)
+        .expect("unknown level");
+    let level: Level = ron::from_str(source).expect("invalid level");
+    Engine::new(level)
+}
+
+fn run(engine: &mut Engine, steps: u32) -> Vec<BodyState> {
+    engine.run_n_iterations(steps, STEP_MICROSECONDS);
+    engine.body_states()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn no_nan_positions() {
+    for (name, _) in LEVELS {
+        let mut engine = load(name);
+
+        for body in run(&mut engine, 600) {
+            assert!(
+                body.centroid.0.is_finite() && body.centroid.1.is_finite(),
+                "{name}: {body:?}"
+            );
+            assert!(
+                body.velocity.0.is_finite() && body.velocity.1.is_finite(),
+                "{name}: {body:?}"
+            );
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn resting_ball_stays_put() {
+    let mut engine = load("resting");
+
+    let settled = run(&mut engine, 100)[0].centroid;
+    let later = run(&mut engine, 300)[0].centroid;
+
+    assert!((settled.0 - later.0).abs() < 0.01, "{settled:?} {later:?}");
+    assert!((settled.1 - later.1).abs() < 0.01, "{settled:?} {later:?}");
+    assert!((later.1 + 0.1).abs() < 0.02, "{later:?}");
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn stacked_boxes_do_not_sink() {
+    let mut engine = load("stack");
+
+    // the boxes start slightly apart and settle onto each other
+    let bodies = run(&mut engine, 400);
+    for (body, expected) in bodies[2..].iter().zip([-0.15, -0.45]) {
+        assert!((body.centroid.1 - expected).abs() < 0.01, "{body:?}");
+        assert!(body.centroid.0.abs() < 0.01, "{body:?}");
+    }
+
+    let later = run(&mut engine, 400);
+    for (before, after) in bodies[2..].iter().zip(&later[2..]) {
+        assert!(
+            (before.centroid.1 - after.centroid.1).abs() < 0.005,
+            "{before:?} {after:?}"
+        );
+    }
+}